@@ -0,0 +1,316 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! signer for TSIG, message authentication with a shared secret
+
+use trust_dns_proto::error::{ProtoErrorKind, ProtoResult};
+use trust_dns_proto::op::EncodableMessage;
+use trust_dns_proto::rr::dnssec::rdata::tsig::{self, TsigAlgorithm};
+
+use op::{Message, MessageFinalizer, ResponseCode};
+use rr::rdata::{DNSSECRData, TSIG};
+use rr::{Name, RData, Record};
+
+/// Default number of seconds of clock skew permitted between the signer and the verifier
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-10), TSIG, November 2020
+///
+/// ```text
+///    The Fudge field is a 16-bit field representing the number of seconds
+///    of permitted clock skew.  A value of 300 seconds is RECOMMENDED.
+/// ```
+pub const DEFAULT_FUDGE: u16 = 300;
+
+/// Use for performing TSIG signing and verification of messages, with a shared secret key.
+///
+/// Unlike SIG(0), which is only used for Update, the TSIG signer authenticates every message it
+///  finalizes, e.g. zone transfers.
+#[derive(Clone)]
+pub struct TSigner {
+    key: Vec<u8>,
+    algorithm: TsigAlgorithm,
+    signer_name: Name,
+    fudge: u16,
+}
+
+impl TSigner {
+    /// Constructs a new TSIG signer
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the shared secret
+    /// * `algorithm` - the HMAC algorithm to use with the key
+    /// * `signer_name` - the name of the key, this must be the same on the client and the server
+    /// * `fudge` - seconds of clock skew permitted in signed messages, see `DEFAULT_FUDGE`
+    pub fn new(key: Vec<u8>, algorithm: TsigAlgorithm, signer_name: Name, fudge: u16) -> Self {
+        TSigner {
+            key,
+            algorithm,
+            signer_name,
+            fudge,
+        }
+    }
+
+    /// the HMAC algorithm to use with the key
+    pub fn algorithm(&self) -> &TsigAlgorithm {
+        &self.algorithm
+    }
+
+    /// the name of the key
+    pub fn signer_name(&self) -> &Name {
+        &self.signer_name
+    }
+
+    /// seconds of clock skew permitted in signed messages
+    pub fn fudge(&self) -> u16 {
+        self.fudge
+    }
+
+    /// Returns the MAC of data which is not a DNS message, e.g. a command to a control channel
+    pub fn mac(&self, data: &[u8]) -> ProtoResult<Vec<u8>> {
+        self.algorithm.mac(&self.key, data)
+    }
+
+    /// Verifies the MAC of data which is not a DNS message, see `mac`
+    pub fn verify_mac(&self, data: &[u8], mac: &[u8]) -> ProtoResult<()> {
+        self.algorithm.verify_mac(&self.key, data, mac)
+    }
+
+    /// Returns the TSIG record for the message
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - the message to sign, responses are signed with the MAC of the request
+    /// * `request_mac` - for responses, the MAC of the TSIG of the request
+    /// * `current_time` - seconds since the Unix epoch
+    pub fn sign_message<M: EncodableMessage>(
+        &self,
+        message: &M,
+        request_mac: Option<&[u8]>,
+        current_time: u64,
+    ) -> ProtoResult<Record> {
+        let pre_tsig = TSIG::new(
+            self.algorithm.clone(),
+            current_time,
+            self.fudge,
+            Vec::new(),
+            message.header().id(),
+            0,
+            Vec::new(),
+        );
+
+        let tbs = tsig::message_tbs(request_mac, message, &self.signer_name, &pre_tsig)?;
+        let mac = self.algorithm.mac(&self.key, &tbs)?;
+
+        Ok(tsig::make_tsig_record(
+            self.signer_name.clone(),
+            pre_tsig.set_mac(mac),
+        ))
+    }
+
+    /// Returns the TSIG record of the BADTIME error response to a request signed with this key
+    ///
+    /// Unlike the other TSIG errors the response is signed, with the time of the request, so
+    ///  that the client accepts it, and the time of the server in the other data.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - the error response to sign
+    /// * `request_tsig` - the TSIG record of the request
+    /// * `current_time` - seconds since the Unix epoch
+    pub fn sign_bad_time<M: EncodableMessage>(
+        &self,
+        message: &M,
+        request_tsig: &Record,
+        current_time: u64,
+    ) -> ProtoResult<Record> {
+        let request = self.tsig_rdata(request_tsig)?;
+
+        // the time of the server is 48 bits, as the time signed
+        let mut other = Vec::with_capacity(6);
+        for shift in &[40, 32, 24, 16, 8, 0] {
+            other.push((current_time >> shift) as u8);
+        }
+
+        let pre_tsig = TSIG::new(
+            self.algorithm.clone(),
+            request.time(),
+            self.fudge,
+            Vec::new(),
+            message.header().id(),
+            ResponseCode::BADTIME.into(),
+            other,
+        );
+
+        let tbs = tsig::message_tbs(Some(request.mac()), message, &self.signer_name, &pre_tsig)?;
+        let mac = self.algorithm.mac(&self.key, &tbs)?;
+
+        Ok(tsig::make_tsig_record(
+            self.signer_name.clone(),
+            pre_tsig.set_mac(mac),
+        ))
+    }
+
+    /// Verifies the TSIG of a message as it was received
+    ///
+    /// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-5.2), TSIG, November 2020
+    ///
+    /// ```text
+    /// 5.2.  Server Processing of Request
+    ///
+    ///    If an incoming message contains a TSIG record, it MUST be the last
+    ///    record in the additional section.  Multiple TSIG records are not
+    ///    allowed.  If multiple TSIG records are detected or a TSIG record is
+    ///    present in any other position, the DNS message is dropped and a
+    ///    response with RCODE 1 (FORMERR) MUST be returned.
+    ///
+    ///    Upon receipt of a message with exactly one correctly placed TSIG RR,
+    ///    a copy of the TSIG RR is stored and then removed from the DNS
+    ///    message and decremented out of the DNS message header's ARCOUNT.  At
+    ///    this point, the keyed message digest operation is performed: until
+    ///    this operation concludes that the signature is valid, the signature
+    ///    MUST be considered to be invalid.
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `request_mac` - for responses, the MAC of the TSIG of the request
+    /// * `unsigned_message` - the message as received, up to but excluding the TSIG
+    /// * `tsig` - the TSIG record of the message
+    /// * `current_time` - seconds since the Unix epoch
+    ///
+    /// # Return
+    ///
+    /// The MAC of the message, with which the response to it is signed, otherwise a
+    ///  `ProtoErrorKind::Tsig` error with BADKEY, BADSIG or BADTIME, checked in that order. The
+    ///  time is only checked once the MAC is valid, so that BADTIME, whose response is signed,
+    ///  is only returned to holders of the key, see `sign_bad_time`.
+    pub fn verify_message_byte(
+        &self,
+        request_mac: Option<&[u8]>,
+        unsigned_message: &[u8],
+        tsig: &Record,
+        current_time: u64,
+    ) -> ProtoResult<Vec<u8>> {
+        let rdata = self.tsig_rdata(tsig)?;
+
+        let tbs = tsig::signed_message_tbs(request_mac, unsigned_message, tsig)?;
+        self.verify_tbs(&tbs, rdata, current_time)
+    }
+
+    /// Returns the TSIG record for a message after the first of a multi-message response, e.g. a
+    ///  zone transfer
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - the message to sign
+    /// * `prior_mac` - the MAC of the last message which was signed
+    /// * `unsigned_messages` - the messages sent since the last one which was signed
+    /// * `current_time` - seconds since the Unix epoch
+    pub fn sign_subsequent_message<M: EncodableMessage>(
+        &self,
+        message: &M,
+        prior_mac: &[u8],
+        unsigned_messages: &[u8],
+        current_time: u64,
+    ) -> ProtoResult<Record> {
+        let pre_tsig = TSIG::new(
+            self.algorithm.clone(),
+            current_time,
+            self.fudge,
+            Vec::new(),
+            message.header().id(),
+            0,
+            Vec::new(),
+        );
+
+        let tbs = tsig::subsequent_message_tbs(prior_mac, unsigned_messages, message, &pre_tsig)?;
+        let mac = self.algorithm.mac(&self.key, &tbs)?;
+
+        Ok(tsig::make_tsig_record(
+            self.signer_name.clone(),
+            pre_tsig.set_mac(mac),
+        ))
+    }
+
+    /// Verifies the TSIG of a message after the first of a multi-message response as it was
+    ///  received, see `verify_message_byte` for the first
+    ///
+    /// # Arguments
+    ///
+    /// * `prior_mac` - the MAC of the last message which was signed
+    /// * `unsigned_messages` - the messages received since the last one which was signed
+    /// * `unsigned_message` - the message as received, up to but excluding the TSIG
+    /// * `tsig` - the TSIG record of the message
+    /// * `current_time` - seconds since the Unix epoch
+    ///
+    /// # Return
+    ///
+    /// The MAC of the message, with which the next message is verified
+    pub fn verify_subsequent_message_byte(
+        &self,
+        prior_mac: &[u8],
+        unsigned_messages: &[u8],
+        unsigned_message: &[u8],
+        tsig: &Record,
+        current_time: u64,
+    ) -> ProtoResult<Vec<u8>> {
+        let rdata = self.tsig_rdata(tsig)?;
+
+        let tbs = tsig::signed_subsequent_message_tbs(
+            prior_mac,
+            unsigned_messages,
+            unsigned_message,
+            tsig,
+        )?;
+        self.verify_tbs(&tbs, rdata, current_time)
+    }
+
+    fn tsig_rdata<'r>(&self, tsig: &'r Record) -> ProtoResult<&'r TSIG> {
+        let rdata = match *tsig.rdata() {
+            RData::DNSSEC(DNSSECRData::TSIG(ref rdata)) => rdata,
+            _ => return Err(ProtoErrorKind::Message("not a TSIG record").into()),
+        };
+
+        if *tsig.name() != self.signer_name || *rdata.algorithm() != self.algorithm {
+            return Err(ProtoErrorKind::Tsig(ResponseCode::BADKEY).into());
+        }
+
+        Ok(rdata)
+    }
+
+    fn verify_tbs(&self, tbs: &[u8], rdata: &TSIG, current_time: u64) -> ProtoResult<Vec<u8>> {
+        // RFC 8945, the MAC is verified before the time, a stale message with a forged MAC is
+        //  BADSIG, which is not signed
+        self.algorithm
+            .verify_mac(&self.key, tbs, rdata.mac())
+            .map_err(|_| ProtoErrorKind::Tsig(ResponseCode::BADSIG))?;
+
+        let skew = if current_time > rdata.time() {
+            current_time - rdata.time()
+        } else {
+            rdata.time() - current_time
+        };
+        if skew > rdata.fudge() as u64 {
+            return Err(ProtoErrorKind::Tsig(ResponseCode::BADTIME).into());
+        }
+
+        Ok(rdata.mac().to_vec())
+    }
+}
+
+impl MessageFinalizer for TSigner {
+    fn finalize_message(&self, message: &Message, current_time: u32) -> ProtoResult<Vec<Record>> {
+        debug!("signing message with TSIG: {:?}", message);
+        Ok(vec![self.sign_message(message, None, current_time as u64)?])
+    }
+
+    /// All messages are signed, not only Updates
+    fn should_finalize_message(&self, _: &Message) -> bool {
+        true
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Writes records in the master file format, which `Parser` reads
+
+use std::io::{self, Write};
+use std::str;
+
+use rr::{RData, Record, RecordType};
+
+/// Writes the records of a zone in the master file format, see `Parser`
+///
+/// Every record is written on its own line, with its fully qualified name, TTL, and class. The
+///  SOA is written first. Only the record types which are read by `Parser` from their text
+///  presentation are supported, i.e. A, AAAA, CNAME, MX, NS, PTR, SOA, SRV, and TXT.
+#[derive(Default)]
+pub struct Emitter;
+
+impl Emitter {
+    /// Returns a new Zone file emitter
+    pub fn new() -> Self {
+        Emitter
+    }
+
+    /// Writes the records, an error is returned for records which can not be written
+    pub fn emit<'r, W, I>(&self, writer: &mut W, records: I) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = &'r Record>,
+    {
+        let (soa, others): (Vec<&Record>, Vec<&Record>) = records
+            .into_iter()
+            .partition(|record| record.rr_type() == RecordType::SOA);
+
+        for record in soa.into_iter().chain(others) {
+            let rdata = Self::rdata(record.rdata())?;
+            let class: &'static str = record.dns_class().into();
+            writeln!(
+                writer,
+                "{} {} {} {} {}",
+                record.name().to_ascii(),
+                record.ttl(),
+                class,
+                record.rr_type(),
+                rdata
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn rdata(rdata: &RData) -> io::Result<String> {
+        let text = match *rdata {
+            RData::A(ref address) => address.to_string(),
+            RData::AAAA(ref address) => address.to_string(),
+            RData::CNAME(ref name) | RData::NS(ref name) | RData::PTR(ref name) => name.to_ascii(),
+            RData::MX(ref mx) => format!("{} {}", mx.preference(), mx.exchange().to_ascii()),
+            RData::SOA(ref soa) => format!(
+                "{} {} {} {} {} {} {}",
+                soa.mname().to_ascii(),
+                soa.rname().to_ascii(),
+                soa.serial(),
+                soa.refresh(),
+                soa.retry(),
+                soa.expire(),
+                soa.minimum()
+            ),
+            RData::SRV(ref srv) => format!(
+                "{} {} {} {}",
+                srv.priority(),
+                srv.weight(),
+                srv.port(),
+                srv.target().to_ascii()
+            ),
+            RData::TXT(ref txt) => txt
+                .txt_data()
+                .iter()
+                .map(|data| quote(data))
+                .collect::<Vec<String>>()
+                .join(" "),
+            ref rdata => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "record type not supported in master files: {}",
+                        rdata.to_record_type()
+                    ),
+                ))
+            }
+        };
+
+        Ok(text)
+    }
+}
+
+// a character-string in quotes, with quotes and backslashes escaped
+//
+// text is written as is, the lexer takes any character in quotes verbatim; only strings which are
+//  not UTF-8, and so are not read from master files, are written with \DDD escaped octets
+fn quote(data: &[u8]) -> String {
+    let mut quoted = String::with_capacity(data.len() + 2);
+    quoted.push('"');
+    match str::from_utf8(data) {
+        Ok(text) => for ch in text.chars() {
+            if ch == '"' || ch == '\\' {
+                quoted.push('\\');
+            }
+            quoted.push(ch);
+        },
+        Err(_) => for &octet in data {
+            match octet {
+                b'"' | b'\\' => {
+                    quoted.push('\\');
+                    quoted.push(octet as char);
+                }
+                0x20...0x7E => quoted.push(octet as char),
+                _ => quoted.push_str(&format!("\\{:03}", octet)),
+            }
+        },
+    }
+    quoted.push('"');
+    quoted
+}
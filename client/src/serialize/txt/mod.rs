@@ -16,11 +16,13 @@
 
 //! Text serialization types
 
+mod master_emit;
 mod master_lex;
 mod master;
 mod parse_rdata;
 mod rdata_parsers;
 
 pub use self::master::Parser;
+pub use self::master_emit::Emitter;
 pub use self::master_lex::Lexer;
 pub use self::master_lex::Token;
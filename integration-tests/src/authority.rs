@@ -183,18 +183,41 @@ pub fn create_example() -> Authority {
 
 #[allow(unused)]
 pub fn create_secure_example() -> Authority {
+    let mut authority: Authority = create_example();
+    let signer = example_signer(&authority);
+
+    authority.add_secure_key(signer);
+    authority.secure_zone();
+
+    authority
+}
+
+/// The example zone with DNSSEC enabled, it is signed again after each update
+#[allow(unused)]
+pub fn create_signed_example() -> Authority {
+    let example: Authority = create_example();
+    let mut authority = Authority::new(
+        example.origin().clone().into(),
+        example.records().clone(),
+        ZoneType::Master,
+        false,
+        true,
+    );
+    let signer = example_signer(&authority);
+
+    authority.add_secure_key(signer);
+    authority.secure_zone().unwrap();
+
+    authority
+}
+
+fn example_signer(authority: &Authority) -> ::trust_dns::rr::dnssec::Signer {
     use chrono::Duration;
     use openssl::rsa::Rsa;
     use trust_dns::rr::dnssec::*;
 
-    let mut authority: Authority = create_example();
     let rsa = Rsa::generate(2048).unwrap();
     let key = KeyPair::from_rsa(rsa).unwrap();
     let dnskey = key.to_dnskey(Algorithm::RSASHA256).unwrap();
-    let signer = Signer::dnssec(dnskey, key, authority.origin().clone().into(), Duration::weeks(1));
-
-    authority.add_secure_key(signer);
-    authority.secure_zone();
-
-    authority
+    Signer::dnssec(dnskey, key, authority.origin().clone().into(), Duration::weeks(1))
 }
@@ -31,6 +31,7 @@ use trust_dns_server::server::{Request, RequestHandler, ResponseHandler};
 
 pub mod authority;
 pub mod mock_client;
+pub mod mock_server;
 
 #[allow(unused)]
 pub struct TestClientStream {
@@ -0,0 +1,84 @@
+//! A server on the loopback address, answering queries over UDP or TCP as a test scripts it
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::thread;
+
+use trust_dns::op::Message;
+use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
+
+/// Starts a server which answers `queries` queries with the responses of `respond`, and then
+///  stops, so further queries time out
+///
+/// The id and the queries of the request are copied to the response.
+pub fn mock_server<F>(queries: usize, mut respond: F) -> SocketAddr
+where
+    F: FnMut(&Message) -> Message + Send + 'static,
+{
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    thread::Builder::new()
+        .name("thread_mock_server".to_string())
+        .spawn(move || {
+            let mut buffer = [0u8; 4096];
+            for _ in 0..queries {
+                let (len, src) = socket.recv_from(&mut buffer).unwrap();
+                let request = Message::from_bytes(&buffer[..len]).unwrap();
+                let response = answer(&request, &mut respond);
+
+                socket.send_to(&response, src).unwrap();
+            }
+        })
+        .unwrap();
+
+    addr
+}
+
+/// Starts a server which accepts `connections` connections over TCP, one after the other, and
+///  answers the queries on each with the responses of `respond`, until the client closes it
+pub fn mock_tcp_server<F>(connections: usize, mut respond: F) -> SocketAddr
+where
+    F: FnMut(&Message) -> Message + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::Builder::new()
+        .name("thread_mock_tcp_server".to_string())
+        .spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                let mut len = [0u8; 2];
+                while stream.read_exact(&mut len).is_ok() {
+                    let mut buffer = vec![0u8; (len[0] as usize) << 8 | len[1] as usize];
+                    stream.read_exact(&mut buffer).unwrap();
+                    let request = Message::from_bytes(&buffer).unwrap();
+                    let response = answer(&request, &mut respond);
+
+                    stream
+                        .write_all(&[(response.len() >> 8) as u8, response.len() as u8])
+                        .unwrap();
+                    stream.write_all(&response).unwrap();
+                }
+            }
+        })
+        .unwrap();
+
+    addr
+}
+
+/// The response of `respond`, with the id and the queries of the request
+fn answer<F>(request: &Message, respond: &mut F) -> Vec<u8>
+where
+    F: FnMut(&Message) -> Message,
+{
+    let mut response = respond(request);
+    response.set_id(request.id());
+    if response.queries().is_empty() {
+        response.add_queries(request.queries().iter().cloned());
+    }
+
+    response.to_bytes().unwrap()
+}
@@ -6,6 +6,7 @@ extern crate trust_dns_server;
 use std::collections::BTreeMap;
 use std::net::*;
 use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
 
 use rusqlite::*;
 
@@ -16,8 +17,9 @@ use trust_dns::rr::rdata::*;
 use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
 
 use trust_dns_server::authority::*;
+use trust_dns_server::server::ClientIdentity;
 
-use trust_dns_integration::authority::{create_example, create_secure_example};
+use trust_dns_integration::authority::{create_example, create_secure_example, create_signed_example};
 
 #[test]
 fn test_search() {
@@ -62,6 +64,66 @@ fn test_search_www() {
     }
 }
 
+#[test]
+fn test_search_cname() {
+    let mut example = create_example();
+    let serial = example.serial();
+    let cnames = [
+        ("alias.example.com.", "www.example.com."),
+        ("alias2.example.com.", "alias.example.com."),
+        ("external.example.com.", "www.example.net."),
+        ("loop1.example.com.", "loop2.example.com."),
+        ("loop2.example.com.", "loop1.example.com."),
+    ];
+    for &(name, target) in cnames.iter() {
+        example.upsert(
+            Record::new()
+                .set_name(Name::parse(name, None).unwrap())
+                .set_ttl(86400)
+                .set_rr_type(RecordType::CNAME)
+                .set_dns_class(DNSClass::IN)
+                .set_rdata(RData::CNAME(Name::parse(target, None).unwrap()))
+                .clone(),
+            serial,
+        );
+    }
+
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("alias2.example.com.", None).unwrap());
+
+    let result = example.search(&query.into(), false, SupportedAlgorithms::new());
+    let result: Vec<&Record> = result.unwrap();
+    assert_eq!(result.len(), 3);
+    assert_eq!(result[0].name(), &Name::parse("alias2.example.com.", None).unwrap());
+    assert_eq!(result[1].name(), &Name::parse("alias.example.com.", None).unwrap());
+    assert_eq!(result[2].name(), &Name::parse("www.example.com.", None).unwrap());
+    assert_eq!(result[2].rdata(), &RData::A(Ipv4Addr::new(93, 184, 216, 34)));
+
+    // the CNAME itself is returned for CNAME queries
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("alias2.example.com.", None).unwrap());
+    query.set_query_type(RecordType::CNAME);
+
+    let result = example.search(&query.into(), false, SupportedAlgorithms::new());
+    assert_eq!(result.unwrap().len(), 1);
+
+    // targets outside of the zone are left to the client
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("external.example.com.", None).unwrap());
+
+    let result = example.search(&query.into(), false, SupportedAlgorithms::new());
+    let result: Vec<&Record> = result.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].rr_type(), RecordType::CNAME);
+
+    // loops end
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("loop1.example.com.", None).unwrap());
+
+    let result = example.search(&query.into(), false, SupportedAlgorithms::new());
+    assert!(result.unwrap().iter().all(|record| record.rr_type() == RecordType::CNAME));
+}
+
 #[test]
 fn test_authority() {
     let authority: Authority = create_example();
@@ -179,6 +241,33 @@ fn test_authorize() {
     // assert!(authority.authorize(&message).is_ok());
 }
 
+#[test]
+fn test_authorize_client_certificate() {
+    let mut authority: Authority = create_example();
+    authority.set_allow_update(true);
+    authority.add_update_certificate(Name::from_str("agent.example.com.").unwrap());
+
+    let mut message = Message::new();
+    message
+        .set_id(10)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Update);
+
+    let bytes = message.to_bytes().unwrap();
+    let message = MessageRequest::from_bytes(&bytes).unwrap();
+
+    let agent = ClientIdentity::new(vec![Name::from_str("Agent.Example.Com.").unwrap()]);
+    let other = ClientIdentity::new(vec![Name::from_str("other.example.com.").unwrap()]);
+
+    assert!(authority.authorize_signer(None, Some(&agent)).is_ok());
+    assert_eq!(
+        authority.authorize_signer(None, Some(&other)),
+        Err(ResponseCode::Refused)
+    );
+    // without a certificate the update must be signed
+    assert_eq!(authority.authorize(&message), Err(ResponseCode::Refused));
+}
+
 #[test]
 fn test_prerequisites() {
     let not_zone = Name::from_str("not.a.domain.com").unwrap();
@@ -968,6 +1057,319 @@ fn test_get_nsec() {
     }
 }
 
+#[test]
+fn test_wildcard() {
+    let mut authority: Authority = create_example();
+    let serial = authority.serial();
+    authority.upsert(
+        Record::new()
+            .set_name(Name::from_str("*.wildcard.example.com").unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 1)))
+            .clone(),
+        serial,
+    );
+    authority.upsert(
+        Record::new()
+            .set_name(Name::from_str("a.b.wildcard.example.com").unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 2)))
+            .clone(),
+        serial,
+    );
+
+    let host = Name::from_str("host.wildcard.example.com").unwrap();
+    let results = authority
+        .expand_wildcard(&host.clone().into(), RecordType::A, false, SupportedAlgorithms::new())
+        .expect("host should be synthesized");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name(), &host);
+    assert_eq!(results[0].rdata(), &RData::A(Ipv4Addr::new(10, 0, 0, 1)));
+
+    // more than one label below the closest encloser
+    let deep = Name::from_str("x.y.wildcard.example.com").unwrap();
+    let results = authority
+        .expand_wildcard(&deep.clone().into(), RecordType::A, false, SupportedAlgorithms::new())
+        .expect("deep should be synthesized");
+    assert_eq!(results[0].name(), &deep);
+
+    // the wildcard exists, but not with the type
+    assert!(
+        authority
+            .expand_wildcard(&host.into(), RecordType::AAAA, false, SupportedAlgorithms::new())
+            .expect("wildcard should exist")
+            .is_empty()
+    );
+
+    // empty non-terminals exist, and block the wildcard for the names below them
+    let empty = Name::from_str("b.wildcard.example.com").unwrap();
+    assert!(
+        authority
+            .expand_wildcard(&empty.into(), RecordType::A, false, SupportedAlgorithms::new())
+            .is_none()
+    );
+    let below_empty = Name::from_str("x.b.wildcard.example.com").unwrap();
+    assert_eq!(
+        authority.closest_encloser(&below_empty.clone().into()),
+        LowerName::from(Name::from_str("b.wildcard.example.com").unwrap())
+    );
+    assert!(
+        authority
+            .expand_wildcard(&below_empty.into(), RecordType::A, false, SupportedAlgorithms::new())
+            .is_none()
+    );
+
+    // no wildcard at the closest encloser
+    let none = Name::from_str("none.example.com").unwrap();
+    assert!(
+        authority
+            .expand_wildcard(&none.into(), RecordType::A, false, SupportedAlgorithms::new())
+            .is_none()
+    );
+}
+
+#[test]
+fn test_secure_wildcard() {
+    let mut authority: Authority = create_secure_example();
+    let serial = authority.serial();
+    authority.upsert(
+        Record::new()
+            .set_name(Name::from_str("*.wildcard.example.com").unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 1)))
+            .clone(),
+        serial,
+    );
+    authority.secure_zone().expect("failed to sign zone");
+
+    let host = Name::from_str("host.wildcard.example.com").unwrap();
+    let results = authority
+        .expand_wildcard(&host.clone().into(), RecordType::A, true, SupportedAlgorithms::all())
+        .expect("host should be synthesized");
+
+    let rrsig = results
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::DNSSEC(DNSSECRData::SIG(ref sig)) => Some((record, sig)),
+            _ => None,
+        })
+        .next()
+        .expect("RRSIG missing");
+    assert_eq!(rrsig.0.name(), &host);
+    assert_eq!(rrsig.1.type_covered(), RecordType::A);
+    // the labels of the wildcard, not the query name
+    assert_eq!(rrsig.1.num_labels(), 3);
+
+    // the NSEC covering the query name, which proves it does not exist
+    let nsecs = authority.get_nsec_records(&host.clone().into(), true, SupportedAlgorithms::all());
+    let nsec = nsecs
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::DNSSEC(DNSSECRData::NSEC(ref nsec)) => Some((record, nsec)),
+            _ => None,
+        })
+        .next()
+        .expect("NSEC missing");
+    assert!(nsec.0.name() < &host);
+    assert!(nsec.1.next_domain_name() > &host);
+}
+
+#[test]
+fn test_secure_subnet() {
+    let mut authority: Authority = create_secure_example();
+    let origin = authority.origin().clone();
+    let internal = Record::new()
+        .set_name(origin.clone().into())
+        .set_ttl(86400)
+        .set_rr_type(RecordType::A)
+        .set_dns_class(DNSClass::IN)
+        .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 1)))
+        .clone();
+    authority.add_subnet(
+        vec![(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8)],
+        vec![internal],
+    );
+    authority.secure_zone().expect("failed to sign zone");
+
+    // the variant is signed with the zone
+    let client = opt::ClientSubnet::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 0)), 24, 0);
+    let (variant, scope) = authority.subnets().lookup(&origin, RecordType::A, &client);
+    assert_eq!(scope, 8);
+    let records = variant
+        .expect("variant missing")
+        .records(true, SupportedAlgorithms::all());
+    assert_eq!(records[0].rdata(), &RData::A(Ipv4Addr::new(10, 0, 0, 1)));
+
+    let rrsig = records
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::DNSSEC(DNSSECRData::SIG(ref sig)) => Some(sig),
+            _ => None,
+        })
+        .next()
+        .expect("RRSIG missing");
+    assert_eq!(rrsig.type_covered(), RecordType::A);
+}
+
+#[test]
+fn test_secure_referral() {
+    let mut authority: Authority = create_secure_example();
+    let serial = authority.serial();
+    let cut = Name::from_str("sub.example.com").unwrap();
+    authority.upsert(
+        Record::new()
+            .set_name(cut.clone())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::NS)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::NS(Name::from_str("ns.sub.example.com").unwrap()))
+            .clone(),
+        serial,
+    );
+    authority.upsert(
+        Record::new()
+            .set_name(Name::from_str("ns.sub.example.com").unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 53)))
+            .clone(),
+        serial,
+    );
+    authority.secure_zone().expect("failed to sign zone");
+
+    assert_eq!(
+        authority.delegation(&Name::from_str("www.sub.example.com").unwrap().into()),
+        Some(cut.clone().into())
+    );
+    assert_eq!(
+        authority.delegation(&Name::from_str("www.example.com").unwrap().into()),
+        None
+    );
+
+    // the NS records at the cut, and the glue, are not signed, nor are there NSEC records for glue
+    assert!(
+        authority
+            .lookup(&cut.clone().into(), RecordType::NS, true, SupportedAlgorithms::all())
+            .iter()
+            .all(|record| record.rr_type() == RecordType::NS)
+    );
+    let glue = Name::from_str("ns.sub.example.com").unwrap();
+    assert!(
+        authority
+            .lookup(&glue.clone().into(), RecordType::A, true, SupportedAlgorithms::all())
+            .iter()
+            .all(|record| record.rr_type() == RecordType::A)
+    );
+    assert!(
+        authority
+            .lookup(
+                &glue.into(),
+                RecordType::DNSSEC(DNSSECRecordType::NSEC),
+                true,
+                SupportedAlgorithms::all()
+            )
+            .is_empty()
+    );
+
+    // without DS records, the NSEC at the cut proves that the child is not signed
+    let referral = authority.referral(&cut.clone().into(), true, SupportedAlgorithms::all());
+    let nsec = referral
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::DNSSEC(DNSSECRData::NSEC(ref nsec)) => Some((record, nsec)),
+            _ => None,
+        })
+        .next()
+        .expect("NSEC missing");
+    assert_eq!(nsec.0.name(), &cut);
+    assert!(nsec.1.type_bit_maps().contains(&RecordType::NS));
+    assert!(
+        !nsec.1
+            .type_bit_maps()
+            .contains(&RecordType::DNSSEC(DNSSECRecordType::DS))
+    );
+}
+
+#[test]
+fn test_nsec3() {
+    let mut authority: Authority = create_secure_example();
+    let salt = vec![0xAA, 0xBB, 0xCC, 0xDD];
+    authority.set_nsec3(Some(NSEC3PARAM::new(
+        Nsec3HashAlgorithm::SHA1,
+        false,
+        12,
+        salt.clone(),
+    )));
+    authority.secure_zone().expect("failed to sign zone");
+
+    let origin: Name = authority.origin().clone().into();
+    let nsec3 = RecordType::DNSSEC(DNSSECRecordType::NSEC3);
+    let hashed_owner = |name: &Name| {
+        Nsec3HashAlgorithm::SHA1
+            .hashed_owner_name(&salt, name, 12, &origin)
+            .unwrap()
+    };
+
+    // the NSEC chain is replaced
+    assert!(
+        !authority
+            .lookup(
+                &origin.clone().into(),
+                RecordType::DNSSEC(DNSSECRecordType::NSEC3PARAM),
+                false,
+                SupportedAlgorithms::new()
+            )
+            .is_empty()
+    );
+    assert!(
+        authority
+            .lookup(
+                &origin.clone().into(),
+                RecordType::DNSSEC(DNSSECRecordType::NSEC),
+                false,
+                SupportedAlgorithms::new()
+            )
+            .is_empty()
+    );
+
+    // the types at a name which exists
+    let www = Name::from_str("www.example.com").unwrap();
+    let results = authority.get_nsec_records(&www.clone().into(), true, SupportedAlgorithms::all());
+    let nsec3s: Vec<&Record> = results.iter().filter(|r| r.rr_type() == nsec3).cloned().collect();
+    assert_eq!(nsec3s.len(), 1);
+    assert_eq!(nsec3s[0].name(), &hashed_owner(&www));
+    if let RData::DNSSEC(DNSSECRData::NSEC3(ref rdata)) = *nsec3s[0].rdata() {
+        assert!(rdata.type_bit_maps().contains(&RecordType::A));
+        assert!(rdata.type_bit_maps().contains(&RecordType::DNSSEC(DNSSECRecordType::RRSIG)));
+    } else {
+        panic!("not an NSEC3 record");
+    }
+    assert!(results.iter().any(|r| r.rr_type() == RecordType::DNSSEC(DNSSECRecordType::RRSIG)));
+
+    // the closest encloser proof of a name which does not exist, the hashes are ordered:
+    //  H(www.example.com) < H(zzz.example.com) < H(example.com)
+    let zzz = Name::from_str("zzz.example.com").unwrap();
+    let results = authority.get_nsec_records(&zzz.into(), false, SupportedAlgorithms::all());
+    assert_eq!(results.len(), 2);
+    // matches the closest encloser
+    assert_eq!(results[0].name(), &hashed_owner(&origin));
+    // covers the next closer name
+    assert_eq!(results[1].name(), &hashed_owner(&www));
+    if let RData::DNSSEC(DNSSECRData::NSEC3(ref rdata)) = *results[1].rdata() {
+        let origin_hash = Nsec3HashAlgorithm::SHA1.hash(&salt, &origin, 12).unwrap();
+        assert_eq!(rdata.next_hashed_owner_name(), origin_hash.as_ref());
+    } else {
+        panic!("not an NSEC3 record");
+    }
+}
+
 #[test]
 fn test_journal() {
     // test that this message can be inserted
@@ -1035,6 +1437,66 @@ fn test_journal() {
     assert!(delete_rrset.is_empty());
 }
 
+#[test]
+fn test_transfer() {
+    let conn = Connection::open_in_memory().expect("could not create in memory DB");
+    let mut journal = Journal::new(conn).unwrap();
+    journal.schema_up().unwrap();
+
+    let example = create_example();
+    let mut authority = Authority::new(
+        example.origin().clone().into(),
+        BTreeMap::new(),
+        ZoneType::Slave,
+        false,
+        false,
+    );
+    authority.set_journal(journal);
+
+    let inside = Record::new()
+        .set_name(Name::from_str("new.example.com.").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 13)))
+        .clone();
+    let outside = Record::new()
+        .set_name(Name::from_str("www.example.net.").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 14)))
+        .clone();
+    let soa = example.soa().iter().next().unwrap().clone();
+    authority
+        .transfer(vec![soa, inside.clone(), outside.clone()])
+        .unwrap();
+
+    // the master may only transfer records of the zone
+    assert_eq!(authority.serial(), example.serial());
+    assert!(authority
+        .records()
+        .values()
+        .flat_map(|rr_set| rr_set.iter())
+        .any(|r| *r == inside));
+    assert!(!authority
+        .records()
+        .values()
+        .flat_map(|rr_set| rr_set.iter())
+        .any(|r| r.name() == outside.name()));
+
+    // the time of the refresh is recovered with the zone
+    let refreshed = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    authority.set_refreshed(refreshed).unwrap();
+
+    let mut recovered_authority = Authority::new(
+        authority.origin().clone().into(),
+        BTreeMap::new(),
+        ZoneType::Slave,
+        false,
+        false,
+    );
+    recovered_authority
+        .recover_with_journal(authority.journal().expect("journal not Some"))
+        .expect("recovery");
+    assert_eq!(recovered_authority.serial(), example.serial());
+    assert_eq!(recovered_authority.refreshed(), Some(refreshed));
+}
+
 #[test]
 fn test_recovery() {
     // test that this message can be inserted
@@ -1092,3 +1554,197 @@ fn test_recovery() {
             })
     }));
 }
+
+#[test]
+fn test_ixfr() {
+    let conn = Connection::open_in_memory().expect("could not create in memory DB");
+    let mut journal = Journal::new(conn).unwrap();
+    journal.schema_up().unwrap();
+
+    let mut authority = create_example();
+    authority.set_journal(journal);
+    authority.persist_to_journal().unwrap();
+    let old_serial = authority.serial();
+
+    let new_record = Record::new()
+        .set_name(Name::from_str("new.example.com").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 13)))
+        .clone();
+    let mut delete_record = Record::new()
+        .set_name(Name::from_str("www.example.com").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(93, 184, 216, 34)))
+        .set_dns_class(DNSClass::NONE)
+        .clone();
+    authority
+        .update_records(&[new_record.clone(), delete_record.clone()], true)
+        .unwrap();
+    let new_serial = authority.serial();
+    assert!(new_serial > old_serial);
+
+    let soa = authority.soa().iter().next().unwrap().clone();
+    let ixfr = authority.ixfr(old_serial).expect("ixfr");
+    assert_eq!(ixfr.len(), 6);
+    assert_eq!(ixfr[0], soa);
+    if let RData::SOA(ref old_soa) = *ixfr[1].rdata() {
+        assert_eq!(old_soa.serial(), old_serial);
+    } else {
+        panic!("expected the old SOA");
+    }
+    assert_eq!(ixfr[2], delete_record.set_dns_class(DNSClass::IN).clone());
+    assert_eq!(ixfr[3], soa);
+    assert_eq!(ixfr[4], new_record);
+    assert_eq!(ixfr[5], soa);
+
+    // up to date, only the SOA
+    assert_eq!(authority.ixfr(new_serial).expect("ixfr"), vec![soa.clone()]);
+
+    // no history, a full transfer is required
+    assert!(authority.ixfr(old_serial - 1).is_none());
+
+    // each update is a difference sequence
+    let newer_record = Record::new()
+        .set_name(Name::from_str("newer.example.com").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 14)))
+        .clone();
+    authority
+        .update_records(&[newer_record.clone()], true)
+        .unwrap();
+
+    let newer_soa = authority.soa().iter().next().unwrap().clone();
+    let ixfr = authority.ixfr(old_serial).expect("ixfr");
+    assert_eq!(ixfr.len(), 9);
+    assert_eq!(ixfr[0], newer_soa);
+    assert_eq!(ixfr[3], soa);
+    assert_eq!(ixfr[5], soa);
+    assert_eq!(ixfr[6], newer_soa);
+    assert_eq!(ixfr[7], newer_record);
+    assert_eq!(ixfr[8], newer_soa);
+    assert_eq!(authority.ixfr(new_serial).expect("ixfr").len(), 5);
+
+    // the differences are recovered from the journal
+    let mut recovered_authority = Authority::new(
+        authority.origin().clone().into(),
+        BTreeMap::new(),
+        ZoneType::Master,
+        false,
+        false,
+    );
+    recovered_authority
+        .recover_with_journal(authority.journal().expect("journal not Some"))
+        .expect("recovery");
+    assert_eq!(recovered_authority.ixfr(old_serial), Some(ixfr));
+
+    // only the last updates are kept
+    authority.set_ixfr_depth(1);
+    assert!(authority.ixfr(old_serial).is_none());
+    assert_eq!(authority.ixfr(new_serial).expect("ixfr").len(), 5);
+}
+
+#[test]
+fn test_ixfr_signed() {
+    use trust_dns::rr::rdata::DNSSECRecordType;
+
+    let conn = Connection::open_in_memory().expect("could not create in memory DB");
+    let mut journal = Journal::new(conn).unwrap();
+    journal.schema_up().unwrap();
+
+    let mut authority = create_signed_example();
+    authority.set_journal(journal);
+    authority.persist_to_journal().unwrap();
+    let old_serial = authority.serial();
+
+    // the records of the zone, with the signatures
+    let zone = |authority: &Authority| -> Vec<Record> {
+        let mut records: Vec<Record> = authority
+            .records()
+            .values()
+            .flat_map(|rr_set| rr_set.iter().chain(rr_set.rrsigs()))
+            .cloned()
+            .collect();
+        records.sort();
+        records
+    };
+    let old_zone = zone(&authority);
+
+    let new_record = Record::new()
+        .set_name(Name::from_str("new.example.com").unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 13)))
+        .clone();
+    authority
+        .update_records(&[new_record.clone()], true)
+        .unwrap();
+    let new_serial = authority.serial();
+    assert!(new_serial > old_serial);
+
+    let soa = authority.soa().iter().next().unwrap().clone();
+    let ixfr = authority.ixfr(old_serial).expect("ixfr");
+    assert_eq!(ixfr.first(), Some(&soa));
+    assert_eq!(ixfr.last(), Some(&soa));
+    if let RData::SOA(ref old_soa) = *ixfr[1].rdata() {
+        assert_eq!(old_soa.serial(), old_serial);
+    } else {
+        panic!("expected the old SOA");
+    }
+
+    // the added records start with the new SOA
+    let added_at = ixfr[2..]
+        .iter()
+        .position(|record| *record == soa)
+        .expect("no added records")
+        + 2;
+    let deleted = &ixfr[1..added_at];
+    let added = &ixfr[added_at..ixfr.len() - 1];
+
+    let is_rrsig_of = |record: &Record, name: &str, covered: RecordType| {
+        record.name() == &Name::from_str(name).unwrap() && match *record.rdata() {
+            RData::DNSSEC(DNSSECRData::SIG(ref sig)) => sig.type_covered() == covered,
+            _ => false,
+        }
+    };
+    let is_nsec = |record: &Record, name: &str| {
+        record.name() == &Name::from_str(name).unwrap()
+            && record.rr_type() == RecordType::DNSSEC(DNSSECRecordType::NSEC)
+    };
+
+    // the new record, its signature and NSEC, and the signature of the new SOA are added
+    assert!(added.contains(&new_record));
+    assert!(
+        added
+            .iter()
+            .any(|r| is_rrsig_of(r, "new.example.com.", RecordType::A))
+    );
+    assert!(added.iter().any(|r| is_nsec(r, "new.example.com.")));
+    assert!(
+        added
+            .iter()
+            .any(|r| is_rrsig_of(r, "example.com.", RecordType::SOA))
+    );
+    // the signature of the old SOA is deleted
+    assert!(
+        deleted
+            .iter()
+            .any(|r| is_rrsig_of(r, "example.com.", RecordType::SOA))
+    );
+
+    // applying the difference sequence to the old zone results in the new zone
+    let mut applied: Vec<Record> = old_zone
+        .into_iter()
+        .filter(|record| !deleted.contains(record))
+        .collect();
+    applied.extend(added.iter().cloned());
+    applied.sort();
+    assert_eq!(applied, zone(&authority));
+
+    // the differences are recovered from the journal
+    let mut recovered_authority = Authority::new(
+        authority.origin().clone().into(),
+        BTreeMap::new(),
+        ZoneType::Master,
+        false,
+        true,
+    );
+    recovered_authority
+        .recover_with_journal(authority.journal().expect("journal not Some"))
+        .expect("recovery");
+    assert_eq!(recovered_authority.ixfr(old_serial), Some(ixfr));
+}
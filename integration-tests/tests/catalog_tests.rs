@@ -1,9 +1,16 @@
+extern crate futures;
+extern crate tokio_core;
 extern crate trust_dns;
 extern crate trust_dns_integration;
 extern crate trust_dns_server;
 
 use std::net::*;
 use std::collections::*;
+use std::sync::Arc;
+
+use futures::Stream;
+use futures::sync::mpsc::unbounded;
+use tokio_core::reactor::Core;
 
 use trust_dns::op::*;
 use trust_dns::rr::*;
@@ -11,9 +18,12 @@ use trust_dns::rr::rdata::*;
 use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
 
 use trust_dns_server::authority::*;
+use trust_dns_server::server::{Request, RequestContext, RequestHandler, ServerCookies,
+                               TruncatingResponseHandle};
 
 use trust_dns_integration::*;
-use trust_dns_integration::authority::create_example;
+use trust_dns_integration::authority::{create_example, create_secure_example,
+                                       create_signed_example};
 
 pub fn create_test() -> Authority {
     let origin: Name = Name::parse("test.com.", None).unwrap();
@@ -258,6 +268,386 @@ fn test_catalog_nx_soa() {
     );
 }
 
+#[test]
+fn test_catalog_wildcard() {
+    let mut example = create_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("*.wildcard.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 1)))
+            .clone(),
+        serial,
+    );
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let mut question: Message = Message::new();
+
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("host.wildcard.example.com.", None).unwrap());
+
+    question.add_query(query);
+
+    // temp request
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.authoritative());
+
+    let answers: &[Record] = result.answers();
+
+    assert_eq!(answers.len(), 1);
+    assert_eq!(
+        answers.first().unwrap().name(),
+        &Name::parse("host.wildcard.example.com.", None).unwrap()
+    );
+    assert_eq!(
+        answers.first().unwrap().rdata(),
+        &RData::A(Ipv4Addr::new(10, 0, 0, 1))
+    );
+}
+
+/// Looks up the name with DNSSEC records, the response is not truncated
+fn secure_lookup(catalog: &Catalog, name: &str, query_type: RecordType) -> Message {
+    use trust_dns::rr::dnssec::SupportedAlgorithms;
+
+    let mut question: Message = Message::new();
+
+    let mut query: Query = Query::new();
+    query
+        .set_name(Name::parse(name, None).unwrap())
+        .set_query_type(query_type);
+    question.add_query(query);
+
+    let mut edns = Edns::new();
+    edns.set_dnssec_ok(true);
+    edns.set_option(opt::EdnsOption::DAU(SupportedAlgorithms::all()));
+    question.set_edns(edns);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .unwrap();
+    response_handler.into_message()
+}
+
+#[test]
+fn test_catalog_secure_wildcard() {
+    let mut example = create_secure_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("*.wildcard.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 1)))
+            .clone(),
+        serial,
+    );
+    example.secure_zone().expect("failed to sign zone");
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let nsec = RecordType::DNSSEC(DNSSECRecordType::NSEC);
+    let soa_and_nsecs = |result: &Message| {
+        assert!(result.name_servers().iter().any(|r| r.rr_type() == RecordType::SOA));
+        assert!(result.name_servers().iter().any(|r| r.rr_type() == nsec));
+    };
+
+    // synthesized from the wildcard, with the proof that the name does not exist
+    let result = secure_lookup(&catalog, "host.wildcard.example.com.", RecordType::A);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.answers().iter().any(|r| r.rr_type() == RecordType::A));
+    assert!(result.name_servers().iter().any(|r| r.rr_type() == nsec));
+
+    // the wildcard does not have the type
+    let result = secure_lookup(&catalog, "host.wildcard.example.com.", RecordType::MX);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.answers().is_empty());
+    soa_and_nsecs(&result);
+
+    // neither the name nor a matching wildcard exist
+    let result = secure_lookup(&catalog, "host.other.example.com.", RecordType::A);
+    assert_eq!(result.response_code(), ResponseCode::NXDomain);
+    assert!(result.answers().is_empty());
+    soa_and_nsecs(&result);
+}
+
+#[test]
+fn test_catalog_nsec3_nxdomain() {
+    use trust_dns::rr::dnssec::Nsec3HashAlgorithm;
+
+    let mut example = create_secure_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("mail.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 25)))
+            .clone(),
+        serial,
+    );
+    let salt = vec![0xAA, 0xBB, 0xCC, 0xDD];
+    example.set_nsec3(Some(NSEC3PARAM::new(
+        Nsec3HashAlgorithm::SHA1,
+        false,
+        12,
+        salt.clone(),
+    )));
+    example.secure_zone().expect("failed to sign zone");
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let origin: Name = origin.into();
+    let hashed_owner = |name: &str| {
+        Nsec3HashAlgorithm::SHA1
+            .hashed_owner_name(&salt, &Name::parse(name, None).unwrap(), 12, &origin)
+            .unwrap()
+    };
+
+    // the hashes are ordered:
+    //  H(mail) < H(host) < H(www) < H(*) < H(example.com)
+    let result = secure_lookup(&catalog, "host.example.com.", RecordType::A);
+    assert_eq!(result.response_code(), ResponseCode::NXDomain);
+    assert!(result.answers().is_empty());
+
+    let mut nsec3_owners: Vec<Name> = result
+        .name_servers()
+        .iter()
+        .filter(|r| r.rr_type() == RecordType::DNSSEC(DNSSECRecordType::NSEC3))
+        .map(|r| r.name().clone())
+        .collect();
+    nsec3_owners.sort();
+    let mut expected = vec![
+        // matches the closest encloser
+        hashed_owner("example.com."),
+        // covers the next closer name
+        hashed_owner("mail.example.com."),
+        // covers the wildcard at the closest encloser
+        hashed_owner("www.example.com."),
+    ];
+    expected.sort();
+    assert_eq!(nsec3_owners, expected);
+    assert!(result.name_servers().iter().any(|r| r.rr_type() == RecordType::SOA));
+}
+
+#[test]
+fn test_catalog_additionals() {
+    let mut example = create_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    example.upsert(
+        Record::new()
+            .set_name(origin.clone().into())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::MX)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::MX(MX::new(
+                10,
+                Name::parse("mail.example.com.", None).unwrap(),
+            )))
+            .clone(),
+        serial,
+    );
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("mail.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 25)))
+            .clone(),
+        serial,
+    );
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let mut question: Message = Message::new();
+
+    let mut query: Query = Query::new();
+    query.set_name(origin.into());
+    query.set_query_type(RecordType::MX);
+
+    question.add_query(query);
+
+    // temp request
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(result.answers().len(), 1);
+    assert_eq!(result.answers()[0].rr_type(), RecordType::MX);
+
+    let additionals: &[Record] = result.additionals();
+
+    assert_eq!(additionals.len(), 1);
+    assert_eq!(
+        additionals[0].name(),
+        &Name::parse("mail.example.com.", None).unwrap()
+    );
+    assert_eq!(
+        additionals[0].rdata(),
+        &RData::A(Ipv4Addr::new(10, 0, 0, 25))
+    );
+}
+
+#[test]
+fn test_catalog_referral() {
+    let mut example = create_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("sub.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::NS)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::NS(Name::parse("ns.sub.example.com.", None).unwrap()))
+            .clone(),
+        serial,
+    );
+    example.upsert(
+        Record::new()
+            .set_name(Name::parse("ns.sub.example.com.", None).unwrap())
+            .set_ttl(86400)
+            .set_rr_type(RecordType::A)
+            .set_dns_class(DNSClass::IN)
+            .set_rdata(RData::A(Ipv4Addr::new(10, 0, 0, 53)))
+            .clone(),
+        serial,
+    );
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let mut question: Message = Message::new();
+
+    let mut query: Query = Query::new();
+    query.set_name(Name::parse("www.sub.example.com.", None).unwrap());
+
+    question.add_query(query);
+
+    // temp request
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(!result.authoritative());
+    assert!(result.answers().is_empty());
+
+    let ns: &[Record] = result.name_servers();
+
+    assert_eq!(ns.len(), 1);
+    assert_eq!(
+        ns[0].rdata(),
+        &RData::NS(Name::parse("ns.sub.example.com.", None).unwrap())
+    );
+
+    let additionals: &[Record] = result.additionals();
+
+    assert_eq!(additionals.len(), 1);
+    assert_eq!(
+        additionals[0].rdata(),
+        &RData::A(Ipv4Addr::new(10, 0, 0, 53))
+    );
+}
+
+#[test]
+fn test_catalog_referral_truncated() {
+    let mut example = create_example();
+    let origin = example.origin().clone();
+    let serial = example.serial();
+    let cut = Name::parse("sub.example.com.", None).unwrap();
+    let ns = Name::parse("ns.sub.example.com.", None).unwrap();
+    example.upsert(
+        Record::from_rdata(cut, 86400, RecordType::NS, RData::NS(ns.clone())),
+        serial,
+    );
+
+    // more glue than fits in 512 bytes
+    for i in 0..40 {
+        example.upsert(
+            Record::from_rdata(
+                ns.clone(),
+                86400,
+                RecordType::A,
+                RData::A(Ipv4Addr::new(10, 0, 0, i)),
+            ),
+            serial,
+        );
+    }
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let mut question: Message = Message::new();
+    question.add_query(Query::query(
+        Name::parse("www.sub.example.com.", None).unwrap(),
+        RecordType::A,
+    ));
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let lookup = |request_max_payload: u16| {
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .lookup(
+                &question_req,
+                None,
+                TruncatingResponseHandle::new(response_handler.clone(), request_max_payload, 4096),
+            )
+            .unwrap();
+        response_handler.into_message()
+    };
+
+    // the referral can not be followed without the glue of the name server in the child zone
+    let result = lookup(512);
+    assert!(result.truncated());
+    assert_eq!(result.name_servers().len(), 1);
+    assert!(result.additionals().is_empty());
+
+    let result = lookup(4096);
+    assert!(!result.truncated());
+    assert_eq!(result.name_servers().len(), 1);
+    assert_eq!(result.additionals().len(), 40);
+}
+
 #[test]
 fn test_axfr() {
     let test = create_test();
@@ -403,3 +793,1568 @@ fn test_axfr() {
 
     assert_eq!(expected_set, answers);
 }
+
+#[test]
+fn test_ixfr_signed() {
+    let mut example = create_signed_example();
+    let origin = example.origin().clone();
+    let old_soa = example.soa().iter().next().unwrap().clone();
+
+    let new_record = Record::new()
+        .set_name(Name::parse("new.example.com.", None).unwrap())
+        .set_rdata(RData::A(Ipv4Addr::new(10, 11, 12, 13)))
+        .clone();
+    example.update_records(&[new_record.clone()], true).unwrap();
+    let ixfr = example.ixfr(example.serial() - 1).expect("ixfr");
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    // the requester has the previous version of the zone
+    let mut query: Query = Query::new();
+    query.set_name(origin.clone().into());
+    query.set_query_type(RecordType::IXFR);
+
+    let mut question: Message = Message::new();
+    question.add_query(query);
+    question.add_name_server(old_soa);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result = response_handler.into_message();
+
+    // the difference sequence with the signatures and NSEC records, not the full zone
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(result.answers(), &ixfr[..]);
+    assert!(result.answers().contains(&new_record));
+    assert!(
+        result
+            .answers()
+            .iter()
+            .any(|r| r.rr_type() == RecordType::DNSSEC(DNSSECRecordType::RRSIG))
+    );
+    assert!(
+        result
+            .answers()
+            .iter()
+            .any(|r| r.rr_type() == RecordType::DNSSEC(DNSSECRecordType::NSEC))
+    );
+}
+
+#[test]
+fn test_notify() {
+    let example = create_example();
+    let origin = example.origin().clone();
+    let master: SocketAddr = "10.0.0.1:53".parse().unwrap();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+
+    let (refresh, refreshes) = unbounded();
+    catalog.set_refresh(origin.clone().into(), &[master], refresh);
+
+    let mut notify: Message = Message::new();
+    notify.set_op_code(OpCode::Notify);
+
+    let mut query: Query = Query::new();
+    query
+        .set_name(origin.clone().into())
+        .set_query_type(RecordType::SOA);
+    notify.add_query(query);
+
+    let notify_bytes = notify.to_bytes().unwrap();
+    let notify_req = MessageRequest::from_bytes(&notify_bytes).unwrap();
+
+    // the port of the master does not matter
+    let request = Request {
+        message: notify_req,
+        src: "10.0.0.1:4053".parse().unwrap(),
+    };
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .notify(&request, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(result.op_code(), OpCode::Notify);
+    assert_eq!(result.message_type(), MessageType::Response);
+
+    // only the masters of the zone are accepted
+    let notify_req = MessageRequest::from_bytes(&notify_bytes).unwrap();
+    let request = Request {
+        message: notify_req,
+        src: "10.0.0.2:53".parse().unwrap(),
+    };
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .notify(&request, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+
+    // the zone was signaled for refresh once
+    drop(catalog);
+    assert_eq!(refreshes.wait().count(), 1);
+}
+
+#[test]
+fn test_axfr_tsig() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let tsigner = TSigner::new(
+        b"shared secret for zone transfers".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    let mut test = create_test();
+    test.add_tsig_key(tsigner.clone());
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let mut query: Query = Query::new();
+    query.set_name(origin.clone().into());
+    query.set_query_type(RecordType::AXFR);
+
+    let mut question: Message = Message::new();
+    question.set_id(10);
+    question.add_query(query);
+
+    // without TSIG the transfer is refused
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+
+    // signed with the key of the zone it is allowed
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    question.finalize(&tsigner, now as u32).unwrap();
+    let request_mac = match *question.sig0()[0].rdata() {
+        RData::DNSSEC(DNSSECRData::TSIG(ref tsig)) => tsig.mac().to_vec(),
+        _ => panic!("expected a TSIG"),
+    };
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result_bytes = response_handler.into_inner();
+    let result = Message::from_bytes(&result_bytes).unwrap();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        result.answers().first().expect("no records found?").rr_type(),
+        RecordType::SOA
+    );
+
+    // and the response is signed with the same key
+    let result_req = MessageRequest::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result_req.sig0().len(), 1);
+    tsigner
+        .verify_message_byte(
+            Some(&request_mac),
+            result_req.unsigned_bytes(),
+            &result_req.sig0()[0],
+            now,
+        )
+        .expect("response TSIG did not verify");
+
+    // signed with an unknown key the request is not authorized
+    let other = TSigner::new(
+        b"some other secret".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    let mut query: Query = Query::new();
+    query.set_name(origin.clone().into());
+    query.set_query_type(RecordType::AXFR);
+
+    let mut question: Message = Message::new();
+    question.add_query(query);
+    question.finalize(&other, now as u32).unwrap();
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result = response_handler.into_message();
+
+    // with the TSIG error, the response is not signed
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    let tsig = response_tsig(&result);
+    assert_eq!(tsig.error(), u16::from(ResponseCode::BADSIG));
+    assert!(tsig.mac().is_empty());
+}
+
+fn response_tsig(response: &Message) -> TSIG {
+    match response.sig0().last().map(|record| record.rdata()) {
+        Some(&RData::DNSSEC(DNSSECRData::TSIG(ref tsig))) => tsig.clone(),
+        _ => panic!("expected a TSIG"),
+    }
+}
+
+#[test]
+fn test_tsig_errors() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let tsigner = TSigner::new(
+        b"shared secret for zone transfers".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    let mut test = create_test();
+    test.add_tsig_key(tsigner.clone());
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let transfer = |signer: &TSigner, time: u64| {
+        let mut query: Query = Query::new();
+        query.set_name(origin.clone().into());
+        query.set_query_type(RecordType::AXFR);
+
+        let mut question: Message = Message::new();
+        question.set_id(10);
+        question.add_query(query);
+        question.finalize(signer, time as u32).unwrap();
+        question.to_bytes().unwrap()
+    };
+
+    // a key the zone does not have
+    let unknown = TSigner::new(
+        b"shared secret for zone transfers".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("unknown.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+    let question_bytes = transfer(&unknown, now);
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    assert!(result.answers().is_empty());
+    let tsig = response_tsig(&result);
+    assert_eq!(tsig.error(), u16::from(ResponseCode::BADKEY));
+    assert!(tsig.mac().is_empty());
+
+    // signed an hour ago, outside of the fudge
+    let signed = now - 3600;
+    let question_bytes = transfer(&tsigner, signed);
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+    let request_mac = response_tsig(&Message::from_bytes(&question_bytes).unwrap())
+        .mac()
+        .to_vec();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result_bytes = response_handler.into_inner();
+    let result = Message::from_bytes(&result_bytes).unwrap();
+
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    assert!(result.answers().is_empty());
+
+    // BADTIME is signed, with the time of the request, and the time of the server in other data
+    let tsig = response_tsig(&result);
+    assert_eq!(tsig.error(), u16::from(ResponseCode::BADTIME));
+    assert_eq!(tsig.time(), signed);
+    let server_time = tsig
+        .other()
+        .iter()
+        .fold(0u64, |time, byte| time << 8 | u64::from(*byte));
+    assert!(server_time >= now);
+
+    let result_req = MessageRequest::from_bytes(&result_bytes).unwrap();
+    tsigner
+        .verify_message_byte(
+            Some(&request_mac),
+            result_req.unsigned_bytes(),
+            &result_req.sig0()[0],
+            signed,
+        )
+        .expect("BADTIME response did not verify");
+
+    // a forged MAC is BADSIG even if the time is stale, and the response is not signed
+    let forged = TSigner::new(
+        b"not the shared secret".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+    let question_bytes = transfer(&forged, signed);
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .expect("lookup failed");
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    assert!(result.answers().is_empty());
+    let tsig = response_tsig(&result);
+    assert_eq!(tsig.error(), u16::from(ResponseCode::BADSIG));
+    assert!(tsig.mac().is_empty());
+}
+
+#[test]
+fn test_truncated_tsig() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let tsigner = TSigner::new(
+        b"shared secret for zone transfers".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    // more TXT records than fit in 512 bytes
+    let mut test = create_test();
+    test.add_tsig_key(tsigner.clone());
+    let www = Name::parse("www.test.com.", None).unwrap();
+    for i in 0..10 {
+        test.upsert(
+            Record::from_rdata(
+                www.clone(),
+                86400,
+                RecordType::TXT,
+                RData::TXT(TXT::new(vec![format!("{:0100}", i)])),
+            ),
+            0,
+        );
+    }
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.into(), test);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut question: Message = Message::new();
+    question.set_id(10);
+    question.add_query(Query::query(www, RecordType::TXT));
+    question.finalize(&tsigner, now as u32).unwrap();
+    let request_mac = response_tsig(&question).mac().to_vec();
+
+    let question_bytes = question.to_bytes().unwrap();
+    let request = Request {
+        message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+        src: "127.0.0.1:4053".parse().unwrap(),
+    };
+
+    let send = |context: &RequestContext| {
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request_with_context(&request, context, response_handler.clone())
+            .expect("handle_request failed");
+        let result_bytes = response_handler.into_inner();
+
+        // the TSIG covers the response as it was sent
+        {
+            let result_req = MessageRequest::from_bytes(&result_bytes).unwrap();
+            assert_eq!(result_req.sig0().len(), 1);
+            tsigner
+                .verify_message_byte(
+                    Some(&request_mac),
+                    result_req.unsigned_bytes(),
+                    &result_req.sig0()[0],
+                    now,
+                )
+                .expect("response TSIG did not verify");
+        }
+        result_bytes
+    };
+
+    // over UDP only the question and the TSIG are sent, with TC
+    let mut context = RequestContext::new();
+    context.set_max_udp_payload(1232);
+    let result_bytes = send(&context);
+    let result = Message::from_bytes(&result_bytes).unwrap();
+
+    assert!(result_bytes.len() <= 512);
+    assert!(result.truncated());
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.answers().is_empty());
+
+    // over TCP all the records are sent
+    let result_bytes = send(&RequestContext::new());
+    let result = Message::from_bytes(&result_bytes).unwrap();
+
+    assert!(!result.truncated());
+    assert_eq!(result.answers().len(), 10);
+}
+
+#[test]
+fn test_catalog_recursion_available() {
+    let mut hints = Authority::new(Name::root(), BTreeMap::new(), ZoneType::Hint, false, false);
+    let root_server = Name::parse("a.root-servers.net.", None).unwrap();
+    hints.upsert(
+        Record::from_rdata(
+            Name::root(),
+            3600000,
+            RecordType::NS,
+            RData::NS(root_server.clone()),
+        ),
+        0,
+    );
+    hints.upsert(
+        Record::from_rdata(
+            root_server.clone(),
+            3600000,
+            RecordType::A,
+            RData::A(Ipv4Addr::new(198, 41, 0, 4)),
+        ),
+        0,
+    );
+    hints.upsert(
+        Record::from_rdata(
+            root_server,
+            3600000,
+            RecordType::AAAA,
+            RData::AAAA(Ipv6Addr::new(0x2001, 0x503, 0xba3e, 0, 0, 0, 2, 0x30)),
+        ),
+        0,
+    );
+
+    let io_loop = Core::new().unwrap();
+    let recursor = Recursor::from_hints(&hints, 16, &io_loop.handle());
+    assert_eq!(
+        recursor.roots(),
+        &[
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 41, 0, 4)), 53),
+            SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0x503, 0xba3e, 0, 0, 0, 2, 0x30)),
+                53
+            ),
+        ]
+    );
+
+    let example = create_example();
+    let origin = example.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+    catalog.set_recursor(recursor);
+
+    // names in the zones are answered authoritatively, recursion is available for the others
+    let mut question: Message = Message::new();
+    question.add_query(Query::query(origin.into(), RecordType::A));
+    question.set_recursion_desired(true);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let question_req = MessageRequest::from_bytes(&question_bytes).unwrap();
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .lookup(&question_req, None, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.authoritative());
+    assert!(result.recursion_available());
+    assert!(!result.answers().is_empty());
+}
+
+#[test]
+fn test_recursion_acl() {
+    let io_loop = Core::new().unwrap();
+    let root = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 41, 0, 4)), 53);
+    let recursor = Recursor::new(vec![root], 16, &io_loop.handle());
+
+    let example = create_example();
+    let origin = example.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), example);
+    catalog.set_recursor(recursor);
+
+    let request = |name: &Name| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(name.clone(), RecordType::A));
+        question.set_recursion_desired(true);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+
+    // only clients on the loopback addresses are allowed recursion by default
+    let result = request(&Name::parse("www.example.net.", None).unwrap());
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+
+    // the zones are still answered
+    let result = request(&origin.into());
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.authoritative());
+    assert!(!result.answers().is_empty());
+}
+
+#[test]
+fn test_transfer_acl() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    // the first element which matches decides
+    let transfer = Acl::new(vec![
+        (
+            true,
+            AclElement::Network {
+                addr: "10.0.0.5".parse().unwrap(),
+                prefix_len: 32,
+            },
+        ),
+        (
+            false,
+            AclElement::Network {
+                addr: "10.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+        ),
+    ]);
+    catalog.set_acls(
+        origin.clone().into(),
+        ZoneAcls::new(Acl::any(), transfer, Acl::any()),
+    );
+
+    let request = |query_type: RecordType, src: &str| {
+        let mut query: Query = Query::new();
+        query.set_name(origin.clone().into()).set_query_type(query_type);
+
+        let mut question: Message = Message::new();
+        question.add_query(query);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: src.parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+
+    let result = request(RecordType::AXFR, "10.1.2.3:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(!result.answers().is_empty());
+
+    let result = request(RecordType::AXFR, "10.0.0.5:53");
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+
+    let result = request(RecordType::AXFR, "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+
+    // queries are still allowed from anywhere
+    let result = request(RecordType::SOA, "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(!result.answers().is_empty());
+}
+
+#[test]
+fn test_transfer_acl_default() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let mut query: Query = Query::new();
+    query
+        .set_name(origin.clone().into())
+        .set_query_type(RecordType::AXFR);
+
+    let mut question: Message = Message::new();
+    question.add_query(query);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let request = Request {
+        message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+        src: "127.0.0.1:53".parse().unwrap(),
+    };
+
+    // without a transfer ACL the zone is not transferred, even to localhost
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .handle_request(&request, response_handler.clone())
+        .expect("handle_request failed");
+    let result = response_handler.into_message();
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+}
+
+#[test]
+fn test_acl_ipv4_mapped() {
+    // a negated network, as in `!10.0.0.0/8; any;`
+    let acl = Acl::new(vec![
+        (
+            true,
+            AclElement::Network {
+                addr: "10.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+        ),
+        (false, AclElement::Any),
+    ]);
+
+    // IPv4 clients of dual-stack sockets have IPv4-mapped addresses
+    assert!(!acl.allows("10.1.2.3".parse().unwrap(), None, None));
+    assert!(!acl.allows("::ffff:10.1.2.3".parse().unwrap(), None, None));
+    assert!(acl.allows("::ffff:192.0.2.1".parse().unwrap(), None, None));
+    assert!(acl.allows("2001:db8::a01:203".parse().unwrap(), None, None));
+
+    // IPv4-compatible addresses are not IPv4 clients
+    assert!(acl.allows("::10.1.2.3".parse().unwrap(), None, None));
+
+    // and the loopback of IPv4 clients is localhost
+    assert!(Acl::localhost().allows("::ffff:127.0.0.1".parse().unwrap(), None, None));
+}
+
+#[test]
+fn test_reload_adopts_unchanged_zones() {
+    let example = create_example();
+    let example_origin: LowerName = example.origin().clone();
+    let test = create_test();
+    let test_origin: LowerName = test.origin().clone();
+    let master: SocketAddr = "10.0.0.1:53".parse().unwrap();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(example_origin.clone(), example);
+    catalog.upsert(test_origin.clone(), test);
+
+    let (refresh, refreshes) = unbounded();
+    catalog.set_refresh(example_origin.clone(), &[master], refresh);
+
+    let reloadable = ReloadableCatalog::new(catalog);
+
+    // the reloaded configuration only has the example zone
+    let mut reloaded: Catalog = Catalog::new();
+    {
+        let previous = reloadable.catalog();
+        assert!(reloaded.adopt(&previous, &example_origin));
+        let removed: LowerName = Name::parse("removed.example.", None).unwrap().into();
+        assert!(!reloaded.adopt(&previous, &removed));
+        assert!(::std::sync::Arc::ptr_eq(
+            previous.get(&example_origin).unwrap(),
+            reloaded.get(&example_origin).unwrap()
+        ));
+    }
+    drop(reloadable.replace(reloaded));
+
+    assert!(reloadable.catalog().get(&example_origin).is_some());
+    assert!(reloadable.catalog().get(&test_origin).is_none());
+
+    // the refresh of the adopted zone is still signaled on NOTIFY
+    let mut notify: Message = Message::new();
+    notify.set_op_code(OpCode::Notify);
+
+    let mut query: Query = Query::new();
+    query
+        .set_name(example_origin.clone().into())
+        .set_query_type(RecordType::SOA);
+    notify.add_query(query);
+
+    let notify_bytes = notify.to_bytes().unwrap();
+    let request = Request {
+        message: MessageRequest::from_bytes(&notify_bytes).unwrap(),
+        src: master,
+    };
+
+    let response_handler = TestResponseHandler::new();
+    reloadable
+        .handle_request(&request, response_handler.clone())
+        .unwrap();
+    let result = response_handler.into_message();
+
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+
+    drop(reloadable);
+    assert_eq!(refreshes.wait().count(), 1);
+}
+
+#[test]
+fn test_catalog_shared_between_threads() {
+    let example = create_example();
+    let origin = example.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone(), example);
+    let reloadable = ReloadableCatalog::new(catalog);
+
+    let mut question: Message = Message::new();
+    question.add_query(Query::query(origin.into(), RecordType::A));
+    let question_bytes = question.to_bytes().unwrap();
+
+    // each worker thread handles requests with its own clone of the catalog
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let reloadable = reloadable.clone();
+            let question_bytes = question_bytes.clone();
+            ::std::thread::spawn(move || {
+                let request = Request {
+                    message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+                    src: "127.0.0.1:53".parse().unwrap(),
+                };
+
+                let response_handler = TestResponseHandler::new();
+                reloadable
+                    .handle_request(&request, response_handler.clone())
+                    .unwrap();
+                response_handler.into_message()
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        let result = worker.join().unwrap();
+        assert_eq!(result.response_code(), ResponseCode::NoError);
+        assert_eq!(
+            result.answers().first().unwrap().rdata(),
+            &RData::A(Ipv4Addr::new(93, 184, 216, 34))
+        );
+    }
+}
+
+#[test]
+fn test_catalog_client_subnet() {
+    let mut test = create_test();
+    let origin = test.origin().clone();
+
+    let internal = Record::from_rdata(
+        origin.clone().into(),
+        86400,
+        RecordType::A,
+        RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+    );
+    test.add_subnet(vec![("10.0.0.0".parse().unwrap(), 8)], vec![internal]);
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let request = |subnet: Option<opt::ClientSubnet>, src: &str| {
+        let mut query: Query = Query::new();
+        query.set_name(origin.clone().into()).set_query_type(RecordType::A);
+
+        let mut question: Message = Message::new();
+        question.add_query(query);
+        if let Some(subnet) = subnet {
+            let mut edns = Edns::new();
+            edns.set_option(opt::EdnsOption::Subnet(subnet));
+            question.set_edns(edns);
+        }
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: src.parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+    let response_subnet = |result: &Message| match result
+        .edns()
+        .and_then(|edns| edns.option(&opt::EdnsCode::Subnet))
+    {
+        Some(&opt::EdnsOption::Subnet(subnet)) => Some(subnet),
+        _ => None,
+    };
+
+    // the subnet of the request decides, with the scope of the network
+    let subnet = opt::ClientSubnet::new("10.1.2.0".parse().unwrap(), 24, 0);
+    let result = request(Some(subnet), "192.0.2.1:53");
+    assert_eq!(result.answers().len(), 1);
+    assert_eq!(
+        result.answers()[0].rdata(),
+        &RData::A(Ipv4Addr::new(10, 0, 0, 1))
+    );
+    let scope = response_subnet(&result).expect("no client subnet in response");
+    assert_eq!(scope.address(), subnet.address());
+    assert_eq!(scope.source_prefix(), 24);
+    assert_eq!(scope.scope_prefix(), 8);
+
+    // clients outside of the networks get the records of the zone, only for their subnet
+    let subnet = opt::ClientSubnet::new("192.0.2.0".parse().unwrap(), 24, 0);
+    let result = request(Some(subnet), "10.0.0.5:53");
+    assert_eq!(
+        result.answers()[0].rdata(),
+        &RData::A(Ipv4Addr::new(94, 184, 216, 34))
+    );
+    assert_eq!(response_subnet(&result).unwrap().scope_prefix(), 24);
+
+    // without the option, the source address of the request decides
+    let result = request(None, "10.0.0.5:53");
+    assert_eq!(
+        result.answers()[0].rdata(),
+        &RData::A(Ipv4Addr::new(10, 0, 0, 1))
+    );
+    assert!(response_subnet(&result).is_none());
+
+    // a source prefix of 0 opts out of tailored answers
+    let subnet = opt::ClientSubnet::new("10.0.0.0".parse().unwrap(), 0, 0);
+    let result = request(Some(subnet), "10.0.0.5:53");
+    assert_eq!(
+        result.answers()[0].rdata(),
+        &RData::A(Ipv4Addr::new(94, 184, 216, 34))
+    );
+    assert_eq!(response_subnet(&result).unwrap().scope_prefix(), 0);
+
+    // an address with bits beyond the source prefix is rejected
+    let mut question: Message = Message::new();
+    question.add_query(Query::query(origin.clone().into(), RecordType::A));
+    let mut edns = Edns::new();
+    edns.set_option(opt::EdnsOption::Unknown(
+        opt::EdnsCode::Subnet.into(),
+        vec![0, 1, 20, 0, 192, 0, 8],
+    ));
+    question.set_edns(edns);
+
+    let question_bytes = question.to_bytes().unwrap();
+    let request = Request {
+        message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+        src: "10.0.0.5:53".parse().unwrap(),
+    };
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .handle_request(&request, response_handler.clone())
+        .expect("handle_request failed");
+    let result = response_handler.into_message();
+    assert_eq!(result.response_code(), ResponseCode::FormErr);
+    assert!(result.answers().is_empty());
+}
+
+#[test]
+fn test_catalog_views() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut internal = create_test();
+    internal.upsert(
+        Record::from_rdata(
+            origin.clone().into(),
+            86400,
+            RecordType::A,
+            RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+        ),
+        0,
+    );
+    let mut internal_catalog: Catalog = Catalog::new();
+    internal_catalog.upsert(origin.clone().into(), internal);
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+    catalog.add_view(View::new(
+        "internal".to_string(),
+        Acl::new(vec![(
+            false,
+            AclElement::Network {
+                addr: "10.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+        )]),
+        Acl::new(vec![(
+            false,
+            AclElement::Network {
+                addr: "192.0.2.53".parse().unwrap(),
+                prefix_len: 32,
+            },
+        )]),
+        internal_catalog,
+    ));
+    assert_eq!(catalog.views().len(), 1);
+    assert_eq!(catalog.views()[0].name(), "internal");
+
+    let request = |src: &str, dst: &str| {
+        let mut query: Query = Query::new();
+        query.set_name(origin.clone().into()).set_query_type(RecordType::A);
+
+        let mut question: Message = Message::new();
+        question.add_query(query);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: src.parse().unwrap(),
+        };
+        let mut context = RequestContext::new();
+        context.set_dst(dst.parse().unwrap());
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request_with_context(&request, &context, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+    let addresses = |result: &Message| -> Vec<RData> {
+        let mut addresses: Vec<RData> = result
+            .answers()
+            .iter()
+            .map(|record| record.rdata().clone())
+            .collect();
+        addresses.sort();
+        addresses
+    };
+
+    // internal clients on the internal listener get the zone of the view
+    let result = request("10.1.2.3:53", "192.0.2.53:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        addresses(&result),
+        vec![
+            RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+            RData::A(Ipv4Addr::new(94, 184, 216, 34)),
+        ]
+    );
+
+    // all other requests are refused, the zones outside of the views are not served
+    let result = request("192.0.2.1:53", "192.0.2.53:53");
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+
+    let result = request("10.1.2.3:53", "198.51.100.53:53");
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+}
+
+#[test]
+fn test_catalog_views_tsig() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let key_name = Name::parse("internal.test.com.", None).unwrap();
+    let tsigner = TSigner::new(
+        b"shared secret for internal clients".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        key_name.clone(),
+        DEFAULT_FUDGE,
+    );
+
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut internal = create_test();
+    internal.add_tsig_key(tsigner.clone());
+    internal.upsert(
+        Record::from_rdata(
+            origin.clone().into(),
+            86400,
+            RecordType::A,
+            RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+        ),
+        0,
+    );
+    let mut internal_catalog: Catalog = Catalog::new();
+    internal_catalog.upsert(origin.clone().into(), internal);
+
+    let mut external_catalog: Catalog = Catalog::new();
+    external_catalog.upsert(origin.clone().into(), test);
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.add_view(View::new(
+        "internal".to_string(),
+        Acl::new(vec![(false, AclElement::Key(key_name.clone()))]),
+        Acl::any(),
+        internal_catalog,
+    ));
+    catalog.add_view(View::new(
+        "external".to_string(),
+        Acl::any(),
+        Acl::any(),
+        external_catalog,
+    ));
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let request = |tsigner: Option<&TSigner>| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(origin.clone().into(), RecordType::A));
+        if let Some(tsigner) = tsigner {
+            question.finalize(tsigner, now as u32).unwrap();
+        }
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+
+    // signed with the key of the view, the request gets the zone of the view
+    let result = request(Some(&tsigner));
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result
+        .answers()
+        .iter()
+        .any(|record| *record.rdata() == RData::A(Ipv4Addr::new(10, 0, 0, 1))));
+
+    // unsigned, it gets the zone of the next view
+    let result = request(None);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        result
+            .answers()
+            .iter()
+            .map(|record| record.rdata().clone())
+            .collect::<Vec<RData>>(),
+        vec![RData::A(Ipv4Addr::new(94, 184, 216, 34))]
+    );
+
+    // with the name of the key, but another secret, it is not authorized by the view
+    let other = TSigner::new(
+        b"some other secret".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        key_name,
+        DEFAULT_FUDGE,
+    );
+    let result = request(Some(&other));
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    assert!(result.answers().is_empty());
+}
+
+#[test]
+fn test_catalog_cookies() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+    let server_cookies = Arc::new(ServerCookies::new());
+
+    let request = |cookie: Option<opt::EdnsOption>, src: &str| {
+        let mut query: Query = Query::new();
+        query.set_name(origin.clone().into()).set_query_type(RecordType::A);
+
+        let mut question: Message = Message::new();
+        question.add_query(query);
+        let mut edns = Edns::new();
+        if let Some(cookie) = cookie {
+            edns.set_option(cookie);
+        }
+        question.set_edns(edns);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: src.parse().unwrap(),
+        };
+        let mut context = RequestContext::new();
+        context.set_server_cookies(server_cookies.clone());
+        context.check_cookie(&request.message, request.src.ip());
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request_with_context(&request, &context, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+    let response_cookie = |result: &Message| match result
+        .edns()
+        .and_then(|edns| edns.option(&opt::EdnsCode::Cookie))
+    {
+        Some(&opt::EdnsOption::Cookie(ref cookie)) => cookie.clone(),
+        option => panic!("no cookie in response: {:?}", option),
+    };
+
+    // the first request of the client only has its own cookie
+    let client_cookie = [1, 2, 3, 4, 5, 6, 7, 8];
+    let cookie = opt::Cookie::new(client_cookie, None);
+    let result = request(Some(opt::EdnsOption::Cookie(cookie)), "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(result.answers().len(), 1);
+    let cookie = response_cookie(&result);
+    assert_eq!(cookie.client(), &client_cookie);
+    let server_cookie = cookie.server().expect("no server cookie").to_vec();
+    assert_eq!(server_cookie.len(), 16);
+
+    // which is echoed while it is valid
+    let cookie =
+        opt::EdnsOption::Cookie(opt::Cookie::new(client_cookie, Some(server_cookie.clone())));
+    let result = request(Some(cookie.clone()), "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(response_cookie(&result).server(), Some(&server_cookie[..]));
+
+    // the cookie of another client is bad, the response has a new one
+    let result = request(Some(cookie), "192.0.2.2:53");
+    assert_eq!(result.response_code(), ResponseCode::BADCOOKIE);
+    assert!(result.answers().is_empty());
+    let cookie = response_cookie(&result);
+    assert_eq!(cookie.client(), &client_cookie);
+    assert!(cookie.server().is_some());
+    assert!(cookie.server() != Some(&server_cookie[..]));
+
+    // cookies of the wrong length are malformed
+    let malformed = opt::EdnsOption::Unknown(u16::from(opt::EdnsCode::Cookie), vec![1; 12]);
+    let result = request(Some(malformed), "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::FormErr);
+
+    // clients without cookies are answered as before
+    let result = request(None, "192.0.2.1:53");
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result
+        .edns()
+        .and_then(|edns| edns.option(&opt::EdnsCode::Cookie))
+        .is_none());
+}
+
+#[test]
+fn test_catalog_cookies_tsig() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let tsigner = TSigner::new(
+        b"shared secret for updates".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("update.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    let mut test = create_test();
+    test.add_tsig_key(tsigner.clone());
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+    let server_cookies = Arc::new(ServerCookies::new());
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let request = |cookie: opt::EdnsOption, tsigner: &TSigner| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(origin.clone().into(), RecordType::A));
+        let mut edns = Edns::new();
+        edns.set_option(cookie);
+        question.set_edns(edns);
+        question.finalize(tsigner, now as u32).unwrap();
+        let request_mac = match *question.sig0()[0].rdata() {
+            RData::DNSSEC(DNSSECRData::TSIG(ref tsig)) => tsig.mac().to_vec(),
+            _ => panic!("expected a TSIG"),
+        };
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+        let mut context = RequestContext::new();
+        context.set_server_cookies(server_cookies.clone());
+        context.check_cookie(&request.message, request.src.ip());
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request_with_context(&request, &context, response_handler.clone())
+            .expect("handle_request failed");
+        (response_handler.into_inner(), request_mac)
+    };
+    let verify = |result_bytes: &[u8], request_mac: &[u8]| {
+        let result_req = MessageRequest::from_bytes(result_bytes).unwrap();
+        assert_eq!(result_req.sig0().len(), 1);
+        tsigner
+            .verify_message_byte(
+                Some(request_mac),
+                result_req.unsigned_bytes(),
+                &result_req.sig0()[0],
+                now,
+            )
+            .expect("response TSIG did not verify");
+    };
+
+    // a server cookie which is not valid is answered with BADCOOKIE, signed with the key
+    let client_cookie = [1, 2, 3, 4, 5, 6, 7, 8];
+    let cookie = opt::EdnsOption::Cookie(opt::Cookie::new(client_cookie, Some(vec![1; 16])));
+    let (result_bytes, request_mac) = request(cookie.clone(), &tsigner);
+    let result = Message::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result.response_code(), ResponseCode::BADCOOKIE);
+    assert!(result.answers().is_empty());
+    verify(&result_bytes, &request_mac);
+
+    // as is a malformed cookie, with FORMERR
+    let malformed = opt::EdnsOption::Unknown(u16::from(opt::EdnsCode::Cookie), vec![1; 12]);
+    let (result_bytes, request_mac) = request(malformed, &tsigner);
+    let result = Message::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result.response_code(), ResponseCode::FormErr);
+    verify(&result_bytes, &request_mac);
+
+    // the signature is verified before the cookie
+    let other = TSigner::new(
+        b"some other secret".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("update.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+    let (result_bytes, _) = request(cookie, &other);
+    let result = Message::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result.response_code(), ResponseCode::NotAuth);
+    assert_eq!(
+        response_tsig(&result).error(),
+        u16::from(ResponseCode::BADSIG)
+    );
+}
+
+#[test]
+fn test_catalog_keepalive_padding() {
+    use std::time::Duration;
+
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let request = |options: Vec<opt::EdnsOption>, context: &RequestContext| {
+        let mut question: Message = Message::new();
+        question.add_query(Query::query(origin.clone().into(), RecordType::A));
+        let mut edns = Edns::new();
+        for option in options {
+            edns.set_option(option);
+        }
+        question.set_edns(edns);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request_with_context(&request, context, response_handler.clone())
+            .expect("handle_request failed");
+        let result_bytes = response_handler.into_inner();
+        let result = Message::from_bytes(&result_bytes).unwrap();
+        (result_bytes.len(), result)
+    };
+
+    let option = |result: &Message, code: opt::EdnsCode| {
+        result.edns().and_then(|edns| edns.option(&code)).cloned()
+    };
+
+    let mut tcp = RequestContext::new();
+    tcp.set_idle_timeout(Duration::from_secs(5));
+    let mut tls = tcp.clone();
+    tls.set_encrypted(true);
+
+    // the idle timeout is advertised to clients which sent the option
+    let (_, result) = request(vec![opt::EdnsOption::Keepalive(None)], &tcp);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        option(&result, opt::EdnsCode::Keepalive),
+        Some(opt::EdnsOption::Keepalive(Some(50)))
+    );
+    assert!(option(&result, opt::EdnsCode::Padding).is_none());
+
+    let (_, result) = request(vec![], &tls);
+    assert!(option(&result, opt::EdnsCode::Keepalive).is_none());
+    assert!(option(&result, opt::EdnsCode::Padding).is_none());
+
+    // without an idle timeout the longest is advertised
+    let mut unlimited = RequestContext::new();
+    unlimited.set_idle_timeout(Duration::from_secs(0));
+    let (_, result) = request(vec![opt::EdnsOption::Keepalive(None)], &unlimited);
+    assert_eq!(
+        option(&result, opt::EdnsCode::Keepalive),
+        Some(opt::EdnsOption::Keepalive(Some(u16::max_value())))
+    );
+
+    // and never over UDP
+    let mut udp = RequestContext::new();
+    udp.set_max_udp_payload(1232);
+    let (_, result) = request(vec![opt::EdnsOption::Keepalive(None)], &udp);
+    assert!(option(&result, opt::EdnsCode::Keepalive).is_none());
+
+    // responses are padded over encrypted connections, to clients which sent the option
+    let options = vec![
+        opt::EdnsOption::Keepalive(None),
+        opt::EdnsOption::Padding(20),
+    ];
+    let (len, result) = request(options.clone(), &tls);
+    assert_eq!(len % opt::RESPONSE_PADDING_BLOCK_LEN, 0);
+    assert!(!result.answers().is_empty());
+    assert!(option(&result, opt::EdnsCode::Padding).is_some());
+    assert_eq!(
+        option(&result, opt::EdnsCode::Keepalive),
+        Some(opt::EdnsOption::Keepalive(Some(50)))
+    );
+
+    let mut https = RequestContext::new();
+    https.set_encrypted(true);
+    let (len, result) = request(options.clone(), &https);
+    assert_eq!(len % opt::RESPONSE_PADDING_BLOCK_LEN, 0);
+    assert!(option(&result, opt::EdnsCode::Keepalive).is_none());
+
+    // but not over plain TCP
+    let (_, result) = request(options, &tcp);
+    assert!(option(&result, opt::EdnsCode::Padding).is_none());
+}
+
+#[test]
+fn test_catalog_padding_tsig() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+
+    let tsigner = TSigner::new(
+        b"shared secret for updates".to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("update.test.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    );
+
+    let mut test = create_test();
+    test.add_tsig_key(tsigner.clone());
+    let origin = test.origin().clone();
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut question: Message = Message::new();
+    question.add_query(Query::query(origin.clone().into(), RecordType::A));
+    let mut edns = Edns::new();
+    edns.set_option(opt::EdnsOption::Padding(0));
+    question.set_edns(edns);
+    question.finalize(&tsigner, now as u32).unwrap();
+    let request_mac = match *question.sig0()[0].rdata() {
+        RData::DNSSEC(DNSSECRData::TSIG(ref tsig)) => tsig.mac().to_vec(),
+        _ => panic!("expected a TSIG"),
+    };
+
+    let question_bytes = question.to_bytes().unwrap();
+    let request = Request {
+        message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+        src: "192.0.2.1:53".parse().unwrap(),
+    };
+    let mut context = RequestContext::new();
+    context.set_encrypted(true);
+
+    let response_handler = TestResponseHandler::new();
+    catalog
+        .handle_request_with_context(&request, &context, response_handler.clone())
+        .expect("handle_request failed");
+    let result_bytes = response_handler.into_inner();
+
+    // the response is padded with its TSIG, which covers the padding
+    assert_eq!(result_bytes.len() % opt::RESPONSE_PADDING_BLOCK_LEN, 0);
+    let result = Message::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    let edns = result.edns().unwrap();
+    assert!(edns.option(&opt::EdnsCode::Padding).is_some());
+
+    let result_req = MessageRequest::from_bytes(&result_bytes).unwrap();
+    assert_eq!(result_req.sig0().len(), 1);
+    tsigner
+        .verify_message_byte(
+            Some(&request_mac),
+            result_req.unsigned_bytes(),
+            &result_req.sig0()[0],
+            now,
+        )
+        .expect("response TSIG did not verify");
+}
+
+#[test]
+fn test_catalog_identity() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut identity = Identity::new();
+    identity.set_nsid(b"ns1".to_vec());
+    identity.set_hostname("ns1.example.com".to_string());
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+    catalog.set_identity(Arc::new(identity));
+
+    let request = |name: Name, class: DNSClass, nsid: bool| {
+        let mut query: Query = Query::new();
+        query
+            .set_name(name)
+            .set_query_type(RecordType::TXT)
+            .set_query_class(class);
+
+        let mut question: Message = Message::new();
+        question.add_query(query);
+        let mut edns = Edns::new();
+        if nsid {
+            edns.set_option(opt::EdnsOption::NSID(vec![]));
+        }
+        question.set_edns(edns);
+
+        let question_bytes = question.to_bytes().unwrap();
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+
+    // the NSID is only sent to clients which ask for it
+    let result = request(origin.clone().into(), DNSClass::IN, true);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(
+        result
+            .edns()
+            .and_then(|edns| edns.option(&opt::EdnsCode::NSID)),
+        Some(&opt::EdnsOption::NSID(b"ns1".to_vec()))
+    );
+    let result = request(origin.clone().into(), DNSClass::IN, false);
+    assert!(result
+        .edns()
+        .and_then(|edns| edns.option(&opt::EdnsCode::NSID))
+        .is_none());
+
+    // the configured CHAOS class names are answered
+    let hostname_bind = Name::parse("HOSTNAME.bind.", None).unwrap();
+    let result = request(hostname_bind, DNSClass::CH, false);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(result.authoritative());
+    assert_eq!(result.answers().len(), 1);
+    assert_eq!(result.answers()[0].dns_class(), DNSClass::CH);
+    assert_eq!(
+        *result.answers()[0].rdata(),
+        RData::TXT(TXT::new(vec!["ns1.example.com".to_string()]))
+    );
+
+    // the others are refused
+    let version_bind = Name::parse(VERSION_BIND, None).unwrap();
+    let result = request(version_bind, DNSClass::CH, false);
+    assert_eq!(result.response_code(), ResponseCode::Refused);
+    assert!(result.answers().is_empty());
+}
+
+#[test]
+fn test_catalog_identity_nsid_wire() {
+    let test = create_test();
+    let origin = test.origin().clone();
+
+    let mut identity = Identity::new();
+    identity.set_nsid(b"ns1".to_vec());
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.upsert(origin.clone().into(), test);
+    catalog.set_identity(Arc::new(identity));
+
+    let request = |options: &[u8]| {
+        // a query for test.com. A, as sent by `dig +nsid`
+        let mut question_bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 1, // header
+            4, b't', b'e', b's', b't', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1, // query
+            0, 0, 41, 0x04, 0xd0, 0, 0, 0, 0, // OPT
+        ];
+        question_bytes.push((options.len() >> 8) as u8);
+        question_bytes.push(options.len() as u8);
+        question_bytes.extend_from_slice(options);
+
+        let request = Request {
+            message: MessageRequest::from_bytes(&question_bytes).unwrap(),
+            src: "192.0.2.1:53".parse().unwrap(),
+        };
+
+        let response_handler = TestResponseHandler::new();
+        catalog
+            .handle_request(&request, response_handler.clone())
+            .expect("handle_request failed");
+        response_handler.into_message()
+    };
+
+    let nsid = |result: &Message| {
+        result
+            .edns()
+            .and_then(|edns| edns.option(&opt::EdnsCode::NSID))
+            .cloned()
+    };
+
+    // the empty NSID option, alone
+    let result = request(&[0, 3, 0, 0]);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert!(!result.answers().is_empty());
+    assert_eq!(nsid(&result), Some(opt::EdnsOption::NSID(b"ns1".to_vec())));
+
+    // and followed by another option
+    let result = request(&[0, 3, 0, 0, 0, 12, 0, 2, 0, 0]);
+    assert_eq!(result.response_code(), ResponseCode::NoError);
+    assert_eq!(nsid(&result), Some(opt::EdnsOption::NSID(b"ns1".to_vec())));
+}
@@ -158,11 +158,19 @@ fn test_notify() {
     ));
     assert!(message.is_ok());
     let message = message.unwrap();
-    assert_eq!(
-        message.response_code(),
-        ResponseCode::NotImp,
-        "the catalog must support Notify now, update this"
-    );
+    // the only NOTIFY event is a change of the SOA
+    assert_eq!(message.response_code(), ResponseCode::FormErr);
+
+    // example.com is a master zone, it is not refreshed from a master
+    let message = io_loop.run(client.notify(
+        Name::from_str("example.com").unwrap(),
+        DNSClass::IN,
+        RecordType::SOA,
+        None::<RecordSet>,
+    ));
+    assert!(message.is_ok());
+    let message = message.unwrap();
+    assert_eq!(message.response_code(), ResponseCode::NotAuth);
 }
 
 // update tests
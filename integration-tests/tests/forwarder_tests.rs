@@ -0,0 +1,220 @@
+extern crate tokio_core;
+extern crate trust_dns;
+extern crate trust_dns_integration;
+extern crate trust_dns_resolver;
+extern crate trust_dns_server;
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::thread;
+
+use tokio_core::reactor::Core;
+
+use trust_dns::op::*;
+use trust_dns::rr::rdata::SOA;
+use trust_dns::rr::*;
+use trust_dns_integration::mock_server::mock_server;
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig};
+
+use trust_dns_server::authority::Forwarder;
+
+fn name(name: &str) -> Name {
+    Name::parse(name, None).unwrap()
+}
+
+fn soa() -> Record {
+    Record::from_rdata(
+        name("example.com."),
+        3600,
+        RecordType::SOA,
+        RData::SOA(SOA::new(
+            name("sns.dns.icann.org."),
+            name("noc.dns.icann.org."),
+            2,
+            7200,
+            3600,
+            1209600,
+            300,
+        )),
+    )
+}
+
+/// An upstream server which answers `queries` queries, and then stops
+///
+/// Queries for alias.example.com. are answered with a CNAME to www.example.com. and its address,
+///  with the address of the name server in the additional section, the other names do not exist.
+fn upstream(queries: usize) -> SocketAddr {
+    mock_server(queries, |request| {
+        assert!(request.recursion_desired());
+        let query = request.queries()[0].clone();
+
+        let mut response = Message::new();
+        response
+            .set_message_type(MessageType::Response)
+            .set_recursion_desired(true)
+            .set_recursion_available(true)
+            .set_authentic_data(true);
+
+        if *query.name() == name("alias.example.com.") {
+            response
+                .add_answer(Record::from_rdata(
+                    name("alias.example.com."),
+                    300,
+                    RecordType::CNAME,
+                    RData::CNAME(name("www.example.com.")),
+                ))
+                .add_answer(Record::from_rdata(
+                    name("www.example.com."),
+                    300,
+                    RecordType::A,
+                    RData::A(Ipv4Addr::new(93, 184, 216, 34)),
+                ))
+                .add_additional(Record::from_rdata(
+                    name("ns.example.com."),
+                    300,
+                    RecordType::A,
+                    RData::A(Ipv4Addr::new(93, 184, 216, 53)),
+                ));
+        } else {
+            response
+                .set_response_code(ResponseCode::NXDomain)
+                .add_name_server(soa());
+        }
+
+        response
+    })
+}
+
+fn forwarder(upstream: SocketAddr, io_loop: &Core) -> Forwarder {
+    forwarder_with_cache(upstream, 16, io_loop)
+}
+
+fn forwarder_with_cache(upstream: SocketAddr, cache_size: usize, io_loop: &Core) -> Forwarder {
+    let mut config = ResolverConfig::new();
+    config.add_name_server(NameServerConfig {
+        socket_addr: upstream,
+        protocol: Protocol::Udp,
+    });
+    Forwarder::new(config, cache_size, &io_loop.handle())
+}
+
+#[test]
+fn test_forward_relays_response() {
+    let mut io_loop = Core::new().unwrap();
+    let mut forwarder = forwarder(upstream(1), &io_loop);
+    forwarder.set_authentic_data_trusted(true);
+    let handle = io_loop.handle();
+
+    let query = Query::query(name("alias.example.com."), RecordType::A);
+    let response = io_loop
+        .run(forwarder.forward(query, false, false, &handle))
+        .unwrap();
+
+    // the CNAME chain, the additional records and AD are relayed
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(response.authentic_data());
+    assert_eq!(response.answers().len(), 2);
+    assert_eq!(response.answers()[0].rr_type(), RecordType::CNAME);
+    assert_eq!(*response.answers()[1].name(), name("www.example.com."));
+    assert_eq!(response.additionals().len(), 1);
+}
+
+#[test]
+fn test_forward_clears_untrusted_authentic_data() {
+    let mut io_loop = Core::new().unwrap();
+    let forwarder = forwarder(upstream(1), &io_loop);
+    let handle = io_loop.handle();
+
+    let query = Query::query(name("alias.example.com."), RecordType::A);
+    let response = io_loop
+        .run(forwarder.forward(query.clone(), false, false, &handle))
+        .unwrap();
+    assert_eq!(response.answers().len(), 2);
+    assert!(!response.authentic_data());
+
+    // nor is it set in the cached response
+    let cached = io_loop
+        .run(forwarder.forward(query, false, false, &handle))
+        .unwrap();
+    assert!(!cached.authentic_data());
+}
+
+#[test]
+fn test_forward_nxdomain() {
+    let mut io_loop = Core::new().unwrap();
+    let forwarder = forwarder(upstream(1), &io_loop);
+    let handle = io_loop.handle();
+
+    let query = Query::query(name("nx.example.com."), RecordType::A);
+    let response = io_loop
+        .run(forwarder.forward(query.clone(), false, false, &handle))
+        .unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+    assert!(response.answers().is_empty());
+    assert_eq!(response.name_servers(), &[soa()]);
+
+    // the upstream server only answers once, the second response is cached
+    let cached = io_loop
+        .run(forwarder.forward(query, false, false, &handle))
+        .unwrap();
+    assert_eq!(cached.response_code(), ResponseCode::NXDomain);
+    assert_eq!(cached.name_servers().len(), 1);
+    assert!(cached.name_servers()[0].ttl() <= 3600);
+}
+
+#[test]
+fn test_forward_on_each_reactor() {
+    let mut io_loop = Core::new().unwrap();
+    let forwarder = forwarder(upstream(2), &io_loop);
+    let handle = io_loop.handle();
+
+    let nx = Query::query(name("nx.example.com."), RecordType::A);
+    let response = io_loop
+        .run(forwarder.forward(nx.clone(), false, false, &handle))
+        .unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+
+    // another thread sends its queries from its own reactor, and shares the cache
+    let worker = forwarder.clone();
+    let (response, cached) = thread::spawn(move || {
+        let mut io_loop = Core::new().unwrap();
+        let handle = io_loop.handle();
+
+        let alias = Query::query(name("alias.example.com."), RecordType::A);
+        let response = io_loop
+            .run(worker.forward(alias, false, false, &handle))
+            .unwrap();
+        let cached = io_loop
+            .run(worker.forward(nx, false, false, &handle))
+            .unwrap();
+        (response, cached)
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 2);
+    assert_eq!(cached.response_code(), ResponseCode::NXDomain);
+}
+
+#[test]
+fn test_cache_drops_least_recently_used() {
+    let mut io_loop = Core::new().unwrap();
+    let forwarder = forwarder_with_cache(upstream(3), 2, &io_loop);
+    let handle = io_loop.handle();
+
+    let alias = Query::query(name("alias.example.com."), RecordType::A);
+    let nx = Query::query(name("nx.example.com."), RecordType::A);
+    let other = Query::query(name("other.example.com."), RecordType::A);
+    for query in vec![alias.clone(), nx, alias.clone(), other] {
+        io_loop
+            .run(forwarder.forward(query, false, false, &handle))
+            .unwrap();
+    }
+
+    // the full cache dropped nx.example.com., which was used less recently, the upstream server
+    //  only answers three queries
+    let cached = io_loop
+        .run(forwarder.forward(alias, false, false, &handle))
+        .unwrap();
+    assert_eq!(cached.answers().len(), 2);
+}
@@ -6,16 +6,20 @@ extern crate trust_dns_resolver;
 
 use std::net::*;
 use std::str::FromStr;
+use std::time::Duration;
 
-use tokio_core::reactor::{Core, Handle};
+use tokio_core::reactor::{Core, Handle, Timeout};
 
-use trust_dns::op::{Message, Query};
+use trust_dns::op::{Message, MessageType, Query};
 use trust_dns::rr::{Name, RecordType};
+use trust_dns::rr::rdata::opt::EdnsOption;
 use trust_dns_proto::DnsHandle;
 use trust_dns_resolver::config::*;
 use trust_dns_resolver::error::*;
-use trust_dns_resolver::name_server_pool::{ConnectionProvider, NameServer, NameServerPool};
+use trust_dns_resolver::name_server_pool::{ConnectionProvider, NameServer, NameServerPool,
+                                           StandardConnection};
 use trust_dns_integration::mock_client::*;
+use trust_dns_integration::mock_server::mock_tcp_server;
 
 #[derive(Clone)]
 struct MockConnProvider {}
@@ -183,3 +187,46 @@ fn test_local_mdns() {
     let response = reactor.run(future).unwrap();
     assert_eq!(response.answers()[0], mdns_record);
 }
+
+#[test]
+fn test_reconnect_after_idle_close() {
+    let query = Query::query(
+        Name::from_str("www.example.com.").unwrap(),
+        RecordType::A,
+    );
+    let record = v4_record(query.name().clone(), Ipv4Addr::new(127, 0, 0, 1));
+
+    // the server keeps connections open while idle for 100 milliseconds
+    let answer = record.clone();
+    let server = mock_tcp_server(2, move |_| {
+        let mut response = Message::new();
+        response
+            .set_message_type(MessageType::Response)
+            .add_answer(answer.clone())
+            .edns_mut()
+            .set_option(EdnsOption::Keepalive(Some(1)));
+        response
+    });
+
+    let mut reactor = Core::new().unwrap();
+    let mut name_server = NameServer::<_, StandardConnection>::new(
+        NameServerConfig {
+            socket_addr: server,
+            protocol: Protocol::Tcp,
+        },
+        ResolverOpts::default(),
+        &reactor.handle(),
+    );
+
+    let request = message::<ResolveError>(query.clone(), vec![], vec![], vec![]).unwrap();
+    let response = reactor.run(name_server.send(request)).unwrap();
+    assert_eq!(response.answers()[0], record);
+
+    // the connection is closed while idle, the query is sent on a new one
+    let idle = Timeout::new(Duration::from_millis(300), &reactor.handle()).unwrap();
+    reactor.run(idle).unwrap();
+
+    let request = message::<ResolveError>(query, vec![], vec![], vec![]).unwrap();
+    let response = reactor.run(name_server.send(request)).unwrap();
+    assert_eq!(response.answers()[0], record);
+}
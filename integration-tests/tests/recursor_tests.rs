@@ -0,0 +1,269 @@
+extern crate tokio_core;
+extern crate trust_dns;
+extern crate trust_dns_integration;
+extern crate trust_dns_server;
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio_core::reactor::Core;
+
+use trust_dns::op::*;
+use trust_dns::rr::rdata::SOA;
+use trust_dns::rr::*;
+use trust_dns_integration::mock_server::mock_server;
+
+use trust_dns_server::authority::Recursor;
+
+fn name(name: &str) -> Name {
+    Name::parse(name, None).unwrap()
+}
+
+fn a(owner: &str, last: u8) -> Record {
+    Record::from_rdata(
+        name(owner),
+        300,
+        RecordType::A,
+        RData::A(Ipv4Addr::new(192, 0, 2, last)),
+    )
+}
+
+fn soa(zone: &str) -> Record {
+    Record::from_rdata(
+        name(zone),
+        3600,
+        RecordType::SOA,
+        RData::SOA(SOA::new(
+            name("sns.dns.icann.org."),
+            name("noc.dns.icann.org."),
+            2,
+            7200,
+            3600,
+            1209600,
+            300,
+        )),
+    )
+}
+
+fn response() -> Message {
+    let mut response = Message::new();
+    response.set_message_type(MessageType::Response);
+    response
+}
+
+/// A referral to the server of the cut, with its address on the loopback as glue
+fn referral(cut: &str, server: &str) -> Message {
+    let mut response = response();
+    response
+        .add_name_server(Record::from_rdata(
+            name(cut),
+            86400,
+            RecordType::NS,
+            RData::NS(name(server)),
+        ))
+        .add_additional(Record::from_rdata(
+            name(server),
+            86400,
+            RecordType::A,
+            RData::A(Ipv4Addr::new(127, 0, 0, 1)),
+        ));
+    response
+}
+
+fn answer(answers: Vec<Record>) -> Message {
+    let mut response = response();
+    response.set_authoritative(true).add_answers(answers);
+    response
+}
+
+fn negative(response_code: ResponseCode, zone: &str) -> Message {
+    let mut response = response();
+    response
+        .set_authoritative(true)
+        .set_response_code(response_code)
+        .add_name_server(soa(zone));
+    response
+}
+
+/// The servers of the root, the TLDs and the zones, all on one mock server, which expects the
+///  queries for the names of the script in order, and answers each with its response
+fn servers(script: Vec<(&'static str, Message)>) -> SocketAddr {
+    let queries = script.len();
+    let mut script = script.into_iter();
+
+    mock_server(queries, move |request| {
+        assert!(!request.recursion_desired());
+        let (expected, response) = script.next().unwrap();
+        assert_eq!(*request.queries()[0].name(), name(expected));
+        response
+    })
+}
+
+/// A Recursor with the mock server as the root, the servers below the root are all on its port
+fn recursor(root: SocketAddr, io_loop: &Core) -> Recursor {
+    let mut recursor = Recursor::new(vec![root], 64, &io_loop.handle());
+    recursor.set_port(root.port());
+    recursor
+}
+
+#[test]
+fn test_follow_referrals() {
+    let root = servers(vec![
+        ("www.example.com.", referral("com.", "a.gtld-servers.com.")),
+        ("www.example.com.", referral("example.com.", "ns.example.com.")),
+        ("www.example.com.", answer(vec![a("www.example.com.", 1)])),
+        ("mail.example.com.", answer(vec![a("mail.example.com.", 2)])),
+    ]);
+
+    let mut io_loop = Core::new().unwrap();
+    let recursor = recursor(root, &io_loop);
+    let handle = io_loop.handle();
+
+    let www = Query::query(name("www.example.com."), RecordType::A);
+    let response = io_loop.run(recursor.resolve(www.clone(), &handle)).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers(), &[a("www.example.com.", 1)]);
+
+    // the answer is cached
+    let cached = io_loop.run(recursor.resolve(www, &handle)).unwrap();
+    assert_eq!(cached.answers().len(), 1);
+    assert_eq!(cached.answers()[0].rdata(), a("www.example.com.", 1).rdata());
+
+    // and so are the servers of the zone, which are asked directly
+    let mail = Query::query(name("mail.example.com."), RecordType::A);
+    let response = io_loop.run(recursor.resolve(mail, &handle)).unwrap();
+    assert_eq!(response.answers(), &[a("mail.example.com.", 2)]);
+}
+
+#[test]
+fn test_follow_cname() {
+    let cname = Record::from_rdata(
+        name("www.example.com."),
+        300,
+        RecordType::CNAME,
+        RData::CNAME(name("www.example.net.")),
+    );
+    let root = servers(vec![
+        ("www.example.com.", referral("com.", "a.gtld-servers.com.")),
+        ("www.example.com.", referral("example.com.", "ns.example.com.")),
+        ("www.example.com.", answer(vec![cname.clone()])),
+        // the target is in another zone, it is resolved from the root
+        ("www.example.net.", referral("net.", "a.gtld-servers.net.")),
+        ("www.example.net.", referral("example.net.", "ns.example.net.")),
+        ("www.example.net.", answer(vec![a("www.example.net.", 3)])),
+    ]);
+
+    let mut io_loop = Core::new().unwrap();
+    let recursor = recursor(root, &io_loop);
+    let handle = io_loop.handle();
+
+    let www = Query::query(name("www.example.com."), RecordType::A);
+    let response = io_loop.run(recursor.resolve(www.clone(), &handle)).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers(), &[cname, a("www.example.net.", 3)]);
+
+    // the chain is followed in the cache
+    let cached = io_loop.run(recursor.resolve(www, &handle)).unwrap();
+    assert_eq!(cached.answers().len(), 2);
+    assert_eq!(cached.answers()[0].rr_type(), RecordType::CNAME);
+    assert_eq!(*cached.answers()[1].name(), name("www.example.net."));
+}
+
+#[test]
+fn test_records_outside_of_zone_not_cached() {
+    // the servers of example.com. try to answer for example.org. as well
+    let root = servers(vec![
+        ("www.example.com.", referral("com.", "a.gtld-servers.com.")),
+        ("www.example.com.", referral("example.com.", "ns.example.com.")),
+        (
+            "www.example.com.",
+            answer(vec![a("www.example.com.", 1), a("www.example.org.", 66)]),
+        ),
+        ("www.example.org.", negative(ResponseCode::NXDomain, ".")),
+    ]);
+
+    let mut io_loop = Core::new().unwrap();
+    let recursor = recursor(root, &io_loop);
+    let handle = io_loop.handle();
+
+    let www = Query::query(name("www.example.com."), RecordType::A);
+    io_loop.run(recursor.resolve(www, &handle)).unwrap();
+
+    // the address in example.org. is not taken from the cache, it is resolved from the root
+    let org = Query::query(name("www.example.org."), RecordType::A);
+    let response = io_loop.run(recursor.resolve(org, &handle)).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+    assert!(response.answers().is_empty());
+}
+
+#[test]
+fn test_negative_caching() {
+    let root = servers(vec![
+        ("nx.example.com.", referral("com.", "a.gtld-servers.com.")),
+        ("nx.example.com.", referral("example.com.", "ns.example.com.")),
+        (
+            "nx.example.com.",
+            negative(ResponseCode::NXDomain, "example.com."),
+        ),
+        (
+            "www.example.com.",
+            negative(ResponseCode::NoError, "example.com."),
+        ),
+    ]);
+
+    let mut io_loop = Core::new().unwrap();
+    let recursor = recursor(root, &io_loop);
+    let handle = io_loop.handle();
+
+    let nx = Query::query(name("nx.example.com."), RecordType::A);
+    let response = io_loop.run(recursor.resolve(nx.clone(), &handle)).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+
+    // the name does not exist for the SOA minimum, the SOA is returned with the cached answer
+    let cached = io_loop.run(recursor.resolve(nx, &handle)).unwrap();
+    assert_eq!(cached.response_code(), ResponseCode::NXDomain);
+    assert!(cached.answers().is_empty());
+    assert_eq!(cached.name_servers().len(), 1);
+    assert_eq!(*cached.name_servers()[0].name(), name("example.com."));
+    assert!(cached.name_servers()[0].ttl() <= 300);
+
+    // as does the type of a name which exists
+    let aaaa = Query::query(name("www.example.com."), RecordType::AAAA);
+    let response = io_loop.run(recursor.resolve(aaaa.clone(), &handle)).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(response.answers().is_empty());
+
+    let cached = io_loop.run(recursor.resolve(aaaa, &handle)).unwrap();
+    assert_eq!(cached.response_code(), ResponseCode::NoError);
+    assert!(cached.answers().is_empty());
+    assert_eq!(cached.name_servers().len(), 1);
+    assert_eq!(cached.name_servers()[0].rr_type(), RecordType::SOA);
+}
+
+#[test]
+fn test_max_queries() {
+    // each name is an alias of the next one, without end
+    let queried = Arc::new(AtomicUsize::new(0));
+    let counter = queried.clone();
+    let root = mock_server(32, move |request| {
+        let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+        let query = request.queries()[0].clone();
+        let next = format!("a{}.example.com.", count);
+
+        answer(vec![Record::from_rdata(
+            query.name().clone(),
+            300,
+            RecordType::CNAME,
+            RData::CNAME(name(&next)),
+        )])
+    });
+
+    let mut io_loop = Core::new().unwrap();
+    let recursor = recursor(root, &io_loop);
+    let handle = io_loop.handle();
+
+    let query = Query::query(name("a0.example.com."), RecordType::A);
+    assert!(io_loop.run(recursor.resolve(query, &handle)).is_err());
+    assert_eq!(queried.load(Ordering::Relaxed), 16);
+}
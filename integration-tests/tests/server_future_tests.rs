@@ -1,6 +1,7 @@
 extern crate futures;
 extern crate openssl;
 extern crate rustls;
+extern crate tokio_core;
 extern crate trust_dns;
 extern crate trust_dns_integration;
 extern crate trust_dns_openssl;
@@ -19,17 +20,28 @@ use std::thread;
 use std::time::Duration;
 
 use futures::Stream;
-use openssl::pkcs12::Pkcs12;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MSB_MAYBE_ZERO};
+use openssl::hash::MessageDigest;
+use openssl::nid;
+use openssl::pkcs12::{ParsedPkcs12, Pkcs12};
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::x509::extension::{BasicConstraints, ExtendedKeyUsage, SubjectAlternativeName};
+use openssl::x509::{X509NameBuilder, X509};
 use rustls::Certificate;
+use tokio_core::reactor::Core;
 
 use trust_dns::client::*;
 use trust_dns::op::*;
 use trust_dns::rr::*;
 use trust_dns::udp::UdpClientConnection;
 use trust_dns::tcp::TcpClientConnection;
+use trust_dns_openssl::TlsClientStreamBuilder;
 use trust_dns_rustls::TlsClientConnection;
 
 use trust_dns_server::ServerFuture;
+use trust_dns_server::server::RateLimiter;
 use trust_dns_server::authority::*;
 
 use trust_dns_integration::authority::create_example;
@@ -61,6 +73,74 @@ fn test_server_www_udp() {
     server_thread.join().unwrap();;
 }
 
+#[test]
+fn test_server_shared_rate_limit() {
+    // one response per second, the first is sent, and the rest dropped for a while
+    let rate_limiter = Arc::new(RateLimiter::new(
+        1,
+        Duration::from_secs(60),
+        0,
+        24,
+        56,
+    ));
+    let server_continue = Arc::new(AtomicBool::new(true));
+
+    // two servers, like the workers of named, sharing the limits
+    let mut ipaddrs = vec![];
+    let mut server_threads = vec![];
+    for worker in 0..2 {
+        let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+        let udp_socket = UdpSocket::bind(&addr).unwrap();
+        ipaddrs.push(udp_socket.local_addr().unwrap());
+
+        let rate_limiter = rate_limiter.clone();
+        let server_continue = server_continue.clone();
+        server_threads.push(
+            thread::Builder::new()
+                .name(format!("test_server:rate_limit:server{}", worker))
+                .spawn(move || {
+                    server_thread_udp_rate_limited(udp_socket, rate_limiter, server_continue)
+                })
+                .unwrap(),
+        );
+    }
+
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+
+    let mut message = Message::new();
+    message
+        .set_id(1)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .add_query(Query::query(
+            Name::from_str("www.example.com.").unwrap(),
+            RecordType::A,
+        ));
+    let query = message.to_vec().unwrap();
+
+    // two queries to each server, which would get a response each if they had their own limits
+    for ipaddr in ipaddrs.iter().chain(ipaddrs.iter()) {
+        client.send_to(&query, ipaddr).unwrap();
+    }
+
+    let mut buffer = [0_u8; 512];
+    let mut responses = 0;
+    while let Ok((len, _)) = client.recv_from(&mut buffer) {
+        let response = Message::from_vec(&buffer[..len]).unwrap();
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        responses += 1;
+    }
+    assert_eq!(responses, 1);
+
+    server_continue.store(false, Ordering::Relaxed);
+    for server_thread in server_threads {
+        server_thread.join().unwrap();
+    }
+}
+
 #[test]
 fn test_server_www_tcp() {
     let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
@@ -174,6 +254,152 @@ fn test_server_www_tls() {
     server_thread.join().unwrap();
 }
 
+#[test]
+fn test_server_www_mtls() {
+    let (ca_pkey, ca_cert) = mtls_ca();
+    let ca_der = ca_cert.to_der().unwrap();
+    let server_der = mtls_cert("ns.example.com", &ca_pkey, &ca_cert, false)
+        .to_der()
+        .unwrap();
+
+    // Server address
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let tcp_listener = TcpListener::bind(&addr).unwrap();
+
+    let ipaddr = tcp_listener.local_addr().unwrap();
+    println!("tcp_listner on port: {}", ipaddr);
+    let server_continue = Arc::new(AtomicBool::new(true));
+    let server_continue2 = server_continue.clone();
+    let client_ca_der = ca_der.clone();
+
+    let server_thread = thread::Builder::new()
+        .name("test_server:mtls:server".to_string())
+        .spawn(move || {
+            server_thread_mtls(tcp_listener, server_continue2, server_der, client_ca_der)
+        })
+        .unwrap();
+
+    // the names of the certificate of the client are matched by the query ACL of the zone
+    let identity = mtls_cert("resolv.example.com", &ca_pkey, &ca_cert, true)
+        .parse("mypass")
+        .unwrap();
+    let response = mtls_query(ipaddr, &ca_der, Some(identity));
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 1);
+
+    // a certificate for another name, or none at all, is refused
+    let other = mtls_cert("other.example.com", &ca_pkey, &ca_cert, true)
+        .parse("mypass")
+        .unwrap();
+    let response = mtls_query(ipaddr, &ca_der, Some(other));
+    assert_eq!(response.response_code(), ResponseCode::Refused);
+    assert!(response.answers().is_empty());
+
+    let response = mtls_query(ipaddr, &ca_der, None);
+    assert_eq!(response.response_code(), ResponseCode::Refused);
+
+    server_continue.store(false, Ordering::Relaxed);
+    server_thread.join().unwrap();
+}
+
+// queries www.example.com over TLS, with the client certificate if there is one
+fn mtls_query(ipaddr: SocketAddr, ca_der: &[u8], identity: Option<ParsedPkcs12>) -> Message {
+    let mut io_loop = Core::new().unwrap();
+    let mut builder = TlsClientStreamBuilder::new();
+    builder.add_ca_der(ca_der).unwrap();
+    if let Some(identity) = identity {
+        builder.identity(identity);
+    }
+
+    let (stream, sender) = builder.build(ipaddr, "ns.example.com".to_string(), &io_loop.handle());
+    let mut client = ClientFuture::new(stream, sender, &io_loop.handle(), None);
+    io_loop
+        .run(client.query(
+            Name::from_str("www.example.com.").unwrap(),
+            DNSClass::IN,
+            RecordType::A,
+        ))
+        .expect("mtls query failed")
+}
+
+/// Generates the CA of the server and of the clients
+fn mtls_ca() -> (PKey, X509) {
+    let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(nid::COMMONNAME, "root.example.com")
+        .unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_pubkey(&pkey).unwrap();
+    builder.set_serial_number(&serial()).unwrap();
+    builder
+        .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+        .unwrap();
+
+    builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+    (pkey, builder.build())
+}
+
+/// Generates a certificate for the name signed by the CA, for a client or for the server
+fn mtls_cert(subject_name: &str, ca_pkey: &PKey, ca_cert: &X509, client: bool) -> Pkcs12 {
+    let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(nid::COMMONNAME, subject_name)
+        .unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    builder.set_issuer_name(ca_cert.subject_name()).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_pubkey(&pkey).unwrap();
+    builder.set_serial_number(&serial()).unwrap();
+
+    let mut key_usage = ExtendedKeyUsage::new();
+    if client {
+        key_usage.client_auth();
+    } else {
+        key_usage.server_auth();
+    }
+    builder
+        .append_extension(key_usage.build().unwrap())
+        .unwrap();
+    let alt_name = SubjectAlternativeName::new()
+        .dns(subject_name)
+        .build(&builder.x509v3_context(None, None))
+        .unwrap();
+    builder.append_extension(alt_name).unwrap();
+
+    builder.sign(ca_pkey, MessageDigest::sha256()).unwrap();
+    let cert = builder.build();
+
+    Pkcs12::builder()
+        .build("mypass", subject_name, &pkey, &cert)
+        .unwrap()
+}
+
+fn serial() -> openssl::asn1::Asn1Integer {
+    let mut serial = BigNum::new().unwrap();
+    serial.pseudo_rand(32, MSB_MAYBE_ZERO, false).unwrap();
+    serial.to_asn1_integer().unwrap()
+}
+
 fn lazy_udp_client(ipaddr: SocketAddr) -> UdpClientConnection {
     UdpClientConnection::new(ipaddr).unwrap()
 }
@@ -255,6 +481,22 @@ fn server_thread_udp(udp_socket: UdpSocket, server_continue: Arc<AtomicBool>) {
     }
 }
 
+fn server_thread_udp_rate_limited(
+    udp_socket: UdpSocket,
+    rate_limiter: Arc<RateLimiter>,
+    server_continue: Arc<AtomicBool>,
+) {
+    let catalog = new_catalog();
+
+    let mut server = ServerFuture::new(catalog).expect("new udp server failed");
+    server.set_rate_limiter(rate_limiter);
+    server.register_socket(udp_socket);
+
+    while server_continue.load(Ordering::Relaxed) {
+        server.tokio_core().turn(Some(Duration::from_millis(10)));
+    }
+}
+
 fn server_thread_tcp(tcp_listener: TcpListener, server_continue: Arc<AtomicBool>) {
     let catalog = new_catalog();
     let mut server = ServerFuture::new(catalog).expect("new tcp server failed");
@@ -267,6 +509,44 @@ fn server_thread_tcp(tcp_listener: TcpListener, server_continue: Arc<AtomicBool>
     }
 }
 
+fn server_thread_mtls(
+    tls_listener: TcpListener,
+    server_continue: Arc<AtomicBool>,
+    pkcs12_der: Vec<u8>,
+    client_ca_der: Vec<u8>,
+) {
+    // only clients with a certificate for resolv.example.com may query the zone
+    let mut catalog = new_catalog();
+    let client = Name::parse("resolv.example.com.", None).unwrap();
+    catalog.set_acls(
+        Name::parse("example.com.", None).unwrap().into(),
+        ZoneAcls::new(
+            Acl::new(vec![(false, AclElement::Certificate(client))]),
+            Acl::none(),
+            Acl::none(),
+        ),
+    );
+
+    let mut server = ServerFuture::new(catalog).expect("new tcp server failed");
+    let pkcs12 = Pkcs12::from_der(&pkcs12_der)
+        .expect("bad pkcs12 der")
+        .parse("mypass")
+        .expect("Pkcs12::from_der");
+    let client_ca = X509::from_der(&client_ca_der).expect("bad client ca der");
+    server
+        .register_tls_listener_with_client_ca(
+            tls_listener,
+            Duration::from_secs(30),
+            pkcs12,
+            vec![client_ca],
+        )
+        .expect("tcp registration failed");
+
+    while server_continue.load(Ordering::Relaxed) {
+        server.tokio_core().turn(Some(Duration::from_millis(10)));
+    }
+}
+
 fn server_thread_tls(
     tls_listener: TcpListener,
     server_continue: Arc<AtomicBool>,
@@ -0,0 +1,327 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate trust_dns;
+extern crate trust_dns_server;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::sync::mpsc::unbounded;
+use futures::sync::oneshot;
+use tokio_core::reactor::Core;
+
+use trust_dns::op::*;
+use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+use trust_dns::rr::rdata::{DNSSECRData, SOA};
+use trust_dns::rr::*;
+use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
+
+use trust_dns_server::authority::{Authority, MessageRequest, NotifyFuture, SlaveFuture,
+                                  ZoneType};
+
+fn tsigner(secret: &[u8]) -> TSigner {
+    TSigner::new(
+        secret.to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("transfer.example.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn origin() -> Name {
+    Name::parse("example.com.", None).unwrap()
+}
+
+fn soa() -> Record {
+    Record::from_rdata(
+        origin(),
+        3600,
+        RecordType::SOA,
+        RData::SOA(SOA::new(
+            Name::parse("sns.dns.icann.org.", None).unwrap(),
+            Name::parse("noc.dns.icann.org.", None).unwrap(),
+            2,
+            7200,
+            3600,
+            1209600,
+            3600,
+        )),
+    )
+}
+
+fn a(name: &str, last: u8) -> Record {
+    Record::from_rdata(
+        Name::parse(name, None).unwrap(),
+        86400,
+        RecordType::A,
+        RData::A(Ipv4Addr::new(93, 184, 216, last)),
+    )
+}
+
+fn mac(tsig: &Record) -> Vec<u8> {
+    match *tsig.rdata() {
+        RData::DNSSEC(DNSSECRData::TSIG(ref tsig)) => tsig.mac().to_vec(),
+        _ => panic!("expected a TSIG"),
+    }
+}
+
+fn read_message(stream: &mut TcpStream) -> Vec<u8> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).unwrap();
+    let mut bytes = vec![0u8; (len[0] as usize) << 8 | len[1] as usize];
+    stream.read_exact(&mut bytes).unwrap();
+    bytes
+}
+
+fn write_message(stream: &mut TcpStream, bytes: &[u8]) {
+    stream
+        .write_all(&[(bytes.len() >> 8) as u8, bytes.len() as u8])
+        .unwrap();
+    stream.write_all(bytes).unwrap();
+}
+
+/// A master which answers the SOA query, and then sends the AXFR in three messages, of which the
+///  middle one is not signed
+fn master(tsigner: TSigner, sign_last: bool) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::Builder::new()
+        .name("thread_slave_master".to_string())
+        .spawn(move || {
+            // the SOA and the AXFR are each queried on their own connection
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let bytes = read_message(&mut stream);
+
+                let request = Message::from_bytes(&bytes).unwrap();
+                let signed = MessageRequest::from_bytes(&bytes).unwrap();
+                let request_mac = tsigner
+                    .verify_message_byte(None, signed.unsigned_bytes(), &signed.sig0()[0], now())
+                    .expect("query to the master did not verify");
+
+                let query_type = request.queries()[0].query_type();
+                let messages = if query_type == RecordType::SOA {
+                    vec![vec![soa()]]
+                } else {
+                    vec![
+                        vec![soa(), a("www.example.com.", 34)],
+                        vec![a("mail.example.com.", 35)],
+                        vec![a("ftp.example.com.", 36), soa()],
+                    ]
+                };
+
+                let last = messages.len() - 1;
+                let mut prior_mac = request_mac;
+                let mut unsigned = Vec::new();
+                for (i, answers) in messages.into_iter().enumerate() {
+                    let mut response = Message::new();
+                    response
+                        .set_id(request.id())
+                        .set_message_type(MessageType::Response)
+                        .set_authoritative(true)
+                        .add_query(request.queries()[0].clone())
+                        .add_answers(answers);
+
+                    let tsig = if i == 0 {
+                        Some(tsigner.sign_message(&response, Some(&prior_mac[..]), now()))
+                    } else if i == last && sign_last {
+                        Some(tsigner.sign_subsequent_message(
+                            &response,
+                            &prior_mac,
+                            &unsigned,
+                            now(),
+                        ))
+                    } else {
+                        None
+                    };
+
+                    match tsig {
+                        Some(tsig) => {
+                            let tsig = tsig.unwrap();
+                            prior_mac = mac(&tsig);
+                            unsigned.clear();
+                            response.add_sig0(tsig);
+                            write_message(&mut stream, &response.to_bytes().unwrap());
+                        }
+                        None => {
+                            let bytes = response.to_bytes().unwrap();
+                            unsigned.extend_from_slice(&bytes);
+                            write_message(&mut stream, &bytes);
+                        }
+                    }
+                }
+            }
+        })
+        .unwrap();
+
+    addr
+}
+
+/// Runs the first refresh of a slave zone, signed with the key
+fn transfer(master: SocketAddr, tsigner: TSigner) -> Arc<RwLock<Authority>> {
+    let mut authority = Authority::new(origin(), BTreeMap::new(), ZoneType::Slave, false, false);
+    authority.add_tsig_key(tsigner);
+    refresh(master, authority)
+}
+
+/// Runs the first refresh of the slave zone
+fn refresh(master: SocketAddr, authority: Authority) -> Arc<RwLock<Authority>> {
+    let authority = Arc::new(RwLock::new(authority));
+
+    // without a Catalog to send notifications the slave stops after the first refresh
+    let (refresh, refreshes) = unbounded();
+    drop(refresh);
+
+    let mut io_loop = Core::new().unwrap();
+    let slave = SlaveFuture::new(
+        authority.clone(),
+        vec![master],
+        refreshes,
+        &io_loop.handle(),
+    );
+    io_loop.run(slave).unwrap();
+
+    authority
+}
+
+#[test]
+fn test_multi_message_transfer() {
+    let addr = master(tsigner(b"transfer secret"), true);
+    let authority = transfer(addr, tsigner(b"transfer secret"));
+
+    let authority = authority.read().unwrap();
+    assert_eq!(authority.serial(), 2);
+    assert!(!authority.is_expired());
+
+    // the records of all messages were transferred, the closing SOA is not duplicated
+    assert_eq!(authority.records().len(), 4);
+    for name in &["www.example.com.", "mail.example.com.", "ftp.example.com."] {
+        let key = RrKey::new(
+            LowerName::from(Name::from_str(name).unwrap()),
+            RecordType::A,
+        );
+        assert!(authority.records().contains_key(&key), "missing: {}", name);
+    }
+}
+
+#[test]
+fn test_transfer_unsigned_last_message() {
+    let addr = master(tsigner(b"transfer secret"), false);
+    let authority = transfer(addr, tsigner(b"transfer secret"));
+
+    // the last message must be signed, otherwise the transfer is discarded
+    let authority = authority.read().unwrap();
+    assert!(authority.records().is_empty());
+}
+
+#[test]
+fn test_refresh_up_to_date() {
+    let addr = master(tsigner(b"transfer secret"), true);
+
+    // a zone recovered at the serial of the master
+    let mut authority = Authority::new(origin(), BTreeMap::new(), ZoneType::Slave, false, false);
+    authority.add_tsig_key(tsigner(b"transfer secret"));
+    authority.upsert(soa(), 2);
+    authority.upsert(a("old.example.com.", 33), 2);
+    let authority = refresh(addr, authority);
+
+    // the zone is not transferred again
+    let authority = authority.read().unwrap();
+    assert_eq!(authority.serial(), 2);
+    assert_eq!(authority.records().len(), 2);
+    assert!(authority.refreshed().is_some());
+    assert!(!authority.is_expired());
+}
+
+#[test]
+fn test_stale_zone_expires() {
+    // no master is listening
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // the zone was last refreshed longer ago than the SOA expire, e.g. before a restart
+    let mut authority = Authority::new(origin(), BTreeMap::new(), ZoneType::Slave, false, false);
+    authority.upsert(soa(), 2);
+    authority
+        .set_refreshed(SystemTime::now() - Duration::from_secs(1209600 + 60))
+        .unwrap();
+    let authority = refresh(addr, authority);
+
+    let authority = authority.read().unwrap();
+    assert_eq!(authority.serial(), 2);
+    assert!(authority.is_expired());
+}
+
+#[test]
+fn test_notify_retransmitted_until_response() {
+    let tsigner = tsigner(b"transfer secret");
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let slave = socket.local_addr().unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    // a slave which drops the first NOTIFY, and responds to the retransmission
+    let (responded, notified) = oneshot::channel();
+    let verifier = tsigner.clone();
+    thread::Builder::new()
+        .name("thread_slave_notified".to_string())
+        .spawn(move || {
+            let mut buffer = [0u8; 4096];
+            socket.recv_from(&mut buffer).unwrap();
+            let (len, src) = socket.recv_from(&mut buffer).unwrap();
+
+            let request = Message::from_bytes(&buffer[..len]).unwrap();
+            assert_eq!(request.op_code(), OpCode::Notify);
+            assert_eq!(*request.queries()[0].name(), origin());
+            let signed = MessageRequest::from_bytes(&buffer[..len]).unwrap();
+            verifier
+                .verify_message_byte(None, signed.unsigned_bytes(), &signed.sig0()[0], now())
+                .expect("NOTIFY did not verify");
+
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(OpCode::Notify)
+                .add_query(request.queries()[0].clone());
+            socket.send_to(&response.to_bytes().unwrap(), src).unwrap();
+
+            // no NOTIFY follows the response, the next would be due after 400ms
+            socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+            let retransmitted = socket.recv_from(&mut buffer).is_ok();
+            responded.send(retransmitted).unwrap();
+        })
+        .unwrap();
+
+    let mut authority = Authority::new(origin(), BTreeMap::new(), ZoneType::Master, false, false);
+    authority.add_tsig_key(tsigner);
+    authority.upsert(soa(), 2);
+
+    let mut io_loop = Core::new().unwrap();
+    let (notify, notifications) = unbounded();
+    let mut notify_future = NotifyFuture::new(
+        Arc::new(RwLock::new(authority)),
+        vec![slave],
+        notifications,
+        &io_loop.handle(),
+    );
+    notify_future.set_retry_interval(Duration::from_millis(200));
+    io_loop.handle().spawn(notify_future);
+
+    notify.unbounded_send(()).unwrap();
+    assert!(!io_loop.run(notified).unwrap());
+}
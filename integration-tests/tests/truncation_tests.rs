@@ -0,0 +1,118 @@
+extern crate trust_dns;
+extern crate trust_dns_integration;
+extern crate trust_dns_server;
+
+use std::net::Ipv4Addr;
+
+use trust_dns::op::*;
+use trust_dns::rr::*;
+use trust_dns::rr::rdata::TXT;
+use trust_dns::serialize::binary::BinDecodable;
+
+use trust_dns_server::server::{ResponseHandler, TruncatingResponseHandle};
+
+use trust_dns_integration::TestResponseHandler;
+
+fn response(txt_records: usize, additionals: usize) -> Message {
+    let name = Name::parse("www.example.com.", None).unwrap();
+
+    let mut response = Message::new();
+    response
+        .set_id(10)
+        .set_message_type(MessageType::Response)
+        .add_query(Query::query(name.clone(), RecordType::TXT))
+        .set_edns(Edns::new());
+
+    for i in 0..txt_records {
+        response.add_answer(Record::from_rdata(
+            name.clone(),
+            86400,
+            RecordType::TXT,
+            RData::TXT(TXT::new(vec![format!("{:0200}", i)])),
+        ));
+    }
+
+    for i in 0..additionals {
+        response.add_additional(Record::from_rdata(
+            Name::parse(&format!("ns{}.example.com.", i), None).unwrap(),
+            86400,
+            RecordType::A,
+            RData::A(Ipv4Addr::new(192, 0, 2, i as u8)),
+        ));
+    }
+
+    response
+}
+
+fn send(response: Message, request_max_payload: u16, max_udp_payload: u16) -> (usize, Message) {
+    let response_handler = TestResponseHandler::new();
+    TruncatingResponseHandle::new(
+        response_handler.clone(),
+        request_max_payload,
+        max_udp_payload,
+    ).send(response)
+        .unwrap();
+
+    let bytes = response_handler.into_inner();
+    (bytes.len(), Message::from_bytes(&bytes).unwrap())
+}
+
+#[test]
+fn test_response_fits() {
+    let (len, sent) = send(response(1, 2), 512, 1232);
+
+    assert!(len <= 512);
+    assert!(!sent.truncated());
+    assert_eq!(sent.answers().len(), 1);
+    assert_eq!(sent.additionals().len(), 2);
+
+    // the server advertises its own limit
+    assert_eq!(sent.edns().unwrap().max_payload(), 1232);
+}
+
+#[test]
+fn test_additionals_left_out() {
+    // the answer fits, the glue does not
+    let (len, sent) = send(response(2, 20), 512, 1232);
+
+    assert!(len <= 512);
+    assert!(!sent.truncated());
+    assert_eq!(sent.answers().len(), 2);
+    assert!(sent.additionals().is_empty());
+}
+
+#[test]
+fn test_truncated() {
+    let (len, sent) = send(response(3, 0), 512, 1232);
+
+    assert!(len <= 512);
+    assert!(sent.truncated());
+    assert_eq!(sent.id(), 10);
+    assert_eq!(sent.queries().len(), 1);
+    assert!(sent.answers().is_empty());
+    assert!(sent.edns().is_some());
+
+    // with EDNS the client accepts the whole response
+    let (_, sent) = send(response(3, 0), 4096, 1232);
+    assert!(!sent.truncated());
+    assert_eq!(sent.answers().len(), 3);
+}
+
+#[test]
+fn test_server_max_udp_payload() {
+    // the client accepts 4096 bytes, but the server sends no more than 1232
+    let (len, sent) = send(response(8, 0), 4096, 1232);
+    assert!(len <= 1232);
+    assert!(sent.truncated());
+    assert!(sent.answers().is_empty());
+
+    let (len, sent) = send(response(8, 0), 4096, 4096);
+    assert!(len > 1232);
+    assert!(!sent.truncated());
+    assert_eq!(sent.answers().len(), 8);
+
+    // a client without EDNS is limited to 512 bytes, whatever it advertises
+    let (len, sent) = send(response(3, 0), 100, 100);
+    assert!(len <= 512);
+    assert!(sent.truncated());
+}
@@ -20,6 +20,7 @@ use tokio_tls::TlsStream as TokioTlsStream;
 
 use trust_dns::tcp::TcpClientStream;
 use trust_dns::error::ClientError;
+use trust_dns_proto::error::FromProtoError;
 use trust_dns_proto::{BufDnsStreamHandle, DnsStreamHandle};
 
 use TlsStreamBuilder;
@@ -67,12 +68,27 @@ impl TlsClientStreamBuilder {
         Box<Future<Item = TlsClientStream, Error = io::Error>>,
         Box<DnsStreamHandle<Error = ClientError>>,
     ) {
-        let (stream_future, sender) = self.0.build(name_server, dns_name, loop_handle);
+        self.build_with_error(name_server, dns_name, loop_handle)
+    }
+
+    /// Creates a new TlsStream to the specified name_server, see `build`, whose handle returns
+    ///  errors of the type of another client than `trust_dns::client`, e.g. the resolver
+    pub fn build_with_error<E: FromProtoError + 'static>(
+        self,
+        name_server: SocketAddr,
+        dns_name: String,
+        loop_handle: &Handle,
+    ) -> (
+        Box<Future<Item = TlsClientStream, Error = io::Error>>,
+        Box<DnsStreamHandle<Error = E>>,
+    ) {
+        let (stream_future, sender) = self.0.build_with_error(name_server, dns_name, loop_handle);
 
         let new_future: Box<Future<Item = TlsClientStream, Error = io::Error>> =
             Box::new(stream_future.map(move |tls_stream| TcpClientStream::from_stream(tls_stream)));
 
-        let sender = Box::new(BufDnsStreamHandle::new(name_server, sender));
+        // requests are padded, and the connection is kept open, see `DnsStreamHandle::is_encrypted`
+        let sender = Box::new(BufDnsStreamHandle::new_encrypted(name_server, sender));
 
         (new_future, sender)
     }
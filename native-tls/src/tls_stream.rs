@@ -21,6 +21,7 @@ use tokio_tls::{TlsConnectorExt, TlsStream as TokioTlsStream};
 use trust_dns::BufStreamHandle;
 use trust_dns::error::ClientError;
 use trust_dns::tcp::TcpStream;
+use trust_dns_proto::error::FromProtoError;
 
 /// A TlsStream counterpart to the TcpStream which embeds a secure TlsStream
 pub type TlsStream = TcpStream<TokioTlsStream<TokioTcpStream>>;
@@ -141,6 +142,20 @@ impl TlsStreamBuilder {
     ) -> (
         Box<Future<Item = TlsStream, Error = io::Error>>,
         BufStreamHandle<ClientError>,
+    ) {
+        self.build_with_error(name_server, dns_name, loop_handle)
+    }
+
+    /// Creates a new TlsStream to the specified name_server, see `build`, whose handle returns
+    ///  errors of the type of another client than `trust_dns::client`, e.g. the resolver
+    pub fn build_with_error<E: FromProtoError>(
+        self,
+        name_server: SocketAddr,
+        dns_name: String,
+        loop_handle: &Handle,
+    ) -> (
+        Box<Future<Item = TlsStream, Error = io::Error>>,
+        BufStreamHandle<E>,
     ) {
         let (message_sender, outbound_messages) = unbounded();
         let message_sender = BufStreamHandle::new(message_sender);
@@ -11,7 +11,7 @@ use std::io;
 
 use futures::Future;
 #[cfg(feature = "mtls")]
-use openssl::pkcs12::Pkcs12;
+use openssl::pkcs12::ParsedPkcs12;
 use openssl::x509::X509;
 use tokio_core::net::TcpStream as TokioTcpStream;
 use tokio_core::reactor::Handle;
@@ -54,8 +54,11 @@ impl TlsClientStreamBuilder {
     }
 
     /// Client side identity for client auth in TLS (aka mutual TLS auth)
+    ///
+    /// The certificate is presented to servers which ask for one, it must be signed by one of
+    ///  the client CAs of the server, see `tls_server::new_acceptor_with_client_ca`.
     #[cfg(feature = "mtls")]
-    pub fn identity(&mut self, pkcs12: Pkcs12) {
+    pub fn identity(&mut self, pkcs12: ParsedPkcs12) {
         self.0.identity(pkcs12);
     }
 
@@ -80,7 +83,8 @@ impl TlsClientStreamBuilder {
         let new_future: Box<Future<Item = TlsClientStream, Error = io::Error>> =
             Box::new(stream_future.map(move |tls_stream| TcpClientStream::from_stream(tls_stream)));
 
-        let sender = Box::new(BufDnsStreamHandle::new(name_server, sender));
+        // requests are padded, and the connection is kept open, see `DnsStreamHandle::is_encrypted`
+        let sender = Box::new(BufDnsStreamHandle::new_encrypted(name_server, sender));
 
         (new_future, sender)
     }
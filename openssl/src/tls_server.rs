@@ -1,6 +1,7 @@
 use openssl::pkcs12::*;
 use openssl::ssl;
-use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod};
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod, SslRef};
+use openssl::x509::store::X509StoreBuilder;
 
 use std::fs::File;
 use std::io;
@@ -8,6 +9,7 @@ use std::io::Read;
 use std::path::Path;
 
 pub use openssl::pkcs12::ParsedPkcs12;
+pub use openssl::x509::X509;
 pub use tokio_openssl::SslAcceptorExt;
 
 pub fn read_cert(path: &Path, password: Option<&str>) -> Result<ParsedPkcs12, String> {
@@ -28,7 +30,74 @@ pub fn read_cert(path: &Path, password: Option<&str>) -> Result<ParsedPkcs12, St
 }
 
 
+/// Reads the certificates of the CAs of clients, for mutual TLS, from a PEM file
+pub fn read_client_ca(path: &Path) -> Result<Vec<X509>, String> {
+    let mut file = File::open(&path).map_err(|e| {
+        format!("error opening client ca file: {:?}: {}", path, e)
+    })?;
+
+    let mut pem = vec![];
+    file.read_to_end(&mut pem).map_err(|e| {
+        format!("could not read client ca from: {:?}: {}", path, e)
+    })?;
+    let client_ca = X509::stack_from_pem(&pem).map_err(|e| {
+        format!("badly formated client ca from: {:?}: {}", path, e)
+    })?;
+
+    if client_ca.is_empty() {
+        return Err(format!("no certificates found in: {:?}", path));
+    }
+    Ok(client_ca)
+}
+
 pub fn new_acceptor(pkcs12: &ParsedPkcs12) -> io::Result<SslAcceptor> {
+    build_acceptor(pkcs12, &[], &[])
+}
+
+/// Returns a new acceptor, which negotiates one of the protocols with ALPN, e.g. `h2` for
+///  HTTP/2, no protocol is negotiated if there are none
+pub fn new_acceptor_with_alpn(
+    pkcs12: &ParsedPkcs12,
+    alpn_protocols: &[&[u8]],
+) -> io::Result<SslAcceptor> {
+    build_acceptor(pkcs12, alpn_protocols, &[])
+}
+
+/// Returns a new acceptor for mutual TLS, which asks clients for their certificate
+///
+/// Clients which present a certificate must have it signed by one of the CAs, otherwise the
+///  handshake fails, see `peer_names` for the identity of the client. Clients without a
+///  certificate are accepted.
+pub fn new_acceptor_with_client_ca(
+    pkcs12: &ParsedPkcs12,
+    client_ca: &[X509],
+) -> io::Result<SslAcceptor> {
+    build_acceptor(pkcs12, &[], client_ca)
+}
+
+/// Returns the names of the verified certificate of the client, the DNS names of its subject
+///  alternative names, the common name of the subject is not used
+pub fn peer_names(ssl: &SslRef) -> Vec<String> {
+    let cert = match ssl.peer_certificate() {
+        Some(cert) => cert,
+        None => return vec![],
+    };
+
+    cert.subject_alt_names()
+        .map(|alt_names| {
+            alt_names
+                .iter()
+                .filter_map(|alt_name| alt_name.dnsname().map(|name| name.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn build_acceptor(
+    pkcs12: &ParsedPkcs12,
+    alpn_protocols: &[&[u8]],
+    client_ca: &[X509],
+) -> io::Result<SslAcceptor> {
     let mut builder = SslAcceptorBuilder::mozilla_modern(
         SslMethod::tls(),
         &pkcs12.pkey,
@@ -49,6 +118,45 @@ pub fn new_acceptor(pkcs12: &ParsedPkcs12) -> io::Result<SslAcceptor> {
             ssl::SSL_OP_NO_SSLV2 | ssl::SSL_OP_NO_SSLV3 | ssl::SSL_OP_NO_TLSV1
                 | ssl::SSL_OP_NO_TLSV1_1,
         );
+
+        if !alpn_protocols.is_empty() {
+            ssl_context_bldr.set_alpn_protocols(alpn_protocols).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("tls error: {}", e),
+                )
+            })?;
+        }
+
+        if !client_ca.is_empty() {
+            let mut store = X509StoreBuilder::new().map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("tls error: {}", e),
+                )
+            })?;
+
+            for ca in client_ca {
+                store.add_cert(ca.clone()).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        format!("tls error: {}", e),
+                    )
+                })?;
+            }
+
+            ssl_context_bldr
+                .set_verify_cert_store(store.build())
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        format!("tls error: {}", e),
+                    )
+                })?;
+
+            // the certificate is optional, but if one is presented it must verify
+            ssl_context_bldr.set_verify(ssl::SSL_VERIFY_PEER);
+        }
     }
 
     Ok(builder.build())
@@ -240,11 +240,7 @@ fn config_mtls(
         let (_ /*client_pkey*/, _ /*client_cert*/, client_identity) =
             cert(client_name, root_pkey, root_name, root_cert);
 
-        let client_identity = Pkcs12::from_der(&client_identity)
-            .and_then(|p| p.parse("mypass"))
-            .expect("Pkcs12::from_der");
-        let client_identity =
-            Pkcs12::from_der(&client_identity.to_der().unwrap(), "mypass").unwrap();
+        let client_identity = client_identity.parse("mypass").expect("Pkcs12::parse");
 
         builder.identity(client_identity);
     }
@@ -322,7 +318,11 @@ fn cert(subject_name: &str, ca_pkey: &PKey, ca_name: &X509Name, _: &X509) -> (PK
     x509_build.set_pubkey(&pkey).unwrap();
     x509_build.set_serial_number(&serial).unwrap();
 
-    let ext_key_usage = ExtendedKeyUsage::new().server_auth().build().unwrap();
+    let ext_key_usage = ExtendedKeyUsage::new()
+        .server_auth()
+        .client_auth()
+        .build()
+        .unwrap();
     x509_build.append_extension(ext_key_usage).unwrap();
 
     let subject_key_identifier = SubjectKeyIdentifier::new()
@@ -77,6 +77,11 @@ error_chain! {
         display("dns key value unknown, must be 3: {}", value)
       }
 
+      Closed {
+        description("connection closed")
+        display("connection closed, the request may be retried on a new connection")
+      }
+
       DomainNameTooLong(len: usize) {
         description("name label data exceed 255")
         display("name label data exceed 255: {}", len)
@@ -156,6 +161,11 @@ error_chain! {
         description("maximum buffer size exceeded")
         display("maximum buffer size exceeded: {}", size)
       }
+
+      Tsig(error: ::op::ResponseCode) {
+        description("TSIG did not verify")
+        display("TSIG did not verify: {}", error)
+      }
     }
 }
 
@@ -218,6 +228,7 @@ impl Clone for ProtoErrorKind {
             ProtoErrorKind::AddrParseError => ProtoErrorKind::AddrParseError,
             ProtoErrorKind::Canceled(ref c) => ProtoErrorKind::Canceled(*c),
             ProtoErrorKind::CharacterDataTooLong(len) => ProtoErrorKind::CharacterDataTooLong(len),
+            ProtoErrorKind::Closed => ProtoErrorKind::Closed,
             ProtoErrorKind::DnsKeyProtocolNot3(value) => ProtoErrorKind::DnsKeyProtocolNot3(value),
             ProtoErrorKind::DomainNameTooLong(len) => ProtoErrorKind::DomainNameTooLong(len),
             ProtoErrorKind::EdnsNameNotRoot(ref found) => {
@@ -265,6 +276,7 @@ impl Clone for ProtoErrorKind {
             ProtoErrorKind::MaxBufferSizeExceeded(ref max) => {
                 ProtoErrorKind::MaxBufferSizeExceeded(*max)
             }
+            ProtoErrorKind::Tsig(error) => ProtoErrorKind::Tsig(error),
         }
     }
 }
@@ -104,6 +104,7 @@ where
 {
     name_server: SocketAddr,
     sender: BufStreamHandle<E>,
+    encrypted: bool,
 }
 
 impl<E> BufDnsStreamHandle<E>
@@ -120,6 +121,22 @@ where
         BufDnsStreamHandle {
             name_server: name_server,
             sender: sender,
+            encrypted: false,
+        }
+    }
+
+    /// Constructs a new Buffered Stream Handle for an encrypted stream, see
+    ///  `DnsStreamHandle::is_encrypted`
+    ///
+    /// # Arguments
+    ///
+    /// * `name_server` - the address of the DNS server
+    /// * `sender` - the handle being used to send data to the server
+    pub fn new_encrypted(name_server: SocketAddr, sender: BufStreamHandle<E>) -> Self {
+        BufDnsStreamHandle {
+            name_server: name_server,
+            sender: sender,
+            encrypted: true,
         }
     }
 }
@@ -138,4 +155,8 @@ where
             .unbounded_send((buffer, name_server))
             .map_err(|e| E::from(ProtoErrorKind::Msg(format!("mpsc::SendError {}", e)).into()))
     }
+
+    fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
 }
@@ -101,10 +101,7 @@ impl MdnsClientStream {
                 }
             }));
 
-        let sender = Box::new(BufDnsStreamHandle {
-            name_server: mdns_addr,
-            sender: sender,
-        });
+        let sender = Box::new(BufDnsStreamHandle::new(mdns_addr, sender));
 
         (new_future, sender)
     }
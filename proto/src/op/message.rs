@@ -115,6 +115,12 @@ pub trait EncodableMessage {
     /// Any SIG0 records for signed messages
     fn sig0(&self) -> &[Record];
 
+    /// True if the additionals hold the in-domain glue of a referral, the addresses of name
+    ///  servers within the delegated zone, without which the referral can not be followed
+    fn has_in_domain_glue(&self) -> bool {
+        false
+    }
+
     /// Returns a new Header with accurate counts for each Message section
     fn update_header_counts(&self, include_sig0: bool) -> Header {
         assert!(self.queries_len() <= u16::max_value() as usize);
@@ -381,12 +387,15 @@ impl Message {
         self
     }
 
-    /// Add a SIG0 record, i.e. sign this message
+    /// Add a SIG0 or TSIG record, i.e. sign this message
     ///
     /// This must be don't only after all records have been associated. Generally this will be handled by the client and not need to be used directly
     #[cfg(feature = "dnssec")]
     pub fn add_sig0(&mut self, record: Record) -> &mut Self {
-        assert_eq!(RecordType::DNSSEC(DNSSECRecordType::SIG), record.rr_type());
+        assert!(
+            record.rr_type() == RecordType::DNSSEC(DNSSECRecordType::SIG)
+                || record.rr_type() == RecordType::DNSSEC(DNSSECRecordType::TSIG)
+        );
         self.sig0.push(record);
         self
     }
@@ -594,7 +603,7 @@ impl Message {
     ///
     /// # Returns
     ///
-    /// This returns a tuple of first standard Records, then a possibly associated Edns, and then finally any optionally associated SIG0 records.
+    /// This returns a tuple of first standard Records, then a possibly associated Edns, and then finally any optionally associated SIG0 or TSIG records.
     #[cfg_attr(not(feature = "dnssec"), allow(unused_mut))]
     pub fn read_records(
         decoder: &mut BinDecoder,
@@ -624,6 +633,20 @@ impl Message {
                         saw_sig0 = true;
                         sig0s.push(record);
                     }
+                    // TSIG is also final, and there is only ever one
+                    #[cfg(feature = "dnssec")]
+                    RecordType::DNSSEC(DNSSECRecordType::TSIG) => {
+                        if saw_sig0 {
+                            return Err(
+                                ProtoErrorKind::Message(
+                                    "tsig must be final resource \
+                                     record",
+                                ).into(),
+                            );
+                        }
+                        saw_sig0 = true;
+                        sig0s.push(record);
+                    }
                     RecordType::OPT => {
                         if saw_sig0 {
                             return Err(
@@ -699,9 +722,11 @@ impl Message {
         // append all records to message
         for fin in finals {
             match fin.rr_type() {
-                // SIG0's are special, and come at the very end of the message
+                // SIG0's and TSIG's are special, and come at the very end of the message
                 #[cfg(feature = "dnssec")]
                 RecordType::DNSSEC(DNSSECRecordType::SIG) => self.add_sig0(fin),
+                #[cfg(feature = "dnssec")]
+                RecordType::DNSSEC(DNSSECRecordType::TSIG) => self.add_sig0(fin),
                 _ => self.add_additional(fin),
             };
         }
@@ -727,6 +752,13 @@ pub trait MessageFinalizer {
     ///
     /// A vector to append to the additionals section of the message, sorted in the order as they should appear in the message.
     fn finalize_message(&self, message: &Message, current_time: u32) -> ProtoResult<Vec<Record>>;
+
+    /// Returns true if the message should be finalized before it is sent
+    ///
+    /// By default only Update messages are finalized, i.e. signed with SIG(0).
+    fn should_finalize_message(&self, message: &Message) -> bool {
+        message.op_code() == OpCode::Update
+    }
 }
 
 /// A MessageFinalizer which does nothing
@@ -172,6 +172,39 @@ impl Nsec3HashAlgorithm {
         };
         DigestType::SHA1.digest_all(&[to_digest, salt])
     }
+
+    /// Returns the owner name of the NSEC3 record for the name
+    ///
+    /// ```text
+    /// RFC 5155                         NSEC3                        March 2008
+    ///
+    /// 3.  The NSEC3 Resource Record
+    ///
+    ///    The owner name for the NSEC3 RR is the base32 encoding of the hashed
+    ///    owner name prepended as a single label to the name of the zone.
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `salt` - the salt of the NSEC3 chain
+    /// * `name` - the name to hash
+    /// * `iterations` - the additional iterations of the NSEC3 chain
+    /// * `zone` - the name of the zone of the NSEC3 chain
+    #[cfg(any(feature = "openssl", feature = "ring"))]
+    pub fn hashed_owner_name(
+        &self,
+        salt: &[u8],
+        name: &Name,
+        iterations: u16,
+        zone: &Name,
+    ) -> ProtoResult<Name> {
+        use data_encoding::BASE32_DNSSEC;
+
+        let hash = self.hash(salt, name, iterations)?;
+        let label = BASE32_DNSSEC.encode(hash.as_ref());
+
+        Ok(Name::from_labels(vec![label.as_str()])?.append_domain(zone))
+    }
 }
 
 impl From<Nsec3HashAlgorithm> for u8 {
@@ -286,6 +319,24 @@ fn test_known_hashes() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "openssl", feature = "ring"))]
+fn test_hashed_owner_name() {
+    // NSEC3PARAM 1 0 12 aabbccdd
+    let zone = Name::parse("example.", None).unwrap();
+    let known_salt = [0xAAu8, 0xBBu8, 0xCCu8, 0xDDu8];
+
+    let owner = Nsec3HashAlgorithm::SHA1
+        .hashed_owner_name(&known_salt, &Name::parse("a.example.", None).unwrap(), 12, &zone)
+        .unwrap();
+
+    // H(a.example)     = 35mthgpgcu1qg68fab165klnsnk3dpvl
+    assert_eq!(
+        owner,
+        Name::parse("35mthgpgcu1qg68fab165klnsnk3dpvl.example.", None).unwrap()
+    );
+}
+
 #[cfg(test)]
 #[cfg(any(feature = "openssl", feature = "ring"))]
 fn hash_with_base32(name: &str) -> String {
@@ -0,0 +1,602 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! transaction signature for authenticating messages with a shared secret
+
+#[cfg(all(not(feature = "ring"), feature = "openssl"))]
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+#[cfg(feature = "ring")]
+use ring::{digest, hmac};
+
+use error::*;
+use op::{EncodableMessage, ResponseCode};
+use rr::dnssec::rdata::{DNSSECRData, DNSSECRecordType};
+use rr::{DNSClass, Name, RData, Record, RecordType};
+use serialize::binary::*;
+
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945), Secret Key Transaction Authentication for DNS (TSIG), November 2020
+///
+/// ```text
+/// 4.2.  TSIG Record Format
+///
+///    The fields of the TSIG RR are described below.  All multi-octet
+///    integers in the record are sent in network byte order (see
+///    Section 2.3.2 of [RFC1035]).
+///
+///    NAME:  The name of the key used, in domain name syntax.  The name
+///       should reflect the names of the hosts and uniquely identify the
+///       key among a set of keys these two hosts may share at any given
+///       time.
+///
+///    TYPE:  This MUST be TSIG (250: Transaction SIGnature).
+///
+///    CLASS:  This MUST be ANY.
+///
+///    TTL:  This MUST be 0.
+///
+///    RDATA:  The RDATA for a TSIG RR consists of a number of fields,
+///       described below:
+///
+///                            1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       /                         Algorithm Name                        /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |                                                               |
+///       |          Time Signed          +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |                               |            Fudge              |
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |          MAC Size             |                               /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+             MAC               /
+///       /                                                               /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |          Original ID          |            Error              |
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |          Other Len            |                               /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+           Other Data          /
+///       /                                                               /
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct TSIG {
+    algorithm: TsigAlgorithm,
+    time: u64,
+    fudge: u16,
+    mac: Vec<u8>,
+    oid: u16,
+    error: u16,
+    other: Vec<u8>,
+}
+
+/// Algorithms for the MAC of a TSIG, the algorithm is identified by name
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-6), TSIG, November 2020
+///
+/// ```text
+///    Implementations that support TSIG MUST also implement HMAC SHA1 and
+///    HMAC SHA256 and MAY implement gss-tsig and the other algorithms
+///    listed below.  SHA-1 truncated to 80 bits and SHA-256 truncated to
+///    128 bits are RECOMMENDED to be implemented.
+/// ```
+///
+/// Only the untruncated SHA-2 HMACs are supported.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum TsigAlgorithm {
+    /// HMAC-SHA256, `hmac-sha256.`
+    HmacSha256,
+    /// HMAC-SHA512, `hmac-sha512.`
+    HmacSha512,
+    /// An unsupported algorithm, by name
+    Unknown(Name),
+}
+
+impl TSIG {
+    /// Constructs a new TSIG
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - the MAC algorithm
+    /// * `time` - seconds since the Unix epoch when the message was signed, only 48 bits are used
+    /// * `fudge` - seconds of error permitted in `time`
+    /// * `mac` - the MAC of the message, empty before signing
+    /// * `oid` - the original id of the message
+    /// * `error` - the extended response code, `0` for no error
+    /// * `other` - other data, the server time for BADTIME errors
+    pub fn new(
+        algorithm: TsigAlgorithm,
+        time: u64,
+        fudge: u16,
+        mac: Vec<u8>,
+        oid: u16,
+        error: u16,
+        other: Vec<u8>,
+    ) -> Self {
+        TSIG {
+            algorithm,
+            time,
+            fudge,
+            mac,
+            oid,
+            error,
+            other,
+        }
+    }
+
+    /// Add actual MAC value to existing TSIG record data.
+    pub fn set_mac(self, mac: Vec<u8>) -> Self {
+        TSIG { mac, ..self }
+    }
+
+    /// the MAC algorithm
+    pub fn algorithm(&self) -> &TsigAlgorithm {
+        &self.algorithm
+    }
+
+    /// seconds since the Unix epoch when the message was signed
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// seconds of error permitted in `time`
+    pub fn fudge(&self) -> u16 {
+        self.fudge
+    }
+
+    /// the MAC of the message
+    pub fn mac(&self) -> &[u8] {
+        &self.mac
+    }
+
+    /// the original id of the message
+    pub fn oid(&self) -> u16 {
+        self.oid
+    }
+
+    /// the extended response code, i.e. BADSIG (16), BADKEY (17), BADTIME (18), BADTRUNC (22)
+    pub fn error(&self) -> u16 {
+        self.error
+    }
+
+    /// other data, the server time for BADTIME errors
+    pub fn other(&self) -> &[u8] {
+        &self.other
+    }
+}
+
+impl TsigAlgorithm {
+    /// Returns the name of the algorithm, as used in the TSIG record
+    pub fn to_name(&self) -> Name {
+        match *self {
+            TsigAlgorithm::HmacSha256 => Name::from_ascii("hmac-sha256.").unwrap(),
+            TsigAlgorithm::HmacSha512 => Name::from_ascii("hmac-sha512.").unwrap(),
+            TsigAlgorithm::Unknown(ref name) => name.clone(),
+        }
+    }
+
+    /// Returns the algorithm for the name, names are compared case insensitively
+    pub fn from_name(name: Name) -> Self {
+        let lower = name.to_lowercase();
+        if lower == TsigAlgorithm::HmacSha256.to_name() {
+            TsigAlgorithm::HmacSha256
+        } else if lower == TsigAlgorithm::HmacSha512.to_name() {
+            TsigAlgorithm::HmacSha512
+        } else {
+            TsigAlgorithm::Unknown(name)
+        }
+    }
+
+    /// Computes the MAC of the message with the key
+    #[cfg(feature = "ring")]
+    pub fn mac(&self, key: &[u8], message: &[u8]) -> ProtoResult<Vec<u8>> {
+        let key = hmac::SigningKey::new(self.to_ring_digest_alg()?, key);
+        Ok(hmac::sign(&key, message).as_ref().to_vec())
+    }
+
+    /// Verifies the MAC of the message with the key, in constant time
+    #[cfg(feature = "ring")]
+    pub fn verify_mac(&self, key: &[u8], message: &[u8], mac: &[u8]) -> ProtoResult<()> {
+        let key = hmac::SigningKey::new(self.to_ring_digest_alg()?, key);
+        hmac::verify_with_own_key(&key, message, mac).map_err(|_| "TSIG MAC mismatch".into())
+    }
+
+    #[cfg(feature = "ring")]
+    fn to_ring_digest_alg(&self) -> ProtoResult<&'static digest::Algorithm> {
+        match *self {
+            TsigAlgorithm::HmacSha256 => Ok(&digest::SHA256),
+            TsigAlgorithm::HmacSha512 => Ok(&digest::SHA512),
+            TsigAlgorithm::Unknown(ref name) => {
+                Err(ProtoErrorKind::Msg(format!("unsupported TSIG algorithm: {}", name)).into())
+            }
+        }
+    }
+
+    /// Computes the MAC of the message with the key
+    #[cfg(all(not(feature = "ring"), feature = "openssl"))]
+    pub fn mac(&self, key: &[u8], message: &[u8]) -> ProtoResult<Vec<u8>> {
+        let key = PKey::hmac(key)?;
+        let mut signer = Signer::new(self.to_openssl_digest()?, &key)?;
+        signer.update(message)?;
+        signer.sign_to_vec().map_err(|e| e.into())
+    }
+
+    /// Verifies the MAC of the message with the key, in constant time
+    #[cfg(all(not(feature = "ring"), feature = "openssl"))]
+    pub fn verify_mac(&self, key: &[u8], message: &[u8], mac: &[u8]) -> ProtoResult<()> {
+        let expected = self.mac(key, message)?;
+        if expected.len() == mac.len() && memcmp::eq(&expected, mac) {
+            Ok(())
+        } else {
+            Err("TSIG MAC mismatch".into())
+        }
+    }
+
+    #[cfg(all(not(feature = "ring"), feature = "openssl"))]
+    fn to_openssl_digest(&self) -> ProtoResult<MessageDigest> {
+        match *self {
+            TsigAlgorithm::HmacSha256 => Ok(MessageDigest::sha256()),
+            TsigAlgorithm::HmacSha512 => Ok(MessageDigest::sha512()),
+            TsigAlgorithm::Unknown(ref name) => {
+                Err(ProtoErrorKind::Msg(format!("unsupported TSIG algorithm: {}", name)).into())
+            }
+        }
+    }
+
+    /// This will always error, enable openssl or ring feature at compile time
+    #[cfg(not(any(feature = "openssl", feature = "ring")))]
+    pub fn mac(&self, _: &[u8], _: &[u8]) -> ProtoResult<Vec<u8>> {
+        Err(ProtoErrorKind::Message("The openssl and ring features are both disabled").into())
+    }
+
+    /// This will always error, enable openssl or ring feature at compile time
+    #[cfg(not(any(feature = "openssl", feature = "ring")))]
+    pub fn verify_mac(&self, _: &[u8], _: &[u8], _: &[u8]) -> ProtoResult<()> {
+        Err(ProtoErrorKind::Message("The openssl and ring features are both disabled").into())
+    }
+}
+
+/// Read the RData from the given Decoder
+pub fn read(decoder: &mut BinDecoder, rdata_length: u16) -> ProtoResult<TSIG> {
+    let start_idx = decoder.index();
+
+    let algorithm = TsigAlgorithm::from_name(Name::read(decoder)?);
+    let time_high = decoder.read_u16()? as u64;
+    let time_low = decoder.read_u32()? as u64;
+    let fudge = decoder.read_u16()?;
+    let mac_size = decoder.read_u16()?;
+    let mac = decoder.read_vec(mac_size as usize)?;
+    let oid = decoder.read_u16()?;
+    let error = decoder.read_u16()?;
+    let other_len = decoder.read_u16()?;
+    let other = decoder.read_vec(other_len as usize)?;
+
+    if decoder.index() - start_idx != rdata_length as usize {
+        return Err(ProtoErrorKind::Message("TSIG rdata length mismatch").into());
+    }
+
+    Ok(TSIG::new(
+        algorithm,
+        (time_high << 32) | time_low,
+        fudge,
+        mac,
+        oid,
+        error,
+        other,
+    ))
+}
+
+/// Write the RData to the given Encoder, the algorithm name is never compressed
+pub fn emit(encoder: &mut BinEncoder, tsig: &TSIG) -> ProtoResult<()> {
+    tsig.algorithm().to_name().emit_as_canonical(encoder, true)?;
+    emit_time(encoder, tsig.time())?;
+    encoder.emit_u16(tsig.fudge())?;
+    encoder.emit_u16(tsig.mac().len() as u16)?;
+    encoder.emit_vec(tsig.mac())?;
+    encoder.emit_u16(tsig.oid())?;
+    encoder.emit_u16(tsig.error())?;
+    encoder.emit_u16(tsig.other().len() as u16)?;
+    encoder.emit_vec(tsig.other())?;
+    Ok(())
+}
+
+fn emit_time(encoder: &mut BinEncoder, time: u64) -> ProtoResult<()> {
+    encoder.emit_u16((time >> 32) as u16)?;
+    encoder.emit_u32(time as u32)
+}
+
+/// Returns a TSIG record for the key
+///
+/// # Arguments
+///
+/// * `key_name` - the name of the shared key
+/// * `tsig` - the record data
+pub fn make_tsig_record(key_name: Name, tsig: TSIG) -> Record {
+    let mut record = Record::new();
+    record
+        .set_name(key_name)
+        .set_rr_type(RecordType::DNSSEC(DNSSECRecordType::TSIG))
+        .set_dns_class(DNSClass::ANY)
+        .set_ttl(0)
+        .set_rdata(RData::DNSSEC(DNSSECRData::TSIG(tsig)));
+    record
+}
+
+/// Returns the unsigned TSIG record of the error response to a message whose TSIG did not verify
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-5.3.2), TSIG, November 2020
+///
+/// ```text
+/// 5.3.2.  Generation of TSIG on Error Returns
+///
+///    When a server detects an error relating to the key or MAC in the
+///    incoming request, the server SHOULD send back an unsigned error
+///    message (MAC Size == 0 and empty MAC).  It MUST NOT send back a
+///    signed error message.
+/// ```
+///
+/// # Arguments
+///
+/// * `tsig` - the TSIG record of the message
+/// * `error` - the TSIG error, e.g. BADKEY or BADSIG
+pub fn make_error_tsig_record(tsig: &Record, error: ResponseCode) -> ProtoResult<Record> {
+    let rdata = tsig_rdata(tsig)?;
+
+    Ok(make_tsig_record(
+        tsig.name().clone(),
+        TSIG::new(
+            rdata.algorithm().clone(),
+            rdata.time(),
+            rdata.fudge(),
+            Vec::new(),
+            rdata.oid(),
+            error.into(),
+            Vec::new(),
+        ),
+    ))
+}
+
+/// Returns the data over which the MAC is computed for a message, before the TSIG is added
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-4.3.3), TSIG, November 2020
+///
+/// ```text
+/// 4.3.3.  TSIG Variables
+///
+///    Also included in the digest is certain information present in the
+///    TSIG RR.  Adding this data provides further protection against an
+///    attempt to interfere with the message.
+///
+///    +============+================+====================================+
+///    | Source     | Field Name     | Notes                              |
+///    +============+================+====================================+
+///    | TSIG RR    | NAME           | Key name, in canonical wire format |
+///    | TSIG RR    | CLASS          | MUST be ANY                        |
+///    | TSIG RR    | TTL            | MUST be 0                          |
+///    | TSIG RDATA | Algorithm Name | in canonical wire format           |
+///    | TSIG RDATA | Time Signed    | in network byte order              |
+///    | TSIG RDATA | Fudge          | in network byte order              |
+///    | TSIG RDATA | Error          | in network byte order              |
+///    | TSIG RDATA | Other Len      | in network byte order              |
+///    | TSIG RDATA | Other Data     | exactly as transmitted             |
+///    +------------+----------------+------------------------------------+
+/// ```
+///
+/// # Arguments
+///
+/// * `request_mac` - for responses, the MAC of the request
+/// * `message` - the message to sign, without a TSIG
+/// * `key_name` - the name of the shared key
+/// * `pre_tsig` - the TSIG record data, without the MAC
+pub fn message_tbs<M: EncodableMessage>(
+    request_mac: Option<&[u8]>,
+    message: &M,
+    key_name: &Name,
+    pre_tsig: &TSIG,
+) -> ProtoResult<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::with_capacity(512);
+    {
+        let mut encoder: BinEncoder = BinEncoder::with_mode(&mut buf, EncodeMode::Signing);
+        message.emit(&mut encoder)?;
+    }
+
+    variables_tbs(request_mac, &buf, key_name, pre_tsig)
+}
+
+/// Returns the data over which the MAC is computed for a message as it was received
+///
+/// The header of the message is restored to the state before the TSIG was added, i.e. with the
+///  original id and one less additional record.
+///
+/// # Arguments
+///
+/// * `request_mac` - for responses, the MAC of the request
+/// * `unsigned_message` - the message as received, up to but excluding the TSIG record
+/// * `tsig` - the TSIG record of the message
+pub fn signed_message_tbs(
+    request_mac: Option<&[u8]>,
+    unsigned_message: &[u8],
+    tsig: &Record,
+) -> ProtoResult<Vec<u8>> {
+    let pre_tsig = tsig_rdata(tsig)?;
+    let message = restore_header(unsigned_message, pre_tsig)?;
+
+    variables_tbs(request_mac, &message, tsig.name(), pre_tsig)
+}
+
+/// Returns the data over which the MAC is computed for a message after the first of a
+///  multi-message response, e.g. a zone transfer, before the TSIG is added
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-5.3.1), TSIG, November 2020
+///
+/// ```text
+/// 5.3.1.  TSIG on TCP Connections
+///
+///    A DNS TCP session, such as a zone transfer, can include multiple DNS
+///    messages.  Using TSIG on such a connection can protect the connection
+///    from an attack and provide data integrity.  The TSIG MUST be included
+///    on all DNS messages in the response.  For backward compatibility, a
+///    client that receives DNS messages and verifies TSIG MUST accept up to
+///    99 intermediary messages without a TSIG and MUST verify that both the
+///    first and last message contain a TSIG.
+///
+///    The first message is processed as a standard message (see
+///    Section 4.3.3).  Subsequent messages are processed differently: the
+///    digest components are:
+///
+///    | Prior MAC (including size) |
+///    | DNS Messages since the last TSIG |
+///    | TSIG Timers |
+/// ```
+///
+/// # Arguments
+///
+/// * `prior_mac` - the MAC of the last message which had a TSIG
+/// * `unsigned_messages` - the messages since the last TSIG, as they were sent
+/// * `message` - the message to sign, without a TSIG
+/// * `pre_tsig` - the TSIG record data, without the MAC
+pub fn subsequent_message_tbs<M: EncodableMessage>(
+    prior_mac: &[u8],
+    unsigned_messages: &[u8],
+    message: &M,
+    pre_tsig: &TSIG,
+) -> ProtoResult<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::with_capacity(512);
+    {
+        let mut encoder: BinEncoder = BinEncoder::with_mode(&mut buf, EncodeMode::Signing);
+        message.emit(&mut encoder)?;
+    }
+
+    let mut messages = unsigned_messages.to_vec();
+    messages.extend_from_slice(&buf);
+
+    timers_tbs(prior_mac, &messages, pre_tsig)
+}
+
+/// Returns the data over which the MAC is computed for a message after the first of a
+///  multi-message response as it was received, see `subsequent_message_tbs`
+///
+/// # Arguments
+///
+/// * `prior_mac` - the MAC of the last message which had a TSIG
+/// * `unsigned_messages` - the messages since the last TSIG, as they were received
+/// * `unsigned_message` - the message as received, up to but excluding the TSIG record
+/// * `tsig` - the TSIG record of the message
+pub fn signed_subsequent_message_tbs(
+    prior_mac: &[u8],
+    unsigned_messages: &[u8],
+    unsigned_message: &[u8],
+    tsig: &Record,
+) -> ProtoResult<Vec<u8>> {
+    let pre_tsig = tsig_rdata(tsig)?;
+
+    let mut messages = unsigned_messages.to_vec();
+    messages.extend_from_slice(&restore_header(unsigned_message, pre_tsig)?);
+
+    timers_tbs(prior_mac, &messages, pre_tsig)
+}
+
+fn tsig_rdata(tsig: &Record) -> ProtoResult<&TSIG> {
+    match *tsig.rdata() {
+        RData::DNSSEC(DNSSECRData::TSIG(ref tsig)) => Ok(tsig),
+        _ => Err(ProtoErrorKind::Message("not a TSIG record").into()),
+    }
+}
+
+// the header of the message before the TSIG was added, i.e. with the original id and one less
+//  additional record
+fn restore_header(unsigned_message: &[u8], pre_tsig: &TSIG) -> ProtoResult<Vec<u8>> {
+    // the header is 12 bytes, the id is at 0, and the additional count at 10
+    if unsigned_message.len() < 12 {
+        return Err(ProtoErrorKind::Message("message too short for TSIG").into());
+    }
+
+    let mut message = unsigned_message.to_vec();
+    let additional_count = ((message[10] as u16) << 8 | message[11] as u16)
+        .checked_sub(1)
+        .ok_or_else(|| ProtoError::from(ProtoErrorKind::Message("TSIG is not counted")))?;
+    message[0] = (pre_tsig.oid() >> 8) as u8;
+    message[1] = pre_tsig.oid() as u8;
+    message[10] = (additional_count >> 8) as u8;
+    message[11] = additional_count as u8;
+
+    Ok(message)
+}
+
+// 5.3.1, only the timers of the TSIG are digested for messages after the first
+fn timers_tbs(prior_mac: &[u8], messages: &[u8], pre_tsig: &TSIG) -> ProtoResult<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::with_capacity(prior_mac.len() + messages.len() + 10);
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut buf);
+        encoder.emit_u16(prior_mac.len() as u16)?;
+        encoder.emit_vec(prior_mac)?;
+        encoder.emit_vec(messages)?;
+        emit_time(&mut encoder, pre_tsig.time())?;
+        encoder.emit_u16(pre_tsig.fudge())?;
+    }
+
+    Ok(buf)
+}
+
+fn variables_tbs(
+    request_mac: Option<&[u8]>,
+    message: &[u8],
+    key_name: &Name,
+    pre_tsig: &TSIG,
+) -> ProtoResult<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::with_capacity(message.len() + 128);
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut buf);
+
+        // 4.3.2, the request MAC, with its size, is the first component of the response digest
+        if let Some(request_mac) = request_mac {
+            encoder.emit_u16(request_mac.len() as u16)?;
+            encoder.emit_vec(request_mac)?;
+        }
+
+        encoder.emit_vec(message)?;
+
+        key_name.emit_as_canonical(&mut encoder, true)?;
+        DNSClass::ANY.emit(&mut encoder)?;
+        encoder.emit_u32(0)?;
+        pre_tsig
+            .algorithm()
+            .to_name()
+            .emit_as_canonical(&mut encoder, true)?;
+        emit_time(&mut encoder, pre_tsig.time())?;
+        encoder.emit_u16(pre_tsig.fudge())?;
+        encoder.emit_u16(pre_tsig.error())?;
+        encoder.emit_u16(pre_tsig.other().len() as u16)?;
+        encoder.emit_vec(pre_tsig.other())?;
+    }
+
+    Ok(buf)
+}
+
+#[test]
+fn test() {
+    let rdata = TSIG::new(
+        TsigAlgorithm::HmacSha256,
+        0x0001_5A5B_5C5D,
+        300,
+        vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        0x1234,
+        0,
+        vec![],
+    );
+
+    let mut bytes = Vec::new();
+    let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+    assert!(emit(&mut encoder, &rdata).is_ok());
+    let bytes = encoder.into_bytes();
+
+    let mut decoder: BinDecoder = BinDecoder::new(bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16);
+    assert!(
+        read_rdata.is_ok(),
+        format!("error decoding: {:?}", read_rdata.unwrap_err())
+    );
+    assert_eq!(rdata, read_rdata.unwrap());
+}
@@ -17,6 +17,7 @@
 //! option record for passing protocol options between the client and server
 
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use serialize::binary::*;
 use error::*;
@@ -211,11 +212,17 @@ pub fn read(decoder: &mut BinDecoder, rdata_length: u16) -> ProtoResult<OPT> {
             }
             OptReadState::Code { code } => {
                 let length: usize = decoder.read_u16()? as usize;
-                state = OptReadState::Data {
-                    code: code,
-                    length: length,
-                    collected: Vec::<u8>::with_capacity(length),
-                };
+                if length == 0 {
+                    // options without data, e.g. NSID or Keepalive in queries, have nothing to collect
+                    options.insert(code, (code, &[] as &[u8]).into());
+                    state = OptReadState::ReadCode;
+                } else {
+                    state = OptReadState::Data {
+                        code: code,
+                        length: length,
+                        collected: Vec::<u8>::with_capacity(length),
+                    };
+                }
             }
             OptReadState::Data {
                 code,
@@ -295,19 +302,19 @@ pub enum EdnsCode {
     /// [RFC 6975, NSEC3 Hash Understood](https://tools.ietf.org/html/rfc6975)
     N3U,
 
-    /// [edns-client-subnet, Optional](https://tools.ietf.org/html/draft-vandergaast-edns-client-subnet-02)
+    /// [RFC 7871, Client Subnet in DNS Queries, Optional](https://tools.ietf.org/html/rfc7871)
     Subnet,
 
     /// [RFC 7314, EDNS EXPIRE, Optional](https://tools.ietf.org/html/rfc7314)
     Expire,
 
-    /// [draft-ietf-dnsop-cookies](https://tools.ietf.org/html/draft-ietf-dnsop-cookies-07)
+    /// [RFC 7873, Domain Name System (DNS) Cookies](https://tools.ietf.org/html/rfc7873)
     Cookie,
 
-    /// [draft-ietf-dnsop-edns-tcp-keepalive, Optional](https://tools.ietf.org/html/draft-ietf-dnsop-edns-tcp-keepalive-04)
+    /// [RFC 7828, The edns-tcp-keepalive EDNS0 Option](https://tools.ietf.org/html/rfc7828)
     Keepalive,
 
-    /// [draft-mayrhofer-edns0-padding, Optional](https://tools.ietf.org/html/draft-mayrhofer-edns0-padding-01)
+    /// [RFC 7830, The EDNS(0) Padding Option](https://tools.ietf.org/html/rfc7830)
     Padding,
 
     /// [draft-ietf-dnsop-edns-chain-query](https://tools.ietf.org/html/draft-ietf-dnsop-edns-chain-query-07)
@@ -369,6 +376,11 @@ impl From<EdnsCode> for u16 {
 /// http://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-13
 #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Hash)]
 pub enum EdnsOption {
+    /// [RFC 5001, DNS Name Server Identifier (NSID) Option](https://tools.ietf.org/html/rfc5001)
+    ///
+    /// The identifier of the server, empty in queries.
+    NSID(Vec<u8>),
+
     /// [RFC 6975, DNSSEC Algorithm Understood](https://tools.ietf.org/html/rfc6975)
     #[cfg(feature = "dnssec")]
     DAU(SupportedAlgorithms),
@@ -381,6 +393,22 @@ pub enum EdnsOption {
     #[cfg(feature = "dnssec")]
     N3U(SupportedAlgorithms),
 
+    /// [RFC 7871, Client Subnet in DNS Queries](https://tools.ietf.org/html/rfc7871)
+    Subnet(ClientSubnet),
+
+    /// [RFC 7873, Domain Name System (DNS) Cookies](https://tools.ietf.org/html/rfc7873)
+    Cookie(Cookie),
+
+    /// [RFC 7828, The edns-tcp-keepalive EDNS0 Option](https://tools.ietf.org/html/rfc7828)
+    ///
+    /// The idle timeout of the server, in units of 100 milliseconds, none in queries.
+    Keepalive(Option<u16>),
+
+    /// [RFC 7830, The EDNS(0) Padding Option](https://tools.ietf.org/html/rfc7830)
+    ///
+    /// The number of zero octets of padding.
+    Padding(u16),
+
     /// Unknown, used to deal with unknown or unsupported codes
     Unknown(u16, Vec<u8>),
 }
@@ -393,6 +421,11 @@ impl EdnsOption {
             EdnsOption::DAU(ref algorithms)
             | EdnsOption::DHU(ref algorithms)
             | EdnsOption::N3U(ref algorithms) => algorithms.len(),
+            EdnsOption::NSID(ref nsid) => nsid.len() as u16,
+            EdnsOption::Subnet(ref subnet) => subnet.len(),
+            EdnsOption::Cookie(ref cookie) => cookie.len(),
+            EdnsOption::Keepalive(timeout) => timeout.map_or(0, |_| 2),
+            EdnsOption::Padding(len) => len,
             EdnsOption::Unknown(_, ref data) => data.len() as u16, // TODO: should we verify?
         }
     }
@@ -404,6 +437,10 @@ impl EdnsOption {
             EdnsOption::DAU(ref algorithms)
             | EdnsOption::DHU(ref algorithms)
             | EdnsOption::N3U(ref algorithms) => algorithms.is_empty(),
+            EdnsOption::NSID(ref nsid) => nsid.is_empty(),
+            EdnsOption::Subnet(..) | EdnsOption::Cookie(..) => false,
+            EdnsOption::Keepalive(timeout) => timeout.is_none(),
+            EdnsOption::Padding(len) => len == 0,
             EdnsOption::Unknown(_, ref data) => data.is_empty(),
         }
     }
@@ -416,6 +453,12 @@ impl BinEncodable for EdnsOption {
             EdnsOption::DAU(ref algorithms)
             | EdnsOption::DHU(ref algorithms)
             | EdnsOption::N3U(ref algorithms) => algorithms.emit(encoder),
+            EdnsOption::NSID(ref nsid) => encoder.emit_vec(nsid),
+            EdnsOption::Subnet(ref subnet) => subnet.emit(encoder),
+            EdnsOption::Cookie(ref cookie) => cookie.emit(encoder),
+            EdnsOption::Keepalive(Some(timeout)) => encoder.emit_u16(timeout),
+            EdnsOption::Keepalive(None) => Ok(()),
+            EdnsOption::Padding(len) => encoder.emit_vec(&vec![0; len as usize]),
             EdnsOption::Unknown(_, ref data) => encoder.emit_vec(data), // gah, clone needed or make a crazy api.
         }
     }
@@ -431,6 +474,24 @@ impl<'a> From<(EdnsCode, &'a [u8])> for EdnsOption {
             EdnsCode::DHU => EdnsOption::DHU(value.1.into()),
             #[cfg(feature = "dnssec")]
             EdnsCode::N3U => EdnsOption::N3U(value.1.into()),
+            EdnsCode::NSID => EdnsOption::NSID(value.1.to_vec()),
+            // a malformed subnet is kept as it was received
+            EdnsCode::Subnet => ClientSubnet::read(value.1)
+                .map(EdnsOption::Subnet)
+                .unwrap_or_else(|_| EdnsOption::Unknown(value.0.into(), value.1.to_vec())),
+            // as is a malformed cookie, the server answers it with FORMERR
+            EdnsCode::Cookie => Cookie::read(value.1)
+                .map(EdnsOption::Cookie)
+                .unwrap_or_else(|_| EdnsOption::Unknown(value.0.into(), value.1.to_vec())),
+            EdnsCode::Keepalive => match value.1.len() {
+                0 => EdnsOption::Keepalive(None),
+                2 => EdnsOption::Keepalive(Some(
+                    (u16::from(value.1[0]) << 8) | u16::from(value.1[1]),
+                )),
+                _ => EdnsOption::Unknown(value.0.into(), value.1.to_vec()),
+            },
+            // the content of the padding is ignored
+            EdnsCode::Padding => EdnsOption::Padding(value.1.len() as u16),
             _ => EdnsOption::Unknown(value.0.into(), value.1.to_vec()),
         }
     }
@@ -443,6 +504,26 @@ impl<'a> From<&'a EdnsOption> for Vec<u8> {
             EdnsOption::DAU(ref algorithms)
             | EdnsOption::DHU(ref algorithms)
             | EdnsOption::N3U(ref algorithms) => algorithms.into(),
+            EdnsOption::NSID(ref nsid) => nsid.clone(),
+            EdnsOption::Subnet(ref subnet) => {
+                let mut bytes = Vec::with_capacity(subnet.len() as usize);
+                {
+                    let mut encoder = BinEncoder::new(&mut bytes);
+                    subnet.emit(&mut encoder).expect("subnet is always encodable");
+                }
+                bytes
+            }
+            EdnsOption::Cookie(ref cookie) => {
+                let mut bytes = Vec::with_capacity(cookie.len() as usize);
+                {
+                    let mut encoder = BinEncoder::new(&mut bytes);
+                    cookie.emit(&mut encoder).expect("cookie is always encodable");
+                }
+                bytes
+            }
+            EdnsOption::Keepalive(Some(timeout)) => vec![(timeout >> 8) as u8, timeout as u8],
+            EdnsOption::Keepalive(None) => vec![],
+            EdnsOption::Padding(len) => vec![0; len as usize],
             EdnsOption::Unknown(_, ref data) => data.clone(), // gah, clone needed or make a crazy api.
         }
     }
@@ -457,11 +538,314 @@ impl<'a> From<&'a EdnsOption> for EdnsCode {
             EdnsOption::DHU(..) => EdnsCode::DHU,
             #[cfg(feature = "dnssec")]
             EdnsOption::N3U(..) => EdnsCode::N3U,
+            EdnsOption::NSID(..) => EdnsCode::NSID,
+            EdnsOption::Subnet(..) => EdnsCode::Subnet,
+            EdnsOption::Cookie(..) => EdnsCode::Cookie,
+            EdnsOption::Keepalive(..) => EdnsCode::Keepalive,
+            EdnsOption::Padding(..) => EdnsCode::Padding,
             EdnsOption::Unknown(code, _) => EdnsCode::Unknown(code),
         }
     }
 }
 
+/// The subnet of the client on whose behalf a query is sent, and the subnet to which the response
+///  applies
+///
+/// [RFC 7871, Client Subnet in DNS Queries](https://tools.ietf.org/html/rfc7871#section-6), May 2016
+///
+/// ```text
+/// o  FAMILY, 2 octets, indicates the family of the address contained in
+///    the option, using address family codes as assigned by IANA in
+///    Address Family Numbers [Address_Family_Numbers].
+///
+/// o  SOURCE PREFIX-LENGTH, an unsigned octet representing the leftmost
+///    number of significant bits of ADDRESS to be used for the lookup.
+///    In responses, it mirrors the same value as in the queries.
+///
+/// o  SCOPE PREFIX-LENGTH, an unsigned octet representing the leftmost
+///    number of significant bits of ADDRESS that the response covers.
+///    In queries, it MUST be set to 0.
+///
+/// o  ADDRESS, variable number of octets, contains either an IPv4 or
+///    IPv6 address, depending on FAMILY, which MUST be truncated to the
+///    number of bits indicated by the SOURCE PREFIX-LENGTH field,
+///    padding with 0 bits to pad to the end of the last octet needed.
+/// ```
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct ClientSubnet {
+    address: IpAddr,
+    source_prefix: u8,
+    scope_prefix: u8,
+}
+
+/// The address family of IPv4, from the IANA Address Family Numbers
+const FAMILY_IPV4: u16 = 1;
+/// The address family of IPv6, from the IANA Address Family Numbers
+const FAMILY_IPV6: u16 = 2;
+
+impl ClientSubnet {
+    /// Creates a new ClientSubnet, the bits of the address after the source prefix are cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - the address of the client, or of its network
+    /// * `source_prefix` - the number of leading bits of the address which are significant
+    /// * `scope_prefix` - the number of leading bits of the address to which a response applies,
+    ///                    0 in queries
+    pub fn new(address: IpAddr, source_prefix: u8, scope_prefix: u8) -> Self {
+        let max_prefix = max_prefix(&address);
+        let source_prefix = source_prefix.min(max_prefix);
+
+        ClientSubnet {
+            address: truncate_address(address, source_prefix),
+            source_prefix,
+            scope_prefix: scope_prefix.min(max_prefix),
+        }
+    }
+
+    /// The address of the client, truncated to the source prefix
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// The number of leading bits of the address which are significant
+    pub fn source_prefix(&self) -> u8 {
+        self.source_prefix
+    }
+
+    /// The number of leading bits of the address to which the response applies
+    pub fn scope_prefix(&self) -> u8 {
+        self.scope_prefix
+    }
+
+    /// Sets the number of leading bits of the address to which the response applies
+    pub fn set_scope_prefix(&mut self, scope_prefix: u8) {
+        self.scope_prefix = scope_prefix.min(max_prefix(&self.address));
+    }
+
+    /// The address family, 1 for IPv4 and 2 for IPv6
+    pub fn family(&self) -> u16 {
+        match self.address {
+            IpAddr::V4(..) => FAMILY_IPV4,
+            IpAddr::V6(..) => FAMILY_IPV6,
+        }
+    }
+
+    /// Returns the length in bytes of the option data
+    pub fn len(&self) -> u16 {
+        4 + address_len(self.source_prefix) as u16
+    }
+
+    /// Reads the option data
+    ///
+    /// [RFC 7871](https://tools.ietf.org/html/rfc7871#section-6), Client Subnet in DNS Queries, May 2016
+    ///
+    /// ```text
+    /// A server receiving an ECS option that uses either too few or too many
+    /// ADDRESS octets, or that has non-zero ADDRESS bits set beyond SOURCE
+    /// PREFIX-LENGTH, SHOULD return FORMERR to reject the packet, as a
+    /// signal to the software developer making the request to fix their
+    /// implementation.
+    /// ```
+    pub fn read(data: &[u8]) -> ProtoResult<Self> {
+        if data.len() < 4 {
+            return Err(ProtoErrorKind::Message("client subnet is too short").into());
+        }
+
+        let family = u16::from(data[0]) << 8 | u16::from(data[1]);
+        let (source_prefix, scope_prefix) = (data[2], data[3]);
+        let address = &data[4..];
+
+        // the address is only as long as the source prefix needs
+        let (mut octets, max_prefix) = match family {
+            FAMILY_IPV4 => (vec![0; 4], 32),
+            FAMILY_IPV6 => (vec![0; 16], 128),
+            _ => return Err(ProtoErrorKind::Message("unknown client subnet family").into()),
+        };
+        if source_prefix > max_prefix || scope_prefix > max_prefix
+            || address.len() != address_len(source_prefix)
+        {
+            return Err(ProtoErrorKind::Message("bad client subnet prefix").into());
+        }
+        octets[..address.len()].copy_from_slice(address);
+
+        let address = if family == FAMILY_IPV4 {
+            IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+        } else {
+            let mut ipv6 = [0u8; 16];
+            ipv6.copy_from_slice(&octets);
+            IpAddr::V6(Ipv6Addr::from(ipv6))
+        };
+        if truncate_address(address, source_prefix) != address {
+            return Err(ProtoErrorKind::Message("client subnet address beyond its prefix").into());
+        }
+
+        Ok(ClientSubnet::new(address, source_prefix, scope_prefix))
+    }
+
+    /// Writes the option data
+    pub fn emit(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        encoder.emit_u16(self.family())?;
+        encoder.emit(self.source_prefix)?;
+        encoder.emit(self.scope_prefix)?;
+
+        let len = address_len(self.source_prefix);
+        match self.address {
+            IpAddr::V4(ref address) => encoder.emit_vec(&address.octets()[..len]),
+            IpAddr::V6(ref address) => encoder.emit_vec(&address.octets()[..len]),
+        }
+    }
+}
+
+/// The block length to which queries over encrypted transports are padded
+///
+/// [RFC 8467](https://tools.ietf.org/html/rfc8467#section-4.1), Padding Policies for Extension Mechanisms for DNS (EDNS(0)), October 2018
+///
+/// ```text
+/// Clients SHOULD pad queries to the closest multiple of 128 octets.
+///
+/// If a server receives a query that includes the EDNS(0) Padding
+/// option, it MUST pad the corresponding response (see Section 4 of RFC
+/// 7830) and SHOULD pad the corresponding response to a multiple of 468
+/// octets (see below).
+/// ```
+pub const QUERY_PADDING_BLOCK_LEN: usize = 128;
+/// The block length to which responses over encrypted transports are padded, see
+///  `QUERY_PADDING_BLOCK_LEN`
+pub const RESPONSE_PADDING_BLOCK_LEN: usize = 468;
+
+/// Returns the length of the padding which brings the message to a multiple of the block length
+///
+/// # Arguments
+///
+/// * `message_len` - the length of the encoded message, with an empty Padding option
+/// * `block_len` - the block length, see `QUERY_PADDING_BLOCK_LEN`
+pub fn padding_len(message_len: usize, block_len: usize) -> u16 {
+    ((block_len - message_len % block_len) % block_len) as u16
+}
+
+/// The length of the client cookie
+pub const CLIENT_COOKIE_LEN: usize = 8;
+/// The shortest server cookie
+pub const MIN_SERVER_COOKIE_LEN: usize = 8;
+/// The longest server cookie
+pub const MAX_SERVER_COOKIE_LEN: usize = 32;
+
+/// The cookies of the client and of the server, which prove that the other is not an off-path
+///  attacker, spoofing its address
+///
+/// [RFC 7873, Domain Name System (DNS) Cookies](https://tools.ietf.org/html/rfc7873#section-4), May 2016
+///
+/// ```text
+/// The DNS Client Cookie is a variable-size field.  It MUST be 8 bytes.
+///
+/// The DNS Server Cookie is a variable-size field.  If present, its size
+/// MUST be from 8 to 32 bytes inclusive.
+/// ```
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Hash)]
+pub struct Cookie {
+    client: [u8; CLIENT_COOKIE_LEN],
+    server: Option<Vec<u8>>,
+}
+
+impl Cookie {
+    /// Creates a new Cookie
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the cookie of the client
+    /// * `server` - the cookie of the server, from 8 to 32 bytes, none on the first query of the
+    ///              client to the server
+    pub fn new(client: [u8; CLIENT_COOKIE_LEN], server: Option<Vec<u8>>) -> Self {
+        Cookie { client, server }
+    }
+
+    /// The cookie of the client
+    pub fn client(&self) -> &[u8; CLIENT_COOKIE_LEN] {
+        &self.client
+    }
+
+    /// The cookie of the server, if the client has one
+    pub fn server(&self) -> Option<&[u8]> {
+        self.server.as_ref().map(|server| server.as_slice())
+    }
+
+    /// The length of the option data
+    pub fn len(&self) -> u16 {
+        (CLIENT_COOKIE_LEN + self.server.as_ref().map_or(0, |server| server.len())) as u16
+    }
+
+    /// Reads the cookies from the option data
+    pub fn read(data: &[u8]) -> ProtoResult<Self> {
+        let server_len = data.len().saturating_sub(CLIENT_COOKIE_LEN);
+        if data.len() < CLIENT_COOKIE_LEN
+            || (server_len > 0
+                && (server_len < MIN_SERVER_COOKIE_LEN || server_len > MAX_SERVER_COOKIE_LEN))
+        {
+            return Err(ProtoErrorKind::Message("bad cookie length").into());
+        }
+
+        let mut client = [0; CLIENT_COOKIE_LEN];
+        client.copy_from_slice(&data[..CLIENT_COOKIE_LEN]);
+        let server = if server_len > 0 {
+            Some(data[CLIENT_COOKIE_LEN..].to_vec())
+        } else {
+            None
+        };
+
+        Ok(Cookie::new(client, server))
+    }
+
+    /// Writes the option data
+    pub fn emit(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        encoder.emit_vec(&self.client)?;
+        if let Some(ref server) = self.server {
+            encoder.emit_vec(server)?;
+        }
+        Ok(())
+    }
+}
+
+fn max_prefix(address: &IpAddr) -> u8 {
+    match *address {
+        IpAddr::V4(..) => 32,
+        IpAddr::V6(..) => 128,
+    }
+}
+
+fn address_len(prefix: u8) -> usize {
+    (prefix as usize + 7) / 8
+}
+
+/// Clears the bits of the address after the prefix, e.g. 192.0.2.1 with prefix 24 is 192.0.2.0
+///
+/// # Arguments
+///
+/// * `address` - the address to truncate
+/// * `prefix` - the number of leading bits of the address which are kept, a longer prefix than the
+///              address keeps all of it
+pub fn truncate_address(address: IpAddr, prefix: u8) -> IpAddr {
+    fn clear(octets: &mut [u8], prefix: u8) {
+        for (i, octet) in octets.iter_mut().enumerate() {
+            let bits = (prefix as usize).saturating_sub(i * 8).min(8);
+            *octet &= !(0xFFu16 >> bits) as u8;
+        }
+    }
+
+    match address {
+        IpAddr::V4(address) => {
+            let mut octets = address.octets();
+            clear(&mut octets, prefix);
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        IpAddr::V6(address) => {
+            let mut octets = address.octets();
+            clear(&mut octets, prefix);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+}
+
 #[test]
 #[cfg(feature = "dnssec")]
 pub fn test() {
@@ -483,3 +867,181 @@ pub fn test() {
     );
     assert_eq!(rdata, read_rdata.unwrap());
 }
+
+#[test]
+pub fn test_client_subnet() {
+    let subnet = ClientSubnet::new("192.0.2.77".parse().unwrap(), 20, 0);
+    assert_eq!(subnet.address(), "192.0.0.0".parse::<IpAddr>().unwrap());
+    assert_eq!(subnet.len(), 7);
+
+    let mut rdata = OPT::default();
+    rdata.insert(EdnsOption::Subnet(subnet));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+        assert!(emit(&mut encoder, &rdata).is_ok());
+    }
+    assert_eq!(&bytes[4..], &[0, 1, 20, 0, 192, 0, 0]);
+
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(rdata, read_rdata);
+
+    let subnet = ClientSubnet::new("2001:db8::1".parse().unwrap(), 56, 48);
+    assert_eq!(ClientSubnet::read(&Vec::<u8>::from(&EdnsOption::Subnet(subnet))).unwrap(), subnet);
+
+    // the address must be exactly as long as the source prefix, without bits beyond it
+    assert!(ClientSubnet::read(&[0, 1, 24, 0, 192, 0]).is_err());
+    assert!(ClientSubnet::read(&[0, 1, 20, 0, 192, 0, 8]).is_err());
+    assert!(ClientSubnet::read(&[0, 3, 0, 0]).is_err());
+}
+
+#[test]
+pub fn test_cookie() {
+    let cookie = Cookie::new([1, 2, 3, 4, 5, 6, 7, 8], None);
+    assert_eq!(cookie.len(), 8);
+    assert_eq!(
+        Cookie::read(&Vec::<u8>::from(&EdnsOption::Cookie(cookie.clone()))).unwrap(),
+        cookie
+    );
+
+    let cookie = Cookie::new([1, 2, 3, 4, 5, 6, 7, 8], Some(vec![9; 16]));
+    assert_eq!(cookie.len(), 24);
+    assert_eq!(cookie.server(), Some(&[9; 16][..]));
+
+    let mut rdata = OPT::default();
+    rdata.insert(EdnsOption::Cookie(cookie));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+        assert!(emit(&mut encoder, &rdata).is_ok());
+    }
+
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(rdata, read_rdata);
+
+    // the server cookie is from 8 to 32 bytes
+    assert!(Cookie::read(&[1, 2, 3, 4, 5, 6, 7]).is_err());
+    assert!(Cookie::read(&[1; 15]).is_err());
+    assert!(Cookie::read(&[1; 41]).is_err());
+    match EdnsOption::from((EdnsCode::Cookie, &[1u8; 12][..])) {
+        EdnsOption::Unknown(10, _) => (),
+        option => panic!("malformed cookie was read: {:?}", option),
+    }
+}
+
+#[test]
+pub fn test_keepalive_and_padding() {
+    let mut rdata = OPT::default();
+    rdata.insert(EdnsOption::Keepalive(Some(300)));
+    rdata.insert(EdnsOption::Padding(5));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+        assert!(emit(&mut encoder, &rdata).is_ok());
+    }
+    assert_eq!(bytes.len(), 4 + 2 + 4 + 5);
+
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(rdata, read_rdata);
+
+    assert_eq!(
+        EdnsOption::from((EdnsCode::Keepalive, &[][..])),
+        EdnsOption::Keepalive(None)
+    );
+    assert_eq!(padding_len(100, QUERY_PADDING_BLOCK_LEN), 28);
+    assert_eq!(padding_len(128, QUERY_PADDING_BLOCK_LEN), 0);
+    assert_eq!(padding_len(469, RESPONSE_PADDING_BLOCK_LEN), 467);
+}
+
+#[test]
+pub fn test_nsid() {
+    let mut rdata = OPT::default();
+    rdata.insert(EdnsOption::NSID(b"ns1.example".to_vec()));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+        assert!(emit(&mut encoder, &rdata).is_ok());
+    }
+    assert_eq!(bytes.len(), 4 + 11);
+
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(rdata, read_rdata);
+
+    // queries ask for the identifier with an empty option
+    assert_eq!(
+        EdnsOption::from((EdnsCode::NSID, &[][..])),
+        EdnsOption::NSID(vec![])
+    );
+    assert!(EdnsOption::NSID(vec![]).is_empty());
+}
+
+#[cfg(test)]
+fn emit_and_read(rdata: &OPT) -> OPT {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut bytes);
+        assert!(emit(&mut encoder, rdata).is_ok());
+    }
+
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    read(&mut decoder, bytes.len() as u16).expect("error decoding")
+}
+
+#[test]
+pub fn test_empty_options() {
+    let empty_options = vec![
+        EdnsOption::Keepalive(None),
+        EdnsOption::Padding(0),
+        EdnsOption::NSID(vec![]),
+    ];
+
+    // alone
+    for option in &empty_options {
+        let mut rdata = OPT::default();
+        rdata.insert(option.clone());
+        assert_eq!(emit_and_read(&rdata), rdata);
+    }
+
+    // together
+    let mut rdata = OPT::default();
+    for option in &empty_options {
+        rdata.insert(option.clone());
+    }
+    assert_eq!(emit_and_read(&rdata), rdata);
+
+    // and mixed with options which have data
+    rdata.insert(EdnsOption::Cookie(Cookie::new([1, 2, 3, 4, 5, 6, 7, 8], None)));
+    rdata.insert(EdnsOption::Subnet(ClientSubnet::new(
+        "192.0.2.0".parse().unwrap(),
+        24,
+        0,
+    )));
+    assert_eq!(emit_and_read(&rdata), rdata);
+
+    // an empty option last in the record, and followed by another option
+    let bytes = [0, 3, 0, 0];
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(read_rdata.get(&EdnsCode::NSID), Some(&EdnsOption::NSID(vec![])));
+
+    let bytes = [0, 11, 0, 0, 0, 12, 0, 2, 0, 0];
+    let mut decoder: BinDecoder = BinDecoder::new(&bytes);
+    let read_rdata = read(&mut decoder, bytes.len() as u16).expect("error decoding");
+    assert_eq!(read_rdata.options().len(), 2);
+    assert_eq!(
+        read_rdata.get(&EdnsCode::Keepalive),
+        Some(&EdnsOption::Keepalive(None))
+    );
+    assert_eq!(
+        read_rdata.get(&EdnsCode::Padding),
+        Some(&EdnsOption::Padding(2))
+    );
+}
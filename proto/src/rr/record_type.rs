@@ -82,7 +82,6 @@ pub enum RecordType {
     //  TKEY,       //	249	RFC 2930	Secret key record
     ///	RFC 6698	TLSA certificate association
     TLSA,
-    //  TSIG,       //	250	RFC 2845	Transaction Signature
     /// RFC 1035[1]	Text record
     TXT,
 
@@ -148,6 +147,7 @@ impl FromStr for RecordType {
             "TXT" => Ok(RecordType::TXT),
             "ANY" | "*" => Ok(RecordType::ANY),
             "AXFR" => Ok(RecordType::AXFR),
+            "IXFR" => Ok(RecordType::IXFR),
             _ => Err(ProtoErrorKind::UnknownRecordTypeStr(str.to_string()).into()),
         }
     }
@@ -168,6 +168,7 @@ impl From<u16> for RecordType {
             28 => RecordType::AAAA,
             255 => RecordType::ANY,
             252 => RecordType::AXFR,
+            251 => RecordType::IXFR,
             257 => RecordType::CAA,
             5 => RecordType::CNAME,
             0 => RecordType::ZERO,
@@ -188,7 +189,8 @@ impl From<u16> for RecordType {
             50/*NSEC3*/|
             51/*NSEC3PARAM*/|
             46/*RRSIG*/|
-            24/*SIG*/ => RecordType::DNSSEC(DNSSECRecordType::from(value)),
+            24/*SIG*/|
+            250/*TSIG*/ => RecordType::DNSSEC(DNSSECRecordType::from(value)),
             // all unknown record types
             _ => RecordType::Unknown(value),
         }
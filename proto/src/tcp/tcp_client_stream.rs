@@ -77,10 +77,7 @@ impl TcpClientStream<TokioTcpStream> {
             }
         }));
 
-        let sender = Box::new(BufDnsStreamHandle {
-            name_server: name_server,
-            sender: sender,
-        });
+        let sender = Box::new(BufDnsStreamHandle::new(name_server, sender));
 
         (new_future, sender)
     }
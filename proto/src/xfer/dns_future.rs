@@ -8,6 +8,7 @@
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
 use std::io;
+use std::mem;
 use std::sync::Arc; 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -20,7 +21,9 @@ use tokio_core::reactor::{Handle, Timeout};
 
 use {BasicDnsHandle, DnsStreamHandle};
 use error::*;
-use op::{Message, MessageFinalizer, OpCode};
+use op::{Message, MessageFinalizer};
+use rr::rdata::opt::{self, Cookie, EdnsCode, EdnsOption, CLIENT_COOKIE_LEN,
+                     QUERY_PADDING_BLOCK_LEN};
 use super::ignore_send;
 
 const QOS_MAX_RECEIVE_MSGS: usize = 100; // max number of messages to receive from the UDP socket
@@ -44,6 +47,14 @@ where
     new_receiver: Peekable<StreamFuse<UnboundedReceiver<(Message, Complete<Result<Message, E>>)>>>,
     active_requests: HashMap<u16, (Complete<Result<Message, E>>, Timeout)>,
     signer: Option<Arc<MF>>,
+    // the DNS Cookies of the client and of the server at the other end of the stream
+    client_cookie: [u8; CLIENT_COOKIE_LEN],
+    server_cookie: Option<Vec<u8>>,
+    // the idle timeout advertised by the server with edns-tcp-keepalive, after which the
+    //  connection is closed once there are no active requests
+    idle_timeout: Option<Duration>,
+    idle: Option<Timeout>,
+    closed: bool,
 }
 
 impl<S, E, MF> DnsFuture<S, E, MF>
@@ -61,7 +72,7 @@ where
     /// * `loop_handle` - A Handle to the Tokio reactor Core, this is the Core on which the
     ///                   the Stream will be spawned
     /// * `stream_handle` - The handle for the `stream` on which bytes can be sent/received.
-    /// * `signer` - An optional signer for requests, needed for Updates with Sig0, otherwise not needed
+    /// * `signer` - An optional signer for requests, needed for Updates with Sig0, or for TSIG, otherwise not needed
     pub fn new(
         stream: Box<Future<Item = S, Error = io::Error>>,
         stream_handle: Box<DnsStreamHandle<Error = E>>,
@@ -88,7 +99,7 @@ where
     /// * `timeout_duration` - All requests may fail due to lack of response, this is the time to
     ///                        wait for a response before canceling the request.
     /// * `stream_handle` - The handle for the `stream` on which bytes can be sent/received.
-    /// * `signer` - An optional signer for requests, needed for Updates with Sig0, otherwise not needed
+    /// * `signer` - An optional signer for requests, needed for Updates with Sig0, or for TSIG, otherwise not needed
     pub fn with_timeout(
         stream: Box<Future<Item = S, Error = io::Error>>,
         stream_handle: Box<DnsStreamHandle<Error = E>>,
@@ -110,13 +121,20 @@ where
                         new_receiver: rx.fuse().peekable(),
                         active_requests: HashMap::new(),
                         signer: signer,
+                        client_cookie: rand::random(),
+                        server_cookie: None,
+                        idle_timeout: None,
+                        idle: None,
+                        closed: false,
                     }),
                     Err(stream_error) => ClientStreamOrError::Errored(ClientStreamErrored {
-                        error_msg: format!(
-                            "stream error {}:{}: {}",
-                            file!(),
-                            line!(),
-                            stream_error
+                        error: E::from(
+                            ProtoErrorKind::Msg(format!(
+                                "stream error {}:{}: {}",
+                                file!(),
+                                line!(),
+                                stream_error
+                            )).into(),
                         ),
                         new_receiver: rx.fuse().peekable(),
                     }),
@@ -182,6 +200,91 @@ where
         task::current().notify();
         Async::NotReady
     }
+
+    /// Adds the DNS Cookies to requests with EDNS, with the cookie of the server once it is known
+    fn set_cookie(&self, message: &mut Message) {
+        if message.edns().is_none() {
+            return;
+        }
+
+        let cookie = Cookie::new(self.client_cookie, self.server_cookie.clone());
+        message.edns_mut().set_option(EdnsOption::Cookie(cookie));
+    }
+
+    /// Pads requests with EDNS on encrypted streams, and asks the server to keep the connection
+    ///  open with edns-tcp-keepalive
+    ///
+    /// The padded length is that of the request once signed, as the signature, added after the
+    ///  padding, is part of the message which is encrypted.
+    ///
+    /// [RFC 7830](https://tools.ietf.org/html/rfc7830#section-3), The EDNS(0) Padding Option, May 2016
+    ///
+    /// ```text
+    /// The PADDING octets SHOULD be set to 0x00.  Other values MAY be used,
+    /// for example, in cases where there is a concern that the padded
+    /// message could be subject to compression before encryption.
+    /// ```
+    fn pad(&self, message: &mut Message, now: u32) -> ProtoResult<()> {
+        if message.edns().is_none() || !self.stream_handle.is_encrypted() {
+            return Ok(());
+        }
+
+        message.edns_mut().set_option(EdnsOption::Keepalive(None));
+        message.edns_mut().set_option(EdnsOption::Padding(0));
+        let len = match self.signer {
+            Some(ref signer) if signer.should_finalize_message(message) => {
+                let mut signed = message.clone();
+                signed.finalize::<MF>(signer.borrow(), now)?;
+                signed.to_vec()?.len()
+            }
+            _ => message.to_vec()?.len(),
+        };
+        let padding = opt::padding_len(len, QUERY_PADDING_BLOCK_LEN);
+        message.edns_mut().set_option(EdnsOption::Padding(padding));
+        Ok(())
+    }
+
+    /// Remembers the idle timeout of the server, from the edns-tcp-keepalive of the response
+    fn check_keepalive(&mut self, message: &Message) {
+        if let Some(&EdnsOption::Keepalive(Some(timeout))) = message
+            .edns()
+            .and_then(|edns| edns.option(&EdnsCode::Keepalive))
+        {
+            self.idle_timeout = Some(Duration::from_millis(u64::from(timeout) * 100));
+        }
+    }
+
+    /// Returns false if the response is spoofed, its client cookie is not ours, otherwise
+    ///  remembers the cookie of the server
+    ///
+    /// [RFC 7873](https://tools.ietf.org/html/rfc7873#section-5.3), Domain Name System (DNS) Cookies, May 2016
+    ///
+    /// ```text
+    /// If the COOKIE option is too short to contain a Server Cookie, it is
+    /// discarded as malformed.  If the reply contains a COOKIE option with a
+    /// Client Cookie that does not match the Client Cookie that was sent,
+    /// the reply is discarded.
+    /// ```
+    fn check_cookie(&mut self, message: &Message) -> bool {
+        let option = match message.edns() {
+            Some(edns) => edns.option(&EdnsCode::Cookie),
+            None => return true,
+        };
+
+        match option {
+            Some(&EdnsOption::Cookie(ref cookie)) => {
+                if *cookie.client() != self.client_cookie {
+                    return false;
+                }
+                if let Some(server) = cookie.server() {
+                    self.server_cookie = Some(server.to_vec());
+                }
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
 }
 
 impl<S, E, MF> Future for DnsFuture<S, E, MF>
@@ -224,6 +327,7 @@ where
                     // getting a random query id, this mitigates potential cache poisoning.
                     let query_id = query_id.expect("query_id should have been set above");
                     message.set_id(query_id);
+                    self.set_cookie(&mut message);
 
                     let now = SystemTime::now()
                         .duration_since(UNIX_EPOCH)
@@ -231,9 +335,15 @@ where
                         .as_secs();
                     let now = now as u32; // XXX: truncates u64 to u32.
 
-                    // update messages need to be signed.
-                    if let OpCode::Update = message.op_code() {
-                        if let Some(ref signer) = self.signer {
+                    if let Err(e) = self.pad(&mut message, now) {
+                        warn!("could not pad message: {}", e);
+                        ignore_send(complete.send(Err(e.into())));
+                        continue; // to the next message...
+                    }
+
+                    // update messages need to be signed, TSIG signers sign all messages.
+                    if let Some(ref signer) = self.signer {
+                        if signer.should_finalize_message(&message) {
                             if let Err(e) = message.finalize::<MF>(signer.borrow(), now) {
                                 warn!("could not sign message: {}", e);
                                 ignore_send(complete.send(Err(e.into())));
@@ -308,10 +418,19 @@ where
                     //   deserialize or log decode_error
                     match Message::from_vec(&buffer) {
                         // FIXME: if multicast, ie, multiple responses are expected...
-                        Ok(message) => match self.active_requests.remove(&message.id()) {
-                            Some((complete, _)) => ignore_send(complete.send(Ok(message))),
-                            None => debug!("unexpected request_id: {}", message.id()),
-                        },
+                        Ok(message) => {
+                            if !self.active_requests.contains_key(&message.id()) {
+                                debug!("unexpected request_id: {}", message.id());
+                            } else if !self.check_cookie(&message) {
+                                // keep waiting for the response of the server
+                                warn!("bad client cookie in response: {}", message.id());
+                            } else if let Some((complete, _)) =
+                                self.active_requests.remove(&message.id())
+                            {
+                                self.check_keepalive(&message);
+                                ignore_send(complete.send(Ok(message)));
+                            }
+                        }
                         // TODO: return src address for diagnostics
                         Err(e) => debug!("error decoding message: {}", e),
                     }
@@ -333,6 +452,23 @@ where
             return Ok(().into()); // we are done
         }
 
+        // the server closes connections which are idle for longer than it advertised, close it
+        //  first, so that no request is sent on a connection being closed; the requests which
+        //  follow fail with `ProtoErrorKind::Closed`, and may be retried on a new connection
+        match self.idle_timeout {
+            Some(idle_timeout) if self.active_requests.is_empty() => {
+                if self.idle.is_none() {
+                    self.idle = Timeout::new(idle_timeout, &self.reactor_handle).ok();
+                }
+            }
+            _ => self.idle = None,
+        }
+        if let Some(Ok(Async::Ready(_))) = self.idle.as_mut().map(|idle| idle.poll()) {
+            debug!("connection idle for {:?}, closing", self.idle_timeout);
+            self.closed = true;
+            return Ok(().into());
+        }
+
         // If still active, then if the qos (for _ in 0..100 loop) limit
         // was hit then "yield". This'll make sure that the future is
         // woken up immediately on the next turn of the event loop.
@@ -345,13 +481,12 @@ where
     }
 }
 
-/// Always returns the specified io::Error to the remote Sender
+/// Always returns the specified error to the remote Sender
 struct ClientStreamErrored<E>
 where
     E: FromProtoError,
 {
-    // TODO: is there a better thing to grab here?
-    error_msg: String,
+    error: E,
     new_receiver: Peekable<StreamFuse<UnboundedReceiver<(Message, Complete<Result<Message, E>>)>>>,
 }
 
@@ -365,15 +500,15 @@ where
     fn poll(&mut self) -> Poll<(), Self::Error> {
         match self.new_receiver.poll() {
             Ok(Async::Ready(Some((_, complete)))) => {
-                ignore_send(complete.send(Err(E::from(
-                    ProtoErrorKind::Msg(self.error_msg.clone()).into(),
-                ))));
+                ignore_send(complete.send(Err(self.error.clone())));
 
                 task::current().notify();
                 Ok(Async::NotReady)
             }
             Ok(Async::Ready(None)) => Ok(Async::Ready(())),
-            _ => Err(E::from(ProtoErrorKind::NoError.into())),
+            // wait for the requests which follow, to answer them with the error
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(()) => Err(E::from(ProtoErrorKind::NoError.into())),
         }
     }
 }
@@ -398,9 +533,92 @@ where
     type Error = E;
 
     fn poll(&mut self) -> Poll<(), Self::Error> {
-        match *self {
-            ClientStreamOrError::Future(ref mut f) => f.poll(),
-            ClientStreamOrError::Errored(ref mut e) => e.poll(),
+        let closed = match *self {
+            ClientStreamOrError::Future(ref mut f) => match f.poll()? {
+                // the connection was closed while idle, the stream is dropped, and the requests
+                //  which follow are answered with an error
+                Async::Ready(()) if f.closed => {
+                    let (_, rx) = unbounded();
+                    ClientStreamErrored {
+                        error: E::from(ProtoErrorKind::Closed.into()),
+                        new_receiver: mem::replace(&mut f.new_receiver, rx.fuse().peekable()),
+                    }
+                }
+                poll => return Ok(poll),
+            },
+            ClientStreamOrError::Errored(ref mut e) => return e.poll(),
+        };
+
+        *self = ClientStreamOrError::Errored(closed);
+        self.poll()
+    }
+}
+#[cfg(test)]
+mod test {
+    use futures::future;
+    use futures::sync::mpsc::UnboundedSender;
+    use tokio_core::reactor::Core;
+
+    use DnsHandle;
+    use op::{MessageType, NoopMessageFinalizer, Query};
+    use super::*;
+
+    /// Answers every query, advertising an idle timeout with edns-tcp-keepalive
+    struct KeepaliveServer {
+        responses: UnboundedSender<Vec<u8>>,
+        timeout: u16,
+    }
+
+    impl DnsStreamHandle for KeepaliveServer {
+        type Error = ProtoError;
+
+        fn send(&mut self, buffer: Vec<u8>) -> ProtoResult<()> {
+            let mut response = Message::from_vec(&buffer)?;
+            response.set_message_type(MessageType::Response);
+            response
+                .edns_mut()
+                .set_option(EdnsOption::Keepalive(Some(self.timeout)));
+            self.responses
+                .unbounded_send(response.to_vec()?)
+                .map_err(|e| ProtoErrorKind::Msg(format!("mpsc::SendError {}", e)).into())
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_closed_after_idle_timeout() {
+        let mut io_loop = Core::new().unwrap();
+        let (responses, stream) = unbounded();
+        let stream = stream.map_err(|()| io::Error::new(io::ErrorKind::Other, "closed"));
+        let mut client = DnsFuture::new(
+            Box::new(future::ok(stream)),
+            Box::new(KeepaliveServer {
+                responses: responses,
+                timeout: 1,
+            }),
+            &io_loop.handle(),
+            NoopMessageFinalizer::new(),
+        );
+
+        let query = || {
+            let mut message = Message::new();
+            message.add_query(Query::new());
+            message
+        };
+
+        // the connection is kept open within the idle timeout of 100 milliseconds
+        io_loop.run(client.send(query())).expect("query failed");
+        io_loop.run(client.send(query())).expect("query failed");
+
+        // and closed after it, the requests which follow may be retried on a new connection
+        let idle = Timeout::new(Duration::from_millis(300), &io_loop.handle()).unwrap();
+        io_loop.run(idle).unwrap();
+        match *io_loop.run(client.send(query())).unwrap_err().kind() {
+            ProtoErrorKind::Closed => (),
+            ref kind => panic!("expected the connection to be closed: {}", kind),
+        }
+        match *io_loop.run(client.send(query())).unwrap_err().kind() {
+            ProtoErrorKind::Closed => (),
+            ref kind => panic!("expected the connection to be closed: {}", kind),
+        }
+    }
+}
@@ -49,6 +49,12 @@ pub trait DnsStreamHandle {
 
     /// Sends a message to the Handle for delivery to the server.
     fn send(&mut self, buffer: Vec<u8>) -> Result<(), Self::Error>;
+
+    /// Returns true if the stream is encrypted, e.g. TLS, requests are then padded so that their
+    ///  length does not reveal the name, and the connection is kept open between them
+    fn is_encrypted(&self) -> bool {
+        false
+    }
 }
 
 impl<E> DnsStreamHandle for StreamHandle<E>
@@ -6,6 +6,7 @@
 // copied, modified, or distributed except according to those terms.
 
 //! Configuration for a resolver
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::Duration;
 
@@ -20,6 +21,8 @@ pub struct ResolverConfig {
     search: Vec<Name>,
     // nameservers to use for resolution.
     name_servers: Vec<NameServerConfig>,
+    // names in the certificates of the DNS over TLS nameservers, by their addresses
+    tls_dns_names: HashMap<SocketAddr, String>,
 }
 
 impl ResolverConfig {
@@ -30,6 +33,7 @@ impl ResolverConfig {
             domain: None,
             search: vec![],
             name_servers: vec![],
+            tls_dns_names: HashMap::new(),
         }
     }
 
@@ -49,6 +53,7 @@ impl ResolverConfig {
             domain,
             search,
             name_servers,
+            tls_dns_names: HashMap::new(),
         }
     }
 
@@ -87,6 +92,29 @@ impl ResolverConfig {
     pub fn name_servers(&self) -> &[NameServerConfig] {
         &self.name_servers
     }
+
+    /// Add the configuration for a DNS over TLS name server
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_addr` - the address of the name server, generally with port 853
+    /// * `tls_dns_name` - the name for which the certificate of the name server must be valid
+    #[cfg(feature = "dns-over-native-tls")]
+    pub fn add_tls_name_server(&mut self, socket_addr: SocketAddr, tls_dns_name: String) {
+        self.add_name_server(NameServerConfig {
+            socket_addr,
+            protocol: Protocol::Tls,
+        });
+        self.tls_dns_names.insert(socket_addr, tls_dns_name);
+    }
+
+    /// Returns the name in the certificate of the DNS over TLS name server at the address, see
+    ///  `add_tls_name_server`
+    pub fn tls_dns_name(&self, socket_addr: &SocketAddr) -> Option<&str> {
+        self.tls_dns_names
+            .get(socket_addr)
+            .map(|name| name.as_str())
+    }
 }
 
 impl Default for ResolverConfig {
@@ -125,6 +153,7 @@ impl Default for ResolverConfig {
             domain,
             search: vec![],
             name_servers: vec![google_ns1, google_ns2, google_v6_ns1, google_v6_ns2],
+            tls_dns_names: HashMap::new(),
         }
     }
 }
@@ -137,7 +166,9 @@ pub enum Protocol {
     /// TCP can be used for large queries, but not all NameServers support it
     Tcp,
     // TODO: add client certificate for mTLS?
-    // Tls,
+    /// DNS over TLS, RFC 7858, see `ResolverConfig::add_tls_name_server`
+    #[cfg(feature = "dns-over-native-tls")]
+    Tls,
     /// mDNS protocol for performing multicast lookups
     #[cfg(feature = "mdns")]
     Mdns,
@@ -149,6 +180,8 @@ impl Protocol {
         match *self {
             Protocol::Udp => true,
             Protocol::Tcp => false,
+            #[cfg(feature = "dns-over-native-tls")]
+            Protocol::Tls => false,
             #[cfg(feature = "mdns")]
             Protocol::Mdns => true,
         }
@@ -34,15 +34,22 @@ impl LruValue {
     }
 }
 
+/// An LRU cache of the RData of lookups, each expires after the minimum TTL of its records
 #[derive(Debug)]
-pub(crate) struct DnsLru(LruCache<Query, LruValue>);
+pub struct DnsLru(LruCache<Query, LruValue>);
 
 impl DnsLru {
-    pub(crate) fn new(capacity: usize) -> Self {
+    /// Constructs a new cache
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - the maximum number of queries which are cached
+    pub fn new(capacity: usize) -> Self {
         DnsLru(LruCache::new(capacity))
     }
 
-    pub(crate) fn insert(
+    /// Caches the RData for the query, until the minimum of their TTLs from `now`
+    pub fn insert(
         &mut self,
         query: Query,
         rdatas_and_ttl: Vec<(RData, u32)>,
@@ -75,8 +82,11 @@ impl DnsLru {
         lookup
     }
 
-    /// Generally for inserting a set of records that have already been cached, but with a different Query.
-    pub(crate) fn duplicate(
+    /// Caches an existing lookup for the query, for `ttl` seconds from `now`
+    ///
+    /// Generally for a set of records that has already been cached, but with a different Query.
+    ///  An empty lookup records that the name exists, but has no records of the type.
+    pub fn duplicate(
         &mut self,
         query: Query,
         lookup: Lookup,
@@ -101,7 +111,12 @@ impl DnsLru {
         ResolveErrorKind::NoRecordsFound(query).into()
     }
 
-    pub(crate) fn negative(&mut self, query: Query, ttl: u32, now: Instant) -> ResolveError {
+    /// Caches that the queried name does not exist, for `ttl` seconds from `now`
+    ///
+    /// # Return
+    ///
+    /// The NoRecordsFound error for the query
+    pub fn negative(&mut self, query: Query, ttl: u32, now: Instant) -> ResolveError {
         // TODO: if we are getting a negative response, should we instead fallback to cache?
         //   this would cache indefinitely, probably not correct
 
@@ -119,13 +134,31 @@ impl DnsLru {
         Self::nx_error(query)
     }
 
+    /// Removes all the entries from the cache
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
     /// This needs to be mut b/c it's an LRU, meaning the ordering of elements will potentially change on retrieval...
-    pub(crate) fn get(&mut self, query: &Query, now: Instant) -> Option<Lookup> {
+    pub fn get(&mut self, query: &Query, now: Instant) -> Option<Lookup> {
+        self.get_with_ttl(query, now).and_then(|(lookup, _)| lookup)
+    }
+
+    /// Returns the cached lookup for the query, and the seconds which remain of its TTL
+    ///
+    /// This is for answering with cached records, whose TTLs must count down while cached.
+    ///
+    /// # Return
+    ///
+    /// `None` if nothing current is cached for the query, otherwise `Some(None, ttl)` for a cached
+    ///  negative response, see `negative`, or the cached lookup.
+    pub fn get_with_ttl(&mut self, query: &Query, now: Instant) -> Option<(Option<Lookup>, u32)> {
         let mut out_of_date = false;
         let lookup = self.0.get_mut(query).and_then(|value| {
             if value.is_current(now) {
                 out_of_date = false;
-                value.lookup.clone()
+                let ttl = value.ttl_until.duration_since(now).as_secs() as u32;
+                Some((value.lookup.clone(), ttl))
             } else {
                 out_of_date = true;
                 None
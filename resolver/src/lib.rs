@@ -142,9 +142,11 @@ extern crate lru_cache;
 extern crate resolv_conf;
 extern crate tokio_core;
 extern crate trust_dns_proto;
+#[cfg(feature = "dns-over-native-tls")]
+extern crate trust_dns_native_tls;
 
 pub mod config;
-mod dns_lru;
+pub mod dns_lru;
 pub mod error;
 pub mod lookup_ip;
 pub mod lookup;
@@ -155,9 +157,11 @@ mod resolver;
 pub mod system_conf;
 mod resolver_future;
 mod hosts;
+#[cfg(feature = "dns-over-native-tls")]
+mod tls;
 
 pub use resolver::Resolver;
-pub use resolver_future::ResolverFuture;
+pub use resolver_future::{BasicResolverHandle, ResolverFuture};
 pub use hosts::Hosts;
 
 /// returns a version as specified in Cargo.toml
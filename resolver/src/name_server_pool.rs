@@ -16,6 +16,7 @@ use futures::{future, task, Async, Future, Poll};
 use tokio_core::reactor::Handle;
 
 use trust_dns_proto::{DnsFuture, DnsHandle};
+use trust_dns_proto::error::ProtoErrorKind;
 use trust_dns_proto::op::{Edns, Message, NoopMessageFinalizer, ResponseCode};
 #[cfg(feature = "mdns")]
 use trust_dns_proto::multicast::{MDNS_IPV4, MdnsClientStream, MdnsQueryType};
@@ -174,6 +175,17 @@ pub trait ConnectionProvider: Clone {
         options: &ResolverOpts,
         reactor: &Handle,
     ) -> Self::ConnHandle;
+
+    /// Connects to the name server, over TLS to one with a certificate for `tls_dns_name`, see
+    ///  `ResolverConfig::add_tls_name_server`
+    fn new_connection_with_tls_dns_name(
+        config: &NameServerConfig,
+        _tls_dns_name: Option<&str>,
+        options: &ResolverOpts,
+        reactor: &Handle,
+    ) -> Self::ConnHandle {
+        Self::new_connection(config, options, reactor)
+    }
 }
 
 #[doc(hidden)]
@@ -187,6 +199,16 @@ impl ConnectionProvider for StandardConnection {
         config: &NameServerConfig,
         options: &ResolverOpts,
         reactor: &Handle,
+    ) -> Self::ConnHandle {
+        Self::new_connection_with_tls_dns_name(config, None, options, reactor)
+    }
+
+    #[cfg_attr(not(feature = "dns-over-native-tls"), allow(unused_variables))]
+    fn new_connection_with_tls_dns_name(
+        config: &NameServerConfig,
+        tls_dns_name: Option<&str>,
+        options: &ResolverOpts,
+        reactor: &Handle,
     ) -> Self::ConnHandle {
         let dns_handle = match config.protocol {
             Protocol::Udp => {
@@ -212,7 +234,22 @@ impl ConnectionProvider for StandardConnection {
                     NoopMessageFinalizer::new(),
                 )
             }
-            // TODO: Protocol::Tls => TlsClientStream::new(config.socket_addr, reactor),
+            #[cfg(feature = "dns-over-native-tls")]
+            Protocol::Tls => {
+                let dns_name = tls_dns_name.map_or_else(
+                    || config.socket_addr.ip().to_string(),
+                    |name| name.to_string(),
+                );
+                let (stream, handle) = ::tls::new_tls_stream(config.socket_addr, dns_name, reactor);
+                // TODO: need config for Signer...
+                DnsFuture::with_timeout(
+                    stream,
+                    handle,
+                    reactor,
+                    options.timeout,
+                    NoopMessageFinalizer::new(),
+                )
+            }
             #[cfg(feature = "mdns")]
             Protocol::Mdns => {
                 let (stream, handle) = MdnsClientStream::new(
@@ -242,8 +279,11 @@ impl ConnectionProvider for StandardConnection {
 #[doc(hidden)]
 pub struct NameServer<C: DnsHandle, P: ConnectionProvider<ConnHandle = C>> {
     config: NameServerConfig,
+    tls_dns_name: Option<String>,
     options: ResolverOpts,
     client: C,
+    // the connection which replaced the client after it was closed, see `send`
+    reconnected: Arc<Mutex<Option<C>>>,
     // TODO: switch to FuturesMutex? (Mutex will have some undesireable locking)
     stats: Arc<Mutex<NameServerStats>>,
     reactor: Handle,
@@ -256,13 +296,30 @@ impl<C: DnsHandle, P: ConnectionProvider<ConnHandle = C>> NameServer<C, P> {
         options: ResolverOpts,
         reactor: &Handle,
     ) -> NameServer<BasicResolverHandle, StandardConnection> {
-        let client = StandardConnection::new_connection(&config, &options, reactor);
+        Self::with_tls_dns_name(config, None, options, reactor)
+    }
+
+    /// A name server with a certificate for `tls_dns_name`, if it is connected to over TLS
+    pub(crate) fn with_tls_dns_name(
+        config: NameServerConfig,
+        tls_dns_name: Option<String>,
+        options: ResolverOpts,
+        reactor: &Handle,
+    ) -> NameServer<BasicResolverHandle, StandardConnection> {
+        let client = StandardConnection::new_connection_with_tls_dns_name(
+            &config,
+            tls_dns_name.as_ref().map(|name| name.as_str()),
+            &options,
+            reactor,
+        );
 
         // TODO: setup EDNS
         NameServer {
             config,
+            tls_dns_name,
             options,
             client,
+            reconnected: Arc::new(Mutex::new(None)),
             stats: Arc::new(Mutex::new(NameServerStats::default())),
             reactor: reactor.clone(),
             phantom: PhantomData,
@@ -278,8 +335,10 @@ impl<C: DnsHandle, P: ConnectionProvider<ConnHandle = C>> NameServer<C, P> {
     ) -> NameServer<C, P> {
         NameServer {
             config,
+            tls_dns_name: None,
             options,
             client,
+            reconnected: Arc::new(Mutex::new(None)),
             stats: Arc::new(Mutex::new(NameServerStats::default())),
             reactor: reactor.clone(),
             phantom: PhantomData,
@@ -325,7 +384,12 @@ impl<C: DnsHandle, P: ConnectionProvider<ConnHandle = C>> NameServer<C, P> {
             if Instant::now().duration_since(when) > retry_delay {
                 debug!("reconnecting: {:?}", self.config);
                 // establish a new connection
-                let client = P::new_connection(&self.config, &self.options, &self.reactor);
+                let client = P::new_connection_with_tls_dns_name(
+                    &self.config,
+                    self.tls_dns_name.as_ref().map(|name| name.as_str()),
+                    &self.options,
+                    &self.reactor,
+                );
                 mem::replace(&mut self.client, client);
 
                 // reinitialize the mutex (in case it was poisoned before)
@@ -341,12 +405,19 @@ impl<C: DnsHandle, P: ConnectionProvider<ConnHandle = C>> NameServer<C, P> {
             Ok(())
         }
     }
+
+    /// Takes the connection which replaced the client after it was closed, if any
+    fn take_reconnected(&mut self) {
+        if let Some(client) = self.reconnected.lock().ok().and_then(|mut c| c.take()) {
+            self.client = client;
+        }
+    }
 }
 
 impl<C, P> DnsHandle for NameServer<C, P>
 where
-    C: DnsHandle<Error = ResolveError>,
-    P: ConnectionProvider<ConnHandle = C>,
+    C: DnsHandle<Error = ResolveError> + 'static,
+    P: ConnectionProvider<ConnHandle = C> + 'static,
 {
     type Error = ResolveError;
 
@@ -361,14 +432,61 @@ where
             return Box::new(future::err(error));
         }
 
+        self.take_reconnected();
+
         // Becuase a Poisoned lock error could have occured, make sure to create a new Mutex...
 
         // grab a reference to the stats for this NameServer
         let mutex1 = self.stats.clone();
         let mutex2 = self.stats.clone();
+        let client = self.client.clone();
+        let closed_retry = message.clone();
+        let cookie_retry = message.clone();
+
+        // what is needed to reconnect, if the connection was closed
+        let config = self.config.clone();
+        let tls_dns_name = self.tls_dns_name.clone();
+        let options = self.options.clone();
+        let reactor = self.reactor.clone();
+        let reconnected = self.reconnected.clone();
+
         Box::new(
             self.client
                 .send(message)
+                .map(move |response| (response, client))
+                .or_else(move |error| -> Box<Future<Item = (Message, C), Error = ResolveError>> {
+                    // the server closed the connection, e.g. after it was idle, the request was not
+                    //  sent, it is retried once on a new connection, which replaces the client
+                    if let ResolveErrorKind::Proto(ProtoErrorKind::Closed) = *error.kind() {
+                        debug!("connection closed, reconnecting: {:?}", config);
+                        let mut client = P::new_connection_with_tls_dns_name(
+                            &config,
+                            tls_dns_name.as_ref().map(|name| name.as_str()),
+                            &options,
+                            &reactor,
+                        );
+                        if let Ok(mut reconnected) = reconnected.lock() {
+                            *reconnected = Some(client.clone());
+                        }
+
+                        Box::new(
+                            client
+                                .send(closed_retry)
+                                .map(move |response| (response, client)),
+                        )
+                    } else {
+                        Box::new(future::err(error))
+                    }
+                })
+                .and_then(move |(response, mut client)| -> Box<Future<Item = Message, Error = ResolveError>> {
+                    // the connection now has the cookie of the server, which is sent on the retry
+                    if response.response_code() == ResponseCode::BADCOOKIE {
+                        debug!("bad cookie, retrying with the server cookie");
+                        Box::new(client.send(cookie_retry))
+                    } else {
+                        Box::new(future::ok(response))
+                    }
+                })
                 .and_then(move |response| {
                     // TODO: consider making message::take_edns...
                     let remote_edns = response.edns().cloned();
@@ -466,7 +584,8 @@ pub struct NameServerPool<C: DnsHandle + 'static, P: ConnectionProvider<ConnHand
 }
 
 impl<C: DnsHandle + 'static, P: ConnectionProvider<ConnHandle = C> + 'static> NameServerPool<C, P> {
-    pub(crate) fn from_config(
+    /// Constructs a pool of the name servers of the config
+    pub fn from_config(
         config: &ResolverConfig,
         options: &ResolverOpts,
         reactor: &Handle,
@@ -490,8 +609,11 @@ impl<C: DnsHandle + 'static, P: ConnectionProvider<ConnHandle = C> + 'static> Na
             .iter()
             .filter(|ns_config| ns_config.protocol.is_stream())
             .map(|ns_config| {
-                NameServer::<_, StandardConnection>::new(
+                NameServer::<_, StandardConnection>::with_tls_dns_name(
                     ns_config.clone(),
+                    config
+                        .tls_dns_name(&ns_config.socket_addr)
+                        .map(|name| name.to_string()),
                     options.clone(),
                     reactor,
                 )
@@ -0,0 +1,30 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! DNS over TLS connections to name servers, with native-tls
+
+use std::io;
+use std::net::SocketAddr;
+
+use futures::Future;
+use tokio_core::reactor::Handle;
+use trust_dns_native_tls::{TlsClientStream, TlsClientStreamBuilder};
+use trust_dns_proto::DnsStreamHandle;
+
+use error::ResolveError;
+
+/// Connects to the name server, the certificate of which must be valid for `dns_name`
+pub(crate) fn new_tls_stream(
+    socket_addr: SocketAddr,
+    dns_name: String,
+    reactor: &Handle,
+) -> (
+    Box<Future<Item = TlsClientStream, Error = io::Error>>,
+    Box<DnsStreamHandle<Error = ResolveError>>,
+) {
+    TlsClientStreamBuilder::new().build_with_error(socket_addr, dns_name, reactor)
+}
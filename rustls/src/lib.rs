@@ -22,9 +22,12 @@ extern crate tokio_core;
 extern crate tokio_rustls;
 extern crate trust_dns;
 extern crate trust_dns_proto;
+extern crate untrusted;
+extern crate webpki;
 
 pub mod tls_client_connection;
 pub mod tls_client_stream;
+pub mod tls_server;
 pub mod tls_stream;
 
 pub use self::tls_client_connection::{TlsClientConnection, TlsClientConnectionBuilder};
@@ -28,6 +28,7 @@ use futures::Stream;
 use rustls::Certificate;
 use tokio_core::reactor::Core;
 
+use tls_server;
 use TlsStreamBuilder;
 
 // this fails on linux for some reason. It appears that a buffer somewhere is dirty
@@ -53,6 +54,93 @@ fn test_tls_client_stream_ipv6() {
     tls_client_stream_test(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)), false)
 }
 
+#[test]
+fn test_is_valid_for_name() {
+    let server_path = env::var("TDNS_SERVER_SRC_ROOT").unwrap_or("../server".to_owned());
+
+    // the DNS names of the subject alternative names
+    let cert = Certificate(read_file(&format!("{}/../tests/cert.der", server_path)));
+    assert!(tls_server::is_valid_for_name(&cert, "ns.example.com."));
+    assert!(tls_server::is_valid_for_name(&cert, "NS.Example.Com"));
+    assert!(!tls_server::is_valid_for_name(&cert, "www.example.com."));
+
+    // not the common name, without subject alternative names
+    let root_cert = Certificate(read_file(&format!("{}/../tests/ca.der", server_path)));
+    assert!(!tls_server::is_valid_for_name(&root_cert, "root.example.com."));
+}
+
+#[test]
+fn test_is_valid_for_wildcard() {
+    let subject = der_name(0x0c, b"cn.example.net");
+    let general_names = [der(0x82, b"*.example.com"), der(0x87, &[192, 0, 2, 1])].concat();
+    let cert = Certificate(der_cert(&subject, Some(&der(0x30, &general_names))));
+
+    // a wildcard is for a single label
+    assert!(tls_server::is_valid_for_name(&cert, "a.example.com."));
+    assert!(!tls_server::is_valid_for_name(&cert, "example.com."));
+    assert!(!tls_server::is_valid_for_name(&cert, "a.b.example.com."));
+    assert!(!tls_server::is_valid_for_name(&cert, "cn.example.net."));
+
+    // a certificate which can not be read is for no name
+    let mut truncated = cert.0.clone();
+    truncated.pop();
+    assert!(!tls_server::is_valid_for_name(
+        &Certificate(truncated),
+        "a.example.com."
+    ));
+}
+
+// a DER element, with a length of up to two bytes
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    match contents.len() {
+        len @ 0...0x7f => element.push(len as u8),
+        len @ 0x80...0xff => element.extend_from_slice(&[0x81, len as u8]),
+        len => element.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+    }
+    element.extend_from_slice(contents);
+    element
+}
+
+// a Name with a single common name, of the string type
+fn der_name(string_type: u8, common_name: &[u8]) -> Vec<u8> {
+    // id-at-commonName
+    let oid = der(0x06, &[0x55, 0x04, 0x03]);
+    let attribute = [oid, der(string_type, common_name)].concat();
+    der(0x30, &der(0x31, &der(0x30, &attribute)))
+}
+
+// a v3 certificate of the subject, with the subject alternative names if there are some, the
+//  keys and signatures are not valid, they are not read
+fn der_cert(subject: &[u8], general_names: Option<&[u8]>) -> Vec<u8> {
+    // ecdsa-with-SHA256
+    let oid = der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]);
+    let algorithm = der(0x30, &oid);
+    let validity = [der(0x17, b"180101000000Z"), der(0x17, b"280101000000Z")].concat();
+    // id-ecPublicKey
+    let oid = der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]);
+    let key_algorithm = der(0x30, &oid);
+
+    let mut tbs = vec![
+        der(0xa0, &der(0x02, &[2])),
+        der(0x02, &[1]),
+        algorithm.clone(),
+        der_name(0x0c, b"ca.example.com"),
+        der(0x30, &validity),
+        subject.to_vec(),
+        der(0x30, &[key_algorithm, der(0x03, &[0, 4])].concat()),
+    ];
+    if let Some(general_names) = general_names {
+        let extension = [der(0x06, &[0x55, 0x1d, 0x11]), der(0x04, general_names)].concat();
+        tbs.push(der(0xa3, &der(0x30, &der(0x30, &extension))));
+    }
+
+    der(
+        0x30,
+        &[der(0x30, &tbs.concat()), algorithm, der(0x03, &[0])].concat(),
+    )
+}
+
 const TEST_BYTES: &'static [u8; 8] = b"DEADBEEF";
 const TEST_BYTES_LEN: usize = 8;
 
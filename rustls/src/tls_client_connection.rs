@@ -19,6 +19,8 @@ use std::io;
 
 use futures::Future;
 use rustls::Certificate;
+#[cfg(feature = "mtls")]
+use rustls::PrivateKey;
 use tokio_core::reactor::Handle;
 
 use trust_dns::error::*;
@@ -76,9 +78,14 @@ impl TlsClientConnectionBuilder {
     }
 
     /// Client side identity for client auth in TLS (aka mutual TLS auth)
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_chain` - the certificate of the client, followed by any intermediate certificates
+    /// * `key` - the private key of the certificate
     #[cfg(feature = "mtls")]
-    pub fn identity(&mut self, pkcs12: Pkcs12) {
-        self.0.identity(pkcs12);
+    pub fn identity(&mut self, cert_chain: Vec<Certificate>, key: PrivateKey) {
+        self.0.identity(cert_chain, key);
     }
 
     /// Creates a new client connection.
@@ -9,6 +9,8 @@ use std::net::SocketAddr;
 use std::io;
 
 use futures::Future;
+#[cfg(feature = "mtls")]
+use rustls::PrivateKey;
 use rustls::{Certificate, ClientSession};
 use tokio_core::net::TcpStream as TokioTcpStream;
 use tokio_core::reactor::Handle;
@@ -38,9 +40,14 @@ impl TlsClientStreamBuilder {
     }
 
     /// Client side identity for client auth in TLS (aka mutual TLS auth)
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_chain` - the certificate of the client, followed by any intermediate certificates
+    /// * `key` - the private key of the certificate
     #[cfg(feature = "mtls")]
-    pub fn identity(&mut self, pkcs12: Pkcs12) {
-        self.0.identity(pkcs12);
+    pub fn identity(&mut self, cert_chain: Vec<Certificate>, key: PrivateKey) {
+        self.0.identity(cert_chain, key);
     }
 
     /// Creates a new TlsStream to the specified name_server
@@ -64,7 +71,8 @@ impl TlsClientStreamBuilder {
         let new_future: Box<Future<Item = TlsClientStream, Error = io::Error>> =
             Box::new(stream_future.map(move |tls_stream| TcpClientStream::from_stream(tls_stream)));
 
-        let sender = Box::new(BufDnsStreamHandle::new(name_server, sender));
+        // requests are padded, and the connection is kept open, see `DnsStreamHandle::is_encrypted`
+        let sender = Box::new(BufDnsStreamHandle::new_encrypted(name_server, sender));
 
         (new_future, sender)
     }
@@ -0,0 +1,145 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! TLS for the server side of DNS over TLS, with PEM certificates and keys
+
+use std::fs::File;
+use std::io::{self, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use rustls::internal::pemfile;
+use rustls::sign::RSASigningKey;
+use rustls::Session;
+use untrusted;
+use webpki::EndEntityCert;
+
+pub use rustls::{Certificate, PrivateKey, ServerConfig, ServerSession};
+pub use tokio_rustls::ServerConfigExt;
+
+/// Reads the chain of certificates from a PEM file, the certificate of the server first
+pub fn read_cert(path: &Path) -> Result<Vec<Certificate>, String> {
+    let file =
+        File::open(path).map_err(|e| format!("error opening cert file: {:?}: {}", path, e))?;
+
+    let mut reader = BufReader::new(file);
+    let certs = pemfile::certs(&mut reader)
+        .map_err(|_| format!("badly formatted PEM certificates from: {:?}", path))?;
+
+    if certs.is_empty() {
+        return Err(format!("no certificates found in: {:?}", path));
+    }
+    Ok(certs)
+}
+
+/// Reads the private key of the server from a PEM file, in PKCS#8 or in PKCS#1 (RSA) format
+pub fn read_key(path: &Path) -> Result<PrivateKey, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("error opening private key file: {:?}: {}", path, e))?;
+
+    let mut reader = BufReader::new(file);
+    let mut keys = pemfile::pkcs8_private_keys(&mut reader)
+        .map_err(|_| format!("badly formatted PKCS#8 private key from: {:?}", path))?;
+
+    if keys.is_empty() {
+        reader
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| format!("could not read private key from: {:?}: {}", path, e))?;
+        keys = pemfile::rsa_private_keys(&mut reader)
+            .map_err(|_| format!("badly formatted RSA private key from: {:?}", path))?;
+    }
+
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| format!("no private key found in: {:?}", path))
+}
+
+/// Returns the configuration for accepting TLS connections, see `ServerConfigExt::accept_async`
+///
+/// rustls only negotiates TLS 1.2 and later, and signs with RSA keys.
+///
+/// # Arguments
+///
+/// * `cert_chain` - the certificate of the server, followed by any intermediate certificates
+/// * `key` - the private key of the certificate of the server
+pub fn new_acceptor(cert_chain: Vec<Certificate>, key: PrivateKey) -> io::Result<ServerConfig> {
+    // the config panics on keys which can not be used for signing, check it first
+    RSASigningKey::new(&key).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "tls error: the private key is not a valid RSA key",
+        )
+    })?;
+
+    let mut config = ServerConfig::new();
+    config.set_single_cert(cert_chain, key);
+
+    Ok(config)
+}
+
+/// Returns the configuration for accepting TLS connections with mutual TLS, which asks clients
+///  for their certificate, see `new_acceptor`
+///
+/// The certificates of the clients are verified by the client certificate verifier of rustls,
+///  with the CAs as its roots. Clients which present a certificate must have it signed by one of
+///  the CAs, otherwise the handshake fails, see `peer_certificate` for the identity of the
+///  client.
+///  Clients without a certificate are accepted.
+///
+/// # Arguments
+///
+/// * `cert_chain` - the certificate of the server, followed by any intermediate certificates
+/// * `key` - the private key of the certificate of the server
+/// * `client_ca` - the certificates of the CAs of the clients, see `read_cert`
+pub fn new_acceptor_with_client_ca(
+    cert_chain: Vec<Certificate>,
+    key: PrivateKey,
+    client_ca: Vec<Certificate>,
+) -> io::Result<ServerConfig> {
+    let mut config = new_acceptor(cert_chain, key)?;
+
+    if !client_ca.is_empty() {
+        // the certificate is optional, but if one is presented it must verify
+        config.set_client_auth_roots(client_ca, false);
+    }
+
+    Ok(config)
+}
+
+/// Returns the verified certificate of the client, the first of its chain, if it presented one,
+///  see `is_valid_for_name` for its identity
+pub fn peer_certificate(session: &ServerSession) -> Option<Certificate> {
+    session
+        .get_peer_certificates()
+        .and_then(|certs| certs.into_iter().next())
+}
+
+/// Returns true if the certificate is for the DNS name
+///
+/// The name is matched by webpki, as the name of a server, against the DNS names of the subject
+///  alternative names, which may be wildcards. The common name of the subject is not used, a
+///  certificate which can not be read is for no name.
+///
+/// # Arguments
+///
+/// * `cert` - the certificate of the client, see `peer_certificate`
+/// * `name` - a DNS name in ASCII, the root label is optional
+pub fn is_valid_for_name(cert: &Certificate, name: &str) -> bool {
+    let cert = match EndEntityCert::from(untrusted::Input::from(&cert.0)) {
+        Ok(cert) => cert,
+        Err(_) => return false,
+    };
+
+    // webpki does not accept the root label, and compares the names as they are
+    let name = if name.ends_with('.') {
+        &name[..name.len() - 1]
+    } else {
+        name
+    };
+    let name = name.to_ascii_lowercase();
+    cert.verify_is_valid_for_dns_name(untrusted::Input::from(name.as_bytes()))
+        .is_ok()
+}
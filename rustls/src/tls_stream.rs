@@ -11,7 +11,7 @@ use std::sync::Arc;
 
 use futures::{future, Future, IntoFuture};
 use futures::sync::mpsc::unbounded;
-use rustls::{Certificate, ClientConfig, ClientSession};
+use rustls::{Certificate, ClientConfig, ClientSession, PrivateKey};
 use tokio_core::net::TcpStream as TokioTcpStream;
 use tokio_core::reactor::Handle;
 use tokio_rustls::{ClientConfigExt, TlsStream as TokioTlsStream};
@@ -22,7 +22,10 @@ use trust_dns::tcp::TcpStream;
 
 pub type TlsStream = TcpStream<TokioTlsStream<TokioTcpStream, ClientSession>>;
 
-fn tls_new(certs: &[Certificate] /*, pkcs12: Option<Pkcs12>*/) -> io::Result<Arc<ClientConfig>> {
+fn tls_new(
+    certs: &[Certificate],
+    identity: Option<(Vec<Certificate>, PrivateKey)>,
+) -> io::Result<Arc<ClientConfig>> {
     let mut builder = ClientConfig::new();
 
     // mutate the trust_store
@@ -39,14 +42,10 @@ fn tls_new(certs: &[Certificate] /*, pkcs12: Option<Pkcs12>*/) -> io::Result<Arc
         }
     }
 
-    // if let Some(pkcs12) = pkcs12 {
-    //     try!(builder
-    //              .identity(pkcs12)
-    //              .map_err(|e| {
-    //                           io::Error::new(io::ErrorKind::ConnectionRefused,
-    //                                          format!("tls error: {}", e))
-    //                       }));
-    // }
+    // the certificate of the client, if the server asks for one
+    if let Some((cert_chain, key)) = identity {
+        builder.set_single_client_cert(cert_chain, key);
+    }
 
     Ok(Arc::new(builder))
 }
@@ -69,7 +68,7 @@ pub fn tls_from_stream(
 #[derive(Clone)]
 pub struct TlsStreamBuilder {
     ca_chain: Vec<Certificate>,
-    //identity: Option<Pkcs12>,
+    identity: Option<(Vec<Certificate>, PrivateKey)>,
 }
 
 impl TlsStreamBuilder {
@@ -77,7 +76,7 @@ impl TlsStreamBuilder {
     pub fn new() -> TlsStreamBuilder {
         TlsStreamBuilder {
             ca_chain: vec![],
-            // identity: None,
+            identity: None,
         }
     }
 
@@ -89,9 +88,14 @@ impl TlsStreamBuilder {
     }
 
     /// Client side identity for client auth in TLS (aka mutual TLS auth)
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_chain` - the certificate of the client, followed by any intermediate certificates
+    /// * `key` - the private key of the certificate
     #[cfg(feature = "mtls")]
-    pub fn identity(&mut self, pkcs12: Pkcs12) {
-        self.identity = Some(pkcs12);
+    pub fn identity(&mut self, cert_chain: Vec<Certificate>, key: PrivateKey) {
+        self.identity = Some((cert_chain, key));
     }
 
     /// Creates a new TlsStream to the specified name_server
@@ -132,7 +136,7 @@ impl TlsStreamBuilder {
         let (message_sender, outbound_messages) = unbounded();
         let message_sender = BufStreamHandle::new(message_sender);
 
-        let tls_connector = match ::tls_stream::tls_new(&self.ca_chain /*, self.identity*/) {
+        let tls_connector = match ::tls_stream::tls_new(&self.ca_chain, self.identity) {
             Ok(c) => c,
             Err(e) => {
                 return (
@@ -0,0 +1,191 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Access control lists, of the clients allowed to query, transfer and update zones
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use trust_dns::rr::rdata::opt::truncate_address;
+use trust_dns::rr::Name;
+
+use server::ClientIdentity;
+
+/// An element of an `Acl`, matched against the client of a request
+#[derive(Clone, Debug, PartialEq)]
+pub enum AclElement {
+    /// matches all clients
+    Any,
+    /// matches no clients
+    None,
+    /// matches clients with an address in the network
+    Network {
+        /// the address of the network
+        addr: IpAddr,
+        /// the number of leading bits of the address which must match
+        prefix_len: u8,
+    },
+    /// matches requests signed with the key, with TSIG or SIG(0)
+    Key(Name),
+    /// matches clients which presented a certificate for the name, over mutual TLS
+    Certificate(Name),
+}
+
+impl AclElement {
+    fn matches(&self, src: IpAddr, key: Option<&Name>, identity: Option<&ClientIdentity>) -> bool {
+        match *self {
+            AclElement::Any => true,
+            AclElement::None => false,
+            AclElement::Network { addr, prefix_len } => prefix_matches(addr, src, prefix_len),
+            AclElement::Key(ref name) => key.map_or(false, |key| key == name),
+            AclElement::Certificate(ref name) => {
+                identity.map_or(false, |identity| identity.is(name))
+            }
+        }
+    }
+}
+
+// true if the addresses are of the same family, and their leading `prefix_len` bits are the same,
+//  IPv4 clients of dual-stack sockets, with IPv4-mapped addresses, match IPv4 networks
+pub(crate) fn prefix_matches(addr: IpAddr, src: IpAddr, prefix_len: u8) -> bool {
+    let src = match (addr, src) {
+        (IpAddr::V4(..), IpAddr::V6(src)) => ipv4_mapped(src).map_or(IpAddr::V6(src), IpAddr::V4),
+        (_, src) => src,
+    };
+    truncate_address(addr, prefix_len) == truncate_address(src, prefix_len)
+}
+
+// the IPv4 address of an IPv4-mapped address, ::ffff:a.b.c.d, in ::ffff:0:0/96
+fn ipv4_mapped(addr: Ipv6Addr) -> Option<Ipv4Addr> {
+    match addr.segments() {
+        [0, 0, 0, 0, 0, 0xffff, _, _] => addr.to_ipv4(),
+        _ => None,
+    }
+}
+
+/// An address match list, as in BIND
+///
+/// The elements are matched in order, the first which matches the client decides whether it is
+///  allowed, a negated element denies the client. Clients which match none of the elements are
+///  denied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Acl {
+    elements: Vec<(bool, AclElement)>,
+}
+
+impl Acl {
+    /// Constructs a new Acl
+    ///
+    /// # Arguments
+    ///
+    /// * `elements` - the elements, each with whether it is negated
+    pub fn new(elements: Vec<(bool, AclElement)>) -> Self {
+        Acl { elements }
+    }
+
+    /// An Acl which allows all clients
+    pub fn any() -> Self {
+        Acl::new(vec![(false, AclElement::Any)])
+    }
+
+    /// An Acl which allows no clients
+    pub fn none() -> Self {
+        Acl::new(vec![(false, AclElement::None)])
+    }
+
+    /// An Acl which allows only clients on the loopback addresses, 127.0.0.0/8 and ::1
+    pub fn localhost() -> Self {
+        Acl::new(vec![
+            (
+                false,
+                AclElement::Network {
+                    addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 0)),
+                    prefix_len: 8,
+                },
+            ),
+            (
+                false,
+                AclElement::Network {
+                    addr: IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)),
+                    prefix_len: 128,
+                },
+            ),
+        ])
+    }
+
+    /// Returns true if the client is allowed
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - the address of the client
+    /// * `key` - the name of the key with which the request is signed, if its signature verified
+    /// * `identity` - the identity of the client, if it presented a certificate over mutual TLS
+    pub fn allows(
+        &self,
+        src: IpAddr,
+        key: Option<&Name>,
+        identity: Option<&ClientIdentity>,
+    ) -> bool {
+        self.elements
+            .iter()
+            .find(|&&(_, ref element)| element.matches(src, key, identity))
+            .map_or(false, |&(negated, _)| !negated)
+    }
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Acl::any()
+    }
+}
+
+/// The access control lists of a zone, by default all clients are allowed to query and update the
+///  zone, and none to transfer it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneAcls {
+    query: Acl,
+    transfer: Acl,
+    update: Acl,
+}
+
+impl ZoneAcls {
+    /// Constructs a new ZoneAcls
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the clients allowed to query the zone
+    /// * `transfer` - the clients allowed to transfer the zone, with AXFR or IXFR
+    /// * `update` - the clients allowed to update the zone, the update must still be authorized
+    ///              by the zone, see `Authority::authorize`
+    pub fn new(query: Acl, transfer: Acl, update: Acl) -> Self {
+        ZoneAcls {
+            query,
+            transfer,
+            update,
+        }
+    }
+
+    /// the clients allowed to query the zone
+    pub fn query(&self) -> &Acl {
+        &self.query
+    }
+
+    /// the clients allowed to transfer the zone
+    pub fn transfer(&self) -> &Acl {
+        &self.transfer
+    }
+
+    /// the clients allowed to update the zone
+    pub fn update(&self) -> &Acl {
+        &self.update
+    }
+}
+
+impl Default for ZoneAcls {
+    fn default() -> Self {
+        ZoneAcls::new(Acl::any(), Acl::none(), Acl::any())
+    }
+}
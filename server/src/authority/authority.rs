@@ -8,20 +8,37 @@
 //! All authority related types
 
 use std::borrow::Borrow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "dnssec")]
+use chrono::{DateTime, Utc};
 #[cfg(feature = "dnssec")]
 use trust_dns::error::*;
 use trust_dns::op::{LowerQuery, ResponseCode};
 use trust_dns::rr::{DNSClass, LowerName, Name, RData, Record, RecordSet, RecordType, RrKey};
-use trust_dns::rr::dnssec::{Signer, SupportedAlgorithms};
+use trust_dns::rr::dnssec::{Signer, SupportedAlgorithms, TSigner};
+use trust_dns::rr::rdata::{DNSSECRecordType, NSEC3PARAM};
+use trust_dns::serialize::txt::Emitter;
+use trust_dns_proto::error::ProtoErrorKind;
 
-use authority::{AuthLookup, Journal, MessageRequest, UpdateResult, ZoneType};
+use authority::{AuthLookup, Journal, MessageRequest, Subnets, UpdateResult, ZoneType};
+use authority::slave::serial_gt;
 #[cfg(feature = "dnssec")]
 use authority::UpdateRequest;
 
 use error::{PersistenceErrorKind, PersistenceResult};
+use server::ClientIdentity;
+
+/// The longest chain of CNAMEs which will be followed within the zone, this also stops loops
+const MAX_CNAME_CHAIN: usize = 8;
 
+/// The default number of updates kept for incremental zone transfers, see `set_ixfr_depth`
+pub const DEFAULT_IXFR_DEPTH: usize = 100;
 
 /// Authority is responsible for storing the resource records for a particular zone.
 ///
@@ -30,17 +47,40 @@ use error::{PersistenceErrorKind, PersistenceResult};
 pub struct Authority {
     origin: LowerName,
     class: DNSClass,
-    journal: Option<Journal>,
+    // locked, as the connection of the journal can not be shared, while the Authority is shared
+    //  by the workers
+    journal: Option<Mutex<Journal>>,
     records: BTreeMap<RrKey, RecordSet>,
     zone_type: ZoneType,
     allow_update: bool,
     is_dnssec_enabled: bool,
+    // a Slave zone which could not be refreshed from its masters before the SOA expire period
+    is_expired: bool,
+    // the last time a Slave zone was refreshed from its masters, the expiry is counted from it
+    refreshed: Option<SystemTime>,
+    // dynamic updates are suspended, e.g. while the zone file is edited
+    is_frozen: bool,
     // Private key mapped to the Record of the DNSKey
     //  TODO: these private_keys should be stored securely. Ideally, we have keys only stored per
     //   server instance, but that requires requesting updates from the parent zone, which may or
     //   may not support dynamic updates to register the new key... Trust-DNS will provide support
     //   for this, in some form, perhaps alternate root zones...
     secure_keys: Vec<Signer>,
+    // shared secrets for TSIG, authorizing updates and zone transfers
+    tsig_keys: Vec<TSigner>,
+    // names of client certificates, from mutual TLS, authorizing updates
+    update_certificates: Vec<Name>,
+    // parameters of the NSEC3 chain, if it replaces NSEC for authenticated denial
+    nsec3: Option<NSEC3PARAM>,
+    // the owners of the NSEC3 records, in the order of the chain, see `nsec3_covering`
+    nsec3_owners: BTreeSet<LowerName>,
+    // variants of records for the clients in some networks, see EDNS Client Subnet
+    subnets: Subnets,
+    // the deleted and added records of each update, by the serial before it, see `ixfr()`
+    diffs: HashMap<u32, (Vec<Record>, Vec<Record>)>,
+    // the serials of `diffs`, oldest first
+    diff_serials: VecDeque<u32>,
+    ixfr_depth: usize,
 }
 
 impl Authority {
@@ -74,7 +114,18 @@ impl Authority {
             zone_type: zone_type,
             allow_update: allow_update,
             is_dnssec_enabled: is_dnssec_enabled,
+            is_expired: false,
+            refreshed: None,
+            is_frozen: false,
             secure_keys: Vec::new(),
+            tsig_keys: Vec::new(),
+            update_certificates: Vec::new(),
+            nsec3: None,
+            nsec3_owners: BTreeSet::new(),
+            subnets: Subnets::default(),
+            diffs: HashMap::new(),
+            diff_serials: VecDeque::new(),
+            ixfr_depth: DEFAULT_IXFR_DEPTH,
         }
     }
 
@@ -104,12 +155,144 @@ impl Authority {
         Ok(())
     }
 
+    /// Adds a shared secret for TSIG, requests signed with it are authorized for dynamic updates
+    ///  and zone transfers.
+    ///
+    /// Once a key is added, zone transfers are only allowed with TSIG.
+    ///
+    /// # Arguments
+    ///
+    /// * `tsigner` - the shared secret, with its name and algorithm
+    pub fn add_tsig_key(&mut self, tsigner: TSigner) {
+        self.tsig_keys.push(tsigner);
+    }
+
+    /// The shared secrets for TSIG, see `add_tsig_key`
+    pub fn tsig_keys(&self) -> &[TSigner] {
+        &self.tsig_keys
+    }
+
+    /// Adds the name of a client certificate, updates from clients which presented a certificate
+    ///  for the name over mutual TLS are authorized without TSIG or SIG(0)
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the certificate, see `ClientIdentity`
+    pub fn add_update_certificate(&mut self, name: Name) {
+        self.update_certificates.push(name);
+    }
+
+    /// The names of the client certificates authorized for updates, see `add_update_certificate`
+    pub fn update_certificates(&self) -> &[Name] {
+        &self.update_certificates
+    }
+
+    /// Adds a variant of records of the zone, which is answered instead to the clients in the
+    ///  networks, by the address from the EDNS Client Subnet option or the source of the request
+    ///
+    /// The variants of a secure zone are signed with the rest of the zone, so they should be added
+    ///  before it is secured, see `secure_zone`.
+    ///
+    /// # Arguments
+    ///
+    /// * `networks` - the addresses of the networks, with their prefix lengths
+    /// * `records` - the records of the variant, replacing the records of the same name and type
+    pub fn add_subnet(&mut self, networks: Vec<(IpAddr, u8)>, records: Vec<Record>) {
+        self.subnets.insert(networks, records);
+    }
+
+    /// The variants of records for the clients in some networks, see `add_subnet`
+    pub fn subnets(&self) -> &Subnets {
+        &self.subnets
+    }
+
+    /// Sets the parameters of the NSEC3 chain, which replaces the NSEC chain the next time the zone
+    ///  is secured, see `secure_zone`. `None` reverts to NSEC.
+    pub fn set_nsec3(&mut self, nsec3: Option<NSEC3PARAM>) {
+        self.nsec3 = nsec3;
+    }
+
+    /// The parameters of the NSEC3 chain, see `set_nsec3`
+    pub fn nsec3(&self) -> Option<&NSEC3PARAM> {
+        self.nsec3.as_ref()
+    }
+
+    /// Sets the number of updates for which the changes are kept, the oldest are discarded
+    ///
+    /// Secondaries with an older serial are answered with the full zone on IXFR, see `ixfr()`.
+    pub fn set_ixfr_depth(&mut self, ixfr_depth: usize) {
+        self.ixfr_depth = ixfr_depth;
+        for serial in self.prune_diffs() {
+            if let Some(journal) = self.journal() {
+                if let Err(error) = journal.delete_diff(serial) {
+                    error!("could not remove diff from journal: {}", error);
+                }
+            }
+        }
+    }
+
+    /// Verifies the TSIG of the request against the keys of the zone
+    ///
+    /// # Return
+    ///
+    /// `None` if the request is not signed with TSIG, otherwise the key which signed the request
+    ///  along with its MAC, for signing the response, or the TSIG error if it did not verify with
+    ///  any of the keys: BADKEY if none has its name, otherwise BADSIG, or BADTIME if the MAC is
+    ///  valid but the time is not, see `TSigner::verify_message_byte`.
+    pub fn verify_tsig(
+        &self,
+        request: &MessageRequest,
+    ) -> Option<Result<(&TSigner, Vec<u8>), ResponseCode>> {
+        use trust_dns::rr::rdata::DNSSECRecordType;
+
+        let tsig = match request.sig0().last() {
+            Some(tsig) if tsig.rr_type() == RecordType::DNSSEC(DNSSECRecordType::TSIG) => tsig,
+            _ => return None,
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        // the error of the key with the name of the TSIG, BADKEY if there is none
+        let mut error = ResponseCode::BADKEY;
+        for key in &self.tsig_keys {
+            match key.verify_message_byte(None, request.unsigned_bytes(), tsig, now) {
+                Ok(mac) => {
+                    info!("verified tsig with key: {}", key.signer_name());
+                    return Some(Ok((key, mac)));
+                }
+                Err(e) => {
+                    debug!("did not verify tsig with key: {}: {}", key.signer_name(), e);
+                    match *e.kind() {
+                        ProtoErrorKind::Tsig(ResponseCode::BADKEY) => (),
+                        ProtoErrorKind::Tsig(key_error) => error = key_error,
+                        _ => error = ResponseCode::BADSIG,
+                    }
+                }
+            }
+        }
+
+        warn!(
+            "tsig did not verify for: {}, key: {}, error: {}",
+            self.origin,
+            tsig.name(),
+            error
+        );
+        Some(Err(error))
+    }
+
     /// Recovers the zone from a Journal, returns an error on failure to recover the zone.
     ///
     /// # Arguments
     ///
-    /// * `journal` - the journal from which to load the persisted zone.
-    pub fn recover_with_journal(&mut self, journal: &Journal) -> PersistenceResult<()> {
+    /// * `journal` - the journal from which to load the persisted zone, e.g. the locked journal of
+    ///               another Authority, see `journal()`
+    pub fn recover_with_journal<J: Deref<Target = Journal>>(
+        &mut self,
+        journal: J,
+    ) -> PersistenceResult<()> {
         assert!(
             self.records.is_empty(),
             "records should be empty during a recovery"
@@ -127,30 +310,44 @@ impl Authority {
             }
         }
 
+        for (serial, deleted, added) in journal.select_diffs()? {
+            self.insert_diff(serial, deleted, added);
+        }
+        self.refreshed = journal
+            .select_refresh()?
+            .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp));
+        for serial in self.prune_diffs() {
+            journal.delete_diff(serial)?;
+        }
+
         Ok(())
     }
 
     /// Persist the state of the current zone to the journal, does nothing if there is no associated
     ///  Journal.
     ///
+    /// The journal is replaced by the current state, see `Journal::insert_zone`.
+    ///
     /// Returns an error if there was an issue writing to the persistence layer.
     pub fn persist_to_journal(&self) -> PersistenceResult<()> {
-        if let Some(journal) = self.journal.as_ref() {
+        if let Some(mut journal) = self.journal() {
             let serial = self.serial();
 
             info!("persisting zone to journal at SOA.serial: {}", serial);
 
-            // TODO: THIS NEEDS TO BE IN A TRANSACTION!!!
-            journal.insert_record(serial, Record::new().set_rr_type(RecordType::AXFR))?;
-
-            for rr_set in self.records.values() {
-                // TODO: should we preserve rr_sets or not?
-                for record in rr_set.iter() {
-                    journal.insert_record(serial, record)?;
-                }
-            }
-
-            // TODO: COMMIT THE TRANSACTION!!!
+            // the SOA is written first, so that records are recovered at its serial
+            let records = self.soa()
+                .iter()
+                .chain(
+                    self.records
+                        .values()
+                        .filter(|rr_set| rr_set.record_type() != RecordType::SOA)
+                        .flat_map(|rr_set| rr_set.iter()),
+                )
+                .cloned()
+                .collect::<Vec<Record>>();
+
+            journal.insert_zone(serial, &records)?;
         }
 
         Ok(())
@@ -158,19 +355,143 @@ impl Authority {
 
     /// Associate a backing Journal with this Authority for Updatable zones
     pub fn set_journal(&mut self, journal: Journal) {
-        self.journal = Some(journal);
+        self.journal = Some(Mutex::new(journal));
     }
 
-    /// Returns the associated Journal
-    pub fn journal(&self) -> Option<&Journal> {
-        self.journal.as_ref()
+    /// Returns the associated Journal, it is locked until the guard is dropped
+    pub fn journal(&self) -> Option<MutexGuard<Journal>> {
+        self.journal
+            .as_ref()
+            .map(|journal| journal.lock().expect("journal lock poisoned"))
     }
 
+    /// Removes the associated Journal, it is closed once dropped, e.g. before the zone is loaded
+    ///  again from it
+    pub fn take_journal(&mut self) -> Option<Journal> {
+        self.journal
+            .take()
+            .map(|journal| journal.into_inner().expect("journal lock poisoned"))
+    }
+
+
     /// Enables the zone for dynamic DNS updates
     pub fn set_allow_update(&mut self, allow_update: bool) {
         self.allow_update = allow_update;
     }
 
+    /// Suspends dynamic updates to the zone, updates are refused until the zone is thawed
+    pub fn set_frozen(&mut self, is_frozen: bool) {
+        self.is_frozen = is_frozen;
+    }
+
+    /// Returns true if dynamic updates to the zone are suspended, see `set_frozen()`
+    pub fn is_frozen(&self) -> bool {
+        self.is_frozen
+    }
+
+    /// Writes the records of the zone in the master file format, e.g. to sync the journal of a
+    ///  dynamic zone to its zone file
+    ///
+    /// The records which are generated when the zone is signed, i.e. DNSKEY, NSEC, NSEC3, and
+    ///  RRSIG, are left out, they are generated again when the zone is loaded.
+    pub fn emit_zone_file<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let is_dnssec_enabled = self.is_dnssec_enabled;
+        let records = self.records
+            .values()
+            .filter(|rr_set| match rr_set.record_type() {
+                RecordType::DNSSEC(DNSSECRecordType::DNSKEY)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC3)
+                | RecordType::DNSSEC(DNSSECRecordType::RRSIG) => !is_dnssec_enabled,
+                _ => true,
+            })
+            .flat_map(|rr_set| rr_set.iter());
+
+        Emitter::new().emit(writer, records)
+    }
+
+    /// Replaces all records in the zone with those received in a zone transfer, i.e. AXFR.
+    ///
+    /// The zone is persisted to the journal, if one is associated, and is no longer expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - the full set of records of the zone, including the SOA
+    pub fn transfer(&mut self, records: Vec<Record>) -> PersistenceResult<()> {
+        let serial = records
+            .iter()
+            .filter_map(|record| {
+                if let RData::SOA(ref soa) = *record.rdata() {
+                    Some(soa.serial())
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or(0);
+
+        info!("transferring zone: {} at SOA.serial: {}", self.origin, serial);
+        self.records.clear();
+        // the differences to earlier versions are unknown
+        self.diffs.clear();
+        self.diff_serials.clear();
+        for record in records {
+            if record.dns_class() != self.class {
+                warn!("skipping record of wrong class in transfer: {:?}", record);
+                continue;
+            }
+            // a master must not be able to add records to other zones
+            if !self.origin.zone_of(&LowerName::new(record.name())) {
+                warn!("skipping record outside of the zone in transfer: {:?}", record);
+                continue;
+            }
+
+            self.upsert(record, serial);
+        }
+
+        self.is_expired = false;
+        self.persist_to_journal()
+    }
+
+    /// Marks the zone as expired, an expired zone must no longer be answered authoritatively
+    ///
+    /// [RFC 1035](https://tools.ietf.org/html/rfc1035), DOMAIN NAMES - IMPLEMENTATION AND SPECIFICATION, November 1987
+    ///
+    /// ```text
+    /// EXPIRE          A 32 bit time value that specifies the upper limit on
+    ///                 the time interval that can elapse before the zone is no
+    ///                 longer authoritative.
+    /// ```
+    pub fn set_expired(&mut self, is_expired: bool) {
+        self.is_expired = is_expired;
+    }
+
+    /// Returns true if this zone has expired, see `set_expired()`
+    pub fn is_expired(&self) -> bool {
+        self.is_expired
+    }
+
+    /// Records the time the zone was last refreshed from its masters, persisted to the journal if
+    ///  one is associated, so that the zone still expires in time after a restart
+    pub fn set_refreshed(&mut self, refreshed: SystemTime) -> PersistenceResult<()> {
+        self.refreshed = Some(refreshed);
+
+        if let Some(mut journal) = self.journal() {
+            let timestamp = refreshed
+                .duration_since(UNIX_EPOCH)
+                .map(|timestamp| timestamp.as_secs())
+                .unwrap_or(0);
+            journal.insert_refresh(timestamp)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the time the zone was last refreshed from its masters, see `set_refreshed()`
+    pub fn refreshed(&self) -> Option<SystemTime> {
+        self.refreshed
+    }
+
     /// Retrieve the Signer, which contains the private keys, for this zone
     pub fn secure_keys(&self) -> &[Signer] {
         &self.secure_keys
@@ -263,6 +584,13 @@ impl Authority {
         });
 
         if let Some((soa, serial)) = opt_soa_serial {
+            // the new SOA is journaled, so that the serial is recovered
+            if let Some(journal) = self.journal() {
+                if let Err(error) = journal.insert_record(serial, &soa) {
+                    error!("could not persist soa serial increment: {}", error);
+                }
+            }
+
             self.upsert(soa, serial);
             serial
         } else {
@@ -469,11 +797,29 @@ impl Authority {
     ///   requestor.
     /// ```
     ///
+    /// Updates from clients with a certificate over mutual TLS are authorized by
+    ///  `authorize_signer`, with the identity of the client.
     #[cfg(feature = "dnssec")]
     pub fn authorize(&self, update_message: &MessageRequest) -> UpdateResult<()> {
-        use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType};
-        use trust_dns_proto::rr::dnssec::Verifier;
+        let signer = self.verify_signer(update_message)?;
+        self.authorize_signer(signer.as_ref(), None)
+    }
 
+    /// Checks the permission to update the zone, as `authorize`, for an update whose signature was
+    ///  already verified
+    ///
+    /// # Arguments
+    ///
+    /// * `signer` - the name of the TSIG key or of the SIG(0) signer of the update, if it verified
+    ///              with one of the keys of this zone, see `verify_tsig` and `verify_sig0`
+    /// * `client_identity` - the identity of the client, from its certificate over mutual TLS,
+    ///                       which authorizes the update if it is one of `update_certificates`
+    #[cfg(feature = "dnssec")]
+    pub fn authorize_signer(
+        &self,
+        signer: Option<&Name>,
+        client_identity: Option<&ClientIdentity>,
+    ) -> UpdateResult<()> {
         // 3.3.3 - Pseudocode for Permission Checking
         //
         //      if (security policy exists)
@@ -492,62 +838,107 @@ impl Authority {
             return Err(ResponseCode::Refused);
         }
 
-        // verify sig0, currently the only authorization that is accepted.
-        let sig0s: &[Record] = update_message.sig0();
-        debug!("authorizing with: {:?}", sig0s);
-        if !sig0s.is_empty()
-            && sig0s
-                .iter()
-                .filter_map(|sig0| {
-                    if let RData::DNSSEC(DNSSECRData::SIG(ref sig)) = *sig0.rdata() {
-                        Some(sig)
-                    } else {
-                        None
-                    }
-                })
-                .any(|sig| {
-                    let name = sig.signer_name();
-                    let keys = self.lookup(
-                        &name.into(),
-                        RecordType::DNSSEC(DNSSECRecordType::KEY),
-                        false,
-                        SupportedAlgorithms::new(),
-                    );
-                    debug!("found keys {:?}", keys);
-                    // FIXME: check key usage flags and restrictions
-                    keys.iter()
-                        .filter_map(|rr_set| {
-                            if let RData::DNSSEC(DNSSECRData::KEY(ref key)) = *rr_set.rdata() {
-                                Some(key)
-                            } else {
-                                None
-                            }
-                        })
-                        .any(|key| {
-                            key.verify_message(update_message, sig.sig(), sig)
-                                .map(|_| {
-                                    info!("verified sig: {:?} with key: {:?}", sig, key);
-                                    true
-                                })
-                                .unwrap_or_else(|_| {
-                                    debug!("did not verify sig: {:?} with key: {:?}", sig, key);
-                                    false
-                                })
-                        })
-                }) {
+        if self.is_frozen {
+            warn!("update attempted on frozen Authority: {}", self.origin);
+            return Err(ResponseCode::Refused);
+        }
+
+        // a TSIG with one of the shared secrets of the zone, or a SIG(0) with one of its KEY
+        //  records, authorizes the update
+        if let Some(signer) = signer {
+            info!("update authorized by key: {}", signer);
             return Ok(());
-        } else {
-            warn!(
-                "no sig0 matched registered records: id {}",
-                update_message.id()
-            );
         }
 
+        // a client certificate of the zone authorizes the update
+        if let Some(client_identity) = client_identity {
+            if let Some(name) = self.update_certificates
+                .iter()
+                .find(|name| client_identity.is(name))
+            {
+                info!("update authorized by client certificate: {}", name);
+                return Ok(());
+            }
+        }
+
+        warn!("update not signed with a key of: {}", self.origin);
+
         // getting here, we will always default to rejecting the request
         //  the code will only ever explcitly return authrorized actions.
         Err(ResponseCode::Refused)
     }
 
+    // the name of the key with which the update is signed, with TSIG or SIG(0), if it verified
+    #[cfg(feature = "dnssec")]
+    fn verify_signer(&self, update_message: &MessageRequest) -> UpdateResult<Option<Name>> {
+        match self.verify_tsig(update_message) {
+            Some(Ok((tsigner, _))) => Ok(Some(tsigner.signer_name().clone())),
+            // the TSIG error itself is returned by the Catalog, see `Catalog::update`
+            Some(Err(..)) => Err(ResponseCode::NotAuth),
+            None => Ok(self.verify_sig0(update_message)),
+        }
+    }
+
+    /// Verifies the SIG(0) of the request against the KEY records of the zone
+    ///
+    /// # Return
+    ///
+    /// The name of the signer, if any SIG(0) of the request verified with one of its keys
+    #[cfg(feature = "dnssec")]
+    pub fn verify_sig0(&self, request: &MessageRequest) -> Option<Name> {
+        use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType};
+        use trust_dns_proto::rr::dnssec::Verifier;
+
+        let sig0s: &[Record] = request.sig0();
+        debug!("authorizing with: {:?}", sig0s);
+        sig0s
+            .iter()
+            .filter_map(|sig0| {
+                if let RData::DNSSEC(DNSSECRData::SIG(ref sig)) = *sig0.rdata() {
+                    Some(sig)
+                } else {
+                    None
+                }
+            })
+            .find(|sig| {
+                let name = sig.signer_name();
+                let keys = self.lookup(
+                    &name.into(),
+                    RecordType::DNSSEC(DNSSECRecordType::KEY),
+                    false,
+                    SupportedAlgorithms::new(),
+                );
+                debug!("found keys {:?}", keys);
+                // FIXME: check key usage flags and restrictions
+                keys.iter()
+                    .filter_map(|rr_set| {
+                        if let RData::DNSSEC(DNSSECRData::KEY(ref key)) = *rr_set.rdata() {
+                            Some(key)
+                        } else {
+                            None
+                        }
+                    })
+                    .any(|key| {
+                        key.verify_message(request, sig.sig(), sig)
+                            .map(|_| {
+                                info!("verified sig: {:?} with key: {:?}", sig, key);
+                                true
+                            })
+                            .unwrap_or_else(|_| {
+                                debug!("did not verify sig: {:?} with key: {:?}", sig, key);
+                                false
+                            })
+                    })
+            })
+            .map(|sig| sig.signer_name().clone())
+    }
+
+    /// SIG(0) is not verified without DNSSEC
+    #[cfg(not(feature = "dnssec"))]
+    pub fn verify_sig0(&self, _request: &MessageRequest) -> Option<Name> {
+        None
+    }
+
     /// [RFC 2136](https://tools.ietf.org/html/rfc2136), DNS Update, April 1997
     ///
     /// ```text
@@ -671,9 +1062,22 @@ impl Authority {
         let mut updated = false;
         let serial: u32 = self.serial();
 
+        // the rrsets before the update, for the difference sequence of incremental transfers. A
+        //  signed zone is signed again, with a new NSEC or NSEC3 chain, all of it is compared.
+        let before = if auto_signing_and_increment {
+            let rr_keys = if self.is_dnssec_enabled {
+                self.records.keys().cloned().collect()
+            } else {
+                self.update_keys(records)
+            };
+            Some((self.rr_sets(&rr_keys), rr_keys))
+        } else {
+            None
+        };
+
         // the persistence act as a write-ahead log. The WAL will also be used for recovery of a zone
         //  subsequent to a failure of the server.
-        if let Some(ref journal) = self.journal {
+        if let Some(journal) = self.journal() {
             if let Err(error) = journal.insert_records(serial, records) {
                 error!("could not persist update records: {}", error);
                 return Err(ResponseCode::ServFail);
@@ -819,11 +1223,102 @@ impl Authority {
                 //  dnssec, then we need to do it here...
                 self.increment_soa_serial();
             }
+
+            if let Some((before, mut rr_keys)) = before {
+                if self.is_dnssec_enabled {
+                    rr_keys.extend(self.records.keys().cloned());
+                }
+                let after = self.rr_sets(&rr_keys);
+                self.add_diff(&before, &after);
+            }
         }
 
         Ok(updated)
     }
 
+    /// The keys of the rrsets which may be changed by the update records, and of the SOA
+    fn update_keys(&self, records: &[Record]) -> BTreeSet<RrKey> {
+        let mut rr_keys = BTreeSet::new();
+        rr_keys.insert(RrKey::new(self.origin.clone(), RecordType::SOA));
+
+        for rr in records {
+            if rr.dns_class() == DNSClass::ANY && rr.rr_type() == RecordType::ANY {
+                let name = LowerName::new(rr.name());
+                rr_keys.extend(self.records.keys().filter(|k| k.name == name).cloned());
+            } else {
+                rr_keys.insert(RrKey::new(rr.name().into(), rr.rr_type()));
+            }
+        }
+
+        rr_keys
+    }
+
+    /// The records of the rrsets, with their signatures
+    fn rr_sets(&self, rr_keys: &BTreeSet<RrKey>) -> BTreeSet<Record> {
+        rr_keys
+            .iter()
+            .filter_map(|rr_key| self.records.get(rr_key))
+            .flat_map(|rr_set| rr_set.iter().chain(rr_set.rrsigs()))
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps the difference sequence of an update, in memory and in the journal
+    fn add_diff(&mut self, before: &BTreeSet<Record>, after: &BTreeSet<Record>) {
+        let is_soa = |record: &&Record| record.rr_type() == RecordType::SOA;
+        let old_soa = before.iter().find(|r| is_soa(r));
+        let new_soa = after.iter().find(|r| is_soa(r));
+        let (old_soa, new_soa) = match (old_soa, new_soa) {
+            (Some(old_soa), Some(new_soa)) => (old_soa.clone(), new_soa.clone()),
+            _ => return,
+        };
+        let serial = match *old_soa.rdata() {
+            RData::SOA(ref soa) => soa.serial(),
+            _ => return,
+        };
+
+        let mut deleted = vec![old_soa];
+        deleted.extend(before.difference(after).filter(|r| !is_soa(r)).cloned());
+        let mut added = vec![new_soa];
+        added.extend(after.difference(before).filter(|r| !is_soa(r)).cloned());
+
+        if let Some(mut journal) = self.journal() {
+            if let Err(error) = journal.insert_diff(serial, &deleted, &added) {
+                error!("could not persist diff of update: {}", error);
+            }
+        }
+
+        self.insert_diff(serial, deleted, added);
+        for serial in self.prune_diffs() {
+            if let Some(journal) = self.journal() {
+                if let Err(error) = journal.delete_diff(serial) {
+                    error!("could not remove diff from journal: {}", error);
+                }
+            }
+        }
+    }
+
+    fn insert_diff(&mut self, serial: u32, deleted: Vec<Record>, added: Vec<Record>) {
+        // a serial could be reused, only the latest difference from it is kept
+        if self.diffs.insert(serial, (deleted, added)).is_some() {
+            self.diff_serials.retain(|s| *s != serial);
+        }
+        self.diff_serials.push_back(serial);
+    }
+
+    /// Discards the oldest differences beyond the depth, returns their serials
+    fn prune_diffs(&mut self) -> Vec<u32> {
+        let mut pruned = Vec::new();
+        while self.diff_serials.len() > self.ixfr_depth {
+            if let Some(serial) = self.diff_serials.pop_front() {
+                self.diffs.remove(&serial);
+                pruned.push(serial);
+            }
+        }
+
+        pruned
+    }
+
     /// Inserts or updates a `Record` depending on it's existence in the authority.
     ///
     /// Guarantees that SOA, CNAME only has one record, will implicitly update if they already exist.
@@ -906,8 +1401,32 @@ impl Authority {
     ///  returned in the case of bad data, etc.
     #[cfg(feature = "dnssec")]
     pub fn update(&mut self, update: &MessageRequest) -> UpdateResult<bool> {
+        let signer = self.verify_signer(update)?;
+        self.update_signed(update, signer.as_ref(), None)
+    }
+
+    /// Always fail when DNSSEC is disabled.
+    #[cfg(not(feature = "dnssec"))]
+    pub fn update(&mut self, _update: &MessageRequest) -> UpdateResult<bool> {
+        Err(ResponseCode::NotImp)
+    }
+
+    /// Updates the zone, as `update`, with the signature of the update already verified
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - the update, see `update`
+    /// * `signer` - the key with which the update is signed, see `authorize_signer`
+    /// * `client_identity` - The identity of the client over mutual TLS, see `authorize_signer`
+    #[cfg(feature = "dnssec")]
+    pub fn update_signed(
+        &mut self,
+        update: &MessageRequest,
+        signer: Option<&Name>,
+        client_identity: Option<&ClientIdentity>,
+    ) -> UpdateResult<bool> {
         // the spec says to authorize after prereqs, seems better to auth first.
-        self.authorize(update)?;
+        self.authorize_signer(signer, client_identity)?;
         self.verify_prerequisites(update.prerequisites())?;
         self.pre_scan(update.updates())?;
 
@@ -916,7 +1435,12 @@ impl Authority {
 
     /// Always fail when DNSSEC is disabled.
     #[cfg(not(feature = "dnssec"))]
-    pub fn update(&mut self, _update: &MessageRequest) -> UpdateResult<bool> {
+    pub fn update_signed(
+        &mut self,
+        _update: &MessageRequest,
+        _signer: Option<&Name>,
+        _client_identity: Option<&ClientIdentity>,
+    ) -> UpdateResult<bool> {
         Err(ResponseCode::NotImp)
     }
 
@@ -938,7 +1462,13 @@ impl Authority {
         supported_algorithms: SupportedAlgorithms,
     ) -> AuthLookup<'s> {
         let lookup_name = query.name();
-        let record_type: RecordType = query.query_type();
+
+        // an IXFR which can not be answered incrementally, see `ixfr()`, is answered with the full
+        //  zone, in the same format as an AXFR
+        let record_type: RecordType = match query.query_type() {
+            RecordType::IXFR => RecordType::AXFR,
+            record_type => record_type,
+        };
 
         // if this is an AXFR zone transfer, verify that this is either the slave or master
         //  for AXFR the first and last record must be the SOA
@@ -980,47 +1510,355 @@ impl Authority {
             }
         }
 
-        query_result
+        // the name exists, but without the type, it might be an alias
+        match query_result {
+            AuthLookup::NameExists if record_type != RecordType::CNAME => {
+                self.chase_cname(lookup_name, record_type, is_secure, supported_algorithms)
+            }
+            query_result => query_result,
+        }
     }
 
-    /// Looks up all Resource Records matching the giving `Name` and `RecordType`.
+    /// Returns the zone cut at or above the name, if it is in a child zone delegated from this one
     ///
-    /// # Arguments
+    /// [RFC 1034](https://tools.ietf.org/html/rfc1034#section-4.2.1), Domain Concepts and Facilities, November 1987
     ///
-    /// * `name` - The `Name`, label, to lookup.
-    /// * `rtype` - The `RecordType`, to lookup. `RecordType::ANY` will return all records matching
-    ///             `name`. `RecordType::AXFR` will return all record types except `RecordType::SOA`
-    ///             due to the requirements that on zone transfers the `RecordType::SOA` must both
-    ///             preceed and follow all other records.
-    /// * `is_secure` - If the DO bit is set on the EDNS OPT record, then return RRSIGs as well.
+    /// ```text
+    /// 4.2.1. Technical considerations
+    ///
+    ///    The RRs that describe cuts around the bottom of the zone are NS RRs
+    ///    that name the servers for the subzones.  Since the cuts are between
+    ///    nodes, these RRs are NOT part of the authoritative data of the zone,
+    ///    and should be exactly the same as the corresponding RRs in the top
+    ///    node of the subzone.
+    /// ```
     ///
     /// # Return value
     ///
-    /// None if there are no matching records, otherwise a `Vec` containing the found records.
-    pub fn lookup<'s>(
-        &'s self,
-        name: &LowerName,
-        rtype: RecordType,
-        is_secure: bool,
-        supported_algorithms: SupportedAlgorithms,
-    ) -> AuthLookup<'s> {
-        let rr_key = RrKey::new(name.clone(), rtype);
+    /// The highest name between the apex and the name, inclusive of the name, with NS records.
+    pub fn delegation(&self, name: &LowerName) -> Option<LowerName> {
+        if !self.origin.zone_of(name) {
+            return None;
+        }
 
-        // Collect the records from each rr_set
-        let result: Vec<&Record> = match rtype {
-            RecordType::ANY | RecordType::AXFR => self.records
-                .values()
-                .filter(|rr_set| {
-                    rtype == RecordType::ANY || rr_set.record_type() != RecordType::SOA
-                })
-                .filter(|rr_set| rtype == RecordType::AXFR || &LowerName::new(rr_set.name()) == name)
-                .fold(Vec::<&Record>::new(), |mut vec, rr_set| {
-                    vec.append(&mut rr_set.records(is_secure, supported_algorithms));
-                    vec
-                }),
-            _ => self.records.get(&rr_key).map_or(vec![], |rr_set| {
-                rr_set
-                    .records(is_secure, supported_algorithms)
+        let mut cut = None;
+        let mut name = name.clone();
+        while name != self.origin && !name.is_root() {
+            if self.records
+                .contains_key(&RrKey::new(name.clone(), RecordType::NS))
+            {
+                cut = Some(name.clone());
+            }
+            name = name.base_name();
+        }
+
+        cut
+    }
+
+    /// Returns true if the RRSet is authoritative data in this zone
+    ///
+    /// At a zone cut only the DS and NSEC RRSets belong to this zone, the NS RRSet belongs to the
+    ///  child, and anything below the cut is glue.
+    #[cfg(feature = "dnssec")]
+    fn is_authoritative(&self, key: &RrKey) -> bool {
+        use trust_dns::rr::rdata::DNSSECRecordType;
+
+        match self.delegation(&key.name) {
+            None => true,
+            Some(ref cut) if *cut == key.name => match key.record_type {
+                RecordType::DNSSEC(DNSSECRecordType::DS)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC) => true,
+                _ => false,
+            },
+            Some(_) => false,
+        }
+    }
+
+    /// Returns the records of a referral to the child zone at the cut, for the authority section
+    ///
+    /// [RFC 4035](https://tools.ietf.org/html/rfc4035#section-3.1.4), DNSSEC Protocol Modifications, March 2005
+    ///
+    /// ```text
+    /// 3.1.4.  Including DS RRs in a Response
+    ///
+    ///    When returning a referral, if the DS RRset exists at the delegation
+    ///    point, the name server MUST return both the DS RRset and its
+    ///    associated RRSIG RR(s) in the Authority section along with the NS
+    ///    RRset.
+    ///
+    ///    If no DS RRset exists at the delegation point, the name server MUST
+    ///    return the NSEC RR that proves that the DS RRset does not exist,
+    ///    along with the NSEC RR's associated RRSIG RR(s).
+    /// ```
+    ///
+    /// The glue for the referral is found with `additionals`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cut` - the zone cut, see `delegation`
+    /// * `is_secure` - if true then the DS records, or the NSEC proving there are none, are included
+    pub fn referral<'s>(
+        &'s self,
+        cut: &LowerName,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> Vec<&'s Record> {
+        use trust_dns::rr::rdata::DNSSECRecordType;
+
+        let mut referral = match self.lookup(cut, RecordType::NS, false, supported_algorithms) {
+            AuthLookup::Records(ns) => ns,
+            AuthLookup::NameExists | AuthLookup::NoName => vec![],
+        };
+
+        if is_secure {
+            match self.lookup(
+                cut,
+                RecordType::DNSSEC(DNSSECRecordType::DS),
+                is_secure,
+                supported_algorithms,
+            ) {
+                AuthLookup::Records(mut ds) => referral.append(&mut ds),
+                AuthLookup::NameExists | AuthLookup::NoName => referral.append(
+                    &mut self.get_nsec_records(cut, is_secure, supported_algorithms),
+                ),
+            }
+        }
+
+        referral
+    }
+
+    /// Follows the chain of CNAMEs from the name within the zone, to the records of the type
+    ///
+    /// [RFC 1034](https://tools.ietf.org/html/rfc1034#section-4.3.2), Domain Concepts and Facilities, November 1987
+    ///
+    /// ```text
+    /// 4.3.2. Algorithm
+    ///
+    ///    3. Start matching down, label by label, in the zone.  The
+    ///       matching process can terminate several ways:
+    ///
+    ///          a. If the whole of QNAME is matched, we have found the
+    ///             node.
+    ///
+    ///             If the data at the node is a CNAME, and QTYPE doesn't
+    ///             match CNAME, copy the CNAME RR into the answer section
+    ///             of the response, change QNAME to the canonical name in
+    ///             the CNAME RR, and go back to step 1.
+    /// ```
+    ///
+    /// The chain ends at the first target outside of the zone, or in a child zone, which the client
+    ///  must resolve.
+    ///
+    /// # Return value
+    ///
+    /// `NameExists` if there is no CNAME at the name, otherwise the CNAMEs of the chain followed by
+    ///  any records of the type at the end of it.
+    fn chase_cname<'s>(
+        &'s self,
+        name: &LowerName,
+        rtype: RecordType,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> AuthLookup<'s> {
+        let mut chain: Vec<&Record> = vec![];
+        let mut name = name.clone();
+
+        for _ in 0..MAX_CNAME_CHAIN {
+            let cnames = match self.lookup(&name, RecordType::CNAME, is_secure, supported_algorithms) {
+                AuthLookup::Records(cnames) => cnames,
+                AuthLookup::NameExists | AuthLookup::NoName => break,
+            };
+
+            let target = cnames
+                .iter()
+                .filter_map(|record| match *record.rdata() {
+                    RData::CNAME(ref target) => Some(LowerName::new(target)),
+                    _ => None,
+                })
+                .next();
+            chain.extend(cnames);
+
+            name = match target {
+                Some(ref target)
+                    if self.origin.zone_of(target) && self.delegation(target).is_none() =>
+                {
+                    target.clone()
+                }
+                _ => break,
+            };
+
+            if let AuthLookup::Records(records) =
+                self.lookup(&name, rtype, is_secure, supported_algorithms)
+            {
+                chain.extend(records);
+                break;
+            }
+        }
+
+        if chain.is_empty() {
+            AuthLookup::NameExists
+        } else {
+            AuthLookup::Records(chain)
+        }
+    }
+
+    /// Returns the address records for the targets of the records, to go in the additional section
+    ///
+    /// [RFC 1034](https://tools.ietf.org/html/rfc1034#section-3.6.2), Domain Concepts and Facilities, November 1987
+    ///
+    /// ```text
+    ///    Additional section processing ... when a name server or resolver
+    ///    returns an MX RR, the name server includes the A RRs for the exchange
+    ///    host in the additional section of the response, as well as the NS RRs
+    ///    in the authority section
+    /// ```
+    ///
+    /// The targets of MX, NS, and SRV records which are in this zone are looked up for A and AAAA
+    ///  records, those which are already in `records` are not repeated.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - the answers and name servers of the response
+    /// * `is_secure` - if true then it will return RRSIG records as well
+    pub fn additionals<'s>(
+        &'s self,
+        records: &[&Record],
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> Vec<&'s Record> {
+        let mut additionals: Vec<&Record> = vec![];
+
+        let targets = records.iter().filter_map(|record| match *record.rdata() {
+            RData::MX(ref mx) => Some(mx.exchange()),
+            RData::NS(ref ns) => Some(ns),
+            RData::SRV(ref srv) => Some(srv.target()),
+            _ => None,
+        });
+
+        for target in targets {
+            let target = LowerName::new(target);
+            if !self.origin.zone_of(&target) {
+                continue;
+            }
+
+            for rtype in &[RecordType::A, RecordType::AAAA] {
+                if let AuthLookup::Records(addresses) =
+                    self.lookup(&target, *rtype, is_secure, supported_algorithms)
+                {
+                    for record in addresses {
+                        if !records.contains(&record) && !additionals.contains(&record) {
+                            additionals.push(record);
+                        }
+                    }
+                }
+            }
+        }
+
+        additionals
+    }
+
+    /// Returns the changes to the zone since `serial` as an incremental zone transfer
+    ///
+    /// The difference sequences of the updates since `serial` are returned in order, they are
+    ///  kept for the last updates, see `set_ixfr_depth`. For a signed zone they include the
+    ///  signatures and the NSEC or NSEC3 records which changed when the zone was signed again.
+    ///  `None` is returned if the changes since `serial` are no longer known, in that case the
+    ///  full zone should be transferred.
+    ///
+    /// [RFC 1995](https://tools.ietf.org/html/rfc1995), Incremental Zone Transfer in DNS, August 1996
+    ///
+    /// ```text
+    /// 4. Response Format
+    ///
+    ///    If incremental zone transfer is available, one or more difference
+    ///    sequences is returned.  The list of difference sequences is preceded
+    ///    and followed by a copy of the server's current version of the SOA.
+    ///
+    ///    Each difference sequence represents one update to the zone (one SOA
+    ///    serial change) consisting of deleted RRs and added RRs.  The first
+    ///    RR of the deleted RRs is the older SOA RR and the first RR of the
+    ///    added RRs is the newer SOA RR.
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `serial` - the serial of the SOA sent by the requester in the authority section
+    pub fn ixfr(&self, serial: u32) -> Option<Vec<Record>> {
+        let soa: Record = match self.soa().iter().next() {
+            Some(soa) => soa.clone(),
+            None => return None,
+        };
+
+        // RFC 1995, 2. the same or newer version is answered with only the current SOA
+        let current_serial = self.serial();
+        if !serial_gt(current_serial, serial) {
+            return Some(vec![soa]);
+        }
+
+        let mut ixfr = vec![soa.clone()];
+        let mut next_serial = serial;
+        // each serial is followed once, a reused serial could otherwise loop
+        for _ in 0..self.diffs.len() {
+            let (deleted, added) = match self.diffs.get(&next_serial) {
+                Some(&(ref deleted, ref added)) => (deleted, added),
+                None => break,
+            };
+
+            ixfr.extend(deleted.iter().cloned());
+            ixfr.extend(added.iter().cloned());
+
+            next_serial = match added.first().map(Record::rdata) {
+                Some(&RData::SOA(ref soa)) => soa.serial(),
+                _ => break,
+            };
+            if next_serial == current_serial {
+                ixfr.push(soa);
+                return Some(ixfr);
+            }
+        }
+
+        debug!("no history for zone: {} from serial: {}", self.origin, serial);
+        None
+    }
+
+    /// Looks up all Resource Records matching the giving `Name` and `RecordType`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The `Name`, label, to lookup.
+    /// * `rtype` - The `RecordType`, to lookup. `RecordType::ANY` will return all records matching
+    ///             `name`. `RecordType::AXFR` will return all record types except `RecordType::SOA`
+    ///             due to the requirements that on zone transfers the `RecordType::SOA` must both
+    ///             preceed and follow all other records.
+    /// * `is_secure` - If the DO bit is set on the EDNS OPT record, then return RRSIGs as well.
+    ///
+    /// # Return value
+    ///
+    /// None if there are no matching records, otherwise a `Vec` containing the found records.
+    pub fn lookup<'s>(
+        &'s self,
+        name: &LowerName,
+        rtype: RecordType,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> AuthLookup<'s> {
+        let rr_key = RrKey::new(name.clone(), rtype);
+
+        // Collect the records from each rr_set
+        let result: Vec<&Record> = match rtype {
+            RecordType::ANY | RecordType::AXFR => self.records
+                .values()
+                .filter(|rr_set| {
+                    rtype == RecordType::ANY || rr_set.record_type() != RecordType::SOA
+                })
+                .filter(|rr_set| rtype == RecordType::AXFR || &LowerName::new(rr_set.name()) == name)
+                .fold(Vec::<&Record>::new(), |mut vec, rr_set| {
+                    vec.append(&mut rr_set.records(is_secure, supported_algorithms));
+                    vec
+                }),
+            _ => self.records.get(&rr_key).map_or(vec![], |rr_set| {
+                rr_set
+                    .records(is_secure, supported_algorithms)
                     .into_iter()
                     .collect()
             }),
@@ -1031,7 +1869,10 @@ impl Authority {
         //   always return empty sets. This is only important in the negative case, where other DNS authorities
         //   generally return NoError and no results when other types exist at the same name. bah.
         if result.is_empty() {
-            if self.records.keys().any(|key| key.name() == name) {
+            if self
+                .first_key_from(name)
+                .map_or(false, |key| key.name() == name)
+            {
                 return AuthLookup::NameExists;
             } else {
                 return AuthLookup::NoName;
@@ -1041,19 +1882,167 @@ impl Authority {
         AuthLookup::Records(result)
     }
 
+    /// Returns true if there are records at the name, or at names below it
+    ///
+    /// A name with no records of its own but with records below it is an empty non-terminal, which
+    ///  still exists, see RFC 4592 section 2.2.2.
+    fn name_exists(&self, name: &LowerName) -> bool {
+        self.first_key_from(name)
+            .map_or(false, |key| name.zone_of(&key.name))
+    }
+
+    /// Returns the first key of the name, or of the names after it
+    ///
+    /// In canonical order the names below a name directly follow it, so if there are any records
+    ///  at or below the name the first of them is found here.
+    fn first_key_from(&self, name: &LowerName) -> Option<&RrKey> {
+        self.records
+            .range(RrKey::new(name.clone(), RecordType::ZERO)..)
+            .next()
+            .map(|(key, _)| key)
+    }
+
+    /// Returns the closest encloser of a name in the zone, i.e. its longest existing ancestor
+    ///
+    /// [RFC 4592](https://tools.ietf.org/html/rfc4592#section-3.3.1), Wildcards in the DNS, July 2006
+    ///
+    /// ```text
+    /// 3.3.1.  Closest Encloser and the Source of Synthesis
+    ///
+    ///    The closest encloser is the node in the zone's tree of existing
+    ///    domain names that has the most labels matching the query name
+    ///    (consecutively, counting from the root label downward).  Each match
+    ///    is a "label match" and the order of the labels is the same.
+    ///
+    ///    The closest encloser is, by definition, an existing name in the zone.
+    ///    The closest encloser might be an empty non-terminal or even be a
+    ///    wildcard domain name itself.  In no circumstances is the closest
+    ///    encloser to be used to synthesize records for the current query.
+    ///
+    ///    The source of synthesis is defined in the context of a query process
+    ///    as that wildcard domain name immediately descending from the closest
+    ///    encloser, provided that this wildcard domain name exists.
+    /// ```
+    pub fn closest_encloser(&self, name: &LowerName) -> LowerName {
+        let mut encloser = name.clone();
+        while !encloser.is_root() && encloser != self.origin && !self.name_exists(&encloser) {
+            encloser = encloser.base_name();
+        }
+
+        encloser
+    }
+
+    /// Returns the wildcard at the closest encloser of the name, which may not exist
+    fn wildcard_of(&self, name: &LowerName) -> LowerName {
+        let encloser: Name = self.closest_encloser(name).into();
+        Name::from_ascii("*")
+            .expect("* is a valid name")
+            .append_domain(&encloser)
+            .into()
+    }
+
+    /// Returns the source of synthesis for the name, if it does not exist in the zone and there is
+    ///  a wildcard at its closest encloser
+    pub fn wildcard_source(&self, name: &LowerName) -> Option<LowerName> {
+        if !self.origin.zone_of(name) || self.name_exists(name) {
+            return None;
+        }
+
+        let wildcard = self.wildcard_of(name);
+        if self.name_exists(&wildcard) {
+            Some(wildcard)
+        } else {
+            None
+        }
+    }
+
+    /// Synthesizes the records for a name which does not exist in the zone from the matching
+    ///  wildcard.
+    ///
+    /// [RFC 4592](https://tools.ietf.org/html/rfc4592#section-3.3.1), Wildcards in the DNS, July 2006
+    ///
+    /// ```text
+    /// 2.1.1.  Wildcard Domain Name and Asterisk Label
+    ///
+    ///    A "wildcard domain name" is defined by having its initial (i.e.,
+    ///    leftmost or least significant) label be, in binary format:
+    ///
+    ///         0000 0001 0010 1010 (binary) = 0x01 0x2a (hexadecimal)
+    ///
+    /// 3.3.2.  Responding to a Query
+    ///
+    ///    If the source of synthesis has RRs of the query type, the RRs are
+    ///    returned with their owner name changed to the query name.
+    /// ```
+    ///
+    /// The RRSIGs of the wildcard are returned as they are, other than the owner name. Their label
+    ///  count is that of the wildcard, without the `*`, which tells validators that the answer was
+    ///  synthesized, RFC 4035 section 5.3.4.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the query name
+    /// * `rtype` - the query type
+    /// * `is_secure` - if true then it will return RRSIG records as well
+    ///
+    /// # Return value
+    ///
+    /// `None` if the name exists, or there is no matching wildcard. Otherwise the synthesized
+    ///  records, which are empty if the wildcard does not have records of the type.
+    pub fn expand_wildcard(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> Option<Vec<Record>> {
+        let wildcard = match self.wildcard_source(name) {
+            Some(wildcard) => wildcard,
+            None => return None,
+        };
+
+        let owner: Name = name.clone().into();
+        let lookup = match self.lookup(&wildcard, rtype, is_secure, supported_algorithms) {
+            AuthLookup::NameExists if rtype != RecordType::CNAME => {
+                self.lookup(&wildcard, RecordType::CNAME, is_secure, supported_algorithms)
+            }
+            lookup => lookup,
+        };
+        let records = lookup
+            .iter()
+            .map(|record| {
+                let mut record = record.clone();
+                record.set_name(owner.clone());
+                record
+            })
+            .collect();
+
+        debug!("synthesized: {} from wildcard: {}", name, wildcard);
+        Some(records)
+    }
+
     /// Return the NSEC records based on the given name
     ///
     /// # Arguments
     ///
     /// * `name` - given this name (i.e. the lookup name), return the NSEC record that is less than
-    ///            this
+    ///            or equal to this, i.e. which covers the name
     /// * `is_secure` - if true then it will return RRSIG records as well
+    ///
+    /// For a zone with an NSEC3 chain, the NSEC3 records of `get_nsec3_records` are returned.
     pub fn get_nsec_records(
         &self,
         name: &LowerName,
         is_secure: bool,
         supported_algorithms: SupportedAlgorithms,
     ) -> Vec<&Record> {
+        #[cfg(feature = "dnssec")]
+        {
+            if self.nsec3.is_some() {
+                return self.get_nsec3_records(name, is_secure, supported_algorithms);
+            }
+        }
+
         #[cfg(feature = "dnssec")]
         fn is_nsec_rrset(rr_set: &RecordSet) -> bool {
             use trust_dns::rr::rdata::DNSSECRecordType;
@@ -1071,8 +2060,8 @@ impl Authority {
         self.records
             .values()
             .filter(|rr_set| is_nsec_rrset(rr_set))
-            .skip_while(|rr_set| name < &rr_set.name().into())
-            .next()
+            .take_while(|rr_set| &LowerName::new(rr_set.name()) <= name)
+            .last()
             .map_or(vec![], |rr_set| {
                 rr_set
                     .records(is_secure, supported_algorithms)
@@ -1081,12 +2070,159 @@ impl Authority {
             })
     }
 
+    /// Return the NSEC3 records which prove the types at the name, or that it does not exist
+    ///
+    /// [RFC 5155](https://tools.ietf.org/html/rfc5155#section-7.2.1), NSEC3, March 2008
+    ///
+    /// ```text
+    /// 7.2.1.  Closest Encloser Proof
+    ///
+    ///    For many NSEC3 responses a proof of the closest encloser is required.
+    ///    This is essentially a proof that no longer name exists.
+    ///
+    ///    Because the NSEC3 hashes are not ordered in the same way as the
+    ///    original names, a NSEC3 RR that covers the closest encloser does not
+    ///    indicate that there are no longer names.
+    ///
+    ///    To prove that the closest encloser is the closest encloser, the
+    ///    response MUST contain the NSEC3 RR that matches the closest encloser
+    ///    and the NSEC3 RR that covers the "next closer" name.
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the lookup name
+    /// * `is_secure` - if true then it will return RRSIG records as well
+    ///
+    /// # Return value
+    ///
+    /// The NSEC3 record matching the name if there is one, otherwise the closest encloser proof of
+    ///  the name. With opt-out, that is of the closest provable encloser, which has an NSEC3 record.
+    #[cfg(feature = "dnssec")]
+    pub fn get_nsec3_records(
+        &self,
+        name: &LowerName,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> Vec<&Record> {
+        let params = match self.nsec3 {
+            Some(ref params) => params,
+            None => return vec![],
+        };
+
+        if let Some(rr_set) = self.nsec3_matching(params, name) {
+            return rr_set.records(is_secure, supported_algorithms);
+        }
+
+        let mut encloser = self.closest_encloser(name);
+        let mut matching = self.nsec3_matching(params, &encloser);
+        while matching.is_none() && encloser != self.origin && !encloser.is_root() {
+            encloser = encloser.base_name();
+            matching = self.nsec3_matching(params, &encloser);
+        }
+
+        let mut records = matching.map_or(vec![], |rr_set| {
+            rr_set.records(is_secure, supported_algorithms)
+        });
+
+        let next_closer = Name::from(name.clone()).trim_to(encloser.num_labels() as usize + 1);
+        if let Some(rr_set) = self.nsec3_covering(params, &next_closer.into()) {
+            for record in rr_set.records(is_secure, supported_algorithms) {
+                if !records.contains(&record) {
+                    records.push(record);
+                }
+            }
+        }
+
+        records
+    }
+
+    /// Returns the NSEC3 RRSet of the name, if it is in the chain
+    #[cfg(feature = "dnssec")]
+    fn nsec3_matching(&self, params: &NSEC3PARAM, name: &LowerName) -> Option<&RecordSet> {
+        use trust_dns::rr::rdata::DNSSECRecordType;
+
+        let owner = match params.hash_algorithm().hashed_owner_name(
+            params.salt(),
+            &name.clone().into(),
+            params.iterations(),
+            &self.origin.clone().into(),
+        ) {
+            Ok(owner) => owner,
+            Err(e) => {
+                warn!("could not hash name: {}: {}", name, e);
+                return None;
+            }
+        };
+
+        self.records.get(&RrKey::new(
+            owner.into(),
+            RecordType::DNSSEC(DNSSECRecordType::NSEC3),
+        ))
+    }
+
+    /// Returns the NSEC3 RRSet which covers the hash of the name, i.e. the one with the greatest
+    ///  hash which is less than that of the name, or the last in the chain if there is none
+    #[cfg(feature = "dnssec")]
+    fn nsec3_covering(&self, params: &NSEC3PARAM, name: &LowerName) -> Option<&RecordSet> {
+        use trust_dns::rr::rdata::DNSSECRecordType;
+
+        let owner: LowerName = match params.hash_algorithm().hashed_owner_name(
+            params.salt(),
+            &name.clone().into(),
+            params.iterations(),
+            &self.origin.clone().into(),
+        ) {
+            Ok(owner) => owner.into(),
+            Err(e) => {
+                warn!("could not hash name: {}: {}", name, e);
+                return None;
+            }
+        };
+
+        // the hashed owners are all a single label in the zone, so the canonical order of the
+        //  names is that of the base32hex encoded hashes, which is the order of the hashes
+        let covering = self.nsec3_owners
+            .range(..owner)
+            .next_back()
+            .or_else(|| self.nsec3_owners.iter().next_back());
+
+        covering.and_then(|covering| {
+            self.records.get(&RrKey::new(
+                covering.clone(),
+                RecordType::DNSSEC(DNSSECRecordType::NSEC3),
+            ))
+        })
+    }
+
+    /// Return the NSEC records for the wildcard at the closest encloser of the name
+    ///
+    /// For a name which does not exist this proves that there is no wildcard which matches it, and
+    ///  for a wildcard without records of the query type that it does not have the type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the lookup name
+    /// * `is_secure` - if true then it will return RRSIG records as well
+    pub fn get_wildcard_nsec_records(
+        &self,
+        name: &LowerName,
+        is_secure: bool,
+        supported_algorithms: SupportedAlgorithms,
+    ) -> Vec<&Record> {
+        self.get_nsec_records(&self.wildcard_of(name), is_secure, supported_algorithms)
+    }
+
     /// (Re)generates the nsec records, increments the serial number nad signs the zone
     #[cfg(feature = "dnssec")]
     pub fn secure_zone(&mut self) -> DnsSecResult<()> {
         // TODO: only call nsec_zone after adds/deletes
         // needs to be called before incrementing the soa serial, to make sur IXFR works properly
-        self.nsec_zone();
+        if self.nsec3.is_some() {
+            self.nsec3_zone()?;
+        } else {
+            self.nsec_zone();
+        }
 
         // need to resign any records at the current serial number and bump the number.
         // first bump the serial number on the SOA, so that it is resigned with the new serial.
@@ -1113,11 +2249,14 @@ impl Authority {
         }
         debug!("generating nsec records: {}", self.origin);
 
-        // first remove all existing nsec records
+        // first remove all existing nsec records, and any NSEC3 chain
         let delete_keys: Vec<RrKey> = self.records
             .keys()
-            .filter(|k| {
-                k.record_type == RecordType::DNSSEC(DNSSECRecordType::NSEC)
+            .filter(|k| match k.record_type {
+                RecordType::DNSSEC(DNSSECRecordType::NSEC)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC3)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC3PARAM) => true,
+                _ => false,
             })
             .cloned()
             .collect();
@@ -1125,6 +2264,7 @@ impl Authority {
         for key in delete_keys {
             self.records.remove(&key);
         }
+        self.nsec3_owners.clear();
 
         // now go through and generate the nsec records
         let ttl = self.minimum_ttl();
@@ -1133,7 +2273,11 @@ impl Authority {
 
         {
             let mut nsec_info: Option<(&Name, Vec<RecordType>)> = None;
-            for key in self.records.keys() {
+            // names below a zone cut belong to the child zone
+            for key in self.records
+                .keys()
+                .filter(|key| self.delegation(&key.name).map_or(true, |cut| cut == key.name))
+            {
                 match nsec_info {
                     None => nsec_info = Some((key.name.borrow(), vec![key.record_type])),
                     Some((name, ref mut vec)) if &LowerName::new(name) == &key.name => vec.push(key.record_type),
@@ -1174,13 +2318,168 @@ impl Authority {
         }
     }
 
+    /// (Re)generates the NSEC3 chain, and NSEC3PARAM, of the zone from the parameters of `set_nsec3`
+    ///
+    /// [RFC 5155](https://tools.ietf.org/html/rfc5155#section-7.1), NSEC3, March 2008
+    ///
+    /// ```text
+    /// 7.1.  Zone Signing
+    ///
+    ///    o  Each owner name within the zone that owns authoritative RRSets
+    ///       MUST have a corresponding NSEC3 RR.  Owner names that correspond
+    ///       to unsigned delegations MAY have a corresponding NSEC3 RR.
+    ///       However, if there is not a corresponding NSEC3 RR, there MUST be
+    ///       an Opt-Out NSEC3 RR that covers the "next closer" name to the
+    ///       delegation.
+    ///
+    ///    o  Each empty non-terminal MUST have a corresponding NSEC3 RR, unless
+    ///       the empty non-terminal is only derived from an insecure delegation
+    ///       covered by an Opt-Out NSEC3 RR.
+    /// ```
+    #[cfg(feature = "dnssec")]
+    fn nsec3_zone(&mut self) -> DnsSecResult<()> {
+        use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType, NSEC3};
+
+        let params = match self.nsec3 {
+            Some(ref params) => params.clone(),
+            None => return Ok(()),
+        };
+
+        // only create nsec3 records for secure zones
+        if self.secure_keys.is_empty() {
+            return Ok(());
+        }
+        debug!("generating nsec3 records: {}", self.origin);
+
+        // first remove the existing chain, NSEC or NSEC3
+        let delete_keys: Vec<RrKey> = self.records
+            .keys()
+            .filter(|k| match k.record_type {
+                RecordType::DNSSEC(DNSSECRecordType::NSEC)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC3)
+                | RecordType::DNSSEC(DNSSECRecordType::NSEC3PARAM) => true,
+                _ => false,
+            })
+            .cloned()
+            .collect();
+
+        for key in delete_keys {
+            self.records.remove(&key);
+        }
+        self.nsec3_owners.clear();
+
+        let ttl = self.minimum_ttl();
+        let serial = self.serial();
+
+        // the flags of the NSEC3PARAM are always zero, opt-out is only set on the NSEC3 records
+        let nsec3param = Record::from_rdata(
+            self.origin.clone().into(),
+            ttl,
+            RecordType::DNSSEC(DNSSECRecordType::NSEC3PARAM),
+            RData::DNSSEC(DNSSECRData::NSEC3PARAM(NSEC3PARAM::new(
+                params.hash_algorithm(),
+                false,
+                params.iterations(),
+                params.salt().to_vec(),
+            ))),
+        );
+        self.upsert(nsec3param, serial);
+
+        let ds = RecordType::DNSSEC(DNSSECRecordType::DS);
+        let mut names: BTreeMap<LowerName, Vec<RecordType>> = BTreeMap::new();
+        for key in self.records.keys() {
+            match self.delegation(&key.name) {
+                // names below a zone cut belong to the child zone
+                Some(ref cut) if *cut != key.name => continue,
+                _ => (),
+            }
+
+            names
+                .entry(key.name.clone())
+                .or_insert_with(Vec::new)
+                .push(key.record_type);
+        }
+
+        // with opt-out, the delegations to unsigned child zones are not in the chain
+        if params.opt_out() {
+            names = names
+                .into_iter()
+                .filter(|&(ref name, ref types)| {
+                    self.delegation(name).is_none() || types.contains(&ds)
+                })
+                .collect();
+        }
+
+        // all but the NS records at an unsigned zone cut are signed
+        for (name, types) in names.iter_mut() {
+            if self.delegation(name).is_none() || types.contains(&ds) {
+                types.push(RecordType::DNSSEC(DNSSECRecordType::RRSIG));
+            }
+        }
+
+        // the empty non-terminals
+        let owners: Vec<LowerName> = names.keys().cloned().collect();
+        for owner in owners {
+            let mut name = owner.base_name();
+            while name != self.origin && self.origin.zone_of(&name) {
+                names.entry(name.clone()).or_insert_with(Vec::new);
+                name = name.base_name();
+            }
+        }
+
+        // the chain is in the order of the hashes
+        let mut chain: BTreeMap<Vec<u8>, (Name, Vec<RecordType>)> = BTreeMap::new();
+        for (name, types) in names {
+            let name: Name = name.into();
+            let hash = params
+                .hash_algorithm()
+                .hash(params.salt(), &name, params.iterations())?;
+            let owner = params.hash_algorithm().hashed_owner_name(
+                params.salt(),
+                &name,
+                params.iterations(),
+                &self.origin.clone().into(),
+            )?;
+
+            chain.insert(hash.as_ref().to_vec(), (owner, types));
+        }
+
+        let hashes: Vec<Vec<u8>> = chain.keys().cloned().collect();
+        let mut records: Vec<Record> = Vec::with_capacity(hashes.len());
+        for (i, (_, (owner, types))) in chain.into_iter().enumerate() {
+            // the last in the chain refers back to the first
+            let next = hashes[(i + 1) % hashes.len()].clone();
+            let rdata = NSEC3::new(
+                params.hash_algorithm(),
+                params.opt_out(),
+                params.iterations(),
+                params.salt().to_vec(),
+                next,
+                types,
+            );
+
+            records.push(Record::from_rdata(
+                owner,
+                ttl,
+                RecordType::DNSSEC(DNSSECRecordType::NSEC3),
+                RData::DNSSEC(DNSSECRData::NSEC3(rdata)),
+            ));
+        }
+
+        // insert all the nsec3 records
+        for record in records {
+            self.nsec3_owners.insert(LowerName::new(record.name()));
+            self.upsert(record, serial);
+        }
+
+        Ok(())
+    }
+
     /// Signs any records in the zone that have serial numbers greater than or equal to `serial`
+    ///
+    /// The variants of the records for some networks are signed as well, see `add_subnet`.
     #[cfg(feature = "dnssec")]
     fn sign_zone(&mut self) -> DnsSecResult<()> {
-        use chrono::Utc;
-        use trust_dns::rr::dnssec::tbs;
-        use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType, SIG};
-
         debug!("signing zone: {}", self.origin);
         let inception = Utc::now();
         let zone_ttl = self.minimum_ttl();
@@ -1190,89 +2489,122 @@ impl Authority {
             warn!("attempt to sign_zone for dnssec, but no keys available!")
         }
 
+        // the NS records at zone cuts and glue are not signed, RFC 4035 section 2.2
+        let unsigned: BTreeSet<RrKey> = self.records
+            .keys()
+            .filter(|key| !self.is_authoritative(key))
+            .cloned()
+            .collect();
+
         // sign all record_sets, as of 0.12.1 this includes DNSKEY
         for rr_set in self.records.values_mut() {
             rr_set.clear_rrsigs();
-            let rrsig_temp = Record::with(
-                rr_set.name().clone(),
-                RecordType::DNSSEC(DNSSECRecordType::RRSIG),
-                zone_ttl,
-            );
-
-            for signer in &self.secure_keys {
-                debug!(
-                    "signing rr_set: {}, {} with: {}",
-                    rr_set.name(),
-                    rr_set.record_type(),
-                    signer.algorithm(),
-                );
+            if unsigned.contains(&RrKey::new(rr_set.name().into(), rr_set.record_type())) {
+                continue;
+            }
 
-                let expiration = inception + signer.sig_duration();
-
-                let tbs = tbs::rrset_tbs(
-                    rr_set.name(),
-                    self.class,
-                    rr_set.name().num_labels(),
-                    rr_set.record_type(),
-                    signer.algorithm(),
-                    rr_set.ttl(),
-                    expiration.timestamp() as u32,
-                    inception.timestamp() as u32,
-                    signer.calculate_key_tag()?,
-                    signer.signer_name(),
-                    // TODO: this is a nasty clone... the issue is that the vec
-                    //  from records is of Vec<&R>, but we really want &[R]
-                    &rr_set
-                        .records_without_rrsigs()
-                        .into_iter()
-                        .cloned()
-                        .collect::<Vec<Record>>(),
-                );
+            sign_rrset(rr_set, &self.secure_keys, self.class, zone_ttl, inception)?;
+        }
 
-                // TODO, maybe chain these with some ETL operations instead?
-                let tbs = match tbs {
-                    Ok(tbs) => tbs,
-                    Err(err) => {
-                        error!("could not serialize rrset to sign: {}", err);
-                        continue;
-                    }
-                };
-
-                let signature = signer.sign(&tbs);
-                let signature = match signature {
-                    Ok(signature) => signature,
-                    Err(err) => {
-                        error!("could not sign rrset: {}", err);
-                        continue;
-                    }
-                };
-
-                let mut rrsig = rrsig_temp.clone();
-                rrsig.set_rdata(RData::DNSSEC(DNSSECRData::SIG(SIG::new(
-                    // type_covered: RecordType,
-                    rr_set.record_type(),
-                    // algorithm: Algorithm,
-                    signer.algorithm(),
-                    // num_labels: u8,
-                    rr_set.name().num_labels(),
-                    // original_ttl: u32,
-                    rr_set.ttl(),
-                    // sig_expiration: u32,
-                    expiration.timestamp() as u32,
-                    // sig_inception: u32,
-                    inception.timestamp() as u32,
-                    // key_tag: u16,
-                    signer.calculate_key_tag()?,
-                    // signer_name: Name,
-                    signer.signer_name().clone(),
-                    // sig: Vec<u8>
-                    signature,
-                ))));
-
-                rr_set.insert_rrsig(rrsig);
-            }
+        for rr_set in self.subnets.record_sets_mut() {
+            rr_set.clear_rrsigs();
+            sign_rrset(rr_set, &self.secure_keys, self.class, zone_ttl, inception)?;
         }
 
         Ok(())
     }
 }
+
+/// Signs the record set with each of the keys, the signatures are valid from the inception
+#[cfg(feature = "dnssec")]
+fn sign_rrset(
+    rr_set: &mut RecordSet,
+    secure_keys: &[Signer],
+    class: DNSClass,
+    zone_ttl: u32,
+    inception: DateTime<Utc>,
+) -> DnsSecResult<()> {
+    use trust_dns::rr::dnssec::tbs;
+    use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType, SIG};
+
+    let rrsig_temp = Record::with(
+        rr_set.name().clone(),
+        RecordType::DNSSEC(DNSSECRecordType::RRSIG),
+        zone_ttl,
+    );
+
+    for signer in secure_keys {
+        debug!(
+            "signing rr_set: {}, {} with: {}",
+            rr_set.name(),
+            rr_set.record_type(),
+            signer.algorithm(),
+        );
+
+        let expiration = inception + signer.sig_duration();
+
+        let tbs = tbs::rrset_tbs(
+            rr_set.name(),
+            class,
+            rr_set.name().num_labels(),
+            rr_set.record_type(),
+            signer.algorithm(),
+            rr_set.ttl(),
+            expiration.timestamp() as u32,
+            inception.timestamp() as u32,
+            signer.calculate_key_tag()?,
+            signer.signer_name(),
+            // TODO: this is a nasty clone... the issue is that the vec
+            //  from records is of Vec<&R>, but we really want &[R]
+            &rr_set
+                .records_without_rrsigs()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<Record>>(),
+        );
+
+        // TODO, maybe chain these with some ETL operations instead?
+        let tbs = match tbs {
+            Ok(tbs) => tbs,
+            Err(err) => {
+                error!("could not serialize rrset to sign: {}", err);
+                continue;
+            }
+        };
+
+        let signature = signer.sign(&tbs);
+        let signature = match signature {
+            Ok(signature) => signature,
+            Err(err) => {
+                error!("could not sign rrset: {}", err);
+                continue;
+            }
+        };
+
+        let mut rrsig = rrsig_temp.clone();
+        rrsig.set_rdata(RData::DNSSEC(DNSSECRData::SIG(SIG::new(
+            // type_covered: RecordType,
+            rr_set.record_type(),
+            // algorithm: Algorithm,
+            signer.algorithm(),
+            // num_labels: u8,
+            rr_set.name().num_labels(),
+            // original_ttl: u32,
+            rr_set.ttl(),
+            // sig_expiration: u32,
+            expiration.timestamp() as u32,
+            // sig_inception: u32,
+            inception.timestamp() as u32,
+            // key_tag: u16,
+            signer.calculate_key_tag()?,
+            // signer_name: Name,
+            signer.signer_name().clone(),
+            // sig: Vec<u8>
+            signature,
+        ))));
+
+        rr_set.insert_rrsig(rrsig);
+    }
+
+    Ok(())
+}
@@ -18,19 +18,47 @@
 //  then, if requested, do a recursive lookup... i.e. the catalog would only point to files.
 use std::collections::HashMap;
 use std::io;
-use std::sync::RwLock;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
 
-use trust_dns::op::{Edns, Header, MessageType, OpCode, LowerQuery, ResponseCode};
-use trust_dns::rr::{LowerName, RecordType};
-use trust_dns::rr::dnssec::{Algorithm, SupportedAlgorithms};
-use trust_dns::rr::rdata::opt::{EdnsCode, EdnsOption};
-use server::{Request, RequestHandler, ResponseHandler};
+use futures::Future;
+use futures::sync::mpsc::UnboundedSender;
+use tokio_core::reactor::{Handle, Remote};
 
-use authority::{AuthLookup, Authority, MessageRequest, MessageResponse, ZoneType};
+use trust_dns::op::{Edns, Header, LowerQuery, Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns::rr::{DNSClass, LowerName, Name, RData, Record, RecordType};
+use trust_dns::rr::dnssec::{Algorithm, SupportedAlgorithms, TSigner};
+use trust_dns::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use trust_dns::rr::rdata::{DNSSECRecordType, TXT};
+use trust_dns_proto::op::EncodableMessage;
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use server::{send_fitted, ClientIdentity, Request, RequestContext, RequestHandler,
+             ResponseHandler};
+
+use authority::{Acl, AuthLookup, Authority, Forwarder, Identity, MessageRequest,
+                MessageResponse, Recursor, Refresh, View, ZoneAcls, ZoneType};
+use metrics;
 
 /// Set of authorities, zones, available to this server.
 pub struct Catalog {
-    authorities: HashMap<LowerName, RwLock<Authority>>,
+    authorities: HashMap<LowerName, Arc<RwLock<Authority>>>,
+    // Slave zones, refreshed on NOTIFY from one of the masters
+    refreshes: HashMap<LowerName, (Vec<IpAddr>, UnboundedSender<Refresh>)>,
+    // Master zones, the slaves are sent a NOTIFY after updates
+    notifies: HashMap<LowerName, UnboundedSender<()>>,
+    // Resolves names outside of all the zones, when recursion is desired
+    recursor: Option<Recursor>,
+    // Forward zones, all the names in the zone are resolved by upstream servers
+    forwarders: HashMap<LowerName, Forwarder>,
+    // Clients whose queries are resolved recursively or forwarded
+    recursion_acl: Acl,
+    // Clients allowed to query, transfer and update each zone, and names outside of the zones
+    acls: HashMap<LowerName, ZoneAcls>,
+    default_acls: ZoneAcls,
+    // Views, with their own zones, checked in order before the zones of this catalog
+    views: Vec<View>,
+    // the NSID and the CHAOS class answers of the server
+    identity: Option<Arc<Identity>>,
 }
 
 fn send_response<R: ResponseHandler + 'static>(
@@ -38,6 +66,126 @@ fn send_response<R: ResponseHandler + 'static>(
     mut response: MessageResponse,
     response_handle: R,
 ) -> io::Result<()> {
+    set_response_edns(&mut response, response_edns);
+    response_handle.send(response)
+}
+
+// requests signed with TSIG get responses signed with the same key, covering the request MAC. The
+//  responses are fit into the payload size of the client, and padded over encrypted connections,
+//  before they are signed, so the TSIG covers what is sent, and fits with it.
+struct SignedResponseHandle<R: ResponseHandler> {
+    response_handle: R,
+    tsig: Option<(TSigner, Vec<u8>)>,
+    max_payload: Option<u16>,
+    padding: bool,
+}
+
+impl<R: ResponseHandler> SignedResponseHandle<R> {
+    fn new(
+        response_handle: R,
+        signature: &Signature,
+        request: &MessageRequest,
+        context: &RequestContext,
+    ) -> Self {
+        // RFC 8467, the responses to clients which pad their queries are padded
+        let padding = context.is_encrypted()
+            && request
+                .edns()
+                .map_or(false, |edns| edns.option(&EdnsCode::Padding).is_some());
+
+        SignedResponseHandle {
+            response_handle,
+            tsig: signature.tsig.clone(),
+            max_payload: context.max_payload(request),
+            padding,
+        }
+    }
+}
+
+impl<R: ResponseHandler> ResponseHandler for SignedResponseHandle<R> {
+    fn send<M: EncodableMessage>(self, response: M) -> io::Result<()> {
+        let tsig = self
+            .tsig
+            .as_ref()
+            .map(|&(ref tsigner, ref request_mac)| (tsigner, &request_mac[..]));
+        send_fitted(
+            self.response_handle,
+            &response,
+            self.max_payload,
+            tsig,
+            self.padding,
+        )
+    }
+}
+
+// clients which are not allowed recursion are refused, rather than told the name does not exist
+fn send_recursion_refused<R: ResponseHandler + 'static>(
+    request: &MessageRequest,
+    response_edns: Option<Edns>,
+    response_handle: R,
+) -> io::Result<()> {
+    warn!("request: {} refused recursion", request.id());
+    let response = MessageResponse::new(Some(request.raw_queries()));
+    send_response(
+        response_edns,
+        response.error_msg(request.id(), request.op_code(), ResponseCode::Refused),
+        response_handle,
+    )
+}
+
+// RFC 8945, requests whose TSIG did not verify are answered with NOTAUTH and the TSIG error, only
+//  BADTIME is signed, with the key of the request
+fn send_tsig_error<R: ResponseHandler + 'static>(
+    request: &MessageRequest,
+    tsigner: Option<&TSigner>,
+    error: ResponseCode,
+    response_edns: Option<Edns>,
+    response_handle: R,
+) -> io::Result<()> {
+    let mut response = MessageResponse::new(Some(request.raw_queries())).error_msg(
+        request.id(),
+        request.op_code(),
+        ResponseCode::NotAuth,
+    );
+    set_response_edns(&mut response, response_edns);
+
+    if let Some(request_tsig) = request.sig0().last() {
+        if let Err(e) = response.tsig_error(request_tsig, error, tsigner) {
+            warn!("could not add TSIG error to response: {}", e);
+        }
+    }
+
+    response_handle.send(response)
+}
+
+// BADCOOKIE and FORMERR of the options of the EDNS of the request, the high bits of extended
+//  errors are in the EDNS of the response, with the cookies of the server
+fn send_edns_error<R: ResponseHandler + 'static>(
+    request: &MessageRequest,
+    error: ResponseCode,
+    mut response_edns: Edns,
+    response_handle: R,
+) -> io::Result<()> {
+    let mut response = MessageResponse::new(Some(request.raw_queries()));
+    let mut response_header = Header::default();
+    response_header.set_id(request.id());
+    response_header.set_response_code(error);
+    response_edns.set_rcode_high(error.high() as u8);
+    response.edns(response_edns);
+
+    response_handle.send(response.build(response_header))
+}
+
+// the signature of a request, verified once with the keys of the zone of its first query
+#[derive(Default)]
+struct Signature {
+    // the name of the TSIG key or of the SIG(0) signer, matched by the access control lists
+    key: Option<Name>,
+    // the TSIG key and the MAC of the request, the response is signed with them
+    tsig: Option<(TSigner, Vec<u8>)>,
+}
+
+fn set_response_edns(response: &mut MessageResponse, response_edns: Option<Edns>) {
     if let Some(mut resp_edns) = response_edns {
         // set edns DAU and DHU
         // send along the algorithms which are supported by this authority
@@ -55,8 +203,73 @@ fn send_response<R: ResponseHandler + 'static>(
 
         response.set_edns(resp_edns);
     }
+}
 
-    response_handle.send(response)
+// the response is sent once the resolution of the query, recursive or forwarded, completes. The
+//  query is resolved on the reactor which received the request, or without one, e.g. from
+//  `lookup`, on the reactor of the resolver if it is the current one
+fn send_resolved<F, R>(
+    resolve: F,
+    handle: Option<&Handle>,
+    remote: &Remote,
+    request: &MessageRequest,
+    query: Query,
+    response_edns: Option<Edns>,
+    response_handle: R,
+) -> io::Result<()>
+where
+    F: FnOnce(&Handle) -> Box<Future<Item = Message, Error = ResolveError>>,
+    R: ResponseHandler + 'static,
+{
+    let id = request.id();
+    let recursion_desired = request.recursion_desired();
+
+    let respond = move |result: Result<Message, ResolveError>| {
+        let mut response = Message::new();
+        response
+            .set_id(id)
+            .set_message_type(MessageType::Response)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(recursion_desired)
+            .set_recursion_available(true);
+        response.add_query(query);
+        if let Some(edns) = response_edns {
+            response.set_edns(edns);
+        }
+
+        // the resolved response is relayed as it was received, only its EDNS is this server's;
+        //  AD was cleared by the Forwarder, unless its upstream servers are trusted, see
+        //  `Forwarder::set_authentic_data_trusted`, the Recursor does not validate, nor set it
+        match result {
+            Ok(mut resolved) => {
+                response
+                    .set_response_code(resolved.response_code())
+                    .set_authentic_data(resolved.authentic_data());
+                response.insert_answers(resolved.take_answers());
+                response.insert_name_servers(resolved.take_name_servers());
+                response.insert_additionals(resolved.take_additionals());
+            }
+            Err(error) => {
+                warn!("request: {} could not be resolved: {}", id, error);
+                response.set_response_code(ResponseCode::ServFail);
+            }
+        }
+
+        response_handle
+            .send(response)
+            .map_err(|error| warn!("request: {} response could not be sent: {}", id, error))
+    };
+
+    match handle.cloned().or_else(|| remote.handle()) {
+        Some(handle) => handle.spawn(resolve(&handle).then(respond)),
+        None => {
+            respond(Err(ResolveError::from(ResolveErrorKind::Message(
+                "no reactor to resolve the query on",
+            ))))
+            .ok();
+        }
+    }
+    Ok(())
 }
 
 impl RequestHandler for Catalog {
@@ -71,9 +284,315 @@ impl RequestHandler for Catalog {
         request: &'q Request,
         response_handle: R,
     ) -> io::Result<()> {
-        let request_message = &request.message;
-        trace!("request: {:?}", request_message);
+        self.handle_request_with_context(request, &RequestContext::new(), response_handle)
+    }
 
+    /// Determine's what needs to happen given the type of request, in the context in which it
+    ///  was received.
+    ///
+    /// Responses to requests received over UDP are fit into the payload size of the client, and
+    ///  then signed with the TSIG key of the request, if there is one, see `send_fitted`. Over
+    ///  TCP and TLS the idle timeout of the connection is advertised to clients which sent the
+    ///  edns-tcp-keepalive option, and over TLS and HTTPS the responses to clients which sent
+    ///  the Padding option are padded, see `RequestContext`. With
+    ///  views, the request is handled by the first which matches it, or refused, see `add_view`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the requested action to perform.
+    /// * `context` - how the request was received, e.g. the payload size of UDP responses
+    /// * `response_handle` - sink for the response message to be sent
+    fn handle_request_with_context<'q, 'a, R: ResponseHandler + 'static>(
+        &'a self,
+        request: &'q Request,
+        context: &RequestContext,
+        response_handle: R,
+    ) -> io::Result<()> {
+        trace!("request: {:?}", request.message);
+        if self.views.is_empty() {
+            return self.handle_request_with_zones(request, context, false, response_handle);
+        }
+
+        let signer = request_signer(&request.message);
+        match self
+            .views
+            .iter()
+            .find(|view| view.matches(request, signer, context))
+        {
+            Some(view) => {
+                debug!("request: {} view: {}", request.message.id(), view.name());
+                view.catalog()
+                    .handle_request_with_zones(request, context, true, response_handle)
+            }
+            None => {
+                warn!(
+                    "request: {} from: {} matches no view",
+                    request.message.id(),
+                    request.src
+                );
+                let response = MessageResponse::new(Some(request.message.raw_queries()));
+                response_handle.send(response.error_msg(
+                    request.message.id(),
+                    request.message.op_code(),
+                    ResponseCode::Refused,
+                ))
+            }
+        }
+    }
+}
+
+// the name of the key with which the request claims to be signed, the name of its TSIG or the
+//  signer of its SIG(0), before the signature is verified
+#[cfg(feature = "dnssec")]
+fn request_signer<'r>(request: &'r MessageRequest) -> Option<&'r Name> {
+    use trust_dns::rr::rdata::DNSSECRData;
+
+    request.sig0().last().map(|sig0| match *sig0.rdata() {
+        RData::DNSSEC(DNSSECRData::SIG(ref sig)) => sig.signer_name(),
+        _ => sig0.name(),
+    })
+}
+
+#[cfg(not(feature = "dnssec"))]
+fn request_signer<'r>(request: &'r MessageRequest) -> Option<&'r Name> {
+    request.sig0().last().map(Record::name)
+}
+
+// true if the request is a CHAOS class query, for the identity of the server
+fn is_chaos(request: &MessageRequest) -> bool {
+    request.queries().first().map_or(false, |query| {
+        query.original().query_class() == DNSClass::CH
+    })
+}
+
+impl Catalog {
+    /// Constructs a new Catalog
+    pub fn new() -> Self {
+        Catalog {
+            authorities: HashMap::new(),
+            refreshes: HashMap::new(),
+            notifies: HashMap::new(),
+            recursor: None,
+            forwarders: HashMap::new(),
+            recursion_acl: Acl::localhost(),
+            acls: HashMap::new(),
+            default_acls: ZoneAcls::default(),
+            views: Vec::new(),
+            identity: None,
+        }
+    }
+
+    /// Insert or update a zone authority
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    /// * `authority` - the zone data
+    pub fn upsert(&mut self, name: LowerName, authority: Authority) {
+        self.authorities.insert(name, Arc::new(RwLock::new(authority)));
+    }
+
+    /// Insert or update a zone authority which is shared, e.g. one loaded again in place of the
+    ///  authority of a previous Catalog, see `adopt`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    /// * `authority` - the zone data
+    pub fn upsert_shared(&mut self, name: LowerName, authority: Arc<RwLock<Authority>>) {
+        self.authorities.insert(name, authority);
+    }
+
+    /// Serves a zone of a previous Catalog, e.g. on reload of the configuration
+    ///
+    /// The authority is shared with the previous Catalog, with the refresh of a Slave zone and
+    ///  the NOTIFY of the slaves of a Master zone, which continue as if nothing had changed.
+    ///  Returns false if the previous Catalog has no such zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - the Catalog which served the zone
+    /// * `name` - zone name, e.g. example.com.
+    pub fn adopt(&mut self, previous: &Catalog, name: &LowerName) -> bool {
+        let authority = match previous.authorities.get(name) {
+            Some(authority) => authority.clone(),
+            None => return false,
+        };
+        self.authorities.insert(name.clone(), authority);
+
+        if let Some(refresh) = previous.refreshes.get(name) {
+            self.refreshes.insert(name.clone(), refresh.clone());
+        }
+        if let Some(notify) = previous.notifies.get(name) {
+            self.notifies.insert(name.clone(), notify.clone());
+        }
+
+        true
+    }
+
+    /// Returns the zone authority for the exact name, if it exists
+    ///
+    /// The authority is shared, this allows for it to be updated outside of request handling,
+    ///  e.g. by zone transfers for Slave zones.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    pub fn get(&self, name: &LowerName) -> Option<&Arc<RwLock<Authority>>> {
+        self.authorities.get(name)
+    }
+
+    /// Returns the names of all the zones, in order
+    pub fn zones(&self) -> Vec<&LowerName> {
+        let mut zones: Vec<&LowerName> = self.authorities.keys().collect();
+        zones.sort();
+        zones
+    }
+
+    /// Transfers a Slave zone from its masters, whatever their serial
+    ///
+    /// Returns false if the zone is not a Slave zone which is refreshed from masters.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    pub fn retransfer(&self, name: &LowerName) -> bool {
+        match self.refreshes.get(name) {
+            Some(&(_, ref refresh)) => refresh.unbounded_send(Refresh::Retransfer).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Removes all the records from the caches of recursive resolution and of the Forward zones,
+    ///  also of the views
+    pub fn flush_caches(&self) {
+        if let Some(ref recursor) = self.recursor {
+            recursor.flush();
+        }
+        for forwarder in self.forwarders.values() {
+            forwarder.flush();
+        }
+        for view in &self.views {
+            view.catalog().flush_caches();
+        }
+    }
+
+    /// Registers the refresh of a Slave zone, signaled on NOTIFY from one of the masters, or on
+    ///  `retransfer`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    /// * `masters` - the masters of the zone, NOTIFY from any other address is ignored
+    /// * `refresh` - signaled on NOTIFY and on retransfer, see `SlaveFuture`
+    pub fn set_refresh(
+        &mut self,
+        name: LowerName,
+        masters: &[SocketAddr],
+        refresh: UnboundedSender<Refresh>,
+    ) {
+        let masters = masters.iter().map(|master| master.ip()).collect();
+        self.refreshes.insert(name, (masters, refresh));
+    }
+
+    /// Registers the NOTIFY of the slaves of a Master zone, signaled after updates to the zone
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    /// * `notify` - signaled after each update which changed the zone, see `NotifyFuture`
+    pub fn set_notify(&mut self, name: LowerName, notify: UnboundedSender<()>) {
+        self.notifies.insert(name, notify);
+    }
+
+    /// Enables recursive resolution, for queries with RD set of names outside of all the zones
+    ///
+    /// Responses will have RA, recursion available, set.
+    pub fn set_recursor(&mut self, recursor: Recursor) {
+        self.recursor = Some(recursor);
+    }
+
+    /// Returns the Recursor, if recursive resolution is enabled, see `set_recursor`
+    pub fn recursor(&self) -> Option<&Recursor> {
+        self.recursor.as_ref()
+    }
+
+    /// Registers a Forward zone, the queries of names in the zone are sent to upstream servers
+    ///
+    /// Names in zones of this server which are below the Forward zone are not forwarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com., the root forwards all names outside of the zones
+    /// * `forwarder` - the resolver of the upstream servers
+    pub fn set_forwarder(&mut self, name: LowerName, forwarder: Forwarder) {
+        self.forwarders.insert(name, forwarder);
+    }
+
+    /// Returns the Forwarder of the Forward zone, if it exists, see `set_forwarder`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    pub fn forwarder(&self, name: &LowerName) -> Option<&Forwarder> {
+        self.forwarders.get(name)
+    }
+
+    /// Sets the clients whose queries are resolved recursively, or forwarded, by default only the
+    ///  clients on the loopback addresses
+    ///
+    /// Requests from other clients which would be resolved are refused.
+    pub fn set_recursion_acl(&mut self, acl: Acl) {
+        self.recursion_acl = acl;
+    }
+
+    /// Sets the access control lists of a zone, which replace the defaults for names in the zone
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - zone name, e.g. example.com.
+    /// * `acls` - the clients allowed to query, transfer and update the zone
+    pub fn set_acls(&mut self, name: LowerName, acls: ZoneAcls) {
+        self.acls.insert(name, acls);
+    }
+
+    /// Sets the access control lists of names without their own, see `set_acls`, by default all
+    ///  clients are allowed to query and update, and none to transfer, see `ZoneAcls`
+    pub fn set_default_acls(&mut self, acls: ZoneAcls) {
+        self.default_acls = acls;
+    }
+
+    /// Adds a view, split-horizon DNS, the requests which match it are handled with its zones
+    ///
+    /// Views are checked in the order they were added, the first which matches the request
+    ///  handles it, see `View::matches`. Requests which match none of the views are refused, once
+    ///  a view is added the zones of this Catalog are no longer served, a last view which matches
+    ///  all clients serves the zones of the others.
+    pub fn add_view(&mut self, view: View) {
+        self.views.push(view);
+    }
+
+    /// Returns the views, in order, see `add_view`
+    pub fn views(&self) -> &[View] {
+        &self.views
+    }
+
+    /// Sets the identity of the server, its NSID and its answers to CHAOS class TXT queries, see
+    ///  `Identity`. Each view needs it to be set on its own Catalog.
+    pub fn set_identity(&mut self, identity: Arc<Identity>) {
+        self.identity = Some(identity);
+    }
+
+    // handles the request with the zones of this catalog, in a view if it was selected by the key
+    //  with which the request claims to be signed, see `View::matches`, which must verify
+    fn handle_request_with_zones<R: ResponseHandler + 'static>(
+        &self,
+        request: &Request,
+        context: &RequestContext,
+        in_view: bool,
+        response_handle: R,
+    ) -> io::Result<()> {
+        let request_message = &request.message;
         let response_edns: Option<Edns>;
 
         // check if it's edns
@@ -88,7 +607,12 @@ impl RequestHandler for Catalog {
             // TODO: what version are we?
             let our_version = 0;
             resp_edns.set_dnssec_ok(true);
-            resp_edns.set_max_payload(req_edns.max_payload().max(512));
+            // the largest payload of the server over UDP, the signed responses are sent with it
+            resp_edns.set_max_payload(
+                context
+                    .max_udp_payload()
+                    .unwrap_or_else(|| req_edns.max_payload().max(512)),
+            );
             resp_edns.set_version(our_version);
 
             if req_edns.version() > our_version {
@@ -104,6 +628,20 @@ impl RequestHandler for Catalog {
                 return response_handle.send(response.build(response_header));
             }
 
+            // RFC 5001, the NSID is only sent to clients which ask for it
+            if let Some(nsid) = self.identity.as_ref().and_then(|identity| identity.nsid()) {
+                if req_edns.option(&EdnsCode::NSID).is_some() {
+                    resp_edns.set_option(EdnsOption::NSID(nsid.to_vec()));
+                }
+            }
+
+            // RFC 7828, the idle timeout is only sent to clients which sent the option
+            if let Some(timeout) = context.keepalive_timeout() {
+                if req_edns.option(&EdnsCode::Keepalive).is_some() {
+                    resp_edns.set_option(EdnsOption::Keepalive(Some(timeout)));
+                }
+            }
+
             response_edns = Some(resp_edns);
         } else {
             response_edns = None;
@@ -113,11 +651,94 @@ impl RequestHandler for Catalog {
             // TODO think about threading query lookups for multiple lookups, this could be a huge improvement
             //  especially for recursive lookups
             MessageType::Query => match request_message.op_code() {
-                OpCode::Query => {
-                    return self.lookup(request_message, response_edns, response_handle)
+                op_code @ OpCode::Query | op_code @ OpCode::Update => {
+                    // the signature is verified once, for the access control lists and the zone
+                    let signature = match self.verify(request_message) {
+                        Ok(signature) => signature,
+                        Err((error, tsigner)) => {
+                            if op_code == OpCode::Update {
+                                metrics::count_update(false);
+                            }
+                            return send_tsig_error(
+                                request_message,
+                                tsigner.as_ref(),
+                                error,
+                                response_edns,
+                                response_handle,
+                            );
+                        }
+                    };
+                    let response_handle = SignedResponseHandle::new(
+                        response_handle,
+                        &signature,
+                        request_message,
+                        context,
+                    );
+
+                    // RFC 7873, the cookies are answered once the signature is verified, and the
+                    //  errors of the options are signed as the other responses
+                    let response_edns = match self.answer_options(request, context, response_edns) {
+                        Ok(response_edns) => response_edns,
+                        Err((error, response_edns)) => {
+                            return send_edns_error(
+                                request_message,
+                                error,
+                                response_edns,
+                                response_handle,
+                            )
+                        }
+                    };
+
+                    // the key by which the view was selected must be the one which verified
+                    let is_signer =
+                        !in_view || signature.key.as_ref() == request_signer(request_message);
+                    if !is_signer
+                        || !self.is_allowed(
+                            request,
+                            signature.key.as_ref(),
+                            context.client_identity(),
+                        )
+                    {
+                        let response = MessageResponse::new(Some(request_message.raw_queries()));
+                        return send_response(
+                            response_edns,
+                            response.error_msg(
+                                request_message.id(),
+                                op_code,
+                                ResponseCode::Refused,
+                            ),
+                            response_handle,
+                        );
+                    }
+
+                    if op_code == OpCode::Update {
+                        return self.update_signed(
+                            request_message,
+                            &signature,
+                            context.client_identity(),
+                            response_edns,
+                            response_handle,
+                        );
+                    } else if is_chaos(request_message) {
+                        return self.chaos(request_message, response_edns, response_handle);
+                    } else {
+                        return self.lookup_signed(
+                            request_message,
+                            Some(request.src.ip()),
+                            &signature,
+                            context.handle(),
+                            response_edns,
+                            response_handle,
+                        );
+                    }
                 }
-                OpCode::Update => {
-                    return self.update(request_message, response_edns, response_handle)
+                OpCode::Notify => {
+                    return match self.answer_options(request, context, response_edns) {
+                        Ok(response_edns) => self.notify(request, response_edns, response_handle),
+                        Err((error, response_edns)) => {
+                            send_edns_error(request_message, error, response_edns, response_handle)
+                        }
+                    }
                 }
                 c @ _ => {
                     error!("unimplemented op_code: {:?}", c);
@@ -144,24 +765,175 @@ impl RequestHandler for Catalog {
             }
         };
     }
-}
 
-impl Catalog {
-    /// Constructs a new Catalog
-    pub fn new() -> Self {
-        Catalog {
-            authorities: HashMap::new(),
+    // RFC 7873 and RFC 7871, the cookie of the client is answered with the cookies of the server,
+    //  checked once, see `RequestContext::cookie`, and malformed cookies and client subnets are
+    //  rejected. The error is returned with the EDNS of its response, with the cookies.
+    fn answer_options(
+        &self,
+        request: &Request,
+        context: &RequestContext,
+        response_edns: Option<Edns>,
+    ) -> Result<Option<Edns>, (ResponseCode, Edns)> {
+        let (req_edns, mut resp_edns) = match (request.message.edns(), response_edns) {
+            (Some(req_edns), Some(resp_edns)) => (req_edns, resp_edns),
+            (_, response_edns) => return Ok(response_edns),
+        };
+
+        if let Some(server_cookies) = context.server_cookies() {
+            match req_edns.option(&EdnsCode::Cookie) {
+                Some(&EdnsOption::Cookie(ref cookie)) => {
+                    // by the listener which received the request, unless it was not received
+                    let checked = match context.cookie() {
+                        Some(checked) => checked.clone(),
+                        None => server_cookies.check(cookie, request.src.ip()),
+                    };
+                    resp_edns.set_option(EdnsOption::Cookie(checked.response()));
+
+                    if cookie.server().is_some() && !checked.is_valid() {
+                        warn!("bad server cookie from: {}", request.src);
+                        return Err((ResponseCode::BADCOOKIE, resp_edns));
+                    }
+                }
+                // a cookie of the wrong length
+                Some(_) => {
+                    warn!("malformed cookie from: {}", request.src);
+                    return Err((ResponseCode::FormErr, resp_edns));
+                }
+                None => (),
+            }
+        }
+
+        // RFC 7871, a malformed subnet is rejected, see `ClientSubnet::read`
+        if let Some(&EdnsOption::Unknown(..)) = req_edns.option(&EdnsCode::Subnet) {
+            warn!("malformed client subnet from: {}", request.src);
+            return Err((ResponseCode::FormErr, resp_edns));
         }
+
+        Ok(Some(resp_edns))
     }
 
-    /// Insert or update a zone authority
+    /// Answers CHAOS class TXT queries for the identity of the server, e.g. `hostname.bind`
+    ///
+    /// Other CHAOS class queries, and those for names which are not configured, are refused.
+    fn chaos<R: ResponseHandler + 'static>(
+        &self,
+        request: &MessageRequest,
+        response_edns: Option<Edns>,
+        response_handle: R,
+    ) -> io::Result<()> {
+        let query = &request.queries()[0];
+        let txt = match query.query_type() {
+            RecordType::TXT | RecordType::ANY => self
+                .identity
+                .as_ref()
+                .and_then(|identity| identity.chaos_txt(query.name())),
+            _ => None,
+        };
+        let txt = match txt {
+            Some(txt) => txt,
+            None => {
+                debug!("request: {} refused CHAOS: {}", request.id(), query.name());
+                let response = MessageResponse::new(Some(request.raw_queries()));
+                return send_response(
+                    response_edns,
+                    response.error_msg(request.id(), request.op_code(), ResponseCode::Refused),
+                    response_handle,
+                );
+            }
+        };
+
+        let mut record = Record::from_rdata(
+            query.original().name().clone(),
+            0,
+            RecordType::TXT,
+            RData::TXT(TXT::new(vec![txt.to_string()])),
+        );
+        record.set_dns_class(DNSClass::CH);
+
+        let mut response = MessageResponse::new(Some(request.raw_queries()));
+        let mut response_header = Header::new();
+        response_header.set_id(request.id());
+        response_header.set_op_code(OpCode::Query);
+        response_header.set_message_type(MessageType::Response);
+        response_header.set_response_code(ResponseCode::NoError);
+        response_header.set_authoritative(true);
+        response.answers(vec![&record]);
+
+        send_response(
+            response_edns,
+            response.build(response_header),
+            response_handle,
+        )
+    }
+
+    /// Handles a NOTIFY from a master, the Slave zone will be refreshed
+    ///
+    /// [RFC 1996](https://tools.ietf.org/html/rfc1996), DNS NOTIFY, August 1996
+    ///
+    /// ```text
+    /// 3.10. If a slave receives a NOTIFY request from a host that is not a
+    ///    known master for the zone containing the QNAME, it should ignore the
+    ///    request and produce an error message in its operations log.
+    ///
+    /// 3.11. The only defined NOTIFY event at this time is that the SOA RR
+    ///    has changed.  Upon completion of a NOTIFY transaction, the slave
+    ///    should behave as though the zone's REFRESH timer has expired.
+    /// ```
     ///
     /// # Arguments
     ///
-    /// * `name` - zone name, e.g. example.com.
-    /// * `authority` - the zone data
-    pub fn upsert(&mut self, name: LowerName, authority: Authority) {
-        self.authorities.insert(name, RwLock::new(authority));
+    /// * `request` - the NOTIFY message, and the address it was received from
+    /// * `response_handle` - sink for the response message to be sent
+    pub fn notify<'q, R: ResponseHandler + 'static>(
+        &self,
+        request: &'q Request,
+        response_edns: Option<Edns>,
+        response_handle: R,
+    ) -> io::Result<()> {
+        let notify = &request.message;
+        let response = MessageResponse::new(Some(notify.raw_queries()));
+
+        let response_code = match notify.queries().first() {
+            Some(query) if query.query_type() == RecordType::SOA => {
+                match self.refreshes.get(query.name()) {
+                    Some(&(ref masters, ref refresh)) => {
+                        if masters.contains(&request.src.ip()) {
+                            info!("NOTIFY for zone: {} from: {}", query.name(), request.src);
+                            if refresh.unbounded_send(Refresh::Notify).is_err() {
+                                warn!("refresh of zone: {} has stopped", query.name());
+                            }
+                            ResponseCode::NoError
+                        } else {
+                            error!(
+                                "ignoring NOTIFY for zone: {} from unknown master: {}",
+                                query.name(),
+                                request.src
+                            );
+                            ResponseCode::Refused
+                        }
+                    }
+                    None => {
+                        warn!("NOTIFY for zone which is not a slave: {}", query.name());
+                        ResponseCode::NotAuth
+                    }
+                }
+            }
+            _ => ResponseCode::FormErr,
+        };
+
+        let mut response_header = Header::new();
+        response_header.set_id(notify.id());
+        response_header.set_op_code(OpCode::Notify);
+        response_header.set_message_type(MessageType::Response);
+        response_header.set_response_code(response_code);
+        response_header.set_authoritative(response_code == ResponseCode::NoError);
+
+        send_response(
+            response_edns,
+            response.build(response_header),
+            response_handle,
+        )
     }
 
     /// Update the zone given the Update request.
@@ -218,6 +990,39 @@ impl Catalog {
         update: &'q MessageRequest,
         response_edns: Option<Edns>,
         response_handle: R,
+    ) -> io::Result<()> {
+        match self.verify(update) {
+            Ok(signature) => {
+                let response_handle = SignedResponseHandle::new(
+                    response_handle,
+                    &signature,
+                    update,
+                    &RequestContext::new(),
+                );
+                self.update_signed(update, &signature, None, response_edns, response_handle)
+            }
+            Err((error, tsigner)) => {
+                metrics::count_update(false);
+                send_tsig_error(
+                    update,
+                    tsigner.as_ref(),
+                    error,
+                    response_edns,
+                    response_handle,
+                )
+            }
+        }
+    }
+
+    // updates the zone, with the signature of the update already verified, and the identity of the
+    //  client over mutual TLS
+    fn update_signed<'q, R: ResponseHandler + 'static>(
+        &self,
+        update: &'q MessageRequest,
+        signature: &Signature,
+        client_identity: Option<&ClientIdentity>,
+        response_edns: Option<Edns>,
+        response_handle: R,
     ) -> io::Result<()> {
         let response = MessageResponse::new(None);
         let mut response_header = Header::default();
@@ -257,11 +1062,20 @@ impl Catalog {
                     );
                 }
                 ZoneType::Master => {
-                    let update_result = authority.update(update);
+                    let update_result =
+                        authority.update_signed(update, signature.key.as_ref(), client_identity);
+                    metrics::count_update(update_result.is_ok());
                     match update_result {
                         // successful update
-                        Ok(..) => {
+                        Ok(updated) => {
                             response_header.set_response_code(ResponseCode::NoError);
+
+                            // RFC 1996, the slaves are notified of the change to the zone
+                            if let Some(notify) = self.notifies.get(authority.origin()) {
+                                if updated && notify.unbounded_send(()).is_err() {
+                                    warn!("NOTIFY of zone: {} has stopped", authority.origin());
+                                }
+                            }
                         }
                         Err(response_code) => {
                             response_header.set_response_code(response_code);
@@ -307,9 +1121,114 @@ impl Catalog {
         response_edns: Option<Edns>,
         response_handle: R,
     ) -> io::Result<()> {
+        self.lookup_from(request, None, response_edns, response_handle)
+    }
+
+    /// Given the requested query from the client, lookup and return any matching results.
+    ///
+    /// The answers of zones with variants of records for some networks are selected by the subnet
+    ///  of the client, from the EDNS Client Subnet option of the request, otherwise from its
+    ///  source address, see `Authority::add_subnet`.
+    ///
+    /// [RFC 7871](https://tools.ietf.org/html/rfc7871#section-7.2.1), Client Subnet in DNS Queries, May 2016
+    ///
+    /// ```text
+    /// A SOURCE PREFIX-LENGTH value of 0 means that the Recursive Resolver
+    /// MUST NOT add address information of the client to its queries.
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the query message.
+    /// * `src` - the source address of the request, if it is known
+    /// * `response_handle` - sink for the response message to be sent
+    pub fn lookup_from<'q, R: ResponseHandler + 'static>(
+        &self,
+        request: &'q MessageRequest,
+        src: Option<IpAddr>,
+        response_edns: Option<Edns>,
+        response_handle: R,
+    ) -> io::Result<()> {
+        match self.verify(request) {
+            Ok(signature) => {
+                let response_handle = SignedResponseHandle::new(
+                    response_handle,
+                    &signature,
+                    request,
+                    &RequestContext::new(),
+                );
+                self.lookup_signed(
+                    request,
+                    src,
+                    &signature,
+                    None,
+                    response_edns,
+                    response_handle,
+                )
+            }
+            Err((error, tsigner)) => send_tsig_error(
+                request,
+                tsigner.as_ref(),
+                error,
+                response_edns,
+                response_handle,
+            ),
+        }
+    }
+
+    // looks up the query, with the signature of the request already verified, queries which are
+    //  resolved recursively or forwarded are resolved on the reactor of the handle
+    fn lookup_signed<'q, R: ResponseHandler + 'static>(
+        &self,
+        request: &'q MessageRequest,
+        src: Option<IpAddr>,
+        signature: &Signature,
+        handle: Option<&Handle>,
+        mut response_edns: Option<Edns>,
+        response_handle: R,
+    ) -> io::Result<()> {
+        let request_subnet = request.edns().and_then(|edns| {
+            match edns.option(&EdnsCode::Subnet) {
+                Some(&EdnsOption::Subnet(subnet)) => Some(subnet),
+                _ => None,
+            }
+        });
+        let client = match request_subnet {
+            Some(subnet) if subnet.source_prefix() == 0 => None,
+            Some(subnet) => Some(subnet),
+            // the prefix is clamped to the whole address
+            None => src.map(|src| ClientSubnet::new(src, 128, 0)),
+        };
+
+        // requests without a source, see `lookup`, are from this process
+        let recursion_allowed = src.map_or(true, |src| {
+            self.recursion_acl.allows(src, signature.key.as_ref(), None)
+        });
+
         // TODO: the spec is very unclear on what to do with multiple queries
         //  we will search for each, in the future, maybe make this threaded to respond even faster.
         for query in request.queries() {
+            if let Some(forwarder) = self.find_forwarder(query.name()) {
+                if !recursion_allowed {
+                    return send_recursion_refused(request, response_edns, response_handle);
+                }
+
+                info!("request: {} forwarding: {}", request.id(), query.name());
+                let query = query.original().clone();
+                let forward = query.clone();
+                let dnssec_ok = request.edns().map_or(false, |edns| edns.dnssec_ok());
+                let checking_disabled = request.checking_disabled();
+                return send_resolved(
+                    |handle| forwarder.forward(forward, dnssec_ok, checking_disabled, handle),
+                    handle,
+                    forwarder.remote(),
+                    request,
+                    query,
+                    response_edns,
+                    response_handle,
+                );
+            }
+
             if let Some(ref_authority) = self.find_auth_recurse(query.name()) {
                 let authority = &ref_authority.read().unwrap(); // poison errors should panic
                 info!(
@@ -317,12 +1236,31 @@ impl Catalog {
                     request.id(),
                     authority.origin()
                 );
+                metrics::count_zone_query(authority.origin());
 
-                let mut response = MessageResponse::new(Some(request.raw_queries()));
-                let mut response_header = Header::new();
-                response_header.set_id(request.id());
-                response_header.set_op_code(OpCode::Query);
-                response_header.set_message_type(MessageType::Response);
+                // an expired slave zone is no longer authoritative for any data
+                if authority.is_expired() {
+                    warn!("zone expired: {}", authority.origin());
+                    let response = MessageResponse::new(Some(request.raw_queries()));
+                    return send_response(
+                        response_edns,
+                        response.error_msg(request.id(), request.op_code(), ResponseCode::ServFail),
+                        response_handle,
+                    );
+                }
+
+                // with shared secrets for TSIG, zone transfers must be signed with one of them
+                let is_transfer = query.query_type() == RecordType::AXFR
+                    || query.query_type() == RecordType::IXFR;
+                if signature.tsig.is_none() && is_transfer && !authority.tsig_keys().is_empty() {
+                    warn!("zone transfer without TSIG refused: {}", authority.origin());
+                    let response = MessageResponse::new(Some(request.raw_queries()));
+                    return send_response(
+                        response_edns,
+                        response.error_msg(request.id(), request.op_code(), ResponseCode::Refused),
+                        response_handle,
+                    );
+                }
 
                 let (is_dnssec, supported_algorithms) = request.edns().map_or(
                     (false, SupportedAlgorithms::new()),
@@ -348,20 +1286,156 @@ impl Catalog {
                     );
                 }
 
-                let records = authority.search(query, is_dnssec, supported_algorithms);
+                // names in a child zone are referred to its name servers, except for the DS records
+                //  at the cut, which belong to this zone
+                let delegation = match query.query_type() {
+                    RecordType::AXFR | RecordType::IXFR => None,
+                    query_type => authority.delegation(query.name()).and_then(|cut| {
+                        if query_type == RecordType::DNSSEC(DNSSECRecordType::DS)
+                            && cut == *query.name()
+                        {
+                            None
+                        } else {
+                            Some(cut)
+                        }
+                    }),
+                };
+
+                if let Some(cut) = delegation {
+                    info!("request: {} referred to: {}", request.id(), cut);
+                    let mut response = MessageResponse::new(Some(request.raw_queries()));
+                    let mut response_header = Header::new();
+                    response_header.set_id(request.id());
+                    response_header.set_op_code(OpCode::Query);
+                    response_header.set_message_type(MessageType::Response);
+                    response_header.set_response_code(ResponseCode::NoError);
+                    response_header.set_recursion_available(self.recursor.is_some());
+                    // the data of the child zone is not authoritative here
+                    response_header.set_authoritative(false);
+
+                    let referral = authority.referral(&cut, is_dnssec, supported_algorithms);
+                    let glue = authority.additionals(&referral, false, supported_algorithms);
+                    response.name_servers(referral);
+                    if !glue.is_empty() {
+                        // RFC 9471, the glue of name servers in the child zone is required
+                        response.in_domain_glue(
+                            glue.iter()
+                                .any(|record| cut.zone_of(&LowerName::new(record.name()))),
+                        );
+                        response.additionals(glue);
+                    }
+
+                    return send_response(
+                        response_edns,
+                        response.build(response_header),
+                        response_handle,
+                    );
+                }
+
+                // RFC 1995, the IXFR request carries the SOA of the requester in the authority section
+                let ixfr = if query.query_type() == RecordType::IXFR {
+                    request
+                        .name_servers()
+                        .iter()
+                        .filter_map(|record| {
+                            if let RData::SOA(ref soa) = *record.rdata() {
+                                Some(soa.serial())
+                            } else {
+                                None
+                            }
+                        })
+                        .next()
+                        .and_then(|serial| authority.ixfr(serial))
+                } else {
+                    None
+                };
+
+                let lookup = match ixfr {
+                    Some(ref ixfr) => AuthLookup::Records(ixfr.iter().collect()),
+                    None => authority.search(query, is_dnssec, supported_algorithms),
+                };
+
+                // RFC 4592, names which do not exist are synthesized from a matching wildcard
+                let wildcard = match (&lookup, query.query_type()) {
+                    (_, RecordType::AXFR) | (_, RecordType::IXFR) | (_, RecordType::SOA) => None,
+                    (&AuthLookup::NoName, query_type) => authority.expand_wildcard(
+                        query.name(),
+                        query_type,
+                        is_dnssec,
+                        supported_algorithms,
+                    ),
+                    _ => None,
+                };
+
+                let mut response = MessageResponse::new(Some(request.raw_queries()));
+                let mut response_header = Header::new();
+                response_header.set_id(request.id());
+                response_header.set_op_code(OpCode::Query);
+                response_header.set_message_type(MessageType::Response);
+                response_header.set_recursion_available(self.recursor.is_some());
+
+                let is_wildcard = wildcard.is_some();
+                let mut scope_prefix = 0;
+                let records = match wildcard {
+                    Some(ref wildcard) if wildcard.is_empty() => AuthLookup::NameExists,
+                    Some(ref wildcard) => AuthLookup::Records(wildcard.iter().collect()),
+                    None => lookup,
+                };
                 if !records.is_empty() {
                     response_header.set_response_code(ResponseCode::NoError);
                     response_header.set_authoritative(true);
                     // TODO: this is not incorrect, but could be cleaner with a `match` on records
-                    response.answers(records.unwrap());
+                    let mut answers = records.unwrap();
+
+                    // RFC 7871, the answer is tailored to the subnet of the client, the variants
+                    //  are signed with the zone, see `Authority::add_subnet`
+                    if let Some(ref client) = client {
+                        if !is_transfer && !is_wildcard {
+                            let (variant, scope) = authority.subnets().lookup(
+                                query.name(),
+                                query.query_type(),
+                                client,
+                            );
+                            if let Some(variant) = variant {
+                                answers = variant.records(is_dnssec, supported_algorithms);
+                            }
+                            scope_prefix = scope;
+                        }
+                    }
 
                     // get the NS records
-                    let ns = authority.ns(is_dnssec, supported_algorithms);
-                    if ns.is_empty() {
-                        warn!("there are no NS records for: {:?}", authority.origin());
-                    } else {
-                        // TODO: this is not incorrect, but could be cleaner with a `match` on records
-                        response.name_servers(ns.unwrap());
+                    let mut ns = match authority.ns(is_dnssec, supported_algorithms) {
+                        AuthLookup::Records(ns) => ns,
+                        AuthLookup::NoName | AuthLookup::NameExists => {
+                            warn!("there are no NS records for: {:?}", authority.origin());
+                            vec![]
+                        }
+                    };
+
+                    // RFC 4035, a wildcard answer proves that the query name does not exist
+                    if is_dnssec && is_wildcard {
+                        ns.append(&mut authority.get_nsec_records(
+                            query.name(),
+                            is_dnssec,
+                            supported_algorithms,
+                        ));
+                    }
+
+                    // addresses of the MX, NS, and SRV targets save the client another lookup
+                    let mut additionals = vec![];
+                    if !is_transfer {
+                        let mut targets = answers.clone();
+                        targets.extend(ns.iter().cloned());
+                        additionals =
+                            authority.additionals(&targets, is_dnssec, supported_algorithms);
+                    }
+
+                    response.answers(answers);
+                    if !ns.is_empty() {
+                        response.name_servers(ns);
+                    }
+                    if !additionals.is_empty() {
+                        response.additionals(additionals);
                     }
                 } else {
                     // in the not found case it's standard to return the SOA in the authority section
@@ -381,7 +1455,7 @@ impl Catalog {
 
                     let mut ns = vec![];
 
-                    // in the dnssec case, nsec records should exist, we return NoError + NoData + NSec...
+                    // in the dnssec case, nsec records should exist, they prove the NXDomain or the NoData
                     if is_dnssec {
                         // get NSEC records
                         let mut nsecs = authority.get_nsec_records(
@@ -396,7 +1470,20 @@ impl Catalog {
                         );
 
                         ns.append(&mut nsecs);
-                        response_header.set_response_code(ResponseCode::NoError);
+
+                        // RFC 4035, prove there is no wildcard for the name, or that the wildcard
+                        //  does not have the type
+                        if is_wildcard || records == AuthLookup::NoName {
+                            for nsec in authority.get_wildcard_nsec_records(
+                                query.name(),
+                                is_dnssec,
+                                supported_algorithms,
+                            ) {
+                                if !ns.contains(&nsec) {
+                                    ns.push(nsec);
+                                }
+                            }
+                        }
                     } else {
                         info!("request: {} non-existent", request.id());
                     }
@@ -412,12 +1499,40 @@ impl Catalog {
                     response.name_servers(ns);
                 }
 
+                // RFC 7871, the scope of the answer tells resolvers which clients may share it
+                if let (Some(edns), Some(mut subnet)) = (response_edns.as_mut(), request_subnet) {
+                    subnet.set_scope_prefix(scope_prefix);
+                    edns.set_option(EdnsOption::Subnet(subnet));
+                }
+
                 return send_response(
                     response_edns,
                     response.build(response_header),
                     response_handle,
                 );
             }
+
+            // names outside of all the zones are resolved recursively, if requested
+            if let Some(ref recursor) = self.recursor {
+                if request.recursion_desired() {
+                    if !recursion_allowed {
+                        return send_recursion_refused(request, response_edns, response_handle);
+                    }
+
+                    info!("request: {} recursing: {}", request.id(), query.name());
+                    let query = query.original().clone();
+                    let resolve = query.clone();
+                    return send_resolved(
+                        |handle| recursor.resolve(resolve, handle),
+                        handle,
+                        recursor.remote(),
+                        request,
+                        query,
+                        response_edns,
+                        response_handle,
+                    );
+                }
+            }
         }
 
         let response = MessageResponse::new(Some(request.raw_queries()));
@@ -429,7 +1544,7 @@ impl Catalog {
     }
 
     /// recursively searches the catalog for a matching auhtority.
-    fn find_auth_recurse(&self, name: &LowerName) -> Option<&RwLock<Authority>> {
+    fn find_auth_recurse(&self, name: &LowerName) -> Option<&Arc<RwLock<Authority>>> {
         let authority = self.authorities.get(name);
         if authority.is_some() {
             return authority;
@@ -442,4 +1557,110 @@ impl Catalog {
 
         None
     }
+
+    /// checks the request, signed with the key if it verified, against the access control lists of
+    ///  the zone of its first query
+    fn is_allowed(
+        &self,
+        request: &Request,
+        key: Option<&Name>,
+        client_identity: Option<&ClientIdentity>,
+    ) -> bool {
+        let message = &request.message;
+        let query = match message.queries().first() {
+            Some(query) => query,
+            None => return true,
+        };
+
+        let acls = self.find_acls(query.name());
+        let acl = match (message.op_code(), query.query_type()) {
+            (OpCode::Update, _) => acls.update(),
+            (_, RecordType::AXFR) | (_, RecordType::IXFR) => acls.transfer(),
+            _ => acls.query(),
+        };
+
+        let allowed = acl.allows(request.src.ip(), key, client_identity);
+        if !allowed {
+            warn!(
+                "request: {} refused for: {} from: {}",
+                message.id(),
+                query.name(),
+                request.src
+            );
+        }
+        allowed
+    }
+
+    // verifies the TSIG or SIG(0) of the request with the keys of the zone of its first query, if
+    //  the TSIG did not verify its error is returned, with the key of the zone of the same name
+    fn verify(
+        &self,
+        request: &MessageRequest,
+    ) -> Result<Signature, (ResponseCode, Option<TSigner>)> {
+        let authority = match request
+            .queries()
+            .first()
+            .and_then(|query| self.find_auth_recurse(query.name()))
+        {
+            Some(authority) => authority.read().unwrap(), // poison errors should panic
+            None => return Ok(Signature::default()),
+        };
+
+        let signature = match authority.verify_tsig(request) {
+            Some(Ok((tsigner, request_mac))) => Ok(Signature {
+                key: Some(tsigner.signer_name().clone()),
+                tsig: Some((tsigner.clone(), request_mac)),
+            }),
+            Some(Err(error)) => {
+                let tsigner = request.sig0().last().and_then(|request_tsig| {
+                    authority
+                        .tsig_keys()
+                        .iter()
+                        .find(|key| key.signer_name() == request_tsig.name())
+                        .cloned()
+                });
+                Err((error, tsigner))
+            }
+            None => Ok(Signature {
+                key: authority.verify_sig0(request),
+                tsig: None,
+            }),
+        };
+        signature
+    }
+
+    /// searches for the access control lists of the closest zone of the name
+    fn find_acls(&self, name: &LowerName) -> &ZoneAcls {
+        let mut name = name.clone();
+        loop {
+            if let Some(acls) = self.acls.get(&name) {
+                return acls;
+            }
+            if name.is_root() {
+                return &self.default_acls;
+            }
+            name = name.base_name();
+        }
+    }
+
+    /// searches for the closest Forward zone of the name, unless a zone of this server is closer
+    fn find_forwarder(&self, name: &LowerName) -> Option<&Forwarder> {
+        if self.forwarders.is_empty() {
+            return None;
+        }
+
+        let mut name = name.clone();
+        loop {
+            if self.authorities.contains_key(&name) {
+                return None;
+            }
+            if let Some(forwarder) = self.forwarders.get(&name) {
+                return Some(forwarder);
+            }
+            if name.is_root() {
+                return None;
+            }
+            name = name.base_name();
+        }
+    }
 }
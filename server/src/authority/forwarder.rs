@@ -0,0 +1,337 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Forwarding of the queries of Forward zones to upstream servers
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use lru_cache::LruCache;
+
+use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::sync::oneshot;
+use futures::{future, Async, Future, Poll, Stream};
+use tokio_core::reactor::{CoreId, Handle, Remote};
+
+use trust_dns::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns::rr::{RData, Record};
+use trust_dns_proto::DnsHandle;
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::name_server_pool::{NameServerPool, StandardConnection};
+use trust_dns_resolver::BasicResolverHandle;
+
+use authority::shared_cache::{SharedCache, Stripe};
+
+// the maximum payload advertised in queries to the upstream servers
+const MAX_PAYLOAD_LEN: u16 = 1232;
+
+// a query to the upstream servers, and where their response is sent
+type UpstreamQuery = (Message, oneshot::Sender<Result<Message, ResolveError>>);
+
+// the query, and whether the client asked for DNSSEC records and disabled checking, with DO and CD
+type CacheKey = (Query, bool, bool);
+
+/// Answers the queries of a Forward zone from upstream servers, with a cache of their responses
+///  shared between all of the queries.
+///
+/// The Forwarder is shared by the threads of the server, each reactor on which queries are
+///  forwarded has its own pool of connections to the upstream servers, see `forward`.
+///
+/// The queries are sent with a `NameServerPool`, rather than with a `ResolverFuture`, whose
+///  lookups only keep the records of the answers: the response of the upstream servers is relayed
+///  whole, with its response code and its authority and additional sections, e.g. the SOA of a
+///  negative response, and the `ResolverFuture` would itself follow CNAMEs, and try search
+///  domains, which are for the clients to do.
+#[derive(Clone)]
+pub struct Forwarder {
+    upstreams: Arc<Mutex<HashMap<CoreId, UnboundedSender<UpstreamQuery>>>>,
+    cache: Arc<SharedCache<ResponseCache>>,
+    remote: Remote,
+    config: ResolverConfig,
+    authentic_data_trusted: bool,
+}
+
+impl Forwarder {
+    /// Constructs a new Forwarder
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the upstream servers to which queries are sent
+    /// * `cache_size` - the maximum number of responses in the cache
+    /// * `handle` - the reactor on which the Forwarder is constructed, see `remote`
+    pub fn new(config: ResolverConfig, cache_size: usize, handle: &Handle) -> Self {
+        Forwarder {
+            upstreams: Arc::new(Mutex::new(HashMap::new())),
+            cache: Arc::new(SharedCache::new(cache_size)),
+            remote: handle.remote().clone(),
+            config,
+            authentic_data_trusted: false,
+        }
+    }
+
+    /// The upstream servers to which queries are sent
+    pub fn config(&self) -> &ResolverConfig {
+        &self.config
+    }
+
+    /// Relays AD in the responses of the upstream servers, which are trusted to validate them
+    ///
+    /// [RFC 4035](https://tools.ietf.org/html/rfc4035#section-4.9.3), DNSSEC Protocol
+    ///  Modifications, March 2005, the AD of a response must not be trusted unless the server which set it is
+    ///  trusted, and the channel to it is secure. By default AD is cleared, this server does not
+    ///  validate the responses itself.
+    pub fn set_authentic_data_trusted(&mut self, authentic_data_trusted: bool) {
+        self.authentic_data_trusted = authentic_data_trusted;
+    }
+
+    /// Removes all the responses from the cache
+    pub fn flush(&self) {
+        self.cache.clear();
+    }
+
+    /// The reactor on which the Forwarder was constructed
+    pub fn remote(&self) -> &Remote {
+        &self.remote
+    }
+
+    /// Resolves the query with the upstream servers, or from the cache
+    ///
+    /// The response of the upstream servers is returned as it was received, with its response
+    ///  code and all of its sections, and AD if they authenticated the answers and they are
+    ///  trusted, see `set_authentic_data_trusted`. NoError and NXDomain responses are cached, the
+    ///  TTLs of their records count down while they are cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the query of the client
+    /// * `dnssec_ok` - true if the client asked for DNSSEC records, DO in its EDNS
+    /// * `checking_disabled` - true if the client validates the answers itself, CD
+    /// * `handle` - the reactor on which the query is sent to the upstream servers
+    pub fn forward(
+        &self,
+        query: Query,
+        dnssec_ok: bool,
+        checking_disabled: bool,
+        handle: &Handle,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        let key = (query, dnssec_ok, checking_disabled);
+        let cached = self.cache.lock(key.0.name()).get(&key, Instant::now());
+        if let Some(response) = cached {
+            debug!("cached: {} {:?}", key.0.name(), key.0.query_type());
+            return Box::new(future::ok(response));
+        }
+
+        // RFC 6840, AD in the query asks the upstream servers to set it in their response
+        let mut message = Message::new();
+        message.add_query(key.0.clone());
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .set_authentic_data(self.authentic_data_trusted)
+            .set_checking_disabled(checking_disabled);
+        {
+            let edns = message.edns_mut();
+            edns.set_max_payload(MAX_PAYLOAD_LEN);
+            edns.set_version(0);
+            edns.set_dnssec_ok(dnssec_ok);
+        }
+
+        let (complete, response) = oneshot::channel();
+        if self
+            .upstream(handle)
+            .unbounded_send((message, complete))
+            .is_err()
+        {
+            return Box::new(future::err(ResolveError::from(ResolveErrorKind::Message(
+                "the reactor of the forwarder is gone",
+            ))));
+        }
+
+        let cache = self.cache.clone();
+        let authentic_data_trusted = self.authentic_data_trusted;
+        Box::new(
+            response
+                .then(|response| match response {
+                    Ok(response) => response,
+                    Err(_) => Err(ResolveError::from(ResolveErrorKind::Message(
+                        "the query to the upstream servers was canceled",
+                    ))),
+                })
+                .map(move |mut response| {
+                    if !authentic_data_trusted {
+                        response.set_authentic_data(false);
+                    }
+
+                    cache
+                        .lock(key.0.name())
+                        .insert(key, &response, Instant::now());
+                    response
+                }),
+        )
+    }
+
+    // the pool of connections of the reactor, it is spawned with the first query sent from it
+    fn upstream(&self, handle: &Handle) -> UnboundedSender<UpstreamQuery> {
+        let mut upstreams = self.upstreams.lock().expect("upstreams lock poisoned");
+        upstreams
+            .entry(handle.id())
+            .or_insert_with(|| {
+                let (upstream, queries) = unbounded();
+                handle.spawn(UpstreamPool::new(&self.config, queries, handle));
+                upstream
+            })
+            .clone()
+    }
+}
+
+/// The pool of connections to the upstream servers of one reactor
+///
+/// The pool is not `Send`, the queries of the Forwarder on the reactor are sent to the servers
+///  from this future.
+struct UpstreamPool {
+    pool: NameServerPool<BasicResolverHandle, StandardConnection>,
+    queries: UnboundedReceiver<UpstreamQuery>,
+    handle: Handle,
+}
+
+impl UpstreamPool {
+    fn new(
+        config: &ResolverConfig,
+        queries: UnboundedReceiver<UpstreamQuery>,
+        handle: &Handle,
+    ) -> Self {
+        UpstreamPool {
+            pool: NameServerPool::<BasicResolverHandle, StandardConnection>::from_config(
+                config,
+                &ResolverOpts::default(),
+                handle,
+            ),
+            queries,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl Future for UpstreamPool {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            match try_ready!(self.queries.poll()) {
+                Some((message, complete)) => {
+                    self.handle
+                        .spawn(self.pool.send(message).then(move |response| {
+                            // the query was abandoned if the receiver is gone
+                            complete.send(response).ok();
+                            Ok(())
+                        }));
+                }
+                // all the Forwarders are dropped
+                None => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
+
+/// The responses of the upstream servers, each expires after the least TTL of its records, once
+///  the cache is full the least recently used are dropped
+struct ResponseCache {
+    responses: LruCache<CacheKey, (Message, Instant, Instant)>,
+}
+
+impl Stripe for ResponseCache {
+    fn with_capacity(capacity: usize) -> Self {
+        ResponseCache {
+            responses: LruCache::new(capacity),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.responses.clear();
+    }
+}
+
+impl ResponseCache {
+    /// Returns the cached response, with the TTLs of its records counted down
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<Message> {
+        let is_expired = match self.responses.get_mut(key) {
+            Some(&mut (ref response, cached, expires)) if now < expires => {
+                let elapsed = now.duration_since(cached).as_secs() as u32;
+                let mut response = response.clone();
+                let answers = count_down(response.take_answers(), elapsed);
+                let name_servers = count_down(response.take_name_servers(), elapsed);
+                let additionals = count_down(response.take_additionals(), elapsed);
+                response.insert_answers(answers);
+                response.insert_name_servers(name_servers);
+                response.insert_additionals(additionals);
+                return Some(response);
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        if is_expired {
+            self.responses.remove(key);
+        }
+        None
+    }
+
+    /// Caches NoError and NXDomain responses
+    fn insert(&mut self, key: CacheKey, response: &Message, now: Instant) {
+        match response.response_code() {
+            ResponseCode::NoError | ResponseCode::NXDomain if !response.truncated() => (),
+            _ => return,
+        }
+
+        let ttl = if response.answers().is_empty() {
+            negative_ttl(response)
+        } else {
+            response
+                .answers()
+                .iter()
+                .chain(response.name_servers())
+                .chain(response.additionals())
+                .map(|record| record.ttl())
+                .min()
+                .unwrap_or(0)
+        };
+        if ttl == 0 {
+            return;
+        }
+
+        let expires = now + Duration::from_secs(u64::from(ttl));
+        self.responses.insert(key, (response.clone(), now, expires));
+    }
+}
+
+fn count_down(records: Vec<Record>, elapsed: u32) -> Vec<Record> {
+    records
+        .into_iter()
+        .map(|mut record| {
+            let ttl = record.ttl().saturating_sub(elapsed);
+            record.set_ttl(ttl);
+            record
+        })
+        .collect()
+}
+
+// RFC 2308, the TTL of a negative response is the least of the TTL and the MINIMUM of the SOA
+fn negative_ttl(response: &Message) -> u32 {
+    response
+        .name_servers()
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::SOA(ref soa) => Some(soa.minimum().min(record.ttl())),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(0)
+}
@@ -0,0 +1,88 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The identity of the server, for operators to tell which instance of an anycast address
+//!  answered
+
+use std::collections::HashMap;
+
+use trust_dns::rr::{LowerName, Name};
+
+/// The name of the CHAOS class TXT record with the version of the server
+pub const VERSION_BIND: &str = "version.bind.";
+/// The name of the CHAOS class TXT record with the host name of the server
+pub const HOSTNAME_BIND: &str = "hostname.bind.";
+/// The name of the CHAOS class TXT record with the identifier of the server
+pub const ID_SERVER: &str = "id.server.";
+
+/// The NSID of the server, and its answers to CHAOS class TXT queries
+///
+/// [RFC 5001](https://tools.ietf.org/html/rfc5001#section-2.3), DNS Name Server Identifier (NSID) Option, August 2007
+///
+/// ```text
+/// A name server that understands the NSID option and chooses to honor a
+/// particular NSID request responds by including identifying information
+/// in a NSID option in an EDNS OPT pseudo-RR in the response message.
+/// ```
+///
+/// [RFC 4892](https://tools.ietf.org/html/rfc4892#section-2.3), Requirements for a Mechanism Identifying a Name Server Instance, June 2007
+///
+/// ```text
+/// The existing convention used by some (but not all) name server
+/// implementations is to use QNAME = "hostname.bind" with QTYPE = TXT
+/// and QCLASS = CH (CHAOS).
+/// ```
+///
+/// Nothing is disclosed unless it is configured, queries for the names which are not are
+///  refused.
+#[derive(Clone, Debug, Default)]
+pub struct Identity {
+    nsid: Option<Vec<u8>>,
+    chaos_txt: HashMap<LowerName, String>,
+}
+
+impl Identity {
+    /// Returns an identity which discloses nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the NSID returned to clients which ask for it
+    pub fn set_nsid(&mut self, nsid: Vec<u8>) {
+        self.nsid = Some(nsid);
+    }
+
+    /// The NSID returned to clients which ask for it, if there is one
+    pub fn nsid(&self) -> Option<&[u8]> {
+        self.nsid.as_ref().map(|nsid| nsid.as_slice())
+    }
+
+    /// Sets the answer to `version.bind` CHAOS class TXT queries
+    pub fn set_version(&mut self, version: String) {
+        self.set_chaos_txt(VERSION_BIND, version);
+    }
+
+    /// Sets the answer to `hostname.bind` CHAOS class TXT queries
+    pub fn set_hostname(&mut self, hostname: String) {
+        self.set_chaos_txt(HOSTNAME_BIND, hostname);
+    }
+
+    /// Sets the answer to `id.server` CHAOS class TXT queries
+    pub fn set_id(&mut self, id: String) {
+        self.set_chaos_txt(ID_SERVER, id);
+    }
+
+    fn set_chaos_txt(&mut self, name: &str, txt: String) {
+        let name = Name::parse(name, None).expect("bad CHAOS name");
+        self.chaos_txt.insert(LowerName::from(name), txt);
+    }
+
+    /// The answer to a CHAOS class TXT query for the name, if it is configured
+    pub fn chaos_txt(&self, name: &LowerName) -> Option<&str> {
+        self.chaos_txt.get(name).map(|txt| txt.as_str())
+    }
+}
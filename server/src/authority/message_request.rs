@@ -7,6 +7,7 @@
 
 use trust_dns_proto::error::*;
 use trust_dns_proto::rr::Record;
+use trust_dns_proto::rr::rdata::opt::{Cookie, EdnsCode, EdnsOption};
 use trust_dns_proto::serialize::binary::{BinDecodable, BinDecoder, BinEncoder};
 use trust_dns_proto::op::{Edns, EncodableMessage, Header, Message, MessageType, OpCode, ResponseCode};
 use trust_dns::op::LowerQuery;
@@ -21,6 +22,7 @@ pub struct MessageRequest<'r> {
     additionals: Vec<Record>,
     sig0: Vec<Record>,
     edns: Option<Edns>,
+    unsigned: &'r [u8],
 }
 
 impl<'r> MessageRequest<'r> {
@@ -144,11 +146,20 @@ impl<'r> MessageRequest<'r> {
         self.edns.as_ref()
     }
 
-    /// Any SIG0 records for signed messages
+    /// Any SIG0 or TSIG records for signed messages
     pub fn sig0(&self) -> &[Record] {
         &self.sig0
     }
 
+    /// Returns the message as it was received from the client, up to but excluding the final
+    ///  additional record.
+    ///
+    /// For messages signed with TSIG these are the bytes covered by the MAC, re-encoding the
+    ///  message would not preserve the name compression of the client.
+    pub fn unsigned_bytes(&self) -> &'r [u8] {
+        self.unsigned
+    }
+
     /// # Return value
     ///
     /// the max payload value as it's defined in the EDNS section.
@@ -168,6 +179,14 @@ impl<'r> MessageRequest<'r> {
         self.edns.as_ref().map_or(0, |e| e.version())
     }
 
+    /// Returns the DNS Cookies of the request, if it has a well formed COOKIE option
+    pub fn cookie(&self) -> Option<Cookie> {
+        match self.edns.as_ref().and_then(|e| e.option(&EdnsCode::Cookie)) {
+            Some(&EdnsOption::Cookie(ref cookie)) => Some(cookie.clone()),
+            _ => None,
+        }
+    }
+
     /// Returns the queries passed recieved from the client
     pub fn raw_queries(&self) -> &Queries {
         &self.queries
@@ -178,6 +197,7 @@ impl<'r> BinDecodable<'r> for MessageRequest<'r> {
     // TODO: generify this with Message?
     /// Reads a MessageRequest from the decoder
     fn read(decoder: &mut BinDecoder<'r>) -> ProtoResult<Self> {
+        let start = decoder.index();
         let header = Header::read(decoder)?;
 
         // TODO/FIXME: return just header, and in the case of the rest of message getting an error.
@@ -194,7 +214,26 @@ impl<'r> BinDecodable<'r> for MessageRequest<'r> {
         let queries = Queries::read(decoder, query_count)?;
         let (answers, _, _) = Message::read_records(decoder, answer_count, false)?;
         let (name_servers, _, _) = Message::read_records(decoder, name_server_count, false)?;
-        let (additionals, edns, sig0) = Message::read_records(decoder, additional_count, true)?;
+        let (mut additionals, mut edns, mut sig0) =
+            Message::read_records(decoder, additional_count.saturating_sub(1), true)?;
+
+        // a TSIG is always the final record, capture everything before it for verification
+        let unsigned = decoder.slice_from(start)?;
+        if additional_count > 0 {
+            let (mut last, last_edns, mut last_sig0) = Message::read_records(decoder, 1, true)?;
+            if !sig0.is_empty() && last_sig0.is_empty() {
+                return Err(ProtoErrorKind::Message("sig0 must be final resource record").into());
+            }
+            if last_edns.is_some() {
+                if edns.is_some() {
+                    return Err(ProtoErrorKind::Message("more than one edns record present").into());
+                }
+                edns = last_edns;
+            }
+
+            additionals.append(&mut last);
+            sig0.append(&mut last_sig0);
+        }
 
         Ok(MessageRequest {
             header: header,
@@ -204,6 +243,7 @@ impl<'r> BinDecodable<'r> for MessageRequest<'r> {
             additionals: additionals,
             sig0: sig0,
             edns: edns,
+            unsigned: unsigned,
         })
     }
 }
@@ -301,7 +341,7 @@ pub trait UpdateRequest {
     /// Additional records
     fn additionals(&self) -> &[Record];
 
-    /// SIG0 records for verifying the Message
+    /// SIG0 or TSIG records for verifying the Message
     fn sig0(&self) -> &[Record];
 }
 
@@ -5,10 +5,14 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use trust_dns_proto::error::*;
 use trust_dns_proto::op::EncodableMessage;
+use trust_dns_proto::rr::dnssec::rdata::tsig;
 use trust_dns::op::{Edns, Header, MessageType, OpCode, ResponseCode};
 use trust_dns::rr::Record;
+use trust_dns::rr::dnssec::TSigner;
 use trust_dns::serialize::binary::BinEncoder;
 
 use authority::Queries;
@@ -23,6 +27,7 @@ pub struct MessageResponse<'q, 'a> {
     additionals: Vec<&'a Record>,
     sig0: Vec<Record>,
     edns: Option<Edns>,
+    in_domain_glue: bool,
 }
 
 impl<'q, 'a> MessageResponse<'q, 'a> {
@@ -39,6 +44,7 @@ impl<'q, 'a> MessageResponse<'q, 'a> {
             additionals: None,
             sig0: None,
             edns: None,
+            in_domain_glue: false,
         }
     }
 
@@ -47,6 +53,49 @@ impl<'q, 'a> MessageResponse<'q, 'a> {
         self.edns = Some(edns);
         self
     }
+
+    /// Sign the Response with TSIG, this must be the last change to the Response
+    ///
+    /// # Arguments
+    ///
+    /// * `tsigner` - the key which signed the request
+    /// * `request_mac` - the MAC of the TSIG of the request
+    pub fn sign_tsig(&mut self, tsigner: &TSigner, request_mac: &[u8]) -> ProtoResult<()> {
+        let tsig = tsigner.sign_message(self, Some(request_mac), now()?)?;
+        self.sig0.push(tsig);
+        Ok(())
+    }
+
+    /// Adds the TSIG of the error response to a request whose TSIG did not verify, this must be
+    ///  the last change to the Response
+    ///
+    /// # Arguments
+    ///
+    /// * `request_tsig` - the TSIG of the request
+    /// * `error` - the TSIG error, see `Authority::verify_tsig`
+    /// * `tsigner` - the key with the name of the TSIG of the request, which signs BADTIME errors
+    pub fn tsig_error(
+        &mut self,
+        request_tsig: &Record,
+        error: ResponseCode,
+        tsigner: Option<&TSigner>,
+    ) -> ProtoResult<()> {
+        let tsig = match (error, tsigner) {
+            (ResponseCode::BADTIME, Some(tsigner)) => {
+                tsigner.sign_bad_time(self, request_tsig, now()?)?
+            }
+            _ => tsig::make_error_tsig_record(request_tsig, error)?,
+        };
+        self.sig0.push(tsig);
+        Ok(())
+    }
+}
+
+fn now() -> ProtoResult<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .map_err(|_| ProtoError::from("Current time is before the Unix epoch."))
 }
 
 macro_rules! section {
@@ -89,6 +138,10 @@ impl<'q, 'a> EncodableMessage for MessageResponse<'q, 'a> {
     fn sig0(&self) -> &[Record] {
         &self.sig0
     }
+
+    fn has_in_domain_glue(&self) -> bool {
+        self.in_domain_glue
+    }
 }
 
 /// A builder for MessageResponses
@@ -99,6 +152,7 @@ pub struct MessageResponseBuilder<'q, 'a> {
     additionals: Option<Vec<&'a Record>>,
     sig0: Option<Vec<Record>>,
     edns: Option<Edns>,
+    in_domain_glue: bool,
 }
 
 impl<'q, 'a> MessageResponseBuilder<'q, 'a> {
@@ -114,6 +168,19 @@ impl<'q, 'a> MessageResponseBuilder<'q, 'a> {
         self
     }
 
+    /// Associate a set of additionals with the response, generally owned by either a cache or [`trust_dns_server::authorith::Authority`]
+    pub fn additionals(&mut self, records: Vec<&'a Record>) -> &mut Self {
+        self.additionals = Some(records);
+        self
+    }
+
+    /// Marks the additionals as the in-domain glue of a referral, a response without them is
+    ///  truncated, see `TruncatingResponseHandle`
+    pub fn in_domain_glue(&mut self, in_domain_glue: bool) -> &mut Self {
+        self.in_domain_glue = in_domain_glue;
+        self
+    }
+
     /// Associate EDNS with the Response
     pub fn edns(&mut self, edns: Edns) -> &mut Self {
         self.edns = Some(edns);
@@ -134,6 +201,7 @@ impl<'q, 'a> MessageResponseBuilder<'q, 'a> {
             additionals: self.additionals.unwrap_or_default(),
             sig0: self.sig0.unwrap_or_default(),
             edns: self.edns,
+            in_domain_glue: self.in_domain_glue,
         }
     }
 
@@ -164,6 +232,7 @@ impl<'q, 'a> MessageResponseBuilder<'q, 'a> {
             additionals: self.additionals.unwrap_or_default(),
             sig0: self.sig0.unwrap_or_default(),
             edns: self.edns,
+            in_domain_glue: self.in_domain_glue,
         }
     }
 }
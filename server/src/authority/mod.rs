@@ -16,13 +16,14 @@
 
 //! Module for `Catalog` of `Authority` zones which are responsible for storing `RRSet` records.
 
+use rustc_serialize::{Decodable, Decoder};
 use trust_dns::op::ResponseCode;
 
 /// Result of an Update operation
 pub type UpdateResult<T> = Result<T, ResponseCode>;
 
 /// The type of zone stored in a Catalog
-#[derive(RustcDecodable, PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ZoneType {
     /// This authority for a zone, i.e. the Primary
     Master,
@@ -34,16 +35,48 @@ pub enum ZoneType {
     Forward,
 }
 
+// the toml decoder tries every variant in order, so a derived decoder always yields the first one
+impl Decodable for ZoneType {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        match &d.read_str()? as &str {
+            "Master" => Ok(ZoneType::Master),
+            "Slave" => Ok(ZoneType::Slave),
+            "Hint" => Ok(ZoneType::Hint),
+            "Forward" => Ok(ZoneType::Forward),
+            other => Err(d.error(&format!("unknown zone_type: {}", other))),
+        }
+    }
+}
+
+mod acl;
 mod auth_lookup;
 pub mod authority;
 mod catalog;
+mod forwarder;
+mod identity;
 mod message_request;
 mod message_response;
+mod notify;
 pub mod persistence;
+mod recursor;
+mod reloadable_catalog;
+mod shared_cache;
+mod slave;
+mod subnets;
+mod view;
 
+pub use self::acl::{Acl, AclElement, ZoneAcls};
 pub use self::auth_lookup::AuthLookup;
-pub use self::authority::Authority;
+pub use self::authority::{Authority, DEFAULT_IXFR_DEPTH};
 pub use self::catalog::Catalog;
+pub use self::forwarder::Forwarder;
+pub use self::identity::{Identity, HOSTNAME_BIND, ID_SERVER, VERSION_BIND};
 pub use self::message_request::{MessageRequest, Queries, UpdateRequest};
 pub use self::message_response::{MessageResponse, MessageResponseBuilder};
+pub use self::notify::NotifyFuture;
 pub use self::persistence::Journal;
+pub use self::recursor::Recursor;
+pub use self::reloadable_catalog::ReloadableCatalog;
+pub use self::slave::{Refresh, SlaveFuture};
+pub use self::subnets::Subnets;
+pub use self::view::View;
@@ -0,0 +1,234 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! NOTIFY of slaves after changes to Master zones
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Loop};
+use futures::sync::mpsc::UnboundedReceiver;
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::{Handle, Timeout};
+
+use trust_dns::client::{ClientFuture, ClientHandle};
+use trust_dns::error::ClientError;
+use trust_dns::op::Message;
+use trust_dns::rr::dnssec::TSigner;
+use trust_dns::rr::{DNSClass, Name, Record, RecordType};
+use trust_dns::udp::UdpClientStream;
+
+use authority::Authority;
+
+// the time to wait for the response to the first NOTIFY, doubled for each retransmission
+const RETRY_INTERVAL: u64 = 5;
+
+// RFC 1996, a reasonable default for the maximum number of retransmissions over UDP
+const MAX_RETRANSMISSIONS: usize = 5;
+
+/// A Future which sends a NOTIFY to the slaves of a Master zone each time the zone is updated.
+///
+/// [RFC 1996](https://tools.ietf.org/html/rfc1996), DNS NOTIFY, August 1996
+///
+/// ```text
+/// 3.5. If TCP is used, both master and slave must continue to offer
+///    name service during the transaction, even when the TCP transaction is
+///    not complete.  Note that a master or a slave will only be able to
+///    serve one transaction at a time.  Such concurrency is highly
+///    recommended, but not required.
+///
+/// 3.6. The master periodically sends a NOTIFY request to a slave until
+///    either too many copies have been sent (a "timeout"), an ICMP message
+///    indicating that the port is unreachable, or until a NOTIFY response
+///    is received from the slave with a matching query ID, QNAME, IP source
+///    address, and UDP source port number.
+///
+///       Note:
+///       The interval between transmissions, and the total number of
+///       retransmissions, should be operational parameters specifiable by
+///       the name server administrator, perhaps on a per-zone basis.
+///       Reasonable defaults are a 60 second interval (or timeout if
+///       using TCP), and a maximum of 5 retransmissions (for UDP).  It is
+///       considered reasonable to use additive or exponential backoff for
+///       the retry interval.
+///
+/// 3.7. A NOTIFY request has QDCOUNT>0, ANCOUNT>=0, AUCOUNT>=0,
+///    ADCOUNT>=0.  If ANCOUNT>0, then the answer section represents an
+///    unsecure hint at the new RRset for this <QNAME,QCLASS,QTYPE>.
+/// ```
+///
+/// NOTIFY is sent over UDP, with the current SOA of the zone as the hint. It is retransmitted to
+///  each slave until the slave responds, as RFC 1996 section 3.6 requires of the master, waiting
+///  twice as long for each retransmission, up to 5 retransmissions. A later update of the zone
+///  ends the retransmissions of the previous NOTIFY. If the zone has TSIG keys, see
+///  `Authority::add_tsig_key`, the NOTIFY is signed with the first of them, as the queries of
+///  `SlaveFuture` are. Slaves which never respond are logged, they will still pick up the change
+///  at their next SOA refresh.
+#[must_use = "futures do nothing unless polled"]
+pub struct NotifyFuture {
+    authority: Arc<RwLock<Authority>>,
+    slaves: Vec<SocketAddr>,
+    notifications: UnboundedReceiver<()>,
+    // incremented for each NOTIFY, the retransmissions of the previous one stop
+    generation: Arc<AtomicUsize>,
+    retry_interval: Duration,
+    reactor_handle: Handle,
+}
+
+impl NotifyFuture {
+    /// Returns a new NotifyFuture
+    ///
+    /// # Arguments
+    ///
+    /// * `authority` - the Master zone, this should be shared with the `Catalog`
+    /// * `slaves` - addresses of the slaves to notify, i.e. the `also_notify` of the zone
+    /// * `notifications` - signaled after updates to the zone, see `Catalog::set_notify`
+    /// * `reactor_handle` - reactor on which the NOTIFY messages are sent
+    pub fn new(
+        authority: Arc<RwLock<Authority>>,
+        slaves: Vec<SocketAddr>,
+        notifications: UnboundedReceiver<()>,
+        reactor_handle: &Handle,
+    ) -> Self {
+        NotifyFuture {
+            authority,
+            slaves,
+            notifications,
+            generation: Arc::new(AtomicUsize::new(0)),
+            retry_interval: Duration::from_secs(RETRY_INTERVAL),
+            reactor_handle: reactor_handle.clone(),
+        }
+    }
+
+    /// Sets the time to wait for the response to the first NOTIFY, before it is retransmitted,
+    ///  the default is 5 seconds
+    pub fn set_retry_interval(&mut self, retry_interval: Duration) {
+        self.retry_interval = retry_interval;
+    }
+
+    fn notify_slaves(&self) {
+        let (origin, soa, tsigner): (Name, Option<Record>, Option<TSigner>) = {
+            let authority = self.authority.read().unwrap(); // poison errors should panic
+            let soa = authority.soa().iter().next().cloned();
+
+            (
+                authority.origin().clone().into(),
+                soa,
+                authority.tsig_keys().first().cloned(),
+            )
+        };
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+
+        for slave in self.slaves.iter().cloned() {
+            debug!("sending NOTIFY for zone: {} to: {}", origin, slave);
+            let zone = origin.clone();
+            let soa = soa.clone();
+            let tsigner = tsigner.clone();
+            let current = self.generation.clone();
+            let reactor_handle = self.reactor_handle.clone();
+
+            let notified = future::loop_fn(
+                (0, self.retry_interval),
+                move |(retransmissions, interval)| {
+                    let sent = Instant::now();
+                    let current = current.clone();
+                    let reactor_handle = reactor_handle.clone();
+
+                    Self::notify(slave, &zone, &soa, &tsigner, interval, &reactor_handle)
+                        .map(|response| Loop::Break(Some(response)))
+                        .or_else(move |e| {
+                            debug!("no response to NOTIFY from: {}: {}", slave, e);
+                            if retransmissions == MAX_RETRANSMISSIONS {
+                                warn!("failed to NOTIFY slave: {}: {}", slave, e);
+                                return future::Either::A(future::ok(Loop::Break(None)));
+                            }
+
+                            // the next NOTIFY is sent one interval after this one, also after
+                            //  errors which are quicker than a timeout
+                            let retry_at = sent + interval;
+                            let retry = future::result(Timeout::new_at(retry_at, &reactor_handle))
+                                .flatten()
+                                .map_err(move |e| warn!("failed to NOTIFY slave: {}: {}", slave, e))
+                                .map(move |()| {
+                                    if current.load(Ordering::Relaxed) == generation {
+                                        Loop::Continue((retransmissions + 1, interval * 2))
+                                    } else {
+                                        // the zone was updated since, a new NOTIFY was sent
+                                        Loop::Break(None)
+                                    }
+                                });
+                            future::Either::B(retry)
+                        })
+                },
+            );
+
+            let zone = origin.clone();
+            self.reactor_handle.spawn(notified.map(move |response| {
+                if let Some(response) = response {
+                    debug!(
+                        "NOTIFY for zone: {} acknowledged by: {} response: {}",
+                        zone,
+                        slave,
+                        response.response_code()
+                    )
+                }
+            }));
+        }
+    }
+
+    // sends one NOTIFY to the slave, and waits for the response up to the interval
+    fn notify(
+        slave: SocketAddr,
+        origin: &Name,
+        soa: &Option<Record>,
+        tsigner: &Option<TSigner>,
+        interval: Duration,
+        reactor_handle: &Handle,
+    ) -> Box<Future<Item = Message, Error = ClientError>> {
+        let (stream, sender) = UdpClientStream::new(slave, reactor_handle);
+        let mut client = match *tsigner {
+            Some(ref tsigner) => ClientFuture::with_finalizer(
+                stream,
+                sender,
+                reactor_handle,
+                interval,
+                Arc::new(tsigner.clone()),
+            ),
+            None => ClientFuture::with_timeout(stream, sender, reactor_handle, interval, None),
+        };
+
+        client.notify(origin.clone(), DNSClass::IN, RecordType::SOA, soa.clone())
+    }
+}
+
+impl Future for NotifyFuture {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            match self.notifications.poll()? {
+                Async::Ready(Some(())) => (),
+                // the Catalog is gone, there will be no more updates
+                Async::Ready(None) => return Ok(Async::Ready(())),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+
+            // multiple updates since the last poll are covered by one NOTIFY of the current SOA
+            loop {
+                match self.notifications.poll()? {
+                    Async::Ready(Some(())) => (),
+                    Async::Ready(None) | Async::NotReady => break,
+                }
+            }
+
+            self.notify_slaves();
+        }
+    }
+}
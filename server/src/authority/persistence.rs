@@ -14,13 +14,14 @@ use time;
 use rusqlite;
 use rusqlite::Connection;
 
-use trust_dns::rr::Record;
+use trust_dns::rr::{Record, RecordType};
 use trust_dns::serialize::binary::{BinDecodable, BinDecoder, BinEncodable, BinEncoder};
 
 use error::{PersistenceErrorKind, PersistenceResult};
+use metrics;
 
 /// The current Journal version of the application
-pub const CURRENT_VERSION: i64 = 1;
+pub const CURRENT_VERSION: i64 = 3;
 
 /// The Journal is the audit log of all changes to a zone after initial creation.
 pub struct Journal {
@@ -80,6 +81,50 @@ impl Journal {
             "schema version mismatch, schema_up() resolves this"
         );
 
+        Self::insert_row(&self.conn, soa_serial, record)
+    }
+
+    /// Inserts a set of records into the Journal, a convenience method for insert_record
+    pub fn insert_records(&self, soa_serial: u32, records: &[Record]) -> PersistenceResult<()> {
+        // TODO: NEED TRANSACTION HERE
+        for record in records {
+            self.insert_record(soa_serial, record)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the records of the Journal with a full dump of the zone, i.e. an AXFR record
+    ///  followed by all records of the zone.
+    ///
+    /// The dump is the starting point to reconstruct the zone, so earlier records are no longer
+    ///  needed. The differences of the zone, see `insert_diff`, are removed as well, as the zone
+    ///  may have changed arbitrarily, e.g. from a zone transfer.
+    ///
+    /// # Arguments
+    ///
+    /// * `soa_serial` - the serial of the zone
+    /// * `records` - all records of the zone, the SOA should come first
+    pub fn insert_zone(&mut self, soa_serial: u32, records: &[Record]) -> PersistenceResult<()> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM records", &[])?;
+        tx.execute("DELETE FROM diffs", &[])?;
+
+        let axfr = Record::new().set_rr_type(RecordType::AXFR).clone();
+        for record in Some(&axfr).into_iter().chain(records) {
+            Self::insert_row(&tx, soa_serial, record)?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_row(conn: &Connection, soa_serial: u32, record: &Record) -> PersistenceResult<()> {
         let mut serial_record: Vec<u8> = Vec::with_capacity(512);
         {
             let mut encoder = BinEncoder::new(&mut serial_record);
@@ -90,33 +135,173 @@ impl Journal {
         let client_id: i64 = 0; // TODO: we need better id information about the client, like pub_key
         let soa_serial: i64 = soa_serial as i64;
 
-        let count = self.conn.execute(
-            "INSERT
-                                          \
-                                            INTO records (client_id, soa_serial, timestamp, \
-                                            record)
-                                          \
-                                            VALUES ($1, $2, $3, $4)",
+        let count = conn.execute(
+            "INSERT INTO records (client_id, soa_serial, timestamp, record)
+                VALUES ($1, $2, $3, $4)",
             &[&client_id, &soa_serial, &timestamp, &serial_record],
         )?;
-        //
         if count != 1 {
             return Err(PersistenceErrorKind::WrongInsertCount(count, 1).into());
         };
 
+        metrics::count_journal_write();
         Ok(())
     }
 
-    /// Inserts a set of records into the Journal, a convenience method for insert_record
-    pub fn insert_records(&self, soa_serial: u32, records: &[Record]) -> PersistenceResult<()> {
-        // TODO: NEED TRANSACTION HERE
-        for record in records {
-            self.insert_record(soa_serial, record)?;
+    /// Inserts the difference sequence of an update of the zone, for incremental zone transfers
+    ///
+    /// # Arguments
+    ///
+    /// * `soa_serial` - the serial of the zone before the update
+    /// * `deleted` - the records removed by the update, the first is the old SOA
+    /// * `added` - the records added by the update, the first is the new SOA
+    pub fn insert_diff(
+        &mut self,
+        soa_serial: u32,
+        deleted: &[Record],
+        added: &[Record],
+    ) -> PersistenceResult<()> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let tx = self.conn.transaction()?;
+        let soa_serial: i64 = soa_serial as i64;
+
+        // a serial could be reused, only the latest difference from it is kept
+        tx.execute("DELETE FROM diffs WHERE soa_serial = $1", &[&soa_serial])?;
+
+        let sections = deleted
+            .iter()
+            .map(|record| (false, record))
+            .chain(added.iter().map(|record| (true, record)));
+        for (is_added, record) in sections {
+            let mut serial_record: Vec<u8> = Vec::with_capacity(512);
+            {
+                let mut encoder = BinEncoder::new(&mut serial_record);
+                record.emit(&mut encoder)?;
+            }
+
+            let count = tx.execute(
+                "INSERT INTO diffs (soa_serial, added, record) VALUES ($1, $2, $3)",
+                &[&soa_serial, &is_added, &serial_record],
+            )?;
+            if count != 1 {
+                return Err(PersistenceErrorKind::WrongInsertCount(count, 1).into());
+            };
         }
 
+        tx.commit()?;
+        metrics::count_journal_write();
+        Ok(())
+    }
+
+    /// Removes the difference sequence from the serial, see `insert_diff`
+    pub fn delete_diff(&self, soa_serial: u32) -> PersistenceResult<()> {
+        let soa_serial: i64 = soa_serial as i64;
+        self.conn
+            .execute("DELETE FROM diffs WHERE soa_serial = $1", &[&soa_serial])?;
         Ok(())
     }
 
+    /// Selects all difference sequences, in the order they were inserted
+    ///
+    /// # Return
+    ///
+    /// The serial before each update, with the deleted and added records, see `insert_diff`
+    pub fn select_diffs(&self) -> PersistenceResult<Vec<(u32, Vec<Record>, Vec<Record>)>> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let mut stmt =
+            self.conn.prepare("SELECT soa_serial, added, record FROM diffs ORDER BY _rowid_")?;
+
+        let rows =
+            stmt.query_and_then(&[], |row| -> Result<(u32, bool, Record), rusqlite::Error> {
+                let soa_serial: i64 = row.get_checked(0)?;
+                let is_added: bool = row.get_checked(1)?;
+                let record_bytes: Vec<u8> = row.get_checked(2)?;
+                let mut decoder = BinDecoder::new(&record_bytes);
+
+                match Record::read(&mut decoder) {
+                    Ok(record) => Ok((soa_serial as u32, is_added, record)),
+                    Err(decode_error) => Err(rusqlite::Error::InvalidParameterName(format!(
+                        "could not decode: {}",
+                        decode_error
+                    ))),
+                }
+            })?;
+
+        let mut diffs: Vec<(u32, Vec<Record>, Vec<Record>)> = Vec::new();
+        for row in rows {
+            let (soa_serial, is_added, record) = row?;
+
+            let is_next = diffs.last().map_or(true, |diff| diff.0 != soa_serial);
+            if is_next {
+                diffs.push((soa_serial, Vec::new(), Vec::new()));
+            }
+
+            let diff = diffs.last_mut().expect("diff was just pushed");
+            if is_added {
+                diff.2.push(record);
+            } else {
+                diff.1.push(record);
+            }
+        }
+
+        Ok(diffs)
+    }
+
+    /// Replaces the time of the last refresh of the zone from its masters, see `select_refresh`
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - seconds since the UNIX epoch
+    pub fn insert_refresh(&mut self, timestamp: u64) -> PersistenceResult<()> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let tx = self.conn.transaction()?;
+        let timestamp: i64 = timestamp as i64;
+
+        tx.execute("DELETE FROM refreshes", &[])?;
+        let count = tx.execute(
+            "INSERT INTO refreshes (timestamp) VALUES ($1)",
+            &[&timestamp],
+        )?;
+        if count != 1 {
+            return Err(PersistenceErrorKind::WrongInsertCount(count, 1).into());
+        };
+
+        tx.commit()?;
+        metrics::count_journal_write();
+        Ok(())
+    }
+
+    /// Selects the time of the last refresh of the zone, in seconds since the UNIX epoch, `None`
+    ///  if the zone was never refreshed, see `insert_refresh`
+    pub fn select_refresh(&self) -> PersistenceResult<Option<u64>> {
+        assert!(
+            self.version == CURRENT_VERSION,
+            "schema version mismatch, schema_up() resolves this"
+        );
+
+        let mut stmt = self.conn.prepare("SELECT timestamp FROM refreshes")?;
+        let timestamp: Option<Result<i64, rusqlite::Error>> =
+            stmt.query_map(&[], |row| row.get(0))?.next();
+
+        match timestamp {
+            Some(Ok(timestamp)) => Ok(Some(timestamp as u64)),
+            Some(Err(err)) => Err(err)?,
+            None => Ok(None),
+        }
+    }
+
     /// Selects a record from the given row_id.
     ///
     /// This allows for the entire set of records to be iterated through, by starting at 0, and
@@ -227,6 +412,8 @@ impl Journal {
             match self.version + 1 {
                 0 => self.version = self.init_up()?,
                 1 => self.version = self.records_up()?,
+                2 => self.version = self.diffs_up()?,
+                3 => self.version = self.refreshes_up()?,
                 _ => panic!("incorrect version somewhere"), // valid panic, non-recoverable state
             }
 
@@ -280,6 +467,36 @@ impl Journal {
 
         Ok(1)
     }
+
+    /// adds the diffs table, the difference sequences of the updates to the zone, indexed by the
+    ///  serial before the update, from which incremental zone transfers are answered
+    fn diffs_up(&self) -> PersistenceResult<i64> {
+        let conn = &self.conn;
+        conn.execute(
+            "CREATE TABLE diffs (
+                soa_serial     INTEGER NOT NULL,
+                added          BOOLEAN NOT NULL,
+                record         BLOB NOT NULL
+            )",
+            &[],
+        )?;
+        conn.execute("CREATE INDEX diffs_soa_serial ON diffs (soa_serial)", &[])?;
+
+        Ok(2)
+    }
+
+    /// adds the refreshes table, the time of the last refresh of a Slave zone from its masters,
+    ///  from which the expiry of the zone continues after a restart
+    fn refreshes_up(&self) -> PersistenceResult<i64> {
+        self.conn.execute(
+            "CREATE TABLE refreshes (
+                timestamp      INTEGER NOT NULL
+            )",
+            &[],
+        )?;
+
+        Ok(3)
+    }
 }
 
 /// Returns an iterator over all items in a Journal
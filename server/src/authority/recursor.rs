@@ -0,0 +1,772 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Recursive resolution from the root hints, for Hint zones
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::sync::oneshot;
+use futures::{future, Async, Future, Poll, Stream};
+use tokio_core::reactor::{CoreId, Handle, Remote};
+
+use trust_dns::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns::rr::dnssec::SupportedAlgorithms;
+use trust_dns::rr::{LowerName, Name, RData, Record, RecordType};
+use trust_dns_proto::DnsHandle;
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::lookup::Lookup;
+use trust_dns_resolver::name_server_pool::{NameServerPool, StandardConnection};
+use trust_dns_resolver::BasicResolverHandle;
+
+use authority::shared_cache::SharedCache;
+use authority::{AuthLookup, Authority};
+
+/// The maximum number of queries sent to other servers to resolve one query, this bounds
+///  referrals, lookups of the addresses of name servers, and CNAMEs which leave a zone.
+const MAX_QUERIES: usize = 16;
+
+// the maximum payload advertised in queries to other servers
+const MAX_PAYLOAD_LEN: u16 = 1232;
+
+// the maximum number of zones whose pools of connections are kept
+const MAX_POOLS: usize = 256;
+
+type Pool = NameServerPool<BasicResolverHandle, StandardConnection>;
+
+// a query to the servers of a zone, and where its response is sent
+type PoolQuery = (
+    Name,
+    Vec<SocketAddr>,
+    Message,
+    oneshot::Sender<Result<Message, ResolveError>>,
+);
+
+/// Resolves queries by iteration, starting from the root hints, with a cache shared between
+///  all of the queries.
+///
+/// [RFC 1034](https://tools.ietf.org/html/rfc1034#section-5.3.3), Domain Names, November 1987
+///
+/// ```text
+/// 5.3.3. Algorithm
+///
+/// The top level algorithm has four steps:
+///
+///    1. See if the answer is in local information, and if so return
+///       it to the client.
+///
+///    2. Find the best servers to ask.
+///
+///    3. Send them queries until one returns a response.
+///
+///    4. Analyze the response, either:
+///
+///         a. if the response answers the question or contains a name
+///            error, cache the data as well as returning it back to
+///            the client.
+///
+///         b. if the response contains a better delegation to other
+///            servers, cache the delegation information, and go to
+///            step 2.
+///
+///         c. if the response shows a CNAME and that is not the
+///            answer itself, cache the CNAME, change the SNAME to the
+///            canonical name in the CNAME RR and go to step 1.
+///
+///         d. if the response shows a servers failure or other
+///            bizarre contents, delete the server from the SLIST and
+///            go back to step 3.
+/// ```
+#[derive(Clone)]
+pub struct Recursor {
+    roots: Vec<SocketAddr>,
+    // the port of the servers whose addresses are learned from referrals
+    port: u16,
+    cache: Arc<SharedCache>,
+    // the pools of the reactor on which queries are sent, see `resolve`
+    pools: UnboundedSender<PoolQuery>,
+    reactors: Arc<RwLock<HashMap<CoreId, UnboundedSender<PoolQuery>>>>,
+    remote: Remote,
+}
+
+impl Recursor {
+    /// Constructs a new Recursor
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - addresses of the root servers, where iteration starts
+    /// * `cache_size` - the maximum number of RRSets in the cache
+    /// * `handle` - the reactor on which the Recursor is constructed, see `remote`
+    pub fn new(roots: Vec<SocketAddr>, cache_size: usize, handle: &Handle) -> Self {
+        let (pools, queries) = unbounded();
+        handle.spawn(ServerPools::new(queries, handle));
+
+        let mut reactors = HashMap::new();
+        reactors.insert(handle.id(), pools.clone());
+
+        Recursor {
+            roots,
+            port: 53,
+            cache: Arc::new(SharedCache::new(cache_size)),
+            pools,
+            reactors: Arc::new(RwLock::new(reactors)),
+            remote: handle.remote().clone(),
+        }
+    }
+
+    /// Constructs a new Recursor with the root servers of a Hint zone
+    ///
+    /// The roots are the addresses of the NS records at the origin of the zone.
+    pub fn from_hints(hints: &Authority, cache_size: usize, handle: &Handle) -> Self {
+        Self::new(Self::roots_from_hints(hints), cache_size, handle)
+    }
+
+    /// The addresses of the root servers of a Hint zone, see `from_hints`
+    pub fn roots_from_hints(hints: &Authority) -> Vec<SocketAddr> {
+        let servers: Vec<Name> = match hints.ns(false, SupportedAlgorithms::new()) {
+            AuthLookup::Records(records) => records
+                .iter()
+                .filter_map(|record| match *record.rdata() {
+                    RData::NS(ref name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut roots = Vec::new();
+        for server in servers {
+            let server = LowerName::new(&server);
+            for rtype in &[RecordType::A, RecordType::AAAA] {
+                if let AuthLookup::Records(records) =
+                    hints.lookup(&server, *rtype, false, SupportedAlgorithms::new())
+                {
+                    roots.extend(
+                        records
+                            .iter()
+                            .filter_map(|record| to_socket_addr(record.rdata(), 53)),
+                    );
+                }
+            }
+        }
+
+        roots
+    }
+
+    /// The addresses of the root servers
+    pub fn roots(&self) -> &[SocketAddr] {
+        &self.roots
+    }
+
+    /// Sets the port on which the servers of the zones below the roots are queried, 53 by
+    ///  default, e.g. for servers which are not on the standard port in tests
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    /// Removes all the records from the cache
+    pub fn flush(&self) {
+        self.cache.clear();
+    }
+
+    /// The reactor on which the Recursor was constructed
+    pub fn remote(&self) -> &Remote {
+        &self.remote
+    }
+
+    /// Resolves the query, from the cache or by iterating from the closest known servers
+    ///
+    /// Each reactor on which queries are resolved has its own pools of connections to the
+    ///  servers, the cache is shared by all of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the query of the client
+    /// * `handle` - the reactor on which the queries to other servers are sent
+    ///
+    /// # Return
+    ///
+    /// The response to the query, i.e. the answers, or the SOA for NXDomain and NoData responses
+    pub fn resolve(
+        &self,
+        query: Query,
+        handle: &Handle,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        self.on_reactor(handle)
+            .iterate(query, Arc::new(AtomicUsize::new(0)))
+    }
+
+    // a clone which sends its queries from the pools of the reactor, they are spawned with the
+    //  first query sent from it
+    fn on_reactor(&self, handle: &Handle) -> Recursor {
+        let pools = self
+            .reactors
+            .read()
+            .expect("reactors lock poisoned")
+            .get(&handle.id())
+            .cloned();
+        let pools = pools.unwrap_or_else(|| {
+            self.reactors
+                .write()
+                .expect("reactors lock poisoned")
+                .entry(handle.id())
+                .or_insert_with(|| {
+                    let (pools, queries) = unbounded();
+                    handle.spawn(ServerPools::new(queries, handle));
+                    pools
+                })
+                .clone()
+        });
+
+        Recursor {
+            pools,
+            ..self.clone()
+        }
+    }
+
+    fn iterate(
+        &self,
+        query: Query,
+        queries: Arc<AtomicUsize>,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        if let Some(response) = self.cached(&query) {
+            debug!("cached: {} {:?}", query.name(), query.query_type());
+            return Box::new(future::ok(response));
+        }
+
+        let (zone, servers) = self.closest_servers(query.name());
+        self.query_servers(zone, servers, query, queries)
+    }
+
+    /// Returns the response from the cache, following CNAMEs
+    fn cached(&self, query: &Query) -> Option<Message> {
+        let now = Instant::now();
+        let mut answers = Vec::new();
+        let mut name = query.name().clone();
+
+        for _ in 0..MAX_QUERIES {
+            // each stripe of the cache is locked only for the lookup of one name
+            let search = Query::query(name.clone(), query.query_type());
+            let cached = self.cache.lock(&name).get_with_ttl(&search, now);
+            match cached {
+                Some((Some(ref lookup), ttl)) if lookup.iter().next().is_some() => {
+                    answers.extend(to_records(&name, ttl, query.query_type(), lookup));
+                    return Some(to_response(ResponseCode::NoError, answers, None));
+                }
+                // negative answers have the SOA of the zone, without it the query is resolved again
+                Some((Some(_), ttl)) => {
+                    return cached_soa(&self.cache, &name, ttl, now)
+                        .map(|soa| to_response(ResponseCode::NoError, answers, Some(soa)))
+                }
+                Some((None, ttl)) => {
+                    return cached_soa(&self.cache, &name, ttl, now)
+                        .map(|soa| to_response(ResponseCode::NXDomain, answers, Some(soa)))
+                }
+                None if query.query_type() == RecordType::CNAME => return None,
+                None => (),
+            }
+
+            let cname = Query::query(name.clone(), RecordType::CNAME);
+            let cached = self.cache.lock(&name).get_with_ttl(&cname, now);
+            let (lookup, ttl) = match cached {
+                Some((Some(lookup), ttl)) => (lookup, ttl),
+                _ => return None,
+            };
+            let target = match lookup.iter().next() {
+                Some(&RData::CNAME(ref target)) => target.clone(),
+                _ => return None,
+            };
+
+            answers.push(Record::from_rdata(
+                name,
+                ttl,
+                RecordType::CNAME,
+                RData::CNAME(target.clone()),
+            ));
+            name = target;
+        }
+
+        None
+    }
+
+    /// Returns the cached servers of the zone closest to the name, or the roots
+    fn closest_servers(&self, name: &Name) -> (Name, Vec<SocketAddr>) {
+        let mut zone = name.clone();
+
+        loop {
+            let servers = self.cached_servers(&zone);
+            if !servers.is_empty() {
+                return (zone, servers);
+            }
+
+            if zone.is_root() {
+                return (Name::root(), self.roots.clone());
+            }
+            zone = zone.base_name();
+        }
+    }
+
+    /// Returns the cached addresses of the NS of the zone
+    fn cached_servers(&self, zone: &Name) -> Vec<SocketAddr> {
+        let now = Instant::now();
+
+        let ns = self
+            .cache
+            .lock(zone)
+            .get(&Query::query(zone.clone(), RecordType::NS), now);
+        let ns = match ns {
+            Some(ns) => ns,
+            None => return vec![],
+        };
+
+        let mut servers = Vec::new();
+        for rdata in ns.iter() {
+            if let RData::NS(ref server) = *rdata {
+                for rtype in &[RecordType::A, RecordType::AAAA] {
+                    let addresses = self
+                        .cache
+                        .lock(server)
+                        .get(&Query::query(server.clone(), *rtype), now);
+                    if let Some(addresses) = addresses {
+                        servers.extend(
+                            addresses
+                                .iter()
+                                .filter_map(|rdata| to_socket_addr(rdata, self.port)),
+                        );
+                    }
+                }
+            }
+        }
+
+        servers
+    }
+
+    fn query_servers(
+        &self,
+        zone: Name,
+        servers: Vec<SocketAddr>,
+        query: Query,
+        queries: Arc<AtomicUsize>,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        if queries.fetch_add(1, Ordering::Relaxed) >= MAX_QUERIES {
+            return Box::new(future::err(ResolveError::from(ResolveErrorKind::Message(
+                "too many queries to resolve the name",
+            ))));
+        }
+
+        debug!(
+            "querying {} for {} {:?}: {:?}",
+            zone,
+            query.name(),
+            query.query_type(),
+            servers
+        );
+
+        let mut message = Message::new();
+        message.add_query(query.clone());
+        message
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false);
+        {
+            let edns = message.edns_mut();
+            edns.set_max_payload(MAX_PAYLOAD_LEN);
+            edns.set_version(0);
+        }
+
+        let (complete, response) = oneshot::channel();
+        if self
+            .pools
+            .unbounded_send((zone.clone(), servers, message, complete))
+            .is_err()
+        {
+            return Box::new(future::err(ResolveError::from(ResolveErrorKind::Message(
+                "the reactor of the recursor is gone",
+            ))));
+        }
+
+        let recursor = self.clone();
+        Box::new(
+            response
+                .then(|response| match response {
+                    Ok(response) => response,
+                    Err(_) => Err(ResolveError::from(ResolveErrorKind::Message(
+                        "the query to the servers was canceled",
+                    ))),
+                })
+                .and_then(move |response| recursor.handle_response(zone, query, response, queries)),
+        )
+    }
+
+    /// Caches the response, and follows referrals and CNAMEs
+    fn handle_response(
+        &self,
+        zone: Name,
+        query: Query,
+        response: Message,
+        queries: Arc<AtomicUsize>,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        match response.response_code() {
+            ResponseCode::NoError => (),
+            ResponseCode::NXDomain => {
+                let ttl = negative_ttl(&response);
+                self.cache_soa(&zone, &query, &response);
+                self.cache
+                    .lock(query.name())
+                    .negative(query, ttl, Instant::now());
+                return Box::new(future::ok(response));
+            }
+            response_code => {
+                return Box::new(future::err(ResolveError::from(format!(
+                    "servers of {} responded: {}",
+                    zone, response_code
+                ))))
+            }
+        }
+
+        // only records for names in the zone of the servers are trusted
+        if !response.answers().is_empty() {
+            self.cache_records(&zone, response.answers());
+            return self.follow_cname(query, response, queries);
+        }
+
+        // a referral to the servers of a zone closer to the name
+        let cut = response
+            .name_servers()
+            .iter()
+            .find(|record| record.rr_type() == RecordType::NS)
+            .map(|record| record.name().clone());
+
+        match cut {
+            Some(ref cut) if *cut != zone && zone.zone_of(cut) && cut.zone_of(query.name()) => {
+                self.referral(zone, cut.clone(), query, response, queries)
+            }
+            _ if response.authoritative() => {
+                let ttl = negative_ttl(&response);
+                self.cache_soa(&zone, &query, &response);
+                self.cache.lock(query.name()).duplicate(
+                    query,
+                    Lookup::new(Arc::new(vec![])),
+                    ttl,
+                    Instant::now(),
+                );
+                Box::new(future::ok(response))
+            }
+            _ => Box::new(future::err(ResolveError::from(format!(
+                "lame response from the servers of {}",
+                zone
+            )))),
+        }
+    }
+
+    /// Caches the NS and glue of the referral, and continues with the servers of the cut
+    fn referral(
+        &self,
+        zone: Name,
+        cut: Name,
+        query: Query,
+        response: Message,
+        queries: Arc<AtomicUsize>,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        let ns: Vec<&Record> = response
+            .name_servers()
+            .iter()
+            .filter(|record| record.rr_type() == RecordType::NS && *record.name() == cut)
+            .collect();
+        let glue: Vec<&Record> = response
+            .additionals()
+            .iter()
+            .filter(|record| {
+                (record.rr_type() == RecordType::A || record.rr_type() == RecordType::AAAA)
+                    && ns.iter().any(|ns| match *ns.rdata() {
+                        RData::NS(ref server) => server == record.name(),
+                        _ => false,
+                    })
+            })
+            .collect();
+
+        self.cache_records(&zone, ns);
+        self.cache_records(&zone, glue);
+
+        let servers = self.cached_servers(&cut);
+        if !servers.is_empty() {
+            return self.query_servers(cut, servers, query, queries);
+        }
+
+        // without glue, the address of one of the servers must be resolved first
+        let server = match response
+            .name_servers()
+            .iter()
+            .filter_map(|record| match *record.rdata() {
+                RData::NS(ref server) if !cut.zone_of(server) => Some(server.clone()),
+                _ => None,
+            })
+            .next()
+        {
+            Some(server) => server,
+            None => {
+                return Box::new(future::err(ResolveError::from(format!(
+                    "no addresses for the servers of {}",
+                    cut
+                ))))
+            }
+        };
+
+        let recursor = self.clone();
+        Box::new(
+            self.iterate(Query::query(server, RecordType::A), queries.clone())
+                .and_then(
+                    move |_| -> Box<Future<Item = Message, Error = ResolveError>> {
+                        let servers = recursor.cached_servers(&cut);
+                        if servers.is_empty() {
+                            return Box::new(future::err(ResolveError::from(format!(
+                                "no addresses for the servers of {}",
+                                cut
+                            ))));
+                        }
+
+                        recursor.query_servers(cut, servers, query, queries)
+                    },
+                ),
+        )
+    }
+
+    /// Resolves the target of a CNAME which is not answered in the response
+    fn follow_cname(
+        &self,
+        query: Query,
+        mut response: Message,
+        queries: Arc<AtomicUsize>,
+    ) -> Box<Future<Item = Message, Error = ResolveError>> {
+        if query.query_type() == RecordType::CNAME || query.query_type() == RecordType::ANY {
+            return Box::new(future::ok(response));
+        }
+
+        // the end of the chain of CNAMEs in the answers
+        let mut name = query.name().clone();
+        for _ in 0..response.answers().len() {
+            let target = response
+                .answers()
+                .iter()
+                .filter(|record| *record.name() == name)
+                .filter_map(|record| match *record.rdata() {
+                    RData::CNAME(ref target) => Some(target.clone()),
+                    _ => None,
+                })
+                .next();
+
+            match target {
+                Some(target) => name = target,
+                None => break,
+            }
+        }
+
+        let is_answered = response
+            .answers()
+            .iter()
+            .any(|record| *record.name() == name && record.rr_type() == query.query_type());
+        if is_answered || name == *query.name() {
+            return Box::new(future::ok(response));
+        }
+
+        let chain = response.take_answers();
+        Box::new(
+            self.iterate(Query::query(name, query.query_type()), queries)
+                .map(move |mut target| {
+                    let mut answers = chain;
+                    answers.extend(target.take_answers());
+                    target.insert_answers(answers);
+                    target
+                }),
+        )
+    }
+
+    /// Caches the records in the zone as RRSets, each expiring after its TTL
+    fn cache_records<'r, I: IntoIterator<Item = &'r Record>>(&self, zone: &Name, records: I) {
+        let mut rrsets: HashMap<Query, Vec<(RData, u32)>> = HashMap::new();
+        for record in records {
+            if !zone.zone_of(record.name()) {
+                debug!("ignoring record outside of {}: {}", zone, record.name());
+                continue;
+            }
+
+            rrsets
+                .entry(Query::query(record.name().clone(), record.rr_type()))
+                .or_insert_with(Vec::new)
+                .push((record.rdata().clone(), record.ttl()));
+        }
+
+        let now = Instant::now();
+        for (query, rdatas) in rrsets {
+            self.cache.lock(query.name()).insert(query, rdatas, now);
+        }
+    }
+
+    /// Caches the SOA of a negative response, it is returned with the cached negative answer
+    fn cache_soa(&self, zone: &Name, query: &Query, response: &Message) {
+        let soa = response.name_servers().iter().filter(|record| {
+            record.rr_type() == RecordType::SOA && record.name().zone_of(query.name())
+        });
+        self.cache_records(zone, soa);
+    }
+}
+
+/// The pools of connections to the servers of each zone, of one reactor
+///
+/// The pools are not `Send`, the queries of the Recursor on the reactor are sent to the servers
+///  from this future.
+///  The pool of a zone is replaced when its servers change, and the pools of other zones are
+///  dropped once there are `MAX_POOLS`.
+struct ServerPools {
+    pools: HashMap<Name, (Vec<SocketAddr>, Pool)>,
+    queries: UnboundedReceiver<PoolQuery>,
+    options: ResolverOpts,
+    handle: Handle,
+}
+
+impl ServerPools {
+    fn new(queries: UnboundedReceiver<PoolQuery>, handle: &Handle) -> Self {
+        ServerPools {
+            pools: HashMap::new(),
+            queries,
+            options: ResolverOpts::default(),
+            handle: handle.clone(),
+        }
+    }
+
+    fn pool(&mut self, zone: Name, servers: Vec<SocketAddr>) -> &mut Pool {
+        let is_current = self
+            .pools
+            .get(&zone)
+            .map_or(false, |&(ref current, _)| *current == servers);
+        if !is_current {
+            if self.pools.len() >= MAX_POOLS && !self.pools.contains_key(&zone) {
+                self.pools.clear();
+            }
+
+            let mut config = ResolverConfig::new();
+            for server in &servers {
+                config.add_name_server(NameServerConfig {
+                    socket_addr: *server,
+                    protocol: Protocol::Udp,
+                });
+                config.add_name_server(NameServerConfig {
+                    socket_addr: *server,
+                    protocol: Protocol::Tcp,
+                });
+            }
+
+            let pool = Pool::from_config(&config, &self.options, &self.handle);
+            self.pools.insert(zone.clone(), (servers, pool));
+        }
+
+        &mut self.pools.get_mut(&zone).expect("pool was just inserted").1
+    }
+}
+
+impl Future for ServerPools {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            match try_ready!(self.queries.poll()) {
+                Some((zone, servers, message, complete)) => {
+                    let response = self.pool(zone, servers).send(message);
+                    self.handle.spawn(response.then(move |response| {
+                        // the query was abandoned if the receiver is gone
+                        complete.send(response).ok();
+                        Ok(())
+                    }));
+                }
+                // all the Recursors are dropped
+                None => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
+
+fn to_socket_addr(rdata: &RData, port: u16) -> Option<SocketAddr> {
+    match *rdata {
+        RData::A(ip) => Some(SocketAddr::new(IpAddr::V4(ip), port)),
+        RData::AAAA(ip) => Some(SocketAddr::new(IpAddr::V6(ip), port)),
+        _ => None,
+    }
+}
+
+fn to_records(name: &Name, ttl: u32, rtype: RecordType, lookup: &Lookup) -> Vec<Record> {
+    lookup
+        .iter()
+        .map(|rdata| Record::from_rdata(name.clone(), ttl, rtype, rdata.clone()))
+        .collect()
+}
+
+fn to_response(response_code: ResponseCode, answers: Vec<Record>, soa: Option<Record>) -> Message {
+    let mut response = Message::new();
+    response
+        .set_message_type(MessageType::Response)
+        .set_response_code(response_code);
+    response.insert_answers(answers);
+    if let Some(soa) = soa {
+        response.add_name_server(soa);
+    }
+    response
+}
+
+/// Returns the cached SOA of the closest zone of the name, with the TTL of the negative answer
+fn cached_soa(cache: &SharedCache, name: &Name, ttl: u32, now: Instant) -> Option<Record> {
+    let mut zone = name.clone();
+    loop {
+        let lookup = cache
+            .lock(&zone)
+            .get(&Query::query(zone.clone(), RecordType::SOA), now);
+        if let Some(lookup) = lookup {
+            if let Some(&RData::SOA(ref soa)) = lookup.iter().next() {
+                return Some(Record::from_rdata(
+                    zone,
+                    ttl,
+                    RecordType::SOA,
+                    RData::SOA(soa.clone()),
+                ));
+            }
+        }
+
+        if zone.is_root() {
+            return None;
+        }
+        zone = zone.base_name();
+    }
+}
+
+/// [RFC 2308](https://tools.ietf.org/html/rfc2308#section-5), Negative Caching, March 1998
+///
+/// ```text
+/// 5 - Caching Negative Answers
+///
+///    Like normal answers negative answers have a time to live (TTL).  As
+///    there is no record in the answer section to which this TTL can be
+///    applied, the TTL must be carried by another method.  This is done by
+///    including the SOA record from the zone in the authority section of
+///    the reply.  When the authoritative server creates this record its TTL
+///    is taken from the minimum of the SOA.MINIMUM field and SOA's TTL.
+/// ```
+fn negative_ttl(response: &Message) -> u32 {
+    response
+        .name_servers()
+        .iter()
+        .filter_map(|record| match *record.rdata() {
+            RData::SOA(ref soa) => Some(soa.minimum().min(record.ttl())),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(0)
+}
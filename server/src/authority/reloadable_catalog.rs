@@ -0,0 +1,65 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A Catalog which is replaced while the server is running, on reload of the configuration
+
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use authority::Catalog;
+use server::{Request, RequestContext, RequestHandler, ResponseHandler};
+
+/// Handles requests with the current `Catalog`, which can be replaced at any time
+///
+/// Clones share the `Catalog`, one is given to the `ServerFuture` and another is kept to replace
+///  the `Catalog`, e.g. on reload of the configuration. The listening sockets are unaffected by
+///  the replacement, requests which are already being handled complete with the previous
+///  `Catalog`.
+#[derive(Clone)]
+pub struct ReloadableCatalog {
+    catalog: Arc<RwLock<Arc<Catalog>>>,
+}
+
+impl ReloadableCatalog {
+    /// Constructs a new ReloadableCatalog, which handles requests with the `Catalog`
+    pub fn new(catalog: Catalog) -> Self {
+        ReloadableCatalog {
+            catalog: Arc::new(RwLock::new(Arc::new(catalog))),
+        }
+    }
+
+    /// Returns the current `Catalog`
+    pub fn catalog(&self) -> Arc<Catalog> {
+        self.catalog.read().unwrap().clone() // poison errors should panic
+    }
+
+    /// Handles all subsequent requests with the `Catalog`, returning the one it replaces
+    pub fn replace(&self, catalog: Catalog) -> Arc<Catalog> {
+        let mut current = self.catalog.write().unwrap(); // poison errors should panic
+        ::std::mem::replace(&mut *current, Arc::new(catalog))
+    }
+}
+
+impl RequestHandler for ReloadableCatalog {
+    fn handle_request<'q, 'a, R: ResponseHandler + 'static>(
+        &'a self,
+        request: &'q Request,
+        response_handle: R,
+    ) -> io::Result<()> {
+        self.catalog().handle_request(request, response_handle)
+    }
+
+    fn handle_request_with_context<'q, 'a, R: ResponseHandler + 'static>(
+        &'a self,
+        request: &'q Request,
+        context: &RequestContext,
+        response_handle: R,
+    ) -> io::Result<()> {
+        self.catalog()
+            .handle_request_with_context(request, context, response_handle)
+    }
+}
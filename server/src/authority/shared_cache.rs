@@ -0,0 +1,79 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A cache of resolved RRSets, or responses, shared by the reactors of all the workers
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use trust_dns::rr::Name;
+use trust_dns_resolver::dns_lru::DnsLru;
+
+// the maximum number of stripes of the cache
+const STRIPES: usize = 16;
+
+// caches smaller than this many entries per stripe have fewer stripes
+const MIN_STRIPE_LEN: usize = 64;
+
+/// One stripe of a `SharedCache`, each with its own least recently used entries
+pub(crate) trait Stripe {
+    /// Constructs a stripe of at most `capacity` entries
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Removes all the entries
+    fn clear(&mut self);
+}
+
+impl Stripe for DnsLru {
+    fn with_capacity(capacity: usize) -> Self {
+        DnsLru::new(capacity)
+    }
+
+    fn clear(&mut self) {
+        DnsLru::clear(self)
+    }
+}
+
+/// A cache in stripes, each with a lock of its own, so that the workers resolving queries for
+///  different names rarely wait on one another, by default a `DnsLru` of RRSets
+///
+/// All the entries of a name are in the stripe of the name, by its hash, the least recently used
+///  are dropped from each stripe separately.
+pub(crate) struct SharedCache<C = DnsLru> {
+    stripes: Vec<Mutex<C>>,
+}
+
+impl<C: Stripe> SharedCache<C> {
+    /// Constructs a cache of at least `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        let stripes = (capacity / MIN_STRIPE_LEN).max(1).min(STRIPES);
+        let stripe_len = (capacity + stripes - 1) / stripes;
+
+        SharedCache {
+            stripes: (0..stripes).map(|_| Mutex::new(C::with_capacity(stripe_len))).collect(),
+        }
+    }
+
+    /// Locks the stripe of the name
+    ///
+    /// The lock is held until the guard is dropped, no other stripe may be locked meanwhile.
+    pub fn lock(&self, name: &Name) -> MutexGuard<C> {
+        let mut hasher = DefaultHasher::new();
+        name.to_lowercase().hash(&mut hasher);
+        let stripe = hasher.finish() as usize % self.stripes.len();
+
+        self.stripes[stripe].lock().expect("cache lock poisoned")
+    }
+
+    /// Removes all the entries, one stripe at a time
+    pub fn clear(&self) {
+        for stripe in &self.stripes {
+            stripe.lock().expect("cache lock poisoned").clear();
+        }
+    }
+}
@@ -0,0 +1,686 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Zone transfers for Slave zones
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::sync::mpsc::UnboundedReceiver;
+use futures::{future, Async, Future, Poll, Stream};
+use rand;
+use tokio_core::net::TcpStream as TokioTcpStream;
+use tokio_core::reactor::{Handle, Timeout};
+
+use trust_dns::error::{ClientError, ClientErrorKind};
+use trust_dns::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns::rr::dnssec::TSigner;
+use trust_dns::rr::rdata::{DNSSECRData, DNSSECRecordType};
+use trust_dns::rr::{Name, RData, Record, RecordType};
+use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
+use trust_dns::tcp::TcpClientStream;
+use trust_dns_proto::DnsStreamHandle;
+
+use authority::{Authority, MessageRequest};
+
+// used until the zone has been transferred for the first time, and the SOA timers are known
+const DEFAULT_REFRESH: u64 = 3600;
+const DEFAULT_RETRY: u64 = 60;
+const DEFAULT_EXPIRE: u64 = 604800;
+
+// the time allowed for each message of a response from the master, a transfer of many messages
+//  may take longer in total
+const QUERY_TIMEOUT: u64 = 60;
+
+// RFC 8945, up to 99 messages of a response may follow a signed one without a TSIG
+const MAX_UNSIGNED_MESSAGES: usize = 99;
+
+/// A Future which keeps a Slave zone in sync with its masters.
+///
+/// The SOA of the zone is checked against the masters, in order, every SOA refresh interval. If
+///  the serial of a master is greater than the local one, the zone is transferred with AXFR over
+///  TCP. Failed attempts are retried after the SOA retry interval, and if no master could be
+///  reached for the SOA expire interval, the zone is marked as expired and will no longer be
+///  answered authoritatively. The time of the last refresh is kept with the zone, see
+///  `Authority::set_refreshed`, so a zone recovered from its journal expires in time.
+///
+/// A NOTIFY from a master, received by the `Catalog`, starts a refresh immediately. Once no
+///  `Catalog` serves the zone, i.e. all the senders of the notifications are dropped, the
+///  refreshes stop.
+///
+/// The transfer may span many messages, the records are collected up to the closing SOA. If the
+///  zone has TSIG keys, see `Authority::add_tsig_key`, the queries to the masters are signed with
+///  the first of them, and responses which do not verify with it are rejected.
+#[must_use = "futures do nothing unless polled"]
+pub struct SlaveFuture {
+    origin: Name,
+    authority: Arc<RwLock<Authority>>,
+    masters: Vec<SocketAddr>,
+    notifications: Option<UnboundedReceiver<Refresh>>,
+    reactor_handle: Handle,
+    last_refresh: SystemTime,
+    state: SlaveState,
+}
+
+// the result of a refresh, `None` if the zone is already up to date
+type RefreshFuture = Box<Future<Item = Option<Vec<Record>>, Error = ClientError>>;
+
+/// Signals a `SlaveFuture` to refresh its zone, see `Catalog::set_refresh`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Refresh {
+    /// a NOTIFY was received from a master, the zone is transferred if the master has a newer
+    ///  serial
+    Notify,
+    /// the zone is transferred regardless of the serial, e.g. on request of the operator
+    Retransfer,
+}
+
+enum SlaveState {
+    Waiting(Timeout),
+    Refreshing(RefreshFuture),
+}
+
+impl SlaveFuture {
+    /// Returns a new SlaveFuture, the first refresh from the masters is started immediately.
+    ///
+    /// A zone which was recovered, e.g. from its journal, is only transferred if a master has a
+    ///  newer serial, and is expired if it was last refreshed longer ago than the SOA expire.
+    ///
+    /// # Arguments
+    ///
+    /// * `authority` - the Slave zone to keep in sync, this should be shared with the `Catalog`
+    /// * `masters` - addresses of the master servers for the zone, tried in order
+    /// * `notifications` - signaled on NOTIFY from a master, see `Catalog::set_refresh`
+    /// * `reactor_handle` - reactor used for connecting to the masters and the refresh timers
+    pub fn new(
+        authority: Arc<RwLock<Authority>>,
+        masters: Vec<SocketAddr>,
+        notifications: UnboundedReceiver<Refresh>,
+        reactor_handle: &Handle,
+    ) -> Self {
+        let (origin, last_refresh) = {
+            let authority = authority.read().unwrap(); // poison errors should panic
+            let origin: Name = authority.origin().clone().into();
+            (origin, authority.refreshed().unwrap_or_else(SystemTime::now))
+        };
+
+        let timers = Self::soa_timers(&authority);
+        let tsigner = Self::tsigner(&authority);
+        let state = SlaveState::Refreshing(Self::refresh(
+            origin.clone(),
+            &masters,
+            timers.map(|(serial, _, _, _)| serial),
+            tsigner,
+            reactor_handle,
+        ));
+
+        let slave = SlaveFuture {
+            origin,
+            authority,
+            masters,
+            notifications: Some(notifications),
+            reactor_handle: reactor_handle.clone(),
+            last_refresh,
+            state,
+        };
+
+        if let Some((_, _, _, expire)) = timers {
+            slave.expire_if_stale(expire);
+        }
+        slave
+    }
+
+    /// Marks the zone as expired if it was not refreshed within the expire interval
+    fn expire_if_stale(&self, expire: Duration) {
+        // a clock which went back is not counted against the zone
+        let is_stale = self
+            .last_refresh
+            .elapsed()
+            .map_or(false, |elapsed| elapsed >= expire);
+        if !is_stale {
+            return;
+        }
+
+        let mut authority = self.authority.write().unwrap(); // poison errors should panic
+        if !authority.is_expired() {
+            error!("zone expired: {}", self.origin);
+            authority.set_expired(true);
+        }
+    }
+
+    /// Returns the key the queries to the masters are signed with, if the zone has any
+    fn tsigner(authority: &RwLock<Authority>) -> Option<TSigner> {
+        authority
+            .read()
+            .unwrap() // poison errors should panic
+            .tsig_keys()
+            .first()
+            .cloned()
+    }
+
+    /// Returns the (serial, refresh, retry, expire) of the local zone, if it has an SOA
+    fn soa_timers(authority: &RwLock<Authority>) -> Option<(u32, Duration, Duration, Duration)> {
+        let authority = authority.read().unwrap(); // poison errors should panic
+        let timers = authority.soa().iter().next().and_then(|soa| {
+            if let RData::SOA(ref soa) = *soa.rdata() {
+                Some((
+                    soa.serial(),
+                    Duration::from_secs(soa.refresh().max(1) as u64),
+                    Duration::from_secs(soa.retry().max(1) as u64),
+                    Duration::from_secs(soa.expire().max(1) as u64),
+                ))
+            } else {
+                None
+            }
+        });
+
+        timers
+    }
+
+    /// Checks each master in order, stopping at the first one which succeeds
+    fn refresh(
+        origin: Name,
+        masters: &[SocketAddr],
+        serial: Option<u32>,
+        tsigner: Option<TSigner>,
+        reactor_handle: &Handle,
+    ) -> RefreshFuture {
+        let mut refresh: RefreshFuture = Box::new(future::err(ClientError::from(
+            ClientErrorKind::Message("no masters configured"),
+        )));
+
+        for master in masters.iter().cloned() {
+            let origin = origin.clone();
+            let tsigner = tsigner.clone();
+            let reactor_handle = reactor_handle.clone();
+
+            refresh = Box::new(refresh.or_else(move |e| {
+                debug!(
+                    "trying master: {} for: {}, previous error: {}",
+                    master, origin, e
+                );
+                Self::refresh_from(master, origin, serial, tsigner, &reactor_handle)
+            }));
+        }
+
+        refresh
+    }
+
+    /// Queries the SOA of the master, and if the serial is newer than `serial` transfers the zone
+    ///
+    /// Returns `None` if the local zone is up to date.
+    fn refresh_from(
+        master: SocketAddr,
+        origin: Name,
+        serial: Option<u32>,
+        tsigner: Option<TSigner>,
+        reactor_handle: &Handle,
+    ) -> RefreshFuture {
+        let axfr_handle = reactor_handle.clone();
+
+        Box::new(
+            TransferFuture::query(
+                master,
+                origin.clone(),
+                RecordType::SOA,
+                tsigner.clone(),
+                reactor_handle,
+            )
+            .and_then(move |answers| -> RefreshFuture {
+                let master_serial = answers
+                    .iter()
+                    .filter_map(|record| {
+                        if let RData::SOA(ref soa) = *record.rdata() {
+                            Some(soa.serial())
+                        } else {
+                            None
+                        }
+                    })
+                    .next();
+
+                let master_serial = match master_serial {
+                    Some(master_serial) => master_serial,
+                    None => {
+                        return Box::new(future::err(ClientError::from(format!(
+                            "no SOA from master: {}",
+                            master
+                        ))))
+                    }
+                };
+
+                if let Some(serial) = serial {
+                    if !serial_gt(master_serial, serial) {
+                        debug!(
+                            "zone: {} is up to date with master: {} at serial: {}",
+                            origin, master, serial
+                        );
+                        return Box::new(future::ok(None));
+                    }
+                }
+
+                info!(
+                    "transferring zone: {} from master: {} at serial: {}",
+                    origin, master, master_serial
+                );
+                Box::new(
+                    TransferFuture::query(master, origin, RecordType::AXFR, tsigner, &axfr_handle)
+                        .map(|mut records| {
+                            // the closing SOA
+                            records.pop();
+                            Some(records)
+                        }),
+                )
+            }),
+        )
+    }
+
+    /// Returns the refresh which was signaled, if any, all pending notifications are consumed and
+    ///  a retransfer takes precedence
+    fn poll_notified(&mut self) -> Option<Refresh> {
+        let mut notified = None;
+
+        loop {
+            let polled = match self.notifications {
+                Some(ref mut notifications) => notifications.poll(),
+                None => break,
+            };
+
+            match polled {
+                Ok(Async::Ready(Some(refresh))) => {
+                    if notified != Some(Refresh::Retransfer) {
+                        notified = Some(refresh);
+                    }
+                }
+                Ok(Async::NotReady) => break,
+                // the zone is no longer served, e.g. it was removed on reload
+                Ok(Async::Ready(None)) | Err(()) => self.notifications = None,
+            }
+        }
+
+        notified
+    }
+
+    fn wait_for(&self, duration: Duration) -> Result<SlaveState, ()> {
+        debug!("next refresh of zone: {} in {:?}", self.origin, duration);
+        Timeout::new(duration, &self.reactor_handle)
+            .map(SlaveState::Waiting)
+            .map_err(|e| error!("could not create refresh timer: {}", e))
+    }
+}
+
+impl Future for SlaveFuture {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            // a NOTIFY cuts the wait short, during a refresh it is left queued for the next one
+            let is_waiting = match self.state {
+                SlaveState::Waiting(..) => true,
+                SlaveState::Refreshing(..) => false,
+            };
+            let notified = if is_waiting {
+                self.poll_notified()
+            } else {
+                None
+            };
+            if is_waiting && self.notifications.is_none() {
+                info!("zone no longer served, stopping refresh: {}", self.origin);
+                return Ok(Async::Ready(()));
+            }
+
+            let refreshed = match self.state {
+                SlaveState::Waiting(_) if notified == Some(Refresh::Notify) => {
+                    info!("refreshing zone on NOTIFY: {}", self.origin);
+                    None
+                }
+                SlaveState::Waiting(_) if notified == Some(Refresh::Retransfer) => {
+                    info!("retransferring zone: {}", self.origin);
+                    None
+                }
+                SlaveState::Waiting(ref mut timeout) => match timeout.poll() {
+                    Ok(Async::Ready(())) => None,
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => {
+                        error!("error in refresh timer: {}", e);
+                        return Err(());
+                    }
+                },
+                SlaveState::Refreshing(ref mut refresh) => match refresh.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(records)) => Some(Ok(records)),
+                    Err(e) => Some(Err(e)),
+                },
+            };
+
+            let timers = Self::soa_timers(&self.authority);
+            let (serial, refresh, retry, expire) = match timers {
+                Some((serial, refresh, retry, expire)) => (Some(serial), refresh, retry, expire),
+                None => (
+                    None,
+                    Duration::from_secs(DEFAULT_REFRESH),
+                    Duration::from_secs(DEFAULT_RETRY),
+                    Duration::from_secs(DEFAULT_EXPIRE),
+                ),
+            };
+
+            self.state = match refreshed {
+                // the timer fired, or a NOTIFY was received, start the next refresh
+                // without a serial the zone is transferred, whatever the serial of the master
+                None => SlaveState::Refreshing(Self::refresh(
+                    self.origin.clone(),
+                    &self.masters,
+                    if notified == Some(Refresh::Retransfer) {
+                        None
+                    } else {
+                        serial
+                    },
+                    Self::tsigner(&self.authority),
+                    &self.reactor_handle,
+                )),
+                Some(Ok(records)) => {
+                    self.last_refresh = SystemTime::now();
+                    let mut authority = self.authority.write().unwrap(); // poison errors should panic
+
+                    if let Some(records) = records {
+                        if let Err(e) = authority.transfer(records) {
+                            error!("error persisting transfer of zone: {}: {}", self.origin, e);
+                        }
+                    } else {
+                        authority.set_expired(false);
+                    }
+                    if let Err(e) = authority.set_refreshed(self.last_refresh) {
+                        error!("error persisting refresh of zone: {}: {}", self.origin, e);
+                    }
+                    drop(authority);
+
+                    // the SOA may have changed with the transfer
+                    let refresh = Self::soa_timers(&self.authority)
+                        .map_or(refresh, |(_, refresh, _, _)| refresh);
+                    self.wait_for(refresh)?
+                }
+                Some(Err(e)) => {
+                    warn!("failed to refresh zone: {}: {}", self.origin, e);
+                    self.expire_if_stale(expire);
+
+                    self.wait_for(retry)?
+                }
+            };
+        }
+    }
+}
+
+/// A query to a master over TCP, collecting the answers of all the messages of the response
+///
+/// The response to an SOA query is a single message, an AXFR is complete with the closing SOA.
+#[must_use = "futures do nothing unless polled"]
+struct TransferFuture {
+    master: SocketAddr,
+    id: u16,
+    query_type: RecordType,
+    stream: Box<Future<Item = TcpClientStream<TokioTcpStream>, Error = io::Error>>,
+    connected: Option<TcpClientStream<TokioTcpStream>>,
+    // the stream is closed once the sender is dropped
+    _sender: Box<DnsStreamHandle<Error = ClientError>>,
+    // the key, and the MAC of the last message which was signed
+    tsig: Option<(TSigner, Vec<u8>)>,
+    // the messages received since the last one which was signed
+    unsigned_messages: Vec<u8>,
+    unsigned_count: usize,
+    answers: Vec<Record>,
+    timeout: Timeout,
+}
+
+impl TransferFuture {
+    /// Sends the query to the master, signed with the key if there is one
+    fn query(
+        master: SocketAddr,
+        origin: Name,
+        query_type: RecordType,
+        tsigner: Option<TSigner>,
+        reactor_handle: &Handle,
+    ) -> Box<Future<Item = Vec<Record>, Error = ClientError>> {
+        let query = Query::query(origin, query_type);
+
+        let id: u16 = rand::random();
+        let mut message = Message::new();
+        message
+            .set_id(id)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false)
+            .add_query(query);
+
+        let tsig = match tsigner {
+            Some(tsigner) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|now| now.as_secs())
+                    .unwrap_or(0);
+                let signed = message.finalize(&tsigner, now as u32).map(|_| {
+                    match message.sig0().last().map(Record::rdata) {
+                        Some(&RData::DNSSEC(DNSSECRData::TSIG(ref tsig))) => tsig.mac().to_vec(),
+                        _ => Vec::new(),
+                    }
+                });
+
+                match signed {
+                    Ok(request_mac) => Some((tsigner, request_mac)),
+                    Err(e) => return Box::new(future::err(e.into())),
+                }
+            }
+            None => None,
+        };
+
+        let bytes = match message.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => return Box::new(future::err(e.into())),
+        };
+
+        let timeout = match Timeout::new(Duration::from_secs(QUERY_TIMEOUT), reactor_handle) {
+            Ok(timeout) => timeout,
+            Err(e) => return Box::new(future::err(e.into())),
+        };
+
+        let (stream, mut sender) = TcpClientStream::new::<ClientError>(master, reactor_handle);
+        if let Err(e) = sender.send(bytes) {
+            return Box::new(future::err(e));
+        }
+
+        Box::new(TransferFuture {
+            master,
+            id,
+            query_type,
+            stream,
+            connected: None,
+            _sender: sender,
+            tsig,
+            unsigned_messages: Vec::new(),
+            unsigned_count: 0,
+            answers: Vec::new(),
+            timeout,
+        })
+    }
+
+    /// Verifies the TSIG of the message, if the query was signed
+    fn verify(&mut self, response: &MessageRequest, bytes: &[u8]) -> Result<(), ClientError> {
+        let mac = {
+            let (tsigner, prior_mac) = match self.tsig {
+                Some((ref tsigner, ref prior_mac)) => (tsigner, prior_mac),
+                None => return Ok(()),
+            };
+
+            let tsig = match response.sig0().last() {
+                Some(tsig) if tsig.rr_type() == RecordType::DNSSEC(DNSSECRecordType::TSIG) => tsig,
+                // the first and the last message must be signed, the last is checked when complete
+                None if !self.answers.is_empty() && self.unsigned_count < MAX_UNSIGNED_MESSAGES => {
+                    self.unsigned_messages.extend_from_slice(bytes);
+                    self.unsigned_count += 1;
+                    return Ok(());
+                }
+                _ => {
+                    return Err(ClientError::from(format!(
+                        "response from master: {} is not signed",
+                        self.master
+                    )))
+                }
+            };
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or(0);
+            let verified = if self.answers.is_empty() {
+                tsigner.verify_message_byte(
+                    Some(&prior_mac[..]),
+                    response.unsigned_bytes(),
+                    tsig,
+                    now,
+                )
+            } else {
+                tsigner.verify_subsequent_message_byte(
+                    prior_mac,
+                    &self.unsigned_messages,
+                    response.unsigned_bytes(),
+                    tsig,
+                    now,
+                )
+            };
+
+            verified.map_err(|e| {
+                ClientError::from(format!(
+                    "TSIG of response from master: {} did not verify: {}",
+                    self.master, e
+                ))
+            })?
+        };
+
+        if let Some((_, ref mut prior_mac)) = self.tsig {
+            *prior_mac = mac;
+        }
+        self.unsigned_messages.clear();
+        self.unsigned_count = 0;
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        match self.query_type {
+            // an AXFR begins and ends with the SOA of the zone
+            RecordType::AXFR => {
+                self.answers.len() >= 2
+                    && self.answers.last().map(Record::rr_type) == Some(RecordType::SOA)
+            }
+            _ => true,
+        }
+    }
+}
+
+impl Future for TransferFuture {
+    type Item = Vec<Record>;
+    type Error = ClientError;
+
+    fn poll(&mut self) -> Poll<Vec<Record>, ClientError> {
+        loop {
+            if let Async::Ready(()) = self.timeout.poll()? {
+                return Err(ClientErrorKind::Timeout.into());
+            }
+
+            if self.connected.is_none() {
+                let stream = try_ready!(self.stream.poll());
+                self.connected = Some(stream);
+            }
+
+            let bytes = match self.connected {
+                Some(ref mut stream) => try_ready!(stream.poll()),
+                None => continue,
+            };
+            let bytes = bytes.ok_or_else(|| {
+                ClientError::from(format!(
+                    "connection closed by master: {} before the response was complete",
+                    self.master
+                ))
+            })?;
+
+            let response = MessageRequest::from_bytes(&bytes)?;
+            if response.id() != self.id || response.message_type() != MessageType::Response {
+                warn!("ignoring unexpected message from master: {}", self.master);
+                continue;
+            }
+
+            self.verify(&response, &bytes)?;
+
+            // the master is making progress, the next message is given the full time again
+            self.timeout
+                .reset(Instant::now() + Duration::from_secs(QUERY_TIMEOUT));
+
+            if response.response_code() != ResponseCode::NoError {
+                return Err(ClientError::from(format!(
+                    "{} refused by master: {} response: {}",
+                    self.query_type,
+                    self.master,
+                    response.response_code()
+                )));
+            }
+
+            if self.answers.is_empty()
+                && self.query_type == RecordType::AXFR
+                && response.answers().first().map(Record::rr_type) != Some(RecordType::SOA)
+            {
+                return Err(ClientError::from(format!(
+                    "AXFR from master: {} does not begin with the SOA",
+                    self.master
+                )));
+            }
+
+            self.answers.extend(response.answers().iter().cloned());
+
+            if self.is_complete() {
+                if self.unsigned_count > 0 {
+                    return Err(ClientError::from(format!(
+                        "last message from master: {} is not signed",
+                        self.master
+                    )));
+                }
+
+                return Ok(Async::Ready(::std::mem::replace(
+                    &mut self.answers,
+                    Vec::new(),
+                )));
+            }
+        }
+    }
+}
+
+/// Serial number arithmetic, returns true if `s1` is greater than `s2`
+///
+/// [RFC 1982](https://tools.ietf.org/html/rfc1982), Serial Number Arithmetic, August 1996
+pub(crate) fn serial_gt(s1: u32, s2: u32) -> bool {
+    (s1 < s2 && s2 - s1 > 0x8000_0000) || (s1 > s2 && s1 - s2 < 0x8000_0000)
+}
+
+#[test]
+fn test_serial_gt() {
+    assert!(serial_gt(2, 1));
+    assert!(!serial_gt(1, 2));
+    assert!(!serial_gt(1, 1));
+
+    // serials wrap around
+    assert!(serial_gt(0, u32::max_value()));
+    assert!(serial_gt(10, 0xFFFF_FFF0));
+    assert!(!serial_gt(0xFFFF_FFF0, 10));
+
+    // up to half of the serial space ahead is greater
+    assert!(serial_gt(0x7FFF_FFFF, 0));
+    assert!(!serial_gt(0x8000_0001, 0));
+    assert!(serial_gt(0, 0x8000_0001));
+
+    // serials exactly half the space apart are undefined, neither is greater
+    assert!(!serial_gt(0x8000_0000, 0));
+    assert!(!serial_gt(0, 0x8000_0000));
+}
@@ -0,0 +1,129 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Variants of the records of a zone for the clients in some networks, e.g. the addresses of the
+//!  nearest point of presence
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use trust_dns::rr::{LowerName, Record, RecordSet, RecordType};
+use trust_dns::rr::rdata::opt::ClientSubnet;
+
+use authority::acl::prefix_matches;
+
+/// A variant of some records of the zone, for the clients in its networks
+struct SubnetVariant {
+    networks: Vec<(IpAddr, u8)>,
+    records: HashMap<(LowerName, RecordType), RecordSet>,
+}
+
+/// The variants of the records of a zone, selected by the subnet of the client
+///
+/// [RFC 7871](https://tools.ietf.org/html/rfc7871#section-7.2.1), Client Subnet in DNS Queries, May 2016
+///
+/// ```text
+/// The SCOPE PREFIX-LENGTH value indicates the scope of the Authoritative
+/// Nameserver's answer. ... A SCOPE PREFIX-LENGTH value of 0 indicates
+/// that the answer is suitable for all addresses in FAMILY.
+/// ```
+#[derive(Default)]
+pub struct Subnets {
+    variants: Vec<SubnetVariant>,
+}
+
+impl Subnets {
+    /// Adds records which are answered instead of those of the zone to the clients in the networks
+    ///
+    /// # Arguments
+    ///
+    /// * `networks` - the addresses of the networks, with their prefix lengths
+    /// * `records` - the records of the variant, which replace the records of the zone of the same
+    ///               name and type
+    pub fn insert(&mut self, networks: Vec<(IpAddr, u8)>, records: Vec<Record>) {
+        let mut variant = SubnetVariant {
+            networks,
+            records: HashMap::new(),
+        };
+        for record in records {
+            variant
+                .records
+                .entry((LowerName::new(record.name()), record.rr_type()))
+                .or_insert_with(|| RecordSet::new(record.name(), record.rr_type(), 0))
+                .insert(record, 0);
+        }
+
+        self.variants.push(variant);
+    }
+
+    /// The record sets of all the variants, e.g. to sign them with the zone
+    pub fn record_sets_mut<'s>(&'s mut self) -> Box<Iterator<Item = &'s mut RecordSet> + 's> {
+        Box::new(
+            self.variants
+                .iter_mut()
+                .flat_map(|variant| variant.records.values_mut()),
+        )
+    }
+
+    /// Returns true if there are no variants
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Returns the record set of the variant for the subnet of the client, if there is one, with
+    ///  the scope prefix of the answer
+    ///
+    /// The variant of the most specific network which contains the client is chosen. The scope is
+    ///  0 if no variant has records of the name and type, the answer is the same for all clients,
+    ///  otherwise it is the prefix of the chosen network, or the source prefix of the client if
+    ///  none contains it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the records
+    /// * `rtype` - the type of the records
+    /// * `client` - the subnet of the client, from the EDNS of the request or its source address
+    pub fn lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        client: &ClientSubnet,
+    ) -> (Option<&RecordSet>, u8) {
+        let key = (name.clone(), rtype);
+        let mut is_varied = false;
+        let mut chosen: Option<(u8, &RecordSet)> = None;
+
+        for variant in &self.variants {
+            let records = match variant.records.get(&key) {
+                Some(records) => records,
+                None => continue,
+            };
+            is_varied = true;
+
+            for &(addr, prefix_len) in &variant.networks {
+                if !contains(addr, prefix_len, client) {
+                    continue;
+                }
+                if chosen.map_or(true, |(chosen_len, _)| prefix_len > chosen_len) {
+                    chosen = Some((prefix_len, records));
+                }
+            }
+        }
+
+        match chosen {
+            Some((prefix_len, records)) => (Some(records), prefix_len),
+            None if is_varied => (None, client.source_prefix()),
+            None => (None, 0),
+        }
+    }
+}
+
+// the address of the client is only known to its source prefix
+fn contains(addr: IpAddr, prefix_len: u8, client: &ClientSubnet) -> bool {
+    let prefix_len = prefix_len.min(client.source_prefix());
+    prefix_matches(addr, client.address(), prefix_len)
+}
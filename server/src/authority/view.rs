@@ -0,0 +1,92 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Views, split-horizon DNS, the zones served to the clients matching each view
+
+use trust_dns::rr::Name;
+
+use authority::{Acl, Catalog};
+use server::{Request, RequestContext};
+
+/// A set of zones, served to the clients which match the view, as the views of BIND
+///
+/// The same zone may be in several views, with different records, e.g. the addresses of the
+///  internal servers for internal clients, and of the public servers for all others.
+pub struct View {
+    name: String,
+    match_clients: Acl,
+    match_destinations: Acl,
+    catalog: Catalog,
+}
+
+impl View {
+    /// Constructs a new View
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the view, e.g. internal
+    /// * `match_clients` - the clients of the view, by their address, the key with which their
+    ///                     request is signed, or their certificate over mutual TLS
+    /// * `match_destinations` - the addresses of the listeners of the view, as they are bound,
+    ///                          `Acl::any()` for all of them, see `RequestContext::dst`
+    /// * `catalog` - the zones of the view
+    pub fn new(
+        name: String,
+        match_clients: Acl,
+        match_destinations: Acl,
+        catalog: Catalog,
+    ) -> Self {
+        View {
+            name,
+            match_clients,
+            match_destinations,
+            catalog,
+        }
+    }
+
+    /// the name of the view
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the zones of the view
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Returns true if the request is for this view
+    ///
+    /// The key is the one with which the request claims to be signed, its signature is verified
+    ///  once the view is selected, by the zones of the view, and requests whose signature does
+    ///  not verify with that key are not answered from them. The listener is matched by the
+    ///  address it is bound to, not by the destination of the request, listeners bound to the
+    ///  unspecified address, `0.0.0.0` or `::`, can not be told apart. Without a listener, e.g.
+    ///  for requests which were not received by the server, only views of all listeners match.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the request, from its client
+    /// * `signer` - the name of the key with which the request is signed, before it is verified
+    /// * `context` - how the request was received, by which listener, and with the certificate of
+    ///               the client
+    pub fn matches(
+        &self,
+        request: &Request,
+        signer: Option<&Name>,
+        context: &RequestContext,
+    ) -> bool {
+        let is_destination = match context.dst() {
+            Some(dst) => self.match_destinations.allows(dst.ip(), None, None),
+            None => self.match_destinations == Acl::any(),
+        };
+
+        is_destination
+            && self
+                .match_clients
+                .allows(request.src.ip(), signer, context.client_identity())
+    }
+}
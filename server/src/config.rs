@@ -18,29 +18,43 @@
 
 use std::fs::File;
 use std::io::Read;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 
 use log;
 use rustc_serialize::Decodable;
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::FromHex;
 use toml::{Decoder, Value};
 
 #[cfg(feature = "dnssec")]
 use trust_dns::error::*;
+use trust_dns::error::{ParseErrorKind, ParseResult};
 use trust_dns::rr::Name;
+use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
 #[cfg(feature = "dnssec")]
 use trust_dns::rr::dnssec::{Algorithm, KeyFormat};
 use trust_dns_proto::error::ProtoResult;
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig};
 
-use authority::ZoneType;
+use authority::{Acl, AclElement, ZoneAcls, ZoneType, DEFAULT_IXFR_DEPTH};
 use error::{ConfigError, ConfigErrorKind, ConfigResult};
+use server::{DEFAULT_MAX_NETWORKS, DEFAULT_MAX_UDP_PAYLOAD, MIN_PAYLOAD_LEN, SECRET_LEN};
 
 static DEFAULT_PATH: &'static str = "/var/named"; // TODO what about windows (do I care? ;)
 static DEFAULT_PORT: u16 = 53;
 static DEFAULT_TLS_PORT: u16 = 853;
 static DEFAULT_TCP_REQUEST_TIMEOUT: u64 = 5;
+static DEFAULT_CACHE_SIZE: usize = 1024;
+static DEFAULT_METRICS_PORT: u16 = 9153;
+static DEFAULT_CONTROL_PORT: u16 = 953;
+static DEFAULT_RESPONSES_PER_SECOND: u32 = 5;
+static DEFAULT_RATE_LIMIT_WINDOW: u64 = 15;
+static DEFAULT_SLIP: u32 = 2;
+static DEFAULT_IPV4_PREFIX_LEN: u8 = 24;
+static DEFAULT_IPV6_PREFIX_LEN: u8 = 56;
 
 /// Server configuration
 #[derive(RustcDecodable, Debug)]
@@ -53,16 +67,47 @@ pub struct Config {
     listen_port: Option<u16>,
     /// Secure port to listen on
     tls_listen_port: Option<u16>,
+    /// Port on which to listen for DNS over HTTPS, disabled if absent
+    https_listen_port: Option<u16>,
     /// Timeout associated to a request before it is closed.
     tcp_request_timeout: Option<u64>,
+    /// Number of threads handling UDP and TCP requests, each with its own sockets
+    workers: Option<usize>,
     /// Level at which to log, default is INFO
     log_level: Option<String>,
     /// Base configuration directory, i.e. root path for zones
     directory: Option<String>,
     /// List of configurations for zones
     zones: Vec<ZoneConfig>,
+    /// Views, each with its own zones, for the clients which match them
+    views: Option<Vec<ViewConfig>>,
     /// Certificate to associate to TLS connections
     tls_cert: Option<TlsCertConfig>,
+    /// Shared secrets for TSIG, referenced by name from the zones
+    tsig_keys: Option<Vec<TsigKeyConfig>>,
+    /// Maximum number of RRSets cached for recursive resolution, see `ZoneType::Hint`
+    cache_size: Option<usize>,
+    /// Largest UDP response sent, to avoid fragmentation
+    max_udp_payload: Option<u16>,
+    /// Response Rate Limiting of UDP responses, none if absent
+    rate_limit: Option<RateLimitConfig>,
+    /// DNS Cookies of the server, enabled if absent
+    cookies: Option<CookiesConfig>,
+    /// NSID and CHAOS class answers of the server, none disclosed if absent
+    identity: Option<IdentityConfig>,
+    /// Address on which metrics are served over HTTP, disabled if absent
+    metrics_listen_addr: Option<String>,
+    /// Control channel for administering the running server, disabled if absent
+    control: Option<ControlConfig>,
+    /// Clients allowed to query zones without their own query_acl, and names outside of the zones
+    query_acl: Option<Vec<String>>,
+    /// Clients allowed to transfer zones without their own transfer_acl, none if absent
+    transfer_acl: Option<Vec<String>>,
+    /// Clients allowed to update zones without their own update_acl
+    update_acl: Option<Vec<String>>,
+    /// Clients whose queries of names outside of the zones are resolved recursively, localhost if
+    ///  absent
+    recursion_acl: Option<Vec<String>>,
 }
 
 impl Config {
@@ -96,6 +141,10 @@ impl Config {
     pub fn get_tls_listen_port(&self) -> u16 {
         self.tls_listen_port.unwrap_or(DEFAULT_TLS_PORT)
     }
+    /// port on which to listen for DNS over HTTPS connections, none if DoH is disabled
+    pub fn get_https_listen_port(&self) -> Option<u16> {
+        self.https_listen_port
+    }
     /// default timeout for all TCP connections before forceably shutdown
     pub fn get_tcp_request_timeout(&self) -> Duration {
         Duration::from_secs(
@@ -130,10 +179,84 @@ impl Config {
     pub fn get_zones(&self) -> &[ZoneConfig] {
         &self.zones
     }
+    /// the views, split-horizon DNS, in the order in which they are matched
+    ///
+    /// Requests which match none of the views are answered from the zones, see `get_zones`.
+    pub fn get_views(&self) -> &[ViewConfig] {
+        self.views
+            .as_ref()
+            .map_or(&[] as &[ViewConfig], |v| v.as_slice())
+    }
     /// the tls certificate to use for accepting tls connections
     pub fn get_tls_cert(&self) -> Option<&TlsCertConfig> {
         self.tls_cert.as_ref()
     }
+    /// the shared secrets for TSIG, see `ZoneConfig::get_tsig_keys` for their use
+    pub fn get_tsig_keys(&self) -> &[TsigKeyConfig] {
+        self.tsig_keys
+            .as_ref()
+            .map_or(&[] as &[TsigKeyConfig], |k| k.as_slice())
+    }
+    /// the number of threads handling UDP and TCP requests, defaults to 1
+    ///
+    /// With more than one, each thread binds its own sockets to the listening addresses with
+    ///  SO_REUSEPORT, the kernel balances the clients across them. Values below 1 are raised to 1.
+    pub fn get_workers(&self) -> usize {
+        self.workers.unwrap_or(1).max(1)
+    }
+    /// the maximum number of RRSets cached for recursive resolution, defaults to 1024
+    pub fn get_cache_size(&self) -> usize {
+        self.cache_size.unwrap_or(DEFAULT_CACHE_SIZE)
+    }
+    /// the largest UDP response sent, in bytes, defaults to 1232
+    ///
+    /// Larger responses are sent truncated, so the client retries over TCP, see
+    ///  `TruncatingResponseHandle`. Values below 512 are raised to 512.
+    pub fn get_max_udp_payload(&self) -> u16 {
+        self.max_udp_payload
+            .unwrap_or(DEFAULT_MAX_UDP_PAYLOAD)
+            .max(MIN_PAYLOAD_LEN)
+    }
+    /// the Response Rate Limiting of UDP responses, if enabled
+    pub fn get_rate_limit(&self) -> Option<&RateLimitConfig> {
+        self.rate_limit.as_ref()
+    }
+    /// the DNS Cookies of the server, if configured, they are enabled with a random secret
+    ///  otherwise
+    pub fn get_cookies(&self) -> Option<&CookiesConfig> {
+        self.cookies.as_ref()
+    }
+    /// the identity of the server, its NSID and its answers to CHAOS class TXT queries, if
+    ///  configured
+    pub fn get_identity(&self) -> Option<&IdentityConfig> {
+        self.identity.as_ref()
+    }
+    /// the address on which metrics are served over HTTP in the Prometheus text format, if enabled
+    ///
+    /// The address is an IP address with an optional port, the default port is 9153.
+    pub fn get_metrics_listen_addr(&self) -> ConfigResult<Option<SocketAddr>> {
+        self.metrics_listen_addr
+            .as_ref()
+            .map(|addr| parse_addr(addr, DEFAULT_METRICS_PORT))
+            .map_or(Ok(None), |addr| addr.map(Some))
+    }
+    /// the control channel, if enabled
+    pub fn get_control(&self) -> Option<&ControlConfig> {
+        self.control.as_ref()
+    }
+    /// the access control lists of zones without their own, see `ZoneConfig::get_acls`
+    pub fn get_default_acls(&self) -> ConfigResult<ZoneAcls> {
+        Ok(ZoneAcls::new(
+            parse_acl(self.query_acl.as_ref())?.unwrap_or_default(),
+            parse_acl(self.transfer_acl.as_ref())?.unwrap_or_else(Acl::none),
+            parse_acl(self.update_acl.as_ref())?.unwrap_or_default(),
+        ))
+    }
+    /// the clients whose queries are resolved recursively, or forwarded, defaults to the clients
+    ///  on the loopback addresses, see `Catalog::set_recursion_acl`
+    pub fn get_recursion_acl(&self) -> ConfigResult<Acl> {
+        Ok(parse_acl(self.recursion_acl.as_ref())?.unwrap_or_else(Acl::localhost))
+    }
 }
 
 impl FromStr for Config {
@@ -151,10 +274,22 @@ impl FromStr for Config {
 pub struct ZoneConfig {
     zone: String, // TODO: make Domain::Name decodable
     zone_type: ZoneType,
-    file: String,
+    file: Option<String>,
     allow_update: Option<bool>,
     enable_dnssec: Option<bool>,
     keys: Vec<KeyConfig>,
+    masters: Option<Vec<String>>,
+    also_notify: Option<Vec<String>>,
+    tsig_keys: Option<Vec<String>>,
+    update_certificates: Option<Vec<String>>,
+    nsec3: Option<Nsec3Config>,
+    ixfr_depth: Option<usize>,
+    forwarders: Option<Vec<ForwarderConfig>>,
+    trust_authentic_data: Option<bool>,
+    subnets: Option<Vec<SubnetConfig>>,
+    query_acl: Option<Vec<String>>,
+    transfer_acl: Option<Vec<String>>,
+    update_acl: Option<Vec<String>>,
 }
 
 impl ZoneConfig {
@@ -179,10 +314,22 @@ impl ZoneConfig {
         ZoneConfig {
             zone: zone,
             zone_type: zone_type,
-            file: file,
+            file: Some(file),
             allow_update: allow_update,
             enable_dnssec: enable_dnssec,
             keys: keys,
+            masters: None,
+            also_notify: None,
+            tsig_keys: None,
+            update_certificates: None,
+            nsec3: None,
+            ixfr_depth: None,
+            forwarders: None,
+            trust_authentic_data: None,
+            subnets: None,
+            query_acl: None,
+            transfer_acl: None,
+            update_acl: None,
         }
     }
 
@@ -200,9 +347,9 @@ impl ZoneConfig {
     /// path to the zone file, i.e. the base set of original records in the zone
     ///
     /// this is ony used on first load, if dynamic update is enabled for the zone, then the journal
-    /// file is the actual source of truth for the zone.
-    pub fn get_file(&self) -> PathBuf {
-        PathBuf::from(&self.file)
+    /// file is the actual source of truth for the zone. Forward zones have no file.
+    pub fn get_file(&self) -> Option<PathBuf> {
+        self.file.as_ref().map(PathBuf::from)
     }
 
     /// enable dynamic updates for the zone (see SIG0 and the registered keys)
@@ -219,6 +366,367 @@ impl ZoneConfig {
     pub fn get_keys(&self) -> &[KeyConfig] {
         &self.keys
     }
+
+    /// the masters from which a Slave zone is transferred, via AXFR
+    ///
+    /// Each entry is either an IP address, in which case the default port of 53 is used, or an
+    ///  IP address and port, e.g. `10.0.0.1:5353` or `[::1]:5353`.
+    pub fn get_masters(&self) -> ConfigResult<Vec<SocketAddr>> {
+        parse_addrs(self.masters.as_ref())
+    }
+
+    /// the slaves which are sent a NOTIFY after updates to a Master zone
+    ///
+    /// The addresses are in the same format as `get_masters`.
+    pub fn get_also_notify(&self) -> ConfigResult<Vec<SocketAddr>> {
+        parse_addrs(self.also_notify.as_ref())
+    }
+
+    /// names of the TSIG keys, from the top level `tsig_keys`, which are accepted for the zone
+    ///
+    /// A request signed with any of them is authorized for dynamic update, and if any are
+    ///  listed, zone transfers (AXFR and IXFR) are only allowed when signed with one of them.
+    ///  Slave zones sign the transfers from their masters with the first.
+    pub fn get_tsig_keys(&self) -> &[String] {
+        self.tsig_keys.as_ref().map_or(&[] as &[String], |k| k.as_slice())
+    }
+
+    /// names of client certificates which are authorized for dynamic update of the zone
+    ///
+    /// Clients present the certificates over mutual TLS, see `TlsCertConfig::get_client_ca`, the
+    ///  name is one of the DNS names of the certificate, or its common name.
+    pub fn get_update_certificates(&self) -> ParseResult<Vec<Name>> {
+        self.update_certificates.as_ref().map_or(Ok(vec![]), |names| {
+            names
+                .iter()
+                .map(|name| Ok(Name::parse(name, Some(&Name::root()))?))
+                .collect()
+        })
+    }
+
+    /// the parameters of the NSEC3 chain for a signed zone, NSEC is used if there are none
+    pub fn get_nsec3(&self) -> Option<&Nsec3Config> {
+        self.nsec3.as_ref()
+    }
+
+    /// the number of updates for which the changes are kept for incremental zone transfers (IXFR)
+    ///
+    /// Slaves which are further behind are sent the full zone, defaults to `DEFAULT_IXFR_DEPTH`.
+    pub fn get_ixfr_depth(&self) -> usize {
+        self.ixfr_depth.unwrap_or(DEFAULT_IXFR_DEPTH)
+    }
+
+    /// the upstream servers to which the queries of a Forward zone are sent
+    pub fn get_forwarders(&self) -> &[ForwarderConfig] {
+        self.forwarders
+            .as_ref()
+            .map_or(&[] as &[ForwarderConfig], |f| f.as_slice())
+    }
+
+    /// true if AD in the responses of the forwarders is relayed to the clients, i.e. the
+    ///  forwarders validate DNSSEC and the path to them is secure, otherwise AD is cleared, see
+    ///  `Forwarder::set_authentic_data_trusted`
+    pub fn is_authentic_data_trusted(&self) -> bool {
+        self.trust_authentic_data.unwrap_or(false)
+    }
+
+    /// the variants of the records of the zone for the clients in some networks
+    ///
+    /// The client is in a network if the address from the EDNS Client Subnet option of its
+    ///  request, or otherwise its source address, is. The most specific network decides.
+    pub fn get_subnets(&self) -> &[SubnetConfig] {
+        self.subnets
+            .as_ref()
+            .map_or(&[] as &[SubnetConfig], |s| s.as_slice())
+    }
+
+    /// the clients allowed to query, transfer (AXFR and IXFR) and update the zone
+    ///
+    /// Each list is of address match elements, as in BIND, the first element which matches the
+    ///  client decides: `any`, `none`, an IP address with an optional prefix length, e.g.
+    ///  `10.0.0.0/8`, `key <name>` for requests signed by the key with TSIG or SIG(0), or
+    ///  `cert <name>` for clients which presented a certificate for the name over mutual TLS.
+    ///  Elements prefixed with `!` deny the clients they match. Lists which are not configured for the zone
+    ///  are the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `defaults` - the lists of the server, see `Config::get_default_acls`
+    pub fn get_acls(&self, defaults: &ZoneAcls) -> ConfigResult<ZoneAcls> {
+        Ok(ZoneAcls::new(
+            parse_acl(self.query_acl.as_ref())?.unwrap_or_else(|| defaults.query().clone()),
+            parse_acl(self.transfer_acl.as_ref())?.unwrap_or_else(|| defaults.transfer().clone()),
+            parse_acl(self.update_acl.as_ref())?.unwrap_or_else(|| defaults.update().clone()),
+        ))
+    }
+}
+
+/// An upstream server of a Forward zone
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct ForwarderConfig {
+    addr: String,
+    protocol: Option<String>,
+    tls_dns_name: Option<String>,
+}
+
+impl ForwarderConfig {
+    /// Return a new ForwarderConfig
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - IP address of the server, with an optional port
+    /// * `protocol` - one of `udp`, `tcp` or `tls`, defaults to `udp`
+    /// * `tls_dns_name` - for `tls`, the name in the certificate of the server
+    pub fn new(addr: String, protocol: Option<String>, tls_dns_name: Option<String>) -> Self {
+        ForwarderConfig {
+            addr,
+            protocol,
+            tls_dns_name,
+        }
+    }
+
+    /// the configurations of the connections to the server
+    ///
+    /// UDP servers are also connected to with TCP, for truncated responses. The default port is 53,
+    ///  or 853 for TLS.
+    pub fn get_name_servers(&self) -> ConfigResult<Vec<NameServerConfig>> {
+        let name_server = |socket_addr: SocketAddr, protocol: Protocol| NameServerConfig {
+            socket_addr,
+            protocol,
+        };
+
+        match self.protocol.as_ref().map_or("udp", |p| p.as_str()) {
+            "udp" => {
+                let socket_addr = parse_addr(&self.addr, DEFAULT_PORT)?;
+                Ok(vec![
+                    name_server(socket_addr, Protocol::Udp),
+                    name_server(socket_addr, Protocol::Tcp),
+                ])
+            }
+            "tcp" => Ok(vec![
+                name_server(parse_addr(&self.addr, DEFAULT_PORT)?, Protocol::Tcp),
+            ]),
+            #[cfg(feature = "dns-over-native-tls")]
+            "tls" => Ok(vec![
+                name_server(parse_addr(&self.addr, DEFAULT_TLS_PORT)?, Protocol::Tls),
+            ]),
+            #[cfg(not(feature = "dns-over-native-tls"))]
+            "tls" => Err(ConfigError::from(
+                "tls forwarders require the dns-over-native-tls feature",
+            )),
+            protocol => Err(ConfigError::from(format!(
+                "bad forwarder protocol, expected udp, tcp or tls: {}",
+                protocol
+            ))),
+        }
+    }
+
+    /// adds the connections to the server to the configuration of the resolver of the zone, see
+    ///  `get_name_servers`, TLS connections with the name in the certificate of the server
+    pub fn add_name_servers(&self, config: &mut ResolverConfig) -> ConfigResult<()> {
+        for name_server in self.get_name_servers()? {
+            #[cfg(feature = "dns-over-native-tls")]
+            {
+                if let (Protocol::Tls, Some(tls_dns_name)) =
+                    (name_server.protocol, self.tls_dns_name.as_ref())
+                {
+                    config.add_tls_name_server(name_server.socket_addr, tls_dns_name.clone());
+                    continue;
+                }
+            }
+
+            config.add_name_server(name_server);
+        }
+
+        Ok(())
+    }
+}
+
+/// A view, the zones served to the clients which match it
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct ViewConfig {
+    name: String,
+    match_clients: Option<Vec<String>>,
+    match_destinations: Option<Vec<String>>,
+    zones: Vec<ZoneConfig>,
+}
+
+impl ViewConfig {
+    /// the name of the view
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// the clients of the view, an address match list as the ACLs, see `ZoneConfig::get_acls`,
+    ///  all clients if it is not configured
+    pub fn get_match_clients(&self) -> ConfigResult<Acl> {
+        Ok(parse_acl(self.match_clients.as_ref())?.unwrap_or_default())
+    }
+
+    /// the addresses of the listeners of the view, as they are bound, e.g. `192.0.2.53`, all
+    ///  listeners if it is not configured
+    ///
+    /// Listeners bound to the unspecified address, `0.0.0.0` or `::`, can not be told apart.
+    pub fn get_match_destinations(&self) -> ConfigResult<Acl> {
+        Ok(parse_acl(self.match_destinations.as_ref())?.unwrap_or_default())
+    }
+
+    /// the zones of the view
+    pub fn get_zones(&self) -> &[ZoneConfig] {
+        &self.zones
+    }
+}
+
+/// A variant of the records of a zone, answered to the clients in its networks
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct SubnetConfig {
+    networks: Vec<String>,
+    file: String,
+}
+
+impl SubnetConfig {
+    /// Return a new SubnetConfig
+    ///
+    /// # Arguments
+    ///
+    /// * `networks` - IP addresses with optional prefix lengths, e.g. `10.0.0.0/8`
+    /// * `file` - relative to Config base path, to the file of the records of the variant
+    pub fn new(networks: Vec<String>, file: String) -> Self {
+        SubnetConfig { networks, file }
+    }
+
+    /// the networks of the clients, as addresses with their prefix lengths
+    pub fn get_networks(&self) -> ConfigResult<Vec<(IpAddr, u8)>> {
+        self.networks
+            .iter()
+            .map(|network| match parse_network(network)? {
+                AclElement::Network { addr, prefix_len } => Ok((addr, prefix_len)),
+                _ => Err(ConfigError::from(format!("bad network: {}", network))),
+            })
+            .collect()
+    }
+
+    /// path to the file of the records, in the format of a zone file with the origin of the zone
+    ///
+    /// The records replace those of the zone with the same name and type.
+    pub fn get_file(&self) -> PathBuf {
+        PathBuf::from(&self.file)
+    }
+}
+
+/// Parameters of the NSEC3 chain of a signed zone, see RFC 5155
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct Nsec3Config {
+    salt: Option<String>,
+    iterations: Option<u16>,
+    opt_out: Option<bool>,
+}
+
+impl Nsec3Config {
+    /// Return a new Nsec3Config
+    ///
+    /// # Arguments
+    ///
+    /// * `salt` - hex encoded salt for the hashes, `-` or `None` for no salt
+    /// * `iterations` - number of additional times the names are hashed
+    /// * `opt_out` - skip delegations to unsigned zones in the chain
+    pub fn new(salt: Option<String>, iterations: u16, opt_out: bool) -> Self {
+        Nsec3Config {
+            salt,
+            iterations: Some(iterations),
+            opt_out: Some(opt_out),
+        }
+    }
+
+    /// the decoded salt, empty if there is none
+    pub fn salt(&self) -> ParseResult<Vec<u8>> {
+        match self.salt.as_ref().map(|s| s.as_str()) {
+            None | Some("") | Some("-") => Ok(vec![]),
+            Some(salt) => salt.from_hex().map_err(|e| {
+                ParseErrorKind::Msg(format!("bad hex NSEC3 salt {}: {}", salt, e)).into()
+            }),
+        }
+    }
+
+    /// the number of additional times the names are hashed, defaults to `0`
+    pub fn iterations(&self) -> u16 {
+        self.iterations.unwrap_or(0)
+    }
+
+    /// if true, delegations to unsigned zones are not in the chain, defaults to `false`
+    pub fn opt_out(&self) -> bool {
+        self.opt_out.unwrap_or(false)
+    }
+}
+
+/// parses IP addresses with optional ports, the default port of 53 is used if there is none
+fn parse_addrs(addrs: Option<&Vec<String>>) -> ConfigResult<Vec<SocketAddr>> {
+    addrs
+        .map_or(&[] as &[String], |a| a.as_slice())
+        .iter()
+        .map(|s| parse_addr(s, DEFAULT_PORT))
+        .collect()
+}
+
+/// parses an IP address with an optional port, the default port is used if there is none
+fn parse_addr(s: &str, default_port: u16) -> ConfigResult<SocketAddr> {
+    s.parse::<SocketAddr>()
+        .or_else(|_| s.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, default_port)))
+        .map_err(|_| ConfigError::from(format!("bad address: {}", s)))
+}
+
+/// parses an address match list, none if it is not configured
+fn parse_acl(elements: Option<&Vec<String>>) -> ConfigResult<Option<Acl>> {
+    let elements = match elements {
+        Some(elements) => elements,
+        None => return Ok(None),
+    };
+
+    elements
+        .iter()
+        .map(|s| -> ConfigResult<(bool, AclElement)> {
+            let element = s.trim();
+            let (negated, element) = if element.starts_with('!') {
+                (true, element[1..].trim())
+            } else {
+                (false, element)
+            };
+
+            let element = match element {
+                "any" => AclElement::Any,
+                "none" => AclElement::None,
+                _ if element.starts_with("key ") => {
+                    AclElement::Key(Name::parse(element[4..].trim(), Some(&Name::root()))?)
+                }
+                _ if element.starts_with("cert ") => {
+                    AclElement::Certificate(Name::parse(element[5..].trim(), Some(&Name::root()))?)
+                }
+                _ => parse_network(element)?,
+            };
+
+            Ok((negated, element))
+        })
+        .collect::<ConfigResult<Vec<_>>>()
+        .map(|elements| Some(Acl::new(elements)))
+}
+
+/// parses an IP address with an optional prefix length, the default is the whole address
+fn parse_network(s: &str) -> ConfigResult<AclElement> {
+    let mut parts = s.splitn(2, '/');
+    let addr = parts
+        .next()
+        .and_then(|addr| addr.parse::<IpAddr>().ok())
+        .ok_or_else(|| ConfigError::from(format!("bad address in acl: {}", s)))?;
+    let max_len = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix_len = match parts.next() {
+        Some(len) => len.parse::<u8>()
+            .ok()
+            .and_then(|len| if len <= max_len { Some(len) } else { None })
+            .ok_or_else(|| ConfigError::from(format!("bad prefix length in acl: {}", s)))?,
+        None => max_len,
+    };
+
+    Ok(AclElement::Network { addr, prefix_len })
 }
 
 /// Key pair configuration for DNSSec keys for signing a zone
@@ -342,15 +850,64 @@ impl KeyConfig {
 #[derive(RustcDecodable, PartialEq, Debug)]
 pub struct KeyConfig {}
 
+/// Shared secret for TSIG, in the format used by `tsig-keygen` of BIND
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct TsigKeyConfig {
+    name: String,
+    algorithm: String,
+    secret: String,
+}
+
+impl TsigKeyConfig {
+    /// Return a new TsigKeyConfig
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the key, e.g. `transfer.example.com.`, must match the peer
+    /// * `algorithm` - the HMAC algorithm, `hmac-sha256` or `hmac-sha512`
+    /// * `secret` - the base64 encoded shared secret
+    pub fn new(name: String, algorithm: String, secret: String) -> Self {
+        TsigKeyConfig {
+            name,
+            algorithm,
+            secret,
+        }
+    }
+
+    /// the name of the key, as in the TSIG record
+    pub fn name(&self) -> ParseResult<Name> {
+        Ok(Name::parse(&self.name, Some(&Name::root()))?)
+    }
+
+    /// the HMAC algorithm used with the key, either `hmac-sha256` or `hmac-sha512`
+    pub fn algorithm(&self) -> ParseResult<TsigAlgorithm> {
+        match self.algorithm.trim_right_matches('.').to_lowercase().as_str() {
+            "hmac-sha256" => Ok(TsigAlgorithm::HmacSha256),
+            "hmac-sha512" => Ok(TsigAlgorithm::HmacSha512),
+            s => Err(format!("unsupported TSIG algorithm {}", s).into()),
+        }
+    }
+
+    /// the decoded shared secret
+    pub fn secret(&self) -> ParseResult<Vec<u8>> {
+        self.secret.from_base64().map_err(|e| {
+            ParseErrorKind::Msg(format!("bad base64 secret for key {}: {}", self.name, e)).into()
+        })
+    }
+}
+
 /// Configuration for a TLS certificate
 #[derive(RustcDecodable, PartialEq, Debug)]
 pub struct TlsCertConfig {
     path: String,
     password: Option<String>,
+    private_key: Option<String>,
+    client_ca: Option<String>,
 }
 
 impl TlsCertConfig {
-    /// path to the pkcs12 der formated certificate file
+    /// path to the pkcs12 der formated certificate file, or with a `private_key` to the PEM
+    ///  certificate chain
     pub fn get_path(&self) -> &Path {
         Path::new(&self.path)
     }
@@ -358,4 +915,175 @@ impl TlsCertConfig {
     pub fn get_password(&self) -> Option<&str> {
         self.password.as_ref().map(|s| s.as_str())
     }
+    /// optional path to the PEM private key, the certificate is then served with rustls, i.e. the
+    ///  `tls-ring` feature, instead of from a pkcs12 with OpenSSL
+    pub fn get_private_key(&self) -> Option<&Path> {
+        self.private_key.as_ref().map(Path::new)
+    }
+    /// optional path to the PEM certificates of the CAs of clients, for mutual TLS
+    ///
+    /// Clients are asked for a certificate, those which present one must have it signed by one of
+    ///  the CAs, and its names are the identity of the client, see `ClientIdentity`. Clients
+    ///  without a certificate are still accepted.
+    pub fn get_client_ca(&self) -> Option<&Path> {
+        self.client_ca.as_ref().map(Path::new)
+    }
+}
+
+/// Configuration of the control channel, see `control`
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct ControlConfig {
+    unix_socket: Option<String>,
+    listen_addr: Option<String>,
+    tsig_key: Option<String>,
+}
+
+impl ControlConfig {
+    /// the path of the Unix domain socket on which commands are accepted, access to it is
+    ///  controlled by the permissions of the file
+    pub fn get_unix_socket(&self) -> Option<&Path> {
+        self.unix_socket.as_ref().map(Path::new)
+    }
+    /// the address on which commands are accepted over TCP, they must be signed with the
+    ///  `tsig_key`
+    ///
+    /// The address is an IP address with an optional port, the default port is 953.
+    pub fn get_listen_addr(&self) -> ConfigResult<Option<SocketAddr>> {
+        self.listen_addr
+            .as_ref()
+            .map(|addr| parse_addr(addr, DEFAULT_CONTROL_PORT))
+            .map_or(Ok(None), |addr| addr.map(Some))
+    }
+    /// the name of the key, one of the `tsig_keys`, with which commands over TCP are signed
+    pub fn get_tsig_key_name(&self) -> Option<&str> {
+        self.tsig_key.as_ref().map(String::as_str)
+    }
+    /// the key with which commands over TCP are signed, if one is configured
+    ///
+    /// # Arguments
+    ///
+    /// * `tsig_keys` - the shared secrets for TSIG, see `Config::get_tsig_keys`
+    pub fn get_tsig_key(&self, tsig_keys: &[TsigKeyConfig]) -> ConfigResult<Option<TSigner>> {
+        let key_name = match self.tsig_key {
+            Some(ref key_name) => key_name,
+            None => return Ok(None),
+        };
+        let name = Name::parse(key_name, Some(&Name::root()))
+            .map_err(|e| ConfigError::from(format!("bad tsig key name: {}: {}", key_name, e)))?;
+        let key = tsig_keys
+            .iter()
+            .find(|key| key.name().ok().as_ref() == Some(&name))
+            .ok_or_else(|| ConfigError::from(format!("tsig key not defined: {}", name)))?;
+
+        let algorithm = key.algorithm()
+            .map_err(|e| ConfigError::from(format!("bad algorithm for tsig key: {}: {}", name, e)))?;
+        let secret = key.secret()
+            .map_err(|e| ConfigError::from(format!("bad secret for tsig key: {}: {}", name, e)))?;
+
+        Ok(Some(TSigner::new(secret, algorithm, name, DEFAULT_FUDGE)))
+    }
+}
+
+/// Configuration of Response Rate Limiting, see `RateLimiter`
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct RateLimitConfig {
+    responses_per_second: Option<u32>,
+    window: Option<u64>,
+    slip: Option<u32>,
+    ipv4_prefix_len: Option<u8>,
+    ipv6_prefix_len: Option<u8>,
+    max_networks: Option<usize>,
+}
+
+impl RateLimitConfig {
+    /// the responses of each class sent to a client network per second, defaults to 5
+    pub fn get_responses_per_second(&self) -> u32 {
+        self.responses_per_second.unwrap_or(DEFAULT_RESPONSES_PER_SECOND)
+    }
+    /// the period over which the rate is averaged, defaults to 15 seconds
+    pub fn get_window(&self) -> Duration {
+        Duration::from_secs(self.window.unwrap_or(DEFAULT_RATE_LIMIT_WINDOW))
+    }
+    /// every slip-th limited response is sent truncated, 0 drops all of them, defaults to 2
+    pub fn get_slip(&self) -> u32 {
+        self.slip.unwrap_or(DEFAULT_SLIP)
+    }
+    /// the prefix length of IPv4 client networks, defaults to 24
+    pub fn get_ipv4_prefix_len(&self) -> u8 {
+        self.ipv4_prefix_len.unwrap_or(DEFAULT_IPV4_PREFIX_LEN)
+    }
+    /// the prefix length of IPv6 client networks, defaults to 56
+    pub fn get_ipv6_prefix_len(&self) -> u8 {
+        self.ipv6_prefix_len.unwrap_or(DEFAULT_IPV6_PREFIX_LEN)
+    }
+    /// the maximum number of client networks limited separately, defaults to 65536
+    pub fn get_max_networks(&self) -> usize {
+        self.max_networks.unwrap_or(DEFAULT_MAX_NETWORKS)
+    }
+}
+
+/// Configuration of the DNS Cookies of the server, see `ServerCookies`
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct CookiesConfig {
+    enabled: Option<bool>,
+    secret: Option<String>,
+}
+
+impl CookiesConfig {
+    /// true if the server answers the cookies of clients, defaults to true
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+    /// the secret from which the server cookies are derived, 16 bytes in hex, if configured
+    ///
+    /// A configured secret is never replaced, it may be shared by the servers of an anycast
+    ///  address, otherwise a random secret is replaced daily.
+    pub fn get_secret(&self) -> ConfigResult<Option<[u8; SECRET_LEN]>> {
+        let secret = match self.secret {
+            Some(ref secret) => secret,
+            None => return Ok(None),
+        };
+        let bytes = secret
+            .from_hex()
+            .map_err(|e| ConfigError::from(format!("bad hex cookie secret: {}", e)))?;
+        if bytes.len() != SECRET_LEN {
+            return Err(ConfigError::from(format!(
+                "cookie secret is not {} bytes: {}",
+                SECRET_LEN,
+                bytes.len()
+            )));
+        }
+
+        let mut secret = [0; SECRET_LEN];
+        secret.copy_from_slice(&bytes);
+        Ok(Some(secret))
+    }
+}
+
+/// Configuration of the identity of the server, see `Identity`, each is disabled if absent
+#[derive(RustcDecodable, PartialEq, Debug)]
+pub struct IdentityConfig {
+    nsid: Option<String>,
+    version: Option<String>,
+    hostname: Option<String>,
+    id: Option<String>,
+}
+
+impl IdentityConfig {
+    /// the NSID returned to clients which ask for it, sent as the bytes of the string
+    pub fn get_nsid(&self) -> Option<&str> {
+        self.nsid.as_ref().map(|s| s.as_str())
+    }
+    /// the answer to `version.bind` CHAOS class TXT queries
+    pub fn get_version(&self) -> Option<&str> {
+        self.version.as_ref().map(|s| s.as_str())
+    }
+    /// the answer to `hostname.bind` CHAOS class TXT queries
+    pub fn get_hostname(&self) -> Option<&str> {
+        self.hostname.as_ref().map(|s| s.as_str())
+    }
+    /// the answer to `id.server` CHAOS class TXT queries
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_ref().map(|s| s.as_str())
+    }
 }
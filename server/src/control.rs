@@ -0,0 +1,383 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A control channel for administering a running server, e.g. to reload zones
+//!
+//! Each connection carries a single command, as a line of text, and its response, after which
+//!  the connection is closed. The response is `ok` or `error` on the first line, followed by a
+//!  message. Clients which do not send their command within a few seconds are disconnected.
+//!
+//! On a Unix domain socket, access is controlled by the permissions of the socket file, which
+//!  `named` makes readable and writable only by its own user. Over TCP, the command is
+//!  authenticated with a TSIG key. The server first sends a line with a random challenge, the
+//!  line of the client is then `<mac> <command>`, where the mac is the hex encoded HMAC of
+//!  `<challenge> <command>`, so that the command can not be replayed on another connection. The
+//!  response is preceded by a line with the hex encoded HMAC of the request line and the
+//!  response, `<line>\n<response>`, with which the client authenticates the server.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{self, SocketAddr};
+#[cfg(unix)]
+use std::os::unix;
+#[cfg(unix)]
+use std::path::Path;
+use std::rc::Rc;
+use std::str::{self, FromStr};
+use std::time::Duration;
+
+use futures::future::Either;
+use futures::{Future, Stream};
+use rand;
+use rustc_serialize::hex::{FromHex, ToHex};
+use tokio_core;
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_io::io::{read_until, write_all};
+use tokio_io::{AsyncRead, AsyncWrite};
+#[cfg(unix)]
+use tokio_uds;
+
+use trust_dns::rr::dnssec::TSigner;
+use trust_dns::rr::Name;
+
+use error::{ControlError, ControlErrorKind, ControlResult};
+
+/// requests are a single line, longer ones are cut off
+const MAX_REQUEST_LEN: u64 = 4096;
+
+/// the time in seconds a client has to send its command
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+/// A command to the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// reloads the configuration and the zones which changed, or only the zone
+    Reload(Option<Name>),
+    /// suspends dynamic updates to the zone, and writes it to its file, so it can be edited
+    Freeze(Name),
+    /// resumes dynamic updates to the zone, after loading it from its file
+    Thaw(Name),
+    /// writes the zone, with the updates in its journal, to its file
+    Sync(Name),
+    /// transfers the Slave zone from its masters, whatever their serial
+    Retransfer(Name),
+    /// removes all the records from the caches
+    Flush,
+    /// describes the server and its zones
+    Status,
+}
+
+impl Command {
+    fn zone(command: &str, zone: Option<Name>) -> ControlResult<Name> {
+        zone.ok_or_else(|| ControlError::from(format!("{} requires a zone", command)))
+    }
+}
+
+impl FromStr for Command {
+    type Err = ControlError;
+
+    fn from_str(s: &str) -> ControlResult<Self> {
+        let mut words = s.split_whitespace();
+        let command = words
+            .next()
+            .ok_or_else(|| ControlError::from("empty command"))?;
+        let zone = match words.next() {
+            Some(zone) => Some(Name::parse(zone, Some(&Name::root()))?),
+            None => None,
+        };
+        if let Some(word) = words.next() {
+            return Err(format!("unexpected argument: {}", word).into());
+        }
+
+        match command {
+            "reload" => Ok(Command::Reload(zone)),
+            "freeze" => Ok(Command::Freeze(Self::zone(command, zone)?)),
+            "thaw" => Ok(Command::Thaw(Self::zone(command, zone)?)),
+            "sync" => Ok(Command::Sync(Self::zone(command, zone)?)),
+            "retransfer" => Ok(Command::Retransfer(Self::zone(command, zone)?)),
+            "flush" | "status" if zone.is_some() => {
+                Err(format!("{} does not take a zone", command).into())
+            }
+            "flush" => Ok(Command::Flush),
+            "status" => Ok(Command::Status),
+            _ => Err(ControlErrorKind::UnknownCommand(command.to_string()).into()),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Command::Reload(None) => write!(f, "reload"),
+            Command::Reload(Some(ref zone)) => write!(f, "reload {}", zone),
+            Command::Freeze(ref zone) => write!(f, "freeze {}", zone),
+            Command::Thaw(ref zone) => write!(f, "thaw {}", zone),
+            Command::Sync(ref zone) => write!(f, "sync {}", zone),
+            Command::Retransfer(ref zone) => write!(f, "retransfer {}", zone),
+            Command::Flush => write!(f, "flush"),
+            Command::Status => write!(f, "status"),
+        }
+    }
+}
+
+/// Executes the commands received on the control channel
+pub trait ControlHandler {
+    /// Executes the command, returning the message for the response
+    fn execute(&mut self, command: Command) -> ControlResult<String>;
+}
+
+/// Returns a new random challenge, which the server sends first on each connection over TCP
+pub fn challenge() -> String {
+    rand::random::<[u8; 16]>().to_hex()
+}
+
+/// Returns the request line for the command, signed with the key for the challenge of the
+///  server, see the module documentation
+pub fn sign_command(command: &Command, key: &TSigner, challenge: &str) -> ControlResult<String> {
+    let mac = key.mac(format!("{} {}", challenge, command).as_bytes())?;
+    Ok(format!("{} {}", mac.to_hex(), command))
+}
+
+/// Verifies the request line signed with the key for the challenge, see `sign_command`,
+///  returning the command
+pub fn verify_command(line: &str, key: &TSigner, challenge: &str) -> ControlResult<Command> {
+    let mut parts = line.trim().splitn(2, ' ');
+    let (mac, command) = match (parts.next(), parts.next()) {
+        (Some(mac), Some(command)) => (mac, command),
+        _ => return Err(ControlErrorKind::NotAuthorized.into()),
+    };
+    let mac = mac
+        .from_hex()
+        .map_err(|_| ControlError::from(ControlErrorKind::NotAuthorized))?;
+
+    key.verify_mac(format!("{} {}", challenge, command).as_bytes(), &mac)
+        .map_err(|_| ControlError::from(ControlErrorKind::NotAuthorized))?;
+
+    command.parse()
+}
+
+/// Returns the response, signed with the key for the request line, see the module documentation
+pub fn sign_response(response: &str, key: &TSigner, line: &str) -> ControlResult<String> {
+    let mac = key.mac(format!("{}\n{}", line.trim(), response).as_bytes())?;
+    Ok(format!("{}\n{}", mac.to_hex(), response))
+}
+
+/// Verifies the response signed with the key for the request line, see `sign_response`,
+///  returning the response
+pub fn verify_response(signed: &str, key: &TSigner, line: &str) -> ControlResult<String> {
+    let mut parts = signed.splitn(2, '\n');
+    let (mac, response) = match (parts.next(), parts.next()) {
+        (Some(mac), Some(response)) => (mac, response),
+        _ => return Err("the response is not signed".into()),
+    };
+    let mac = mac
+        .from_hex()
+        .map_err(|_| ControlError::from("the response is not signed"))?;
+
+    key.verify_mac(format!("{}\n{}", line.trim(), response).as_bytes(), &mac)
+        .map_err(|_| ControlError::from("the response is not signed with the key"))?;
+
+    Ok(response.to_string())
+}
+
+// the response to the request line, see the module documentation, signed if there is a key
+fn respond<H: ControlHandler>(
+    line: &[u8],
+    key: Option<&TSigner>,
+    challenge: &str,
+    handler: &RefCell<H>,
+) -> String {
+    let result = str::from_utf8(line)
+        .map_err(|_| ControlError::from("command is not utf8"))
+        .and_then(|line| match key {
+            Some(key) => verify_command(line, key, challenge),
+            None => line.parse(),
+        })
+        .and_then(|command| {
+            info!("control command: {}", command);
+            handler.borrow_mut().execute(command)
+        });
+
+    let response = match result {
+        Ok(message) => format!("ok\n{}\n", message),
+        Err(error) => {
+            warn!("control command failed: {}", error);
+            format!("error\n{}\n", error)
+        }
+    };
+
+    match key {
+        Some(key) => {
+            let line = String::from_utf8_lossy(line);
+            sign_response(&response, key, &line).unwrap_or_else(|error| {
+                warn!("could not sign control response: {}", error);
+                format!("\n{}", response)
+            })
+        }
+        None => response,
+    }
+}
+
+// clients which do not send their command within `REQUEST_TIMEOUT_SECS` are disconnected, the
+//  command is then executed, and the response written, without a time limit
+fn serve_connection<S, H>(
+    stream: S,
+    key: Option<TSigner>,
+    handler: Rc<RefCell<H>>,
+    handle: &Handle,
+) -> io::Result<Box<Future<Item = (), Error = io::Error>>>
+where
+    S: AsyncRead + AsyncWrite + 'static,
+    H: ControlHandler + 'static,
+{
+    // the challenge is only sent to the clients which sign their commands
+    let challenge = challenge();
+    let greeting = match key {
+        Some(_) => format!("{}\n", challenge),
+        None => String::new(),
+    };
+    let timeout = Timeout::new(Duration::from_secs(REQUEST_TIMEOUT_SECS), handle)?;
+
+    Ok(Box::new(
+        write_all(stream, greeting.into_bytes())
+            .and_then(|(stream, _)| {
+                let reader = BufReader::new(stream.take(MAX_REQUEST_LEN));
+                read_until(reader, b'\n', Vec::with_capacity(256))
+            })
+            .select2(timeout)
+            .then(|result| match result {
+                Ok(Either::A((request, _))) => Ok(request),
+                Ok(Either::B(_)) => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for the control command",
+                )),
+                Err(Either::A((e, _))) | Err(Either::B((e, _))) => Err(e),
+            })
+            .and_then(move |(reader, line)| {
+                let response = respond(&line, key.as_ref(), &challenge, &handler);
+                write_all(reader.into_inner().into_inner(), response.into_bytes())
+            })
+            .map(|_| ()),
+    ))
+}
+
+/// Accepts commands on a Unix domain socket
+///
+/// # Arguments
+///
+/// * `listener` - a bound Unix domain socket
+/// * `handler` - executes the commands
+/// * `handle` - the reactor on which the commands are served
+#[cfg(unix)]
+pub fn serve_unix<H: ControlHandler + 'static>(
+    listener: unix::net::UnixListener,
+    handler: Rc<RefCell<H>>,
+    handle: &Handle,
+) -> io::Result<()> {
+    let listener = tokio_uds::UnixListener::from_listener(listener, handle)?;
+
+    let inner_handle = handle.clone();
+    handle.spawn(
+        listener
+            .incoming()
+            .for_each(move |(stream, _)| {
+                inner_handle.spawn(
+                    serve_connection(stream, None, handler.clone(), &inner_handle)?
+                        .map_err(|e| debug!("error serving control command: {}", e)),
+                );
+
+                Ok(())
+            })
+            .map_err(|e| warn!("error in inbound control stream: {}", e)),
+    );
+
+    Ok(())
+}
+
+/// Accepts commands over TCP, they must be signed with the key
+///
+/// # Arguments
+///
+/// * `listener` - a bound TCP socket
+/// * `key` - the key with which the commands are signed
+/// * `handler` - executes the commands
+/// * `handle` - the reactor on which the commands are served
+pub fn serve_tcp<H: ControlHandler + 'static>(
+    listener: net::TcpListener,
+    key: TSigner,
+    handler: Rc<RefCell<H>>,
+    handle: &Handle,
+) -> io::Result<()> {
+    let addr = listener.local_addr()?;
+    let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, handle)?;
+
+    let inner_handle = handle.clone();
+    handle.spawn(
+        listener
+            .incoming()
+            .for_each(move |(stream, src_addr)| {
+                debug!("accepted control connection from: {}", src_addr);
+                inner_handle.spawn(
+                    serve_connection(stream, Some(key.clone()), handler.clone(), &inner_handle)?
+                        .map_err(move |e| {
+                            debug!("error serving control command to: {}: {}", src_addr, e)
+                        }),
+                );
+
+                Ok(())
+            })
+            .map_err(|e| warn!("error in inbound control stream: {}", e)),
+    );
+
+    Ok(())
+}
+
+/// Sends the command to the server on the Unix domain socket, returning the message of the
+///  response
+#[cfg(unix)]
+pub fn send_unix(path: &Path, command: &Command) -> ControlResult<String> {
+    let stream = unix::net::UnixStream::connect(path)?;
+    exchange(stream, &command.to_string())
+}
+
+/// Sends the command to the server over TCP, signed with the key, returning the message of the
+///  response, which must be signed with the key as well
+pub fn send_tcp(addr: SocketAddr, key: &TSigner, command: &Command) -> ControlResult<String> {
+    let mut stream = net::TcpStream::connect(addr)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut challenge = String::new();
+    reader.read_line(&mut challenge)?;
+
+    let line = sign_command(command, key, challenge.trim())?;
+    stream.write_all(format!("{}\n", line).as_bytes())?;
+
+    let mut response = String::new();
+    reader.read_to_string(&mut response)?;
+    parse_response(&verify_response(&response, key, &line)?)
+}
+
+#[cfg(unix)]
+fn exchange<S: Read + Write>(mut stream: S, line: &str) -> ControlResult<String> {
+    stream.write_all(format!("{}\n", line).as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    parse_response(&response)
+}
+
+// the message of the response, or its error
+fn parse_response(response: &str) -> ControlResult<String> {
+    let mut lines = response.splitn(2, '\n');
+    let status = lines.next().unwrap_or("");
+    let message = lines.next().unwrap_or("").trim_right().to_string();
+    match status {
+        "ok" => Ok(message),
+        "error" => Err(message.into()),
+        _ => Err(format!("bad response: {}", response).into()),
+    }
+}
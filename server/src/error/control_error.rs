@@ -0,0 +1,42 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::io;
+
+use trust_dns_proto::error::*;
+
+error_chain! {
+  // The type defined for this error. These are the conventional
+  // and recommended names, but they can be arbitrarily chosen.
+  types {
+    Error, ErrorKind, ChainErr, Result;
+  }
+
+  // Automatic conversions between this error chain and other
+  // error chains.
+  links {
+    ProtoError, ProtoErrorKind, ProtoError;
+  }
+
+  // Automatic conversions between this error chain and other
+  // error types not defined by the `error_chain!`.
+  foreign_links {
+    io::Error, Io, "io error";
+  }
+
+  // Define additional `ErrorKind` variants.
+  errors {
+    NotAuthorized {
+      description("control command not authorized")
+      display("control command not authorized")
+    }
+
+    UnknownCommand(command: String) {
+      description("unknown control command")
+      display("unknown control command: {}", command)
+    }
+  }
+}
@@ -20,16 +20,21 @@
 #![allow(missing_docs)]
 
 mod config_error;
+mod control_error;
 mod persistence_error;
 
 pub use self::config_error::Error as ConfigError;
+pub use self::control_error::Error as ControlError;
 pub use self::persistence_error::Error as PersistenceError;
 
 pub use self::config_error::ErrorKind as ConfigErrorKind;
+pub use self::control_error::ErrorKind as ControlErrorKind;
 pub use self::persistence_error::ErrorKind as PersistenceErrorKind;
 
 pub use self::config_error::ChainErr as ConfigChainErr;
+pub use self::control_error::ChainErr as ControlChainErr;
 pub use self::persistence_error::ChainErr as PersistenceChainErr;
 
 pub use self::config_error::Result as ConfigResult;
+pub use self::control_error::Result as ControlResult;
 pub use self::persistence_error::Result as PersistenceResult;
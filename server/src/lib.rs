@@ -27,29 +27,47 @@
 //! * Secure dynamic update
 //! * New features for securing public information
 
+#[cfg(feature = "dns-over-https")]
+extern crate bytes;
 extern crate chrono;
 extern crate env_logger;
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
 extern crate futures;
+#[cfg(feature = "dns-over-https")]
+extern crate h2;
+#[cfg(feature = "dns-over-https")]
+extern crate http;
+#[macro_use]
+extern crate lazy_static;
 #[macro_use]
 extern crate log;
+extern crate lru_cache;
+extern crate rand;
 extern crate rusqlite;
 extern crate rustc_serialize;
 extern crate time;
 extern crate tokio_core;
+extern crate tokio_io;
+#[cfg(unix)]
+extern crate tokio_uds;
 extern crate toml;
 extern crate trust_dns;
 extern crate trust_dns_proto;
+extern crate trust_dns_resolver;
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 extern crate trust_dns_openssl;
+#[cfg(feature = "tls-ring")]
+extern crate trust_dns_rustls;
 
 pub mod authority;
 pub mod config;
+pub mod control;
 pub mod error;
 pub mod logger;
+pub mod metrics;
 pub mod server;
 
 pub use self::server::ServerFuture;
@@ -0,0 +1,505 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Operational metrics of the server, exposed in the Prometheus text format
+//!
+//! Metrics are only collected once they are enabled, see `enable`, and are shared by the whole
+//!  process, like the logger.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::{self, BufReader, Read};
+use std::net::TcpListener;
+use std::str;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT};
+use std::sync::{RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant};
+
+use futures::future::{loop_fn, Either, Loop};
+use futures::{Future, Stream};
+use tokio_core;
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_io::io::{read_until, write_all};
+
+use trust_dns::op::{OpCode, ResponseCode};
+use trust_dns::rr::{LowerName, RecordType};
+use trust_dns_proto::op::EncodableMessage;
+
+use server::ResponseHandler;
+
+/// upper bounds of the buckets of the response latency histogram, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// the longest HTTP request which is read, its request line and headers
+const MAX_REQUEST_LEN: u64 = 8192;
+
+/// the time in seconds a client has to send its request and read the metrics
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+static ENABLED: AtomicBool = ATOMIC_BOOL_INIT;
+
+lazy_static! {
+    static ref METRICS: Metrics = Metrics::default();
+}
+
+// values by label, the values of labels which were seen before are updated under a shared lock,
+//  which is only taken exclusively to add new labels
+struct Labeled<K: Ord, V> {
+    values: RwLock<BTreeMap<K, V>>,
+}
+
+impl<K: Ord + Clone, V: Default> Labeled<K, V> {
+    fn with<F: FnOnce(&V)>(&self, label: &K, f: F) {
+        {
+            let values = self.read();
+            if let Some(value) = values.get(label) {
+                return f(value);
+            }
+        }
+
+        let mut values = self.values.write().expect("metrics lock poisoned");
+        f(values.entry(label.clone()).or_insert_with(V::default))
+    }
+
+    fn increment(&self, label: &K)
+    where
+        V: Counter,
+    {
+        self.with(label, |count| count.increment())
+    }
+}
+
+impl<K: Ord, V> Labeled<K, V> {
+    fn read(&self) -> RwLockReadGuard<BTreeMap<K, V>> {
+        self.values.read().expect("metrics lock poisoned")
+    }
+}
+
+impl<K: Ord, V> Default for Labeled<K, V> {
+    fn default() -> Self {
+        Labeled {
+            values: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+trait Counter {
+    fn increment(&self);
+}
+
+impl Counter for AtomicUsize {
+    fn increment(&self) {
+        self.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct Histogram {
+    buckets: Vec<AtomicUsize>,
+    sum_micros: AtomicUsize,
+    count: AtomicUsize,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            if seconds <= *bound {
+                bucket.increment();
+            }
+        }
+
+        let micros =
+            elapsed.as_secs() as usize * 1_000_000 + elapsed.subsec_nanos() as usize / 1_000;
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.count.increment();
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            buckets: LATENCY_BUCKETS
+                .iter()
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+            sum_micros: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+        }
+    }
+}
+
+// the label of a request, the type of its first query, or its op code for other requests
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RequestKind {
+    Query(u16),
+    Other(u8),
+}
+
+impl RequestKind {
+    fn label(&self) -> String {
+        match *self {
+            RequestKind::Query(query_type) => RecordType::from(query_type).to_string(),
+            RequestKind::Other(op_code) => OpCode::from_u8(op_code)
+                .map(|op_code| format!("{:?}", op_code).to_uppercase())
+                .unwrap_or_else(|_| op_code.to_string()),
+        }
+    }
+}
+
+// the counters are updated concurrently by all the threads of the server, see `Labeled`
+#[derive(Default)]
+struct Metrics {
+    queries: Labeled<(&'static str, RequestKind), AtomicUsize>,
+    responses: Labeled<(&'static str, u16), AtomicUsize>,
+    zone_queries: Labeled<LowerName, AtomicUsize>,
+    response_seconds: Labeled<&'static str, Histogram>,
+    connections: Labeled<&'static str, AtomicUsize>,
+    updates: Labeled<&'static str, AtomicUsize>,
+    journal_writes: AtomicUsize,
+}
+
+/// Starts the collection of metrics, they are not collected by default
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn with_metrics<F: FnOnce(&Metrics)>(f: F) {
+    if ENABLED.load(Ordering::Relaxed) {
+        f(&METRICS)
+    }
+}
+
+/// Counts a request received by the server
+///
+/// # Arguments
+///
+/// * `protocol` - the protocol of the request, e.g. `udp`
+/// * `op_code` - the op code of the request, queries are counted by the type of the first query
+/// * `query_type` - the type of the first query of the request, if any
+pub fn count_request(protocol: &'static str, op_code: OpCode, query_type: Option<RecordType>) {
+    with_metrics(|metrics| {
+        let kind = match (op_code, query_type) {
+            (OpCode::Query, Some(query_type)) => RequestKind::Query(u16::from(query_type)),
+            (op_code, _) => RequestKind::Other(u8::from(op_code)),
+        };
+        metrics.queries.increment(&(protocol, kind));
+    })
+}
+
+/// Counts a response sent by the server, and the time it took to respond
+///
+/// # Arguments
+///
+/// * `protocol` - the protocol of the request, e.g. `udp`
+/// * `response_code` - the response code of the response
+/// * `elapsed` - the time from the receipt of the request to the response
+pub fn count_response(protocol: &'static str, response_code: ResponseCode, elapsed: Duration) {
+    with_metrics(|metrics| {
+        metrics
+            .responses
+            .increment(&(protocol, u16::from(response_code)));
+        metrics
+            .response_seconds
+            .with(&protocol, |histogram| histogram.observe(elapsed));
+    })
+}
+
+/// Counts a query answered from a zone of the server
+pub fn count_zone_query(zone: &LowerName) {
+    with_metrics(|metrics| metrics.zone_queries.increment(zone))
+}
+
+/// Counts a connection accepted by the server, e.g. for `tcp`
+pub fn count_connection(protocol: &'static str) {
+    with_metrics(|metrics| metrics.connections.increment(&protocol))
+}
+
+/// Counts a dynamic update, by whether it succeeded
+pub fn count_update(success: bool) {
+    with_metrics(|metrics| {
+        let result = if success { "success" } else { "failure" };
+        metrics.updates.increment(&result);
+    })
+}
+
+/// Counts a record written to a journal
+pub fn count_journal_write() {
+    with_metrics(|metrics| metrics.journal_writes.increment())
+}
+
+/// Renders all the metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    let metrics = &*METRICS;
+    let mut text = String::new();
+
+    header(
+        &mut text,
+        "trust_dns_requests_total",
+        "counter",
+        "Requests received, by protocol and query type or op code",
+    );
+    for (&(protocol, kind), count) in &*metrics.queries.read() {
+        writeln!(
+            text,
+            "trust_dns_requests_total{{protocol=\"{}\",type=\"{}\"}} {}",
+            protocol,
+            kind.label(),
+            count.load(Ordering::Relaxed)
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_responses_total",
+        "counter",
+        "Responses sent, by protocol and response code",
+    );
+    for (&(protocol, rcode), count) in &*metrics.responses.read() {
+        writeln!(
+            text,
+            "trust_dns_responses_total{{protocol=\"{}\",rcode=\"{}\"}} {}",
+            protocol,
+            format!("{:?}", <ResponseCode as From<u16>>::from(rcode)).to_uppercase(),
+            count.load(Ordering::Relaxed)
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_zone_queries_total",
+        "counter",
+        "Queries answered from each zone",
+    );
+    for (zone, count) in &*metrics.zone_queries.read() {
+        writeln!(
+            text,
+            "trust_dns_zone_queries_total{{zone=\"{}\"}} {}",
+            zone.to_string().replace('\\', "\\\\").replace('"', "\\\""),
+            count.load(Ordering::Relaxed)
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_response_seconds",
+        "histogram",
+        "Time from the receipt of a request to its response",
+    );
+    for (protocol, histogram) in &*metrics.response_seconds.read() {
+        for (bucket, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+            writeln!(
+                text,
+                "trust_dns_response_seconds_bucket{{protocol=\"{}\",le=\"{}\"}} {}",
+                protocol,
+                bound,
+                bucket.load(Ordering::Relaxed)
+            ).unwrap();
+        }
+        let count = histogram.count.load(Ordering::Relaxed);
+        let sum = histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        writeln!(
+            text,
+            "trust_dns_response_seconds_bucket{{protocol=\"{}\",le=\"+Inf\"}} {}",
+            protocol, count
+        ).unwrap();
+        writeln!(
+            text,
+            "trust_dns_response_seconds_sum{{protocol=\"{}\"}} {}",
+            protocol, sum
+        ).unwrap();
+        writeln!(
+            text,
+            "trust_dns_response_seconds_count{{protocol=\"{}\"}} {}",
+            protocol, count
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_connections_total",
+        "counter",
+        "Connections accepted, by protocol",
+    );
+    for (protocol, count) in &*metrics.connections.read() {
+        writeln!(
+            text,
+            "trust_dns_connections_total{{protocol=\"{}\"}} {}",
+            protocol,
+            count.load(Ordering::Relaxed)
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_updates_total",
+        "counter",
+        "Dynamic updates, by result",
+    );
+    for (result, count) in &*metrics.updates.read() {
+        writeln!(
+            text,
+            "trust_dns_updates_total{{result=\"{}\"}} {}",
+            result,
+            count.load(Ordering::Relaxed)
+        ).unwrap();
+    }
+
+    header(
+        &mut text,
+        "trust_dns_journal_writes_total",
+        "counter",
+        "Records written to the journals of the zones",
+    );
+    writeln!(
+        text,
+        "trust_dns_journal_writes_total {}",
+        metrics.journal_writes.load(Ordering::Relaxed)
+    ).unwrap();
+
+    text
+}
+
+/// Counts the response when it is sent, see `count_response`
+pub struct MeteredResponseHandle<R: ResponseHandler> {
+    response_handle: R,
+    protocol: &'static str,
+    received: Instant,
+}
+
+impl<R: ResponseHandler> MeteredResponseHandle<R> {
+    /// Returns a new `MeteredResponseHandle` wrapping the `ResponseHandler`
+    ///
+    /// # Arguments
+    ///
+    /// * `response_handle` - sends the response
+    /// * `protocol` - the protocol of the request, e.g. `udp`
+    /// * `received` - when the request was received
+    pub fn new(response_handle: R, protocol: &'static str, received: Instant) -> Self {
+        MeteredResponseHandle {
+            response_handle,
+            protocol,
+            received,
+        }
+    }
+}
+
+impl<R: ResponseHandler> ResponseHandler for MeteredResponseHandle<R> {
+    fn send<M: EncodableMessage>(self, response: M) -> io::Result<()> {
+        count_response(
+            self.protocol,
+            ResponseCode::from(0, response.header().response_code()),
+            self.received.elapsed(),
+        );
+        self.response_handle.send(response)
+    }
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(text, "# HELP {} {}", name, help).unwrap();
+    writeln!(text, "# TYPE {} {}", name, kind).unwrap();
+}
+
+/// Serves the metrics over HTTP, at `/metrics`, and enables their collection
+///
+/// Clients which do not send their request and read the response within a few seconds are
+///  disconnected.
+///
+/// # Arguments
+///
+/// * `listener` - a bound TCP socket
+/// * `handle` - the reactor on which requests are served
+pub fn serve(listener: TcpListener, handle: &Handle) -> io::Result<()> {
+    enable();
+
+    let addr = listener.local_addr()?;
+    let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, handle)?;
+    info!("serving metrics on: {}", addr);
+
+    let inner_handle = handle.clone();
+    handle.spawn(
+        listener
+            .incoming()
+            .for_each(move |(stream, src_addr)| {
+                debug!("accepted metrics request from: {}", src_addr);
+                let timeout =
+                    Timeout::new(Duration::from_secs(REQUEST_TIMEOUT_SECS), &inner_handle)?;
+                inner_handle.spawn(
+                    read_request(stream)
+                        .and_then(|(stream, request_line)| {
+                            write_all(stream, respond(&request_line))
+                        })
+                        .select2(timeout)
+                        .map(|_| ())
+                        .map_err(move |e| match e {
+                            Either::A((e, _)) | Either::B((e, _)) => {
+                                debug!("error serving metrics to: {}: {}", src_addr, e)
+                            }
+                        }),
+                );
+
+                Ok(())
+            })
+            .map_err(|e| warn!("error in inbound metrics stream: {}", e)),
+    );
+
+    Ok(())
+}
+
+// reads the request line, and then the headers up to the empty line which ends them
+fn read_request(stream: TcpStream) -> Box<Future<Item = (TcpStream, Vec<u8>), Error = io::Error>> {
+    let reader = BufReader::new(stream.take(MAX_REQUEST_LEN));
+    Box::new(read_until(reader, b'\n', Vec::with_capacity(64)).and_then(
+        |(reader, request_line)| {
+            loop_fn(reader, |reader| {
+                read_until(reader, b'\n', Vec::new()).map(|(reader, header)| {
+                    // the end of the stream, or of the longest request, also ends the headers
+                    if header.is_empty() || header == b"\r\n" || header == b"\n" {
+                        Loop::Break(reader)
+                    } else {
+                        Loop::Continue(reader)
+                    }
+                })
+            })
+            .map(|reader| (reader.into_inner().into_inner(), request_line))
+        },
+    ))
+}
+
+// the response to the request line, e.g. GET /metrics HTTP/1.1
+fn respond(request_line: &[u8]) -> Vec<u8> {
+    let request_line = match str::from_utf8(request_line) {
+        Ok(request_line) => request_line.trim_right_matches(&['\r', '\n'][..]),
+        Err(_) => return http_response("400 Bad Request", "bad request\n"),
+    };
+
+    let mut parts = request_line.split(' ');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version), None) if version.starts_with("HTTP/1.") => {
+            // the query of the target is ignored
+            let path = target.split('?').next().unwrap_or(target);
+            match (method, path) {
+                ("GET", "/metrics") => http_response("200 OK", &render()),
+                (_, "/metrics") => http_response("405 Method Not Allowed", "method not allowed\n"),
+                _ => http_response("404 Not Found", "not found\n"),
+            }
+        }
+        _ => http_response("400 Bad Request", "bad request\n"),
+    }
+}
+
+fn http_response(status: &str, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.0 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    ).into_bytes()
+}
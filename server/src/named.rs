@@ -29,49 +29,90 @@
 //!    -z DIR, --zonedir=DIR   Path to the root directory for all zone files, see also config toml
 //!    -p PORT, --port=PORT    Override the listening port
 //!    --tls-port=PORT         Override the listening port for TLS connections
+//!    --https-port=PORT       Listening port for DNS over HTTPS connections
 //! ```
 
 extern crate chrono;
 #[macro_use]
 extern crate clap;
+extern crate futures;
 #[macro_use]
 extern crate log;
 extern crate rustc_serialize;
+extern crate socket2;
+extern crate tokio_core;
+#[cfg(unix)]
+extern crate tokio_signal;
 extern crate trust_dns;
+extern crate trust_dns_resolver;
 extern crate trust_dns_server;
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 extern crate trust_dns_openssl;
+#[cfg(feature = "tls-ring")]
+extern crate trust_dns_rustls;
 
-use std::fs::File;
-use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::collections::{BTreeMap, HashMap};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, ToSocketAddrs, UdpSocket};
 use std::path::{Path, PathBuf};
-use std::io::Read;
+use std::cell::RefCell;
+use std::io::{self, BufWriter, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::SystemTime;
 
 #[cfg(feature = "dnssec")]
 use chrono::Duration;
 
 use clap::{Arg, ArgMatches};
+use futures::sync::mpsc::{unbounded, UnboundedReceiver};
+#[cfg(unix)]
+use futures::{Future, Stream};
+#[cfg(unix)]
+use socket2::Domain;
+use socket2::{Protocol, SockAddr, Socket, Type};
+use tokio_core::reactor::{Core, Handle};
+#[cfg(unix)]
+use tokio_signal::unix::{Signal, SIGHUP};
 
 use trust_dns::error::ParseResult;
 use trust_dns::serialize::txt::{Lexer, Parser};
-use trust_dns::rr::Name;
+use trust_dns::rr::{LowerName, Name, Record};
+use trust_dns::rr::dnssec::{TSigner, DEFAULT_FUDGE};
+use trust_dns_resolver::config::ResolverConfig;
 
 #[cfg(feature = "dnssec")]
-use trust_dns::rr::dnssec::{KeyPair, Signer};
-
-use trust_dns_server::authority::{Authority, Catalog, Journal, ZoneType};
-use trust_dns_server::config::{Config, TlsCertConfig, ZoneConfig};
+use trust_dns::rr::dnssec::{KeyPair, Nsec3HashAlgorithm, Signer};
+#[cfg(feature = "dnssec")]
+use trust_dns::rr::rdata::NSEC3PARAM;
+
+use trust_dns_server::authority::{Authority, Catalog, Forwarder, Identity, Journal,
+                                  NotifyFuture, Recursor, Refresh, ReloadableCatalog, SlaveFuture,
+                                  View, ZoneType};
+use trust_dns_server::config::{Config, RateLimitConfig, TlsCertConfig, TsigKeyConfig, ZoneConfig};
+use trust_dns_server::control::{self, Command, ControlHandler};
+use trust_dns_server::error::{ControlError, ControlResult};
 use trust_dns_server::logger;
+use trust_dns_server::metrics;
 
 #[cfg(feature = "dnssec")]
 use trust_dns_server::config::KeyConfig;
 
-use trust_dns_server::server::ServerFuture;
+use trust_dns_server::server::{RateLimiter, ServerCookies, ServerFuture};
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 use trust_dns_openssl::tls_server::*;
+#[cfg(feature = "tls-ring")]
+use trust_dns_rustls::tls_server::{self as rustls_server, Certificate, PrivateKey};
 
 fn parse_zone_file(
     file: File,
@@ -98,16 +139,56 @@ fn parse_zone_file(
     ))
 }
 
+// the records of a variant for some networks, in the format of a zone file
+fn parse_subnet_file(path: &Path, origin: Name) -> Result<Vec<Record>, String> {
+    let mut buf = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut buf))
+        .map_err(|e| format!("error opening subnet file: {:?}: {}", path, e))?;
+
+    let lexer = Lexer::new(&buf);
+    let (_, records) = Parser::new()
+        .parse(lexer, Some(origin))
+        .map_err(|e| format!("error reading subnet file: {:?}: {}", path, e))?;
+
+    Ok(records
+        .into_iter()
+        .flat_map(|(_, rrset)| rrset.into_iter())
+        .collect())
+}
+
+/// Loads the zone, from its journal if it has one, otherwise from its file
+///
+/// With `from_file`, a dynamic zone is loaded from its file and its journal is replaced by the
+///  zone, e.g. once the file of the frozen zone was edited.
 #[cfg_attr(not(feature = "dnssec"), allow(unused_mut))]
-fn load_zone(zone_dir: &Path, zone_config: &ZoneConfig) -> Result<Authority, String> {
+fn load_zone(
+    zone_dir: &Path,
+    zone_config: &ZoneConfig,
+    tsig_keys: &[TSigner],
+    from_file: bool,
+) -> Result<Authority, String> {
     debug!("loading zone with config: {:#?}", zone_config);
 
     let zone_name: Name = zone_config.get_zone().expect("bad zone name");
-    let zone_path: PathBuf = zone_dir.to_owned().join(zone_config.get_file());
+    let zone_file = zone_config
+        .get_file()
+        .ok_or_else(|| format!("no file for zone: {}", zone_name))?;
+    let zone_path: PathBuf = zone_dir.to_owned().join(zone_file);
     let journal_path: PathBuf = zone_path.with_extension("jrnl");
 
+    // slave zones with masters are transferred, the journal keeps the last transfer across restarts
+    let is_transferred = zone_config.get_zone_type() == ZoneType::Slave
+        && !zone_config
+            .get_masters()
+            .map_err(|e| format!("bad masters: {}", e))?
+            .is_empty();
+
     // load the zone
-    let mut authority = if zone_config.is_update_allowed() && journal_path.exists() {
+    let mut authority = if (zone_config.is_update_allowed() || is_transferred)
+        && !from_file
+        && journal_path.exists()
+    {
         info!("recovering zone from journal: {:?}", journal_path);
         let journal = Journal::from_file(&journal_path).map_err(|e| {
             format!("error opening journal: {:?}: {}", journal_path, e)
@@ -120,6 +201,7 @@ fn load_zone(zone_dir: &Path, zone_config: &ZoneConfig) -> Result<Authority, Str
             zone_config.is_update_allowed(),
             zone_config.is_dnssec_enabled(),
         );
+        authority.set_ixfr_depth(zone_config.get_ixfr_depth());
         authority
             .recover_with_journal(&journal)
             .map_err(|e| format!("error recovering from journal: {}", e))?;
@@ -127,6 +209,22 @@ fn load_zone(zone_dir: &Path, zone_config: &ZoneConfig) -> Result<Authority, Str
         authority.set_journal(journal);
         info!("recovered zone: {}", zone_name);
 
+        authority
+    } else if is_transferred {
+        info!("zone will be transferred from masters: {}", zone_name);
+        let journal = Journal::from_file(&journal_path).map_err(|e| {
+            format!("error creating journal {:?}: {}", journal_path, e)
+        })?;
+
+        let mut authority = Authority::new(
+            zone_name.clone(),
+            BTreeMap::new(),
+            zone_config.get_zone_type(),
+            zone_config.is_update_allowed(),
+            zone_config.is_dnssec_enabled(),
+        );
+        authority.set_journal(journal);
+
         authority
     } else if zone_path.exists() {
         info!("loading zone file: {:?}", zone_path);
@@ -186,6 +284,24 @@ fn load_zone(zone_dir: &Path, zone_config: &ZoneConfig) -> Result<Authority, Str
                     .expect("failed to add key to authority");
             }
 
+            if let Some(nsec3) = zone_config.get_nsec3() {
+                let salt = nsec3
+                    .salt()
+                    .map_err(|e| format!("bad nsec3 config: {}", e))?;
+                info!(
+                    "using nsec3 for zone: {}, iterations: {}, opt_out: {}",
+                    zone_name,
+                    nsec3.iterations(),
+                    nsec3.opt_out()
+                );
+                authority.set_nsec3(Some(NSEC3PARAM::new(
+                    Nsec3HashAlgorithm::SHA1,
+                    nsec3.opt_out(),
+                    nsec3.iterations(),
+                    salt,
+                )));
+            }
+
             info!("signing zone: {}", zone_config.get_zone().unwrap());
             authority.secure_zone().expect("failed to sign zone");
         }
@@ -201,9 +317,57 @@ fn load_zone(zone_dir: &Path, zone_config: &ZoneConfig) -> Result<Authority, Str
         Ok(())
     }
 
+    // the changes of the last updates are kept for incremental zone transfers
+    authority.set_ixfr_depth(zone_config.get_ixfr_depth());
+
+    // clients in these networks are answered with variants of the records, signed with the zone
+    for subnet in zone_config.get_subnets() {
+        let networks = subnet
+            .get_networks()
+            .map_err(|e| format!("bad subnet networks: {}", e))?;
+        let records = parse_subnet_file(
+            &zone_dir.join(subnet.get_file()),
+            authority.origin().clone().into(),
+        )?;
+
+        info!(
+            "adding subnet variant to zone: {}, networks: {:?}",
+            authority.origin(),
+            networks
+        );
+        authority.add_subnet(networks, records);
+    }
+
     // load any keys for the Zone, if it is a dynamic update zone, then keys are required
     load_keys(&mut authority, zone_name, zone_config)?;
 
+    // the TSIG keys are shared by all zones, each zone lists those it accepts
+    for key_name in zone_config.get_tsig_keys() {
+        let key_name = Name::parse(key_name, Some(&Name::root()))
+            .map_err(|e| format!("bad tsig key name: {}: {}", key_name, e))?;
+        let tsigner = tsig_keys
+            .iter()
+            .find(|tsigner| *tsigner.signer_name() == key_name)
+            .ok_or_else(|| format!("tsig key not defined: {}", key_name))?;
+
+        info!("adding tsig key to zone: {}", key_name);
+        authority.add_tsig_key(tsigner.clone());
+    }
+
+    // clients presenting these certificates over mutual TLS may update the zone
+    for name in zone_config
+        .get_update_certificates()
+        .map_err(|e| format!("bad update certificate name: {}", e))?
+    {
+        info!("adding update certificate to zone: {}", name);
+        authority.add_update_certificate(name);
+    }
+
+    // until the first transfer completes, the zone can not be answered authoritatively
+    if is_transferred && authority.soa().is_empty() {
+        authority.set_expired(true);
+    }
+
     info!(
         "zone successfully loaded: {}",
         zone_config.get_zone().unwrap()
@@ -270,7 +434,22 @@ fn load_key(zone_name: Name, key_config: &KeyConfig) -> Result<Signer, String> {
     ))
 }
 
-#[cfg(feature = "tls")]
+/// reads the shared secret for TSIG, the same key must be configured on the peer
+fn load_tsig_key(tsig_key_config: &TsigKeyConfig) -> Result<TSigner, String> {
+    let name = tsig_key_config
+        .name()
+        .map_err(|e| format!("bad tsig key name: {}", e))?;
+    let algorithm = tsig_key_config
+        .algorithm()
+        .map_err(|e| format!("bad algorithm for tsig key: {}: {}", name, e))?;
+    let secret = tsig_key_config
+        .secret()
+        .map_err(|e| format!("bad secret for tsig key: {}: {}", name, e))?;
+
+    Ok(TSigner::new(secret, algorithm, name, DEFAULT_FUDGE))
+}
+
+#[cfg(feature = "tls-openssl")]
 fn load_cert(zone_dir: &Path, tls_cert_config: &TlsCertConfig) -> Result<ParsedPkcs12, String> {
     let path = zone_dir.to_owned().join(tls_cert_config.get_path());
     let password = tls_cert_config.get_password();
@@ -279,6 +458,803 @@ fn load_cert(zone_dir: &Path, tls_cert_config: &TlsCertConfig) -> Result<ParsedP
     read_cert(&path, password)
 }
 
+#[cfg(feature = "tls-ring")]
+fn load_pem_cert(
+    zone_dir: &Path,
+    tls_cert_config: &TlsCertConfig,
+    private_key: &Path,
+) -> Result<(Vec<Certificate>, PrivateKey), String> {
+    let path = zone_dir.to_owned().join(tls_cert_config.get_path());
+    let key_path = zone_dir.to_owned().join(private_key);
+
+    info!("reading TLS certificate chain from: {:?}", path);
+    let cert_chain = rustls_server::read_cert(&path)?;
+    info!("reading TLS private key from: {:?}", key_path);
+    let key = rustls_server::read_key(&key_path)?;
+
+    Ok((cert_chain, key))
+}
+
+/// loads the tsig keys of the configuration, keys which can not be loaded are skipped
+fn load_tsig_keys(config: &Config) -> Vec<TSigner> {
+    config
+        .get_tsig_keys()
+        .iter()
+        .filter_map(|tsig_key_config| {
+            load_tsig_key(tsig_key_config)
+                .map_err(|error| error!("could not load tsig key: {}", error))
+                .ok()
+        })
+        .collect()
+}
+
+// the modification time of the file of the zone, to find the zones which changed on reload
+fn zone_file_modified(zone_dir: &Path, zone_config: &ZoneConfig) -> Option<SystemTime> {
+    zone_config.get_file().and_then(|file| {
+        fs::metadata(zone_dir.join(file))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    })
+}
+
+/// Loads the zone again, in place of its authority, which is kept if the zone can not be loaded
+///
+/// The journal of the authority is closed before the zone is loaded, from the same journal or
+///  with `from_file` from the zone file, see `load_zone`. The authority is locked meanwhile, no
+///  update to it is lost. The zone stays frozen, if it was. Returns the serial of the zone.
+fn reload_authority(
+    zone_dir: &Path,
+    zone_config: &ZoneConfig,
+    tsig_keys: &[TSigner],
+    authority: &RwLock<Authority>,
+    from_file: bool,
+) -> Result<u32, String> {
+    let mut authority = authority.write().unwrap(); // poison errors should panic
+    let is_frozen = authority.is_frozen();
+    let had_journal = authority.take_journal().is_some();
+
+    match load_zone(zone_dir, zone_config, tsig_keys, from_file) {
+        Ok(mut reloaded) => {
+            reloaded.set_frozen(is_frozen);
+            let serial = reloaded.serial();
+            *authority = reloaded;
+            Ok(serial)
+        }
+        Err(error) => {
+            // the updates to the authority which is kept are journaled again
+            match zone_config.get_file() {
+                Some(file) if had_journal => {
+                    let journal_path = zone_dir.join(file).with_extension("jrnl");
+                    match Journal::from_file(&journal_path) {
+                        Ok(journal) => authority.set_journal(journal),
+                        Err(e) => error!("could not reopen journal: {:?}: {}", journal_path, e),
+                    }
+                }
+                _ => (),
+            }
+            Err(error)
+        }
+    }
+}
+
+// the configuration with which the zones were loaded, to find the zones which changed on reload
+struct Loaded {
+    config: Config,
+    zone_dir: PathBuf,
+    // the modification time of the file of each zone, when the zone was loaded
+    modified: HashMap<LowerName, Option<SystemTime>>,
+}
+
+/// The changes to the running zones of a Catalog which is being loaded, made once the whole
+///  configuration is loaded, so that a configuration which fails to load changes none of them
+#[derive(Default)]
+struct Pending<'c> {
+    // the zones with a journal, loaded again in place, with the modification time of their file
+    //  when they were previously loaded, and whether they are loaded from the file
+    reloads: Vec<(LowerName, &'c ZoneConfig, Arc<RwLock<Authority>>, Option<SystemTime>, bool)>,
+    // the Slave zones, transferred from their masters
+    slaves: Vec<(Arc<RwLock<Authority>>, Vec<SocketAddr>, UnboundedReceiver<Refresh>)>,
+    // the Master zones, which notify their slaves after updates
+    notifies: Vec<(Arc<RwLock<Authority>>, Vec<SocketAddr>, UnboundedReceiver<()>)>,
+}
+
+impl<'c> Pending<'c> {
+    /// Reloads the zones with a journal, and starts the transfers of the Slave zones and the
+    ///  NOTIFY of the slaves of the Master zones on the reactor
+    ///
+    /// The modification time of the file of a zone which is kept, because it could not be loaded,
+    ///  is the one of the previous load.
+    fn start(
+        self,
+        zone_dir: &Path,
+        tsig_keys: &[TSigner],
+        modified: &mut HashMap<LowerName, Option<SystemTime>>,
+        handle: &Handle,
+    ) {
+        for (zone_name, zone, authority, loaded_modified, from_file) in self.reloads {
+            match reload_authority(zone_dir, zone, tsig_keys, &authority, from_file) {
+                Ok(serial) => info!("zone reloaded: {} serial: {}", zone_name, serial),
+                Err(error) => {
+                    warn!("keeping the loaded zone {}: {}", zone_name, error);
+                    modified.insert(zone_name, loaded_modified);
+                }
+            }
+        }
+
+        // the transfers start from the reloaded zones
+        for (authority, masters, refreshes) in self.slaves {
+            handle.spawn(SlaveFuture::new(authority, masters, refreshes, handle));
+        }
+        for (authority, also_notify, notifications) in self.notifies {
+            handle.spawn(NotifyFuture::new(
+                authority,
+                also_notify,
+                notifications,
+                handle,
+            ));
+        }
+    }
+}
+
+/// Builds the Catalog of the zones in the configuration, with the views
+///
+/// Zones which are unchanged since they were previously loaded, in their configuration and in
+///  their zone file, are adopted from the previous Catalog. The others are loaded, and the
+///  transfers of Slave zones and the NOTIFY of the slaves of Master zones are started on the
+///  reactor, once the whole configuration is loaded, see `Pending`; the zones with a journal are
+///  then loaded again in place. The zones of the views are always loaded again. The Forwarders and the Recursor,
+///  with their caches, are kept if their configuration is unchanged, also those of the views.
+///  Returns the Catalog, with the modification time of the file of each zone outside of the
+///  views.
+///
+/// The identity of the server is shared by the Catalog and its views.
+fn load_catalog(
+    config: &Config,
+    config_path: &Path,
+    zone_dir: &Path,
+    previous: Option<(&Loaded, &Catalog)>,
+    handle: &Handle,
+) -> Result<(Catalog, HashMap<LowerName, Option<SystemTime>>), String> {
+    let identity = load_identity(config);
+
+    // the caches of the resolvers are kept, unless their size changed
+    let resolvers = match previous {
+        Some((loaded, previous)) if loaded.config.get_cache_size() == config.get_cache_size() => {
+            Some(previous)
+        }
+        _ => None,
+    };
+
+    let mut pending = Pending::default();
+    let (mut catalog, mut modified) = load_zones(
+        config,
+        config_path,
+        zone_dir,
+        config.get_zones(),
+        previous,
+        resolvers,
+        &mut pending,
+        handle,
+    )?;
+
+    // requests which match none of the views are refused, see `Catalog::add_view`
+    if !config.get_views().is_empty() && !config.get_zones().is_empty() {
+        warn!(
+            "with views, the zones outside of them are not served in {:?}",
+            config_path
+        );
+    }
+    for view_config in config.get_views() {
+        let name = view_config.get_name();
+        // as for the ACLs, a bad view must not serve its zones to other clients
+        let match_clients = view_config.get_match_clients().map_err(|e| {
+            format!(
+                "bad match_clients for view {} in {:?}: {}",
+                name, config_path, e
+            )
+        })?;
+        let match_destinations = view_config.get_match_destinations().map_err(|e| {
+            format!(
+                "bad match_destinations for view {} in {:?}: {}",
+                name, config_path, e
+            )
+        })?;
+
+        let view_resolvers = resolvers
+            .and_then(|resolvers| resolvers.views().iter().find(|view| view.name() == name))
+            .map(View::catalog);
+        let (mut view_catalog, _) = load_zones(
+            config,
+            config_path,
+            zone_dir,
+            view_config.get_zones(),
+            None,
+            view_resolvers,
+            &mut pending,
+            handle,
+        )?;
+        if let Some(ref identity) = identity {
+            view_catalog.set_identity(identity.clone());
+        }
+        info!("view: {} zones: {}", name, view_catalog.zones().len());
+        catalog.add_view(View::new(
+            name.to_string(),
+            match_clients,
+            match_destinations,
+            view_catalog,
+        ));
+    }
+    if let Some(identity) = identity {
+        catalog.set_identity(identity);
+    }
+
+    pending.start(zone_dir, &load_tsig_keys(config), &mut modified, handle);
+    Ok((catalog, modified))
+}
+
+/// Returns the identity of the server, its NSID and its CHAOS class answers, if configured
+fn load_identity(config: &Config) -> Option<Arc<Identity>> {
+    let identity_config = match config.get_identity() {
+        Some(identity_config) => identity_config,
+        None => return None,
+    };
+
+    let mut identity = Identity::new();
+    if let Some(nsid) = identity_config.get_nsid() {
+        info!("nsid: {}", nsid);
+        identity.set_nsid(nsid.as_bytes().to_vec());
+    }
+    if let Some(version) = identity_config.get_version() {
+        identity.set_version(version.to_string());
+    }
+    if let Some(hostname) = identity_config.get_hostname() {
+        identity.set_hostname(hostname.to_string());
+    }
+    if let Some(id) = identity_config.get_id() {
+        identity.set_id(id.to_string());
+    }
+
+    Some(Arc::new(identity))
+}
+
+/// Returns the DNS Cookies of the server, unless they are disabled
+fn load_server_cookies(config: &Config) -> Result<Option<Arc<ServerCookies>>, String> {
+    let cookies_config = match config.get_cookies() {
+        Some(cookies_config) if !cookies_config.is_enabled() => return Ok(None),
+        cookies_config => cookies_config,
+    };
+    let secret = match cookies_config {
+        Some(cookies_config) => cookies_config
+            .get_secret()
+            .map_err(|e| format!("bad cookies: {}", e))?,
+        None => None,
+    };
+
+    match secret {
+        Some(secret) => Ok(Some(Arc::new(ServerCookies::with_secret(secret)))),
+        None => Ok(Some(Arc::new(ServerCookies::new()))),
+    }
+}
+
+/// Builds a Catalog of the zones, see `load_catalog`
+///
+/// The Forwarders and the Recursor of `resolvers` are kept if they have the same upstream
+///  servers, or root servers. The changes to the running zones are added to `pending`.
+fn load_zones<'c>(
+    config: &Config,
+    config_path: &Path,
+    zone_dir: &Path,
+    zones: &'c [ZoneConfig],
+    previous: Option<(&Loaded, &Catalog)>,
+    resolvers: Option<&Catalog>,
+    pending: &mut Pending<'c>,
+    handle: &Handle,
+) -> Result<(Catalog, HashMap<LowerName, Option<SystemTime>>), String> {
+    let tsig_keys = load_tsig_keys(config);
+
+    // a bad access control list must not leave the zones open, it is fatal
+    let default_acls = config
+        .get_default_acls()
+        .map_err(|e| format!("bad acl in {:?}: {}", config_path, e))?;
+    let recursion_acl = config
+        .get_recursion_acl()
+        .map_err(|e| format!("bad recursion_acl in {:?}: {}", config_path, e))?;
+
+    let mut catalog: Catalog = Catalog::new();
+    catalog.set_default_acls(default_acls.clone());
+    catalog.set_recursion_acl(recursion_acl);
+    let mut modified: HashMap<LowerName, Option<SystemTime>> = HashMap::new();
+    let mut hints: Option<Authority> = None;
+    // configure our server based on the config_path
+    for zone in zones {
+        let zone_name = zone.get_zone()
+            .map_err(|e| format!("bad zone name in {:?}: {}", config_path, e))?;
+        let acls = zone.get_acls(&default_acls)
+            .map_err(|e| format!("bad acl for zone {} in {:?}: {}", zone_name, config_path, e))?;
+        if zone.get_zone_type() != ZoneType::Hint {
+            catalog.set_acls(zone_name.clone().into(), acls);
+        }
+
+        // forward zones have no records, only the upstream servers
+        if zone.get_zone_type() == ZoneType::Forward {
+            let mut name_servers = ResolverConfig::new();
+            let added: Result<(), _> = zone.get_forwarders()
+                .iter()
+                .map(|forwarder| forwarder.add_name_servers(&mut name_servers))
+                .collect();
+            match added {
+                Ok(()) if name_servers.name_servers().is_empty() => {
+                    error!("no forwarders for zone: {}", zone_name)
+                }
+                Ok(()) => {
+                    info!(
+                        "forwarding zone: {} to: {:?}",
+                        zone_name,
+                        name_servers.name_servers()
+                    );
+                    let zone_name = LowerName::from(zone_name);
+                    let mut forwarder = match resolvers.and_then(|r| r.forwarder(&zone_name)) {
+                        Some(forwarder) if *forwarder.config() == name_servers => forwarder.clone(),
+                        _ => Forwarder::new(name_servers, config.get_cache_size(), handle),
+                    };
+                    forwarder.set_authentic_data_trusted(zone.is_authentic_data_trusted());
+                    catalog.set_forwarder(zone_name, forwarder);
+                }
+                Err(error) => error!("bad forwarders for zone {}: {}", zone_name, error),
+            }
+            continue;
+        }
+
+        // the hint zone has the roots for recursive resolution, it is not authoritative
+        if zone.get_zone_type() == ZoneType::Hint {
+            match load_zone(zone_dir, zone, &tsig_keys, false) {
+                Ok(authority) => {
+                    info!("loaded root hints: {}", zone_name);
+                    hints = Some(authority);
+                }
+                Err(error) => error!("could not load zone {}: {}", zone_name, error),
+            }
+            continue;
+        }
+
+        let zone_name: LowerName = zone_name.into();
+        let mut zone_modified = zone_file_modified(zone_dir, zone);
+
+        let mut reloaded = None;
+        if let Some((loaded, previous)) = previous {
+            let is_unchanged = loaded.zone_dir.as_path() == zone_dir
+                && loaded.config.get_tsig_keys() == config.get_tsig_keys()
+                && loaded.config.get_zones().iter().any(|loaded_zone| loaded_zone == zone)
+                && loaded.modified.get(&zone_name) == Some(&zone_modified);
+            if is_unchanged && catalog.adopt(previous, &zone_name) {
+                debug!("zone unchanged: {}", zone_name);
+                modified.insert(zone_name, zone_modified);
+                continue;
+            }
+
+            // the zones with a journal are loaded again in place, once their journal is closed
+            match previous.get(&zone_name) {
+                Some(authority) if authority.read().unwrap().journal().is_some() => {
+                    let loaded_modified = loaded.modified.get(&zone_name).cloned().unwrap_or(None);
+                    let is_edited = zone.is_update_allowed() && loaded_modified != zone_modified;
+                    let is_frozen = authority.read().unwrap().is_frozen();
+
+                    // the updates in the journal are not in the edited file, unless the zone was
+                    //  frozen, and synced to the file, before it was edited
+                    if is_edited && !is_frozen {
+                        warn!(
+                            "keeping the loaded zone {}: zone file changed, freeze the zone \
+                             before editing it",
+                            zone_name
+                        );
+                        zone_modified = loaded_modified;
+                    } else {
+                        pending.reloads.push((
+                            zone_name.clone(),
+                            zone,
+                            authority.clone(),
+                            loaded_modified,
+                            is_edited,
+                        ));
+                    }
+                    reloaded = Some(authority.clone());
+                }
+                _ => (),
+            }
+        }
+
+        let authority = match reloaded {
+            Some(authority) => authority,
+            None => match load_zone(zone_dir, zone, &tsig_keys, false) {
+                Ok(authority) => Arc::new(RwLock::new(authority)),
+                Err(error) => {
+                    error!("could not load zone {}: {}", zone_name, error);
+                    continue;
+                }
+            },
+        };
+        catalog.upsert_shared(zone_name.clone(), authority.clone());
+
+        // slave zones are kept in sync with their masters
+        let masters = zone.get_masters().unwrap_or_else(|error| {
+            error!("bad masters for zone {}: {}", zone_name, error);
+            Vec::new()
+        });
+        if zone.get_zone_type() == ZoneType::Slave && !masters.is_empty() {
+            info!("refreshing slave zone from masters: {:?}", masters);
+            let (refresh, refreshes) = unbounded();
+            catalog.set_refresh(zone_name.clone(), &masters, refresh);
+            pending.slaves.push((authority.clone(), masters, refreshes));
+        }
+
+        // master zones notify their slaves after updates
+        match zone.get_also_notify() {
+            Ok(ref also_notify) if also_notify.is_empty() => (),
+            Ok(also_notify) => {
+                info!("notifying slaves after updates: {:?}", also_notify);
+                let (notify, notifications) = unbounded();
+                catalog.set_notify(zone_name.clone(), notify);
+                pending.notifies.push((authority, also_notify, notifications));
+            }
+            Err(error) => error!("bad also_notify for zone {}: {}", zone_name, error),
+        }
+
+        modified.insert(zone_name, zone_modified);
+    }
+
+    // names outside of the zones are resolved recursively, starting from the root hints
+    if let Some(hints) = hints {
+        let roots = Recursor::roots_from_hints(&hints);
+        if roots.is_empty() {
+            error!("no root servers in the hint zone: {}", hints.origin());
+        } else {
+            info!("recursion available from the roots: {:?}", roots);
+            let recursor = match resolvers.and_then(|r| r.recursor()) {
+                Some(recursor) if recursor.roots() == &roots[..] => recursor.clone(),
+                _ => Recursor::new(roots, config.get_cache_size(), handle),
+            };
+            catalog.set_recursor(recursor);
+        }
+    }
+
+    Ok((catalog, modified))
+}
+
+/// Administers the server while it keeps running, on SIGHUP and on the control channel
+struct Controller {
+    config_path: PathBuf,
+    flag_zonedir: Option<String>,
+    catalog: ReloadableCatalog,
+    loaded: Loaded,
+    handle: Handle,
+}
+
+impl Controller {
+    /// Replaces the Catalog with one built from the configuration as it is now, the current
+    ///  configuration is kept if the new one can not be loaded
+    ///
+    /// The listeners, the rate limits, the cookies, the metrics and the control channel are set
+    ///  up once, changes to their configuration require a restart.
+    fn reload(&mut self) -> ControlResult<String> {
+        info!("reloading configuration from: {:?}", self.config_path);
+        let config = Config::read_config(&self.config_path).map_err(|error| {
+            ControlError::from(format!(
+                "could not read config, keeping the current one: {:?}: {}",
+                self.config_path, error
+            ))
+        })?;
+        let zone_dir: PathBuf = self.flag_zonedir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| config.get_directory().to_owned());
+
+        let previous = self.catalog.catalog();
+        let modified = match load_catalog(
+            &config,
+            &self.config_path,
+            &zone_dir,
+            Some((&self.loaded, &previous)),
+            &self.handle,
+        ) {
+            Ok((catalog, modified)) => {
+                self.catalog.replace(catalog);
+                modified
+            }
+            Err(error) => {
+                return Err(
+                    format!("could not reload, keeping the current configuration: {}", error).into(),
+                )
+            }
+        };
+
+        if config.get_listen_addrs_ipv4() != self.loaded.config.get_listen_addrs_ipv4()
+            || config.get_listen_addrs_ipv6() != self.loaded.config.get_listen_addrs_ipv6()
+            || config.get_listen_port() != self.loaded.config.get_listen_port()
+        {
+            warn!("the listeners are not reloaded, restart to listen on the new addresses");
+        }
+
+        self.loaded = Loaded {
+            config,
+            zone_dir,
+            modified,
+        };
+        let message = format!("configuration reloaded, zones: {}", self.loaded.modified.len());
+        info!("{}", message);
+        Ok(message)
+    }
+
+    // the authority of the zone, which must be loaded
+    fn authority(&self, name: &LowerName) -> ControlResult<Arc<RwLock<Authority>>> {
+        self.catalog
+            .catalog()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ControlError::from(format!("zone not loaded: {}", name)))
+    }
+
+    /// Loads the zone again from its file, in place of the current one
+    ///
+    /// Dynamic zones must be frozen first, see `freeze`, they are loaded from the zone file, which
+    ///  was synced with their journal when the zone was frozen, and the journal is replaced by the
+    ///  zone. The zone stays frozen.
+    fn reload_zone(&mut self, name: &Name) -> ControlResult<String> {
+        let name = LowerName::from(name.clone());
+        let zone_config = find_zone(&self.loaded.config, &name)?;
+        let authority = self.authority(&name)?;
+        let is_dynamic = zone_config.is_update_allowed();
+        if is_dynamic && !authority.read().unwrap().is_frozen() {
+            return Err(format!("dynamic zone must be frozen to reload: {}", name).into());
+        }
+
+        let tsig_keys = load_tsig_keys(&self.loaded.config);
+        let serial = reload_authority(
+            &self.loaded.zone_dir,
+            zone_config,
+            &tsig_keys,
+            &authority,
+            is_dynamic,
+        )?;
+
+        let zone_modified = zone_file_modified(&self.loaded.zone_dir, zone_config);
+        self.loaded.modified.insert(name.clone(), zone_modified);
+        Ok(format!("zone reloaded: {} serial: {}", name, serial))
+    }
+
+    /// Suspends dynamic updates to the zone, and syncs it to its file, which can then be edited
+    fn freeze(&mut self, name: &Name) -> ControlResult<String> {
+        let authority = {
+            let name = LowerName::from(name.clone());
+            if !find_zone(&self.loaded.config, &name)?.is_update_allowed() {
+                return Err(format!("zone is not dynamic: {}", name).into());
+            }
+            self.authority(&name)?
+        };
+
+        authority.write().unwrap().set_frozen(true);
+        self.sync(name)?;
+        Ok(format!("zone frozen: {}", name))
+    }
+
+    /// Loads the frozen zone from its file, and resumes dynamic updates to it
+    fn thaw(&mut self, name: &Name) -> ControlResult<String> {
+        let authority = {
+            let name = LowerName::from(name.clone());
+            if !find_zone(&self.loaded.config, &name)?.is_update_allowed() {
+                return Err(format!("zone is not dynamic: {}", name).into());
+            }
+            self.authority(&name)?
+        };
+        if !authority.read().unwrap().is_frozen() {
+            return Err(format!("zone is not frozen: {}", name).into());
+        }
+
+        self.reload_zone(name)?;
+        authority.write().unwrap().set_frozen(false);
+        Ok(format!("zone thawed: {}", name))
+    }
+
+    /// Writes the zone, with all of its updates, to its file
+    fn sync(&mut self, name: &Name) -> ControlResult<String> {
+        let name = LowerName::from(name.clone());
+        let zone_config = find_zone(&self.loaded.config, &name)?;
+        let authority = self.authority(&name)?;
+        let zone_path = zone_config
+            .get_file()
+            .map(|file| self.loaded.zone_dir.join(file))
+            .ok_or_else(|| ControlError::from(format!("no file for zone: {}", name)))?;
+
+        // the file is replaced at once, it is never left partially written
+        let temp_path = zone_path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            authority.read().unwrap().emit_zone_file(&mut writer)?;
+            writer.flush()?;
+        }
+        fs::rename(&temp_path, &zone_path)?;
+
+        // the zone is not edited, it must not be reloaded from the file
+        let zone_modified = zone_file_modified(&self.loaded.zone_dir, zone_config);
+        self.loaded.modified.insert(name.clone(), zone_modified);
+        Ok(format!("zone synced: {} to: {:?}", name, zone_path))
+    }
+
+    /// Transfers the Slave zone from its masters
+    fn retransfer(&mut self, name: &Name) -> ControlResult<String> {
+        if self.catalog.catalog().retransfer(&LowerName::from(name.clone())) {
+            Ok(format!("zone transfer requested: {}", name))
+        } else {
+            Err(format!("not a slave zone with masters: {}", name).into())
+        }
+    }
+
+    /// Removes all the records from the caches
+    fn flush(&mut self) -> ControlResult<String> {
+        self.catalog.catalog().flush_caches();
+        Ok("caches flushed".to_string())
+    }
+
+    /// Describes the server and the state of its zones
+    fn status(&mut self) -> ControlResult<String> {
+        let catalog = self.catalog.catalog();
+        let zones = catalog.zones();
+
+        let mut status = format!(
+            "version: {}\nconfig: {:?}\nzones: {}",
+            trust_dns::version(),
+            self.config_path,
+            zones.len()
+        );
+        for name in zones {
+            let authority = catalog.get(name).expect("zone is in the catalog");
+            let authority = authority.read().unwrap(); // poison errors should panic
+            status.push_str(&format!(
+                "\n{} {:?} serial: {}",
+                name,
+                authority.zone_type(),
+                authority.serial()
+            ));
+            if authority.is_frozen() {
+                status.push_str(" frozen");
+            }
+            if authority.is_expired() {
+                status.push_str(" expired");
+            }
+        }
+
+        Ok(status)
+    }
+}
+
+// the configuration of the zone
+fn find_zone<'c>(config: &'c Config, name: &LowerName) -> ControlResult<&'c ZoneConfig> {
+    config
+        .get_zones()
+        .iter()
+        .find(|zone| zone.get_zone().map(LowerName::from).ok().as_ref() == Some(name))
+        .ok_or_else(|| ControlError::from(format!("zone not configured: {}", name)))
+}
+
+impl ControlHandler for Controller {
+    fn execute(&mut self, command: Command) -> ControlResult<String> {
+        match command {
+            Command::Reload(None) => self.reload(),
+            Command::Reload(Some(ref name)) => self.reload_zone(name),
+            Command::Freeze(ref name) => self.freeze(name),
+            Command::Thaw(ref name) => self.thaw(name),
+            Command::Sync(ref name) => self.sync(name),
+            Command::Retransfer(ref name) => self.retransfer(name),
+            Command::Flush => self.flush(),
+            Command::Status => self.status(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn reload_on_hangup(controller: Rc<RefCell<Controller>>, handle: &Handle) {
+    handle.spawn(
+        Signal::new(SIGHUP, handle)
+            .and_then(move |hangups| {
+                hangups.for_each(move |_| {
+                    if let Err(error) = controller.borrow_mut().reload() {
+                        error!("{}", error);
+                    }
+                    Ok(())
+                })
+            })
+            .map_err(|e| error!("error waiting for SIGHUP, reload is disabled: {}", e)),
+    );
+}
+
+#[cfg(not(unix))]
+fn reload_on_hangup(_controller: Rc<RefCell<Controller>>, _handle: &Handle) {
+    warn!("reload on SIGHUP is only supported on unix");
+}
+
+/// Accepts commands on the control channel, on a Unix domain socket and over TCP
+fn serve_control(controller: &Rc<RefCell<Controller>>, handle: &Handle) {
+    let borrowed = controller.borrow();
+    let config = &borrowed.loaded.config;
+    let control_config = match config.get_control() {
+        Some(control_config) => control_config,
+        None => return,
+    };
+
+    if let Some(path) = control_config.get_unix_socket() {
+        serve_control_unix(path, controller, handle);
+    }
+
+    match control_config.get_listen_addr() {
+        Ok(Some(control_addr)) => match control_config.get_tsig_key(config.get_tsig_keys()) {
+            Ok(Some(key)) => {
+                let listener = TcpListener::bind(control_addr)
+                    .expect(&format!("could not bind to control: {}", control_addr));
+                info!("listening for control commands on: {}", control_addr);
+                control::serve_tcp(listener, key, controller.clone(), handle)
+                    .expect("could not serve control commands");
+            }
+            Ok(None) => error!("a tsig_key is required for control over TCP: {}", control_addr),
+            Err(error) => error!("bad control tsig_key: {}", error),
+        },
+        Ok(None) => (),
+        Err(error) => error!("bad control listen_addr: {}", error),
+    }
+}
+
+#[cfg(unix)]
+fn serve_control_unix(path: &Path, controller: &Rc<RefCell<Controller>>, handle: &Handle) {
+    // any local user who can write to the socket can control the server, it is bound in a
+    //  directory only the server can enter, and moved into place once only it can write to it
+    let private_dir = path.with_file_name(format!(
+        ".{}.{}",
+        path.file_name()
+            .map_or("control".into(), |name| name.to_string_lossy()),
+        process::id()
+    ));
+    if private_dir.exists() {
+        fs::remove_dir_all(&private_dir).expect(&format!(
+            "could not remove control socket directory: {:?}",
+            private_dir
+        ));
+    }
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)
+        .expect(&format!(
+            "could not create control socket directory: {:?}",
+            private_dir
+        ));
+
+    let private_path = private_dir.join("control");
+    let listener = UnixListener::bind(&private_path)
+        .expect(&format!("could not bind to control socket: {:?}", path));
+    fs::set_permissions(&private_path, fs::Permissions::from_mode(0o600)).expect(&format!(
+        "could not set the permissions of control socket: {:?}",
+        path
+    ));
+
+    // the socket of a previous run is left behind, it is replaced
+    fs::rename(&private_path, path).expect(&format!(
+        "could not move control socket into place: {:?}",
+        path
+    ));
+    if let Err(e) = fs::remove_dir(&private_dir) {
+        warn!(
+            "could not remove control socket directory: {:?}: {}",
+            private_dir, e
+        );
+    }
+
+    info!("listening for control commands on: {:?}", path);
+    control::serve_unix(listener, controller.clone(), handle)
+        .expect("could not serve control commands");
+}
+
+#[cfg(not(unix))]
+fn serve_control_unix(path: &Path, _controller: &Rc<RefCell<Controller>>, _handle: &Handle) {
+    warn!("control over Unix domain sockets is only supported on unix: {:?}", path);
+}
+
 // argument name constants for the CLI options
 const QUIET_ARG: &str = "quiet";
 const DEBUG_ARG: &str = "debug";
@@ -286,6 +1262,7 @@ const CONFIG_ARG: &str = "config";
 const ZONEDIR_ARG: &str = "zonedir";
 const PORT_ARG: &str = "port";
 const TLS_PORT_ARG: &str = "tls-port";
+const HTTPS_PORT_ARG: &str = "https-port";
 
 /// Args struct for all options
 struct Args {
@@ -295,6 +1272,7 @@ struct Args {
     pub flag_zonedir: Option<String>,
     pub flag_port: Option<u16>,
     pub flag_tls_port: Option<u16>,
+    pub flag_https_port: Option<u16>,
 }
 
 impl<'a> From<ArgMatches<'a>> for Args {
@@ -313,6 +1291,9 @@ impl<'a> From<ArgMatches<'a>> for Args {
             flag_tls_port: matches.value_of(TLS_PORT_ARG).map(|s| {
                 u16::from_str_radix(s, 10).expect("bad tls-port argument")
             }),
+            flag_https_port: matches.value_of(HTTPS_PORT_ARG).map(|s| {
+                u16::from_str_radix(s, 10).expect("bad https-port argument")
+            }),
         }
     }
 }
@@ -364,6 +1345,12 @@ pub fn main() {
                 .help("Listening port for DNS over TLS queries, overrides any value in config file")
                 .value_name(TLS_PORT_ARG),
         )
+        .arg(
+            Arg::with_name(HTTPS_PORT_ARG)
+                .long(HTTPS_PORT_ARG)
+                .help("Listening port for DNS over HTTPS queries, overrides any value in config file")
+                .value_name(HTTPS_PORT_ARG),
+        )
         .get_matches();
 
     let args: Args = args.into();
@@ -385,22 +1372,18 @@ pub fn main() {
     info!("loading configuration from: {:?}", config_path);
     let config = Config::read_config(config_path)
         .expect(&format!("could not read config: {:?}", config_path));
-    let zone_dir: &Path = args.flag_zonedir
+    let zone_dir: PathBuf = args.flag_zonedir
         .as_ref()
-        .map(Path::new)
-        .unwrap_or_else(|| config.get_directory());
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config.get_directory().to_owned());
 
-    let mut catalog: Catalog = Catalog::new();
-    // configure our server based on the config_path
-    for zone in config.get_zones() {
-        let zone_name = zone.get_zone()
-            .expect(&format!("bad zone name in {:?}", config_path));
+    // now, run the server, based on the config
+    let io_loop = Core::new().expect("error creating Core");
+    let handle = io_loop.handle();
 
-        match load_zone(zone_dir, zone) {
-            Ok(authority) => catalog.upsert(zone_name.into(), authority),
-            Err(error) => error!("could not load zone {}: {}", zone_name, error),
-        }
-    }
+    let (catalog, modified) = load_catalog(&config, config_path, &zone_dir, None, &handle)
+        .unwrap_or_else(|error| panic!("could not load zones: {}", error));
+    let catalog = ReloadableCatalog::new(catalog);
 
     // TODO: support all the IPs asked to listen on...
     // TODO:, there should be the option to listen on any port, IP and protocol option...
@@ -421,22 +1404,43 @@ pub fn main() {
         .iter()
         .flat_map(|x| (*x, listen_port).to_socket_addrs().unwrap())
         .collect();
-    let udp_sockets: Vec<UdpSocket> = sockaddrs
-        .iter()
-        .map(|x| {
-            UdpSocket::bind(x).expect(&format!("could not bind to udp: {}", x))
-        })
-        .collect();
-    let tcp_listeners: Vec<TcpListener> = sockaddrs
-        .iter()
-        .map(|x| {
-            TcpListener::bind(x).expect(&format!("could not bind to tcp: {}", x))
-        })
-        .collect();
 
+    // with several workers, each binds its own sockets to the addresses, sharing the port
+    let workers = config.get_workers();
+    let reuse_port = workers > 1;
+    let (udp_sockets, tcp_listeners) = bind_listeners(&sockaddrs, reuse_port);
 
-    // now, run the server, based on the config
-    let mut server = ServerFuture::new(catalog).expect("error creating ServerFuture");
+    let mut server = ServerFuture::with_core(io_loop, catalog.clone());
+    server.set_max_udp_payload(config.get_max_udp_payload());
+
+    // the cookies of the server are shared by all the listeners, and outlive reloads of the
+    //  configuration, so that the cookies of clients remain valid
+    let server_cookies = load_server_cookies(&config).unwrap_or_else(|error| panic!("{}", error));
+    if let Some(ref server_cookies) = server_cookies {
+        server.set_server_cookies(server_cookies.clone());
+    }
+
+    // UDP responses are rate limited, before the sockets are registered, the limits are shared by
+    //  all the workers, so that a client gets no more responses by hitting the sockets of several,
+    //  clients with valid cookies are exempt
+    let rate_limiter = config.get_rate_limit().map(|rate_limit| {
+        info!("rate limiting UDP responses: {:?}", rate_limit);
+        Arc::new(new_rate_limiter(rate_limit))
+    });
+    if let Some(ref rate_limiter) = rate_limiter {
+        server.set_rate_limiter(rate_limiter.clone());
+    }
+
+    // metrics are served over HTTP, for scraping by Prometheus
+    match config.get_metrics_listen_addr() {
+        Ok(Some(metrics_addr)) => {
+            let listener = TcpListener::bind(metrics_addr)
+                .expect(&format!("could not bind to metrics: {}", metrics_addr));
+            metrics::serve(listener, &handle).expect("could not serve metrics");
+        }
+        Ok(None) => (),
+        Err(error) => error!("bad metrics_listen_addr: {}", error),
+    }
 
     // load all the listeners
     for udp_socket in udp_sockets {
@@ -452,6 +1456,24 @@ pub fn main() {
             .expect("could not register TCP listener");
     }
 
+    // the other workers handle UDP and TCP requests on threads of their own
+    for worker in 1..workers {
+        let (udp_sockets, tcp_listeners) = bind_listeners(&sockaddrs, reuse_port);
+        spawn_worker(
+            worker,
+            catalog.clone(),
+            udp_sockets,
+            tcp_listeners,
+            tcp_request_timeout,
+            config.get_max_udp_payload(),
+            rate_limiter.clone(),
+            server_cookies.clone(),
+        ).expect("could not spawn worker");
+    }
+    if workers > 1 {
+        info!("handling UDP and TCP requests on {} threads", workers);
+    }
+
     // and TLS as necessary
     if let Some(tls_cert_config) = config.get_tls_cert() {
         config_tls(
@@ -459,11 +1481,42 @@ pub fn main() {
             &mut server,
             &config,
             tls_cert_config,
-            zone_dir,
+            &zone_dir,
             &listen_addrs,
         );
+
+        // DNS over HTTPS shares the certificate of TLS, but listens only if a port is configured
+        if let Some(https_listen_port) = args.flag_https_port
+            .or_else(|| config.get_https_listen_port())
+        {
+            config_https(
+                &mut server,
+                &config,
+                tls_cert_config,
+                &zone_dir,
+                &listen_addrs,
+                https_listen_port,
+            );
+        }
     }
 
+    // the configuration and the zones are reloaded on SIGHUP, the listeners are kept
+    let controller = Rc::new(RefCell::new(Controller {
+        config_path: config_path.to_owned(),
+        flag_zonedir: args.flag_zonedir.clone(),
+        catalog,
+        loaded: Loaded {
+            config,
+            zone_dir,
+            modified,
+        },
+        handle: handle.clone(),
+    }));
+    reload_on_hangup(controller.clone(), &handle);
+
+    // and administered over the control channel
+    serve_control(&controller, &handle);
+
     // config complete, starting!
     banner();
     info!("awaiting connections...");
@@ -475,10 +1528,182 @@ pub fn main() {
     info!("Trust-DNS {} stopping", trust_dns::version());
 }
 
+/// Binds a UDP socket and a TCP listener to each of the addresses
+///
+/// With reuse_port, the addresses can be bound again, by the sockets of other workers.
+fn bind_listeners(
+    sockaddrs: &[SocketAddr],
+    reuse_port: bool,
+) -> (Vec<UdpSocket>, Vec<TcpListener>) {
+    let udp_sockets: Vec<UdpSocket> = sockaddrs
+        .iter()
+        .map(|x| {
+            bind_udp(x, reuse_port).expect(&format!("could not bind to udp: {}", x))
+        })
+        .collect();
+    let tcp_listeners: Vec<TcpListener> = sockaddrs
+        .iter()
+        .map(|x| {
+            bind_tcp(x, reuse_port).expect(&format!("could not bind to tcp: {}", x))
+        })
+        .collect();
+
+    (udp_sockets, tcp_listeners)
+}
+
+fn bind_udp(addr: &SocketAddr, reuse_port: bool) -> io::Result<UdpSocket> {
+    if !reuse_port {
+        return UdpSocket::bind(addr);
+    }
+
+    let socket = reuse_port_socket(addr, Type::dgram(), Protocol::udp())?;
+    socket.bind(&SockAddr::from(*addr))?;
+    Ok(socket.into_udp_socket())
+}
+
+fn bind_tcp(addr: &SocketAddr, reuse_port: bool) -> io::Result<TcpListener> {
+    if !reuse_port {
+        return TcpListener::bind(addr);
+    }
+
+    let socket = reuse_port_socket(addr, Type::stream(), Protocol::tcp())?;
+    socket.bind(&SockAddr::from(*addr))?;
+    socket.listen(128)?;
+    Ok(socket.into_tcp_listener())
+}
+
+#[cfg(unix)]
+fn reuse_port_socket(
+    addr: &SocketAddr,
+    socket_type: Type,
+    protocol: Protocol,
+) -> io::Result<Socket> {
+    let domain = if addr.is_ipv4() {
+        Domain::ipv4()
+    } else {
+        Domain::ipv6()
+    };
+
+    let socket = Socket::new(domain, socket_type, Some(protocol))?;
+    socket.set_reuse_address(true)?;
+    socket.set_reuse_port(true)?;
+    Ok(socket)
+}
+
+#[cfg(not(unix))]
+fn reuse_port_socket(
+    _addr: &SocketAddr,
+    _socket_type: Type,
+    _protocol: Protocol,
+) -> io::Result<Socket> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "SO_REUSEPORT is only supported on Unix, workers must be 1",
+    ))
+}
+
+/// The Response Rate Limiting of the UDP sockets of all the workers
+fn new_rate_limiter(rate_limit: &RateLimitConfig) -> RateLimiter {
+    let mut rate_limiter = RateLimiter::new(
+        rate_limit.get_responses_per_second(),
+        rate_limit.get_window(),
+        rate_limit.get_slip(),
+        rate_limit.get_ipv4_prefix_len(),
+        rate_limit.get_ipv6_prefix_len(),
+    );
+    rate_limiter.set_max_networks(rate_limit.get_max_networks());
+    rate_limiter
+}
+
+/// Handles UDP and TCP requests on a thread of its own, with its own reactor
+///
+/// The catalog is shared with the main thread, which keeps the TLS and DNS over HTTPS listeners,
+///  the transfers of Slave zones and the control channel. Forwarded and recursive queries are
+///  resolved on the reactor of the worker, see `RequestContext::handle`. The cookies of the
+///  server and the rate limits are shared with the main thread.
+///
+/// A worker which panics is restarted on the same sockets, with a new reactor.
+fn spawn_worker(
+    worker: usize,
+    catalog: ReloadableCatalog,
+    udp_sockets: Vec<UdpSocket>,
+    tcp_listeners: Vec<TcpListener>,
+    tcp_request_timeout: std::time::Duration,
+    max_udp_payload: u16,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    server_cookies: Option<Arc<ServerCookies>>,
+) -> io::Result<thread::JoinHandle<()>> {
+    thread::Builder::new()
+        .name(format!("named-worker-{}", worker))
+        .spawn(move || loop {
+            let run = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_worker(
+                    worker,
+                    &catalog,
+                    &udp_sockets,
+                    &tcp_listeners,
+                    tcp_request_timeout,
+                    max_udp_payload,
+                    &rate_limiter,
+                    &server_cookies,
+                )
+            }));
+
+            match run {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => {
+                    error!("worker {} failed to listen: {}", worker, e);
+                    return;
+                }
+                Err(_) => {
+                    // the panic is reported by the panic hook, don't restart in a busy loop
+                    error!("worker {} panicked, restarting", worker);
+                    thread::sleep(WORKER_RESTART_DELAY);
+                }
+            }
+        })
+}
+
+// the time a worker which panicked waits before it is restarted
+const WORKER_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+// serves the sockets of the worker, until the reactor stops, or a request panics
+fn run_worker(
+    worker: usize,
+    catalog: &ReloadableCatalog,
+    udp_sockets: &[UdpSocket],
+    tcp_listeners: &[TcpListener],
+    tcp_request_timeout: std::time::Duration,
+    max_udp_payload: u16,
+    rate_limiter: &Option<Arc<RateLimiter>>,
+    server_cookies: &Option<Arc<ServerCookies>>,
+) -> io::Result<()> {
+    let mut server = ServerFuture::new(catalog.clone())?;
+    server.set_max_udp_payload(max_udp_payload);
+    if let Some(ref server_cookies) = *server_cookies {
+        server.set_server_cookies(server_cookies.clone());
+    }
+    if let Some(ref rate_limiter) = *rate_limiter {
+        server.set_rate_limiter(rate_limiter.clone());
+    }
+
+    // the sockets are kept for the restarts of the worker, the reactor registers clones of them
+    for udp_socket in udp_sockets {
+        debug!("worker {} listening for UDP on {:?}", worker, udp_socket);
+        server.register_socket(udp_socket.try_clone()?);
+    }
+    for tcp_listener in tcp_listeners {
+        debug!("worker {} listening for TCP on {:?}", worker, tcp_listener);
+        server.register_listener(tcp_listener.try_clone()?, tcp_request_timeout)?;
+    }
+
+    server.listen()
+}
+
 #[cfg(not(feature = "tls"))]
 fn config_tls(
     _args: &Args,
-    _server: &mut ServerFuture<Catalog>,
+    _server: &mut ServerFuture<ReloadableCatalog>,
     _config: &Config,
     _tls_cert_config: &TlsCertConfig,
     _zone_dir: &Path,
@@ -490,7 +1715,7 @@ fn config_tls(
 #[cfg(feature = "tls")]
 fn config_tls(
     args: &Args,
-    server: &mut ServerFuture<Catalog>,
+    server: &mut ServerFuture<ReloadableCatalog>,
     config: &Config,
     tls_cert_config: &TlsCertConfig,
     zone_dir: &Path,
@@ -517,14 +1742,157 @@ fn config_tls(
             "loading cert for DNS over TLS: {:?}",
             tls_cert_config.get_path()
         );
-        // TODO: see about modifying native_tls to impl Clone for Pkcs12
-        let tls_cert =
-            load_cert(zone_dir, tls_cert_config).expect("error loading tls certificate file");
 
         info!("listening for TLS on {:?}", tls_listener);
+        match tls_cert_config.get_private_key() {
+            Some(private_key) => register_pem_listener(
+                server,
+                tls_listener,
+                config.get_tcp_request_timeout(),
+                tls_cert_config,
+                zone_dir,
+                private_key,
+            ),
+            None => register_pkcs12_listener(
+                server,
+                tls_listener,
+                config.get_tcp_request_timeout(),
+                tls_cert_config,
+                zone_dir,
+            ),
+        }
+    }
+}
+
+/// PEM certificates and keys are served with rustls
+#[cfg(feature = "tls-ring")]
+fn register_pem_listener(
+    server: &mut ServerFuture<ReloadableCatalog>,
+    tls_listener: TcpListener,
+    timeout: std::time::Duration,
+    tls_cert_config: &TlsCertConfig,
+    zone_dir: &Path,
+    private_key: &Path,
+) {
+    let (cert_chain, key) = load_pem_cert(zone_dir, tls_cert_config, private_key)
+        .expect("error loading tls certificate or key file");
+
+    // clients are asked for a certificate with mutual TLS
+    let client_ca = match tls_cert_config.get_client_ca() {
+        Some(client_ca) => {
+            let path = zone_dir.to_owned().join(client_ca);
+            info!("reading TLS client CA from: {:?}", path);
+            rustls_server::read_cert(&path).expect("error loading tls client ca file")
+        }
+        None => Vec::new(),
+    };
+
+    server
+        .register_rustls_listener_with_client_ca(tls_listener, timeout, cert_chain, key, client_ca)
+        .expect("could not register TLS listener");
+}
+
+#[cfg(not(feature = "tls-ring"))]
+fn register_pem_listener(
+    _server: &mut ServerFuture<ReloadableCatalog>,
+    _tls_listener: TcpListener,
+    _timeout: std::time::Duration,
+    _tls_cert_config: &TlsCertConfig,
+    _zone_dir: &Path,
+    _private_key: &Path,
+) {
+    panic!("a tls_cert with a private_key, i.e. PEM, requires the tls-ring feature");
+}
+
+/// pkcs12 certificates are served with OpenSSL
+#[cfg(feature = "tls-openssl")]
+fn register_pkcs12_listener(
+    server: &mut ServerFuture<ReloadableCatalog>,
+    tls_listener: TcpListener,
+    timeout: std::time::Duration,
+    tls_cert_config: &TlsCertConfig,
+    zone_dir: &Path,
+) {
+    // TODO: see about modifying native_tls to impl Clone for Pkcs12
+    let tls_cert =
+        load_cert(zone_dir, tls_cert_config).expect("error loading tls certificate file");
+
+    // clients are asked for a certificate with mutual TLS
+    let client_ca = match tls_cert_config.get_client_ca() {
+        Some(client_ca) => {
+            let path = zone_dir.to_owned().join(client_ca);
+            info!("reading TLS client CA from: {:?}", path);
+            read_client_ca(&path).expect("error loading tls client ca file")
+        }
+        None => Vec::new(),
+    };
+
+    server
+        .register_tls_listener_with_client_ca(tls_listener, timeout, tls_cert, client_ca)
+        .expect("could not register TLS listener");
+}
+
+#[cfg(not(feature = "tls-openssl"))]
+fn register_pkcs12_listener(
+    _server: &mut ServerFuture<ReloadableCatalog>,
+    _tls_listener: TcpListener,
+    _timeout: std::time::Duration,
+    _tls_cert_config: &TlsCertConfig,
+    _zone_dir: &Path,
+) {
+    panic!("pkcs12 certificates require the tls-openssl feature, or specify a PEM private_key");
+}
+
+#[cfg(not(feature = "dns-over-https"))]
+fn config_https(
+    _server: &mut ServerFuture<ReloadableCatalog>,
+    _config: &Config,
+    _tls_cert_config: &TlsCertConfig,
+    _zone_dir: &Path,
+    _listen_addrs: &[IpAddr],
+    _https_listen_port: u16,
+) {
+    warn!("an https port was specified, but the dns-over-https feature is not enabled");
+}
+
+#[cfg(feature = "dns-over-https")]
+fn config_https(
+    server: &mut ServerFuture<ReloadableCatalog>,
+    config: &Config,
+    tls_cert_config: &TlsCertConfig,
+    zone_dir: &Path,
+    listen_addrs: &[IpAddr],
+    https_listen_port: u16,
+) {
+    if tls_cert_config.get_private_key().is_some() {
+        warn!("an https port was specified, but DNS over HTTPS requires a pkcs12 tls_cert");
+        return;
+    }
+
+    let https_sockaddrs: Vec<SocketAddr> = listen_addrs
+        .iter()
+        .flat_map(|x| (*x, https_listen_port).to_socket_addrs().unwrap())
+        .collect();
+    let https_listeners: Vec<TcpListener> = https_sockaddrs
+        .iter()
+        .map(|x| TcpListener::bind(x).expect(&format!("could not bind to https: {}", x)))
+        .collect();
+    if https_listeners.is_empty() {
+        warn!("an https port was specified, but no TCP addresses configured to listen on");
+    }
+
+    for https_listener in https_listeners {
+        info!(
+            "loading cert for DNS over HTTPS: {:?}",
+            tls_cert_config.get_path()
+        );
+        let https_cert =
+            load_cert(zone_dir, tls_cert_config).expect("error loading tls certificate file");
+
+        info!("listening for HTTPS on {:?}", https_listener);
         server
-            .register_tls_listener(tls_listener, config.get_tcp_request_timeout(), tls_cert)
-            .expect("could not register TLS listener");
+            .register_https_listener(https_listener, config.get_tcp_request_timeout(), https_cert)
+            .expect("could not register HTTPS listener");
     }
 }
 
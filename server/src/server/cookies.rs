@@ -0,0 +1,504 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! DNS Cookies of the server, which prove that a client has received a response from the server,
+//!  and so is not spoofing its address
+
+#![allow(deprecated)]
+
+use std::fmt;
+use std::hash::{Hasher, SipHasher};
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand;
+
+use trust_dns::rr::rdata::opt::{Cookie, CLIENT_COOKIE_LEN};
+
+/// The length of the shared secret from which the cookies are derived
+pub const SECRET_LEN: usize = 16;
+
+/// The version of the cookies of RFC 9018
+const VERSION: u8 = 1;
+/// The length of the server cookie, version, reserved, timestamp and hash
+const SERVER_COOKIE_LEN: usize = 16;
+/// Random secrets are replaced daily
+const ROTATION_PERIOD: u32 = 24 * 60 * 60;
+/// Cookies which are older than this are replaced in responses
+const REFRESH_AGE: i32 = 30 * 60;
+/// Cookies which are older than this are invalid
+const MAX_AGE: i32 = 60 * 60;
+/// Cookies which are further in the future than this are invalid, the clocks of servers sharing a
+///  secret may drift
+const MAX_FUTURE: i32 = 5 * 60;
+
+#[derive(Clone, Copy)]
+struct Secrets {
+    current: [u8; SECRET_LEN],
+    previous: Option<[u8; SECRET_LEN]>,
+}
+
+/// Generates and validates the cookies of the server
+///
+/// [RFC 9018](https://tools.ietf.org/html/rfc9018#section-4), Interoperable Domain Name System (DNS) Server Cookies, April 2021
+///
+/// ```text
+/// The Server Cookie consists of Sub-Fields specified below.
+///
+///   0                   1                   2                   3
+///   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///  |    Version    |                   Reserved                    |
+///  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///  |                           Timestamp                           |
+///  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///  |                             Hash                              |
+///  |                                                               |
+///  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The hash is SipHash-2-4 of the client cookie, the version, reserved and timestamp fields, and
+///  the address of the client, keyed with the secret. Random secrets are replaced daily, cookies
+///  of the previous secret remain valid until they expire, a configured secret is never replaced,
+///  so that it may be shared by the servers of an anycast address.
+///
+/// The secrets which replace a random secret are derived from it and the number of days since it
+///  was created, so that the cookies are checked by all the workers without any lock.
+pub struct ServerCookies {
+    secret: [u8; SECRET_LEN],
+    // when the random secret was created, None for a configured secret
+    created: Option<u32>,
+}
+
+impl ServerCookies {
+    /// Returns cookies from a random secret, which is replaced daily
+    pub fn new() -> Self {
+        Self::with_rotation(rand::random(), true)
+    }
+
+    /// Returns cookies from a configured secret, which is never replaced
+    pub fn with_secret(secret: [u8; SECRET_LEN]) -> Self {
+        Self::with_rotation(secret, false)
+    }
+
+    fn with_rotation(secret: [u8; SECRET_LEN], rotate: bool) -> Self {
+        ServerCookies {
+            secret,
+            created: if rotate { Some(now()) } else { None },
+        }
+    }
+
+    /// Checks the server cookie of the request, and returns it with the server cookie for the
+    ///  response, the cookie of the request if it is valid and recent, otherwise a new one
+    ///
+    /// The cookie of a request is checked once, for the rate limits and for the response, see
+    ///  `RequestContext::check_cookie`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cookie` - the cookies of the request
+    /// * `client` - the address of the client
+    pub fn check(&self, cookie: &Cookie, client: IpAddr) -> CheckedCookie {
+        self.check_at(cookie, client, now())
+    }
+
+    fn check_at(&self, cookie: &Cookie, client: IpAddr, now: u32) -> CheckedCookie {
+        let secrets = self.secrets(now);
+        let age = validate(&secrets, cookie, client, now);
+
+        let server = match (age, cookie.server()) {
+            (Some(age), Some(server)) if age < REFRESH_AGE => server.to_vec(),
+            _ => {
+                let mut server = Vec::with_capacity(SERVER_COOKIE_LEN);
+                server.extend_from_slice(&[VERSION, 0, 0, 0]);
+                server.extend_from_slice(&to_be_bytes(now));
+                let hash = hash(&secrets.current, cookie.client(), &server, client);
+                server.extend_from_slice(&hash);
+                server
+            }
+        };
+
+        CheckedCookie {
+            client: *cookie.client(),
+            server,
+            is_valid: age.is_some(),
+        }
+    }
+
+    /// Returns true if the server cookie of the request was generated by this server for the
+    ///  client, and has not expired, see `check`
+    pub fn is_valid(&self, cookie: &Cookie, client: IpAddr) -> bool {
+        self.check(cookie, client).is_valid()
+    }
+
+    /// Returns the server cookie for the response, see `check`
+    pub fn server_cookie(&self, cookie: &Cookie, client: IpAddr) -> Vec<u8> {
+        self.check(cookie, client).server
+    }
+
+    // the secrets of the rotation period of `now`, and of the one before
+    fn secrets(&self, now: u32) -> Secrets {
+        let created = match self.created {
+            Some(created) => created,
+            None => {
+                return Secrets {
+                    current: self.secret,
+                    previous: None,
+                }
+            }
+        };
+
+        let period = now.saturating_sub(created) / ROTATION_PERIOD;
+        Secrets {
+            current: self.derive(period),
+            previous: period.checked_sub(1).map(|period| self.derive(period)),
+        }
+    }
+
+    // the secret of a rotation period, the first is the random secret
+    fn derive(&self, period: u32) -> [u8; SECRET_LEN] {
+        if period == 0 {
+            return self.secret;
+        }
+
+        let mut secret = [0; SECRET_LEN];
+        for (i, half) in secret.chunks_mut(8).enumerate() {
+            let mut hasher = SipHasher::new_with_keys(
+                from_le_bytes(&self.secret[..8]),
+                from_le_bytes(&self.secret[8..]),
+            );
+            hasher.write(&to_be_bytes(period));
+            hasher.write_u8(i as u8);
+
+            let hash = hasher.finish();
+            for (j, byte) in half.iter_mut().enumerate() {
+                *byte = (hash >> (j * 8)) as u8;
+            }
+        }
+        secret
+    }
+}
+
+impl Default for ServerCookies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the secrets are not shown
+impl fmt::Debug for ServerCookies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServerCookies")
+            .field("rotate", &self.created.is_some())
+            .finish()
+    }
+}
+
+/// The cookies of a request, once its server cookie is checked, see `ServerCookies::check`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckedCookie {
+    client: [u8; CLIENT_COOKIE_LEN],
+    server: Vec<u8>,
+    is_valid: bool,
+}
+
+impl CheckedCookie {
+    /// Returns true if the request had a server cookie, generated by this server for the client,
+    ///  which has not expired
+    pub fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    /// Returns the cookies for the response, the client cookie of the request and the server
+    ///  cookie of the server
+    pub fn response(&self) -> Cookie {
+        Cookie::new(self.client, Some(self.server.clone()))
+    }
+}
+
+// the age of the cookie, if it is valid for one of the secrets
+fn validate(secrets: &Secrets, cookie: &Cookie, client: IpAddr, now: u32) -> Option<i32> {
+    let server = match cookie.server() {
+        Some(server) if server.len() == SERVER_COOKIE_LEN && server[0] == VERSION => server,
+        _ => return None,
+    };
+
+    // serial number arithmetic, the timestamp wraps
+    let timestamp = from_be_bytes(&server[4..8]);
+    let age = now.wrapping_sub(timestamp) as i32;
+    if age > MAX_AGE || age < -MAX_FUTURE {
+        return None;
+    }
+
+    let is_valid = Some(secrets.current)
+        .into_iter()
+        .chain(secrets.previous)
+        .any(|secret| {
+            constant_time_eq(
+                &hash(&secret, cookie.client(), &server[..8], client),
+                &server[8..],
+            )
+        });
+    if is_valid {
+        Some(age)
+    } else {
+        None
+    }
+}
+
+fn hash(secret: &[u8; SECRET_LEN], client_cookie: &[u8], header: &[u8], client: IpAddr) -> [u8; 8] {
+    let mut hasher =
+        SipHasher::new_with_keys(from_le_bytes(&secret[..8]), from_le_bytes(&secret[8..]));
+    hasher.write(client_cookie);
+    hasher.write(header);
+    match client {
+        IpAddr::V4(ip) => hasher.write(&ip.octets()),
+        IpAddr::V6(ip) => hasher.write(&ip.octets()),
+    }
+
+    let hash = hasher.finish();
+    let mut bytes = [0; 8];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (hash >> (i * 8)) as u8;
+    }
+    bytes
+}
+
+// compares all the bytes, whatever the first which differs, so that the time taken does not tell
+//  how much of a forged hash is right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn from_le_bytes(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+fn from_be_bytes(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | u32::from(byte))
+}
+
+fn to_be_bytes(value: u32) -> [u8; 4] {
+    [
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ]
+}
+
+// seconds since the epoch, truncated as the timestamps of the cookies
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::hex::FromHex;
+
+    use super::*;
+
+    const CLIENT_COOKIE: [u8; CLIENT_COOKIE_LEN] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn client() -> IpAddr {
+        "192.0.2.1".parse().unwrap()
+    }
+
+    // the cookies of a client which has received the server cookie at `now`
+    fn cookie(cookies: &ServerCookies, now: u32) -> Cookie {
+        let checked = cookies.check_at(&Cookie::new(CLIENT_COOKIE, None), client(), now);
+        assert!(!checked.is_valid());
+        checked.response()
+    }
+
+    #[test]
+    fn test_valid() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let now = now();
+        let cookie = cookie(&cookies, now);
+
+        let checked = cookies.check_at(&cookie, client(), now + 1);
+        assert!(checked.is_valid());
+        assert_eq!(checked.response(), cookie);
+
+        // the cookie is only valid for the client to which it was sent
+        assert!(!cookies
+            .check_at(&cookie, "192.0.2.2".parse().unwrap(), now + 1)
+            .is_valid());
+
+        // and for the client cookie
+        let other = Cookie::new([0; CLIENT_COOKIE_LEN], cookie.server().map(|s| s.to_vec()));
+        assert!(!cookies.check_at(&other, client(), now + 1).is_valid());
+
+        // every byte of the hash is checked
+        for i in 8..SERVER_COOKIE_LEN {
+            let mut server = cookie.server().unwrap().to_vec();
+            server[i] ^= 1;
+            let forged = Cookie::new(CLIENT_COOKIE, Some(server));
+            assert!(!cookies.check_at(&forged, client(), now + 1).is_valid());
+        }
+
+        // as is the secret
+        let other = ServerCookies::with_secret([8; SECRET_LEN]);
+        assert!(!other.check_at(&cookie, client(), now + 1).is_valid());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let cookies = ServerCookies::new();
+        let created = cookies.created.unwrap();
+        let first = cookies.secrets(created).current;
+
+        // a cookie of the first secret, shortly before it is replaced
+        let rotated = created + ROTATION_PERIOD;
+        let cookie = cookie(&cookies, rotated - 60);
+        assert_eq!(cookies.secrets(rotated - 60).current, first);
+
+        // it is still accepted once the secret is replaced, and sent back while it is recent
+        let checked = cookies.check_at(&cookie, client(), rotated);
+        {
+            let secrets = cookies.secrets(rotated);
+            assert!(secrets.current != first);
+            assert_eq!(secrets.previous, Some(first));
+
+            // the secret is the same throughout the period, the next one replaces it
+            assert_eq!(
+                cookies.secrets(rotated + ROTATION_PERIOD - 1).current,
+                secrets.current
+            );
+            let next = cookies.secrets(rotated + ROTATION_PERIOD);
+            assert!(next.current != secrets.current && next.current != first);
+            assert_eq!(next.previous, Some(secrets.current));
+        }
+        assert!(checked.is_valid());
+        assert_eq!(checked.response(), cookie);
+
+        // the secrets are derived from the random secret, they differ for other servers
+        let other = ServerCookies::new();
+        assert!(other.derive(1) != cookies.derive(1));
+    }
+
+    #[test]
+    fn test_configured_secret_not_rotated() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let created = now();
+
+        let cookie = cookie(&cookies, created + ROTATION_PERIOD);
+        assert!(cookies
+            .check_at(&cookie, client(), created + ROTATION_PERIOD)
+            .is_valid());
+
+        let secrets = cookies.secrets(created + ROTATION_PERIOD);
+        assert_eq!(secrets.current, [7; SECRET_LEN]);
+        assert_eq!(secrets.previous, None);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let now = now();
+        let cookie = cookie(&cookies, now);
+
+        // a recent cookie is sent back as it is
+        let checked = cookies.check_at(&cookie, client(), now + REFRESH_AGE as u32 - 1);
+        assert!(checked.is_valid());
+        assert_eq!(checked.response(), cookie);
+
+        // an older one is still valid, but replaced with a new one
+        let refreshed = now + REFRESH_AGE as u32;
+        let checked = cookies.check_at(&cookie, client(), refreshed);
+        assert!(checked.is_valid());
+        let response = checked.response();
+        assert!(response != cookie);
+
+        let server = response.server().unwrap();
+        assert_eq!(from_be_bytes(&server[4..8]), refreshed);
+        assert!(cookies.check_at(&response, client(), refreshed).is_valid());
+    }
+
+    #[test]
+    fn test_max_age() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let now = now();
+        let cookie = cookie(&cookies, now);
+
+        assert!(cookies
+            .check_at(&cookie, client(), now + MAX_AGE as u32)
+            .is_valid());
+        let checked = cookies.check_at(&cookie, client(), now + MAX_AGE as u32 + 1);
+        assert!(!checked.is_valid());
+        assert!(checked.response() != cookie);
+    }
+
+    #[test]
+    fn test_max_future() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let now = now();
+
+        // a cookie from a server sharing the secret, whose clock is ahead
+        let cookie = cookie(&cookies, now + MAX_FUTURE as u32);
+        assert!(cookies.check_at(&cookie, client(), now).is_valid());
+
+        let cookie = self::cookie(&cookies, now + MAX_FUTURE as u32 + 1);
+        assert!(!cookies.check_at(&cookie, client(), now).is_valid());
+    }
+
+    #[test]
+    fn test_malformed() {
+        let cookies = ServerCookies::with_secret([7; SECRET_LEN]);
+        let now = now();
+        let cookie = cookie(&cookies, now);
+        let server = cookie.server().unwrap().to_vec();
+
+        // the length, or the version, are not those of the cookies of the server
+        let mut wrong_version = server.clone();
+        wrong_version[0] = VERSION + 1;
+        for server in vec![
+            vec![],
+            server[..8].to_vec(),
+            server[..SERVER_COOKIE_LEN - 1].to_vec(),
+            [&server[..], &[0][..]].concat(),
+            wrong_version,
+        ] {
+            let cookie = Cookie::new(CLIENT_COOKIE, Some(server));
+            assert!(!cookies.check_at(&cookie, client(), now).is_valid());
+        }
+    }
+
+    // RFC 9018 Appendix A, the cookies of the examples of an IPv4 client
+    #[test]
+    fn test_rfc9018_vectors() {
+        let mut secret = [0; SECRET_LEN];
+        secret.copy_from_slice(&"e5e973e5a6b2a43f48e7dc849e37bfcf".from_hex().unwrap());
+        let cookies = ServerCookies::with_secret(secret);
+        let client: IpAddr = "198.51.100.100".parse().unwrap();
+        let mut client_cookie = [0; CLIENT_COOKIE_LEN];
+        client_cookie.copy_from_slice(&"2464c4abcf10c957".from_hex().unwrap());
+
+        // A.1, learning a new server cookie
+        let checked = cookies.check_at(&Cookie::new(client_cookie, None), client, 1559731985);
+        assert!(!checked.is_valid());
+        let learned = "010000005cf79f111f8130c3eee29480".from_hex().unwrap();
+        assert_eq!(checked.response().server(), Some(&learned[..]));
+
+        // A.2, the same client later, the valid server cookie is older than half an hour and is
+        //  refreshed
+        let cookie = Cookie::new(client_cookie, Some(learned));
+        let checked = cookies.check_at(&cookie, client, 1559734385);
+        assert!(checked.is_valid());
+        let refreshed = "010000005cf7a871d4a564a1442aca77".from_hex().unwrap();
+        assert_eq!(checked.response().server(), Some(&refreshed[..]));
+    }
+}
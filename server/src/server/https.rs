@@ -0,0 +1,209 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! DNS over HTTPS, DoH, the requests and responses of HTTP/2 streams
+
+use std::io;
+
+use bytes::Bytes;
+use futures::sync::oneshot;
+use h2;
+use h2::server::SendResponse;
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderValue, Method, Response, StatusCode, Uri};
+use rustc_serialize::base64::FromBase64;
+
+use trust_dns::op::Message;
+use trust_dns::serialize::binary::{BinDecodable, BinEncodable, BinEncoder};
+use trust_dns_proto::op::EncodableMessage;
+
+use server::ResponseHandler;
+
+/// The path of the URI template of the server, `https://<server>/dns-query{?dns}`
+pub const DNS_QUERY_PATH: &str = "/dns-query";
+
+/// The media type of DNS messages in the body of requests and responses
+///
+/// [RFC 8484](https://tools.ietf.org/html/rfc8484#section-6), DNS Queries over HTTPS (DoH), October 2018
+///
+/// ```text
+/// The data payload for the "application/dns-message" media type is a
+/// single message of the DNS on-the-wire format defined in Section 4.2.1
+/// of [RFC1035], which in turn refers to the full wire format defined in
+/// Section 4.1 of that RFC.
+/// ```
+pub const MIME_APPLICATION_DNS: &str = "application/dns-message";
+
+/// DNS messages are no longer than this, as over TCP
+pub const MAX_MESSAGE_LEN: usize = 65_535;
+
+/// Returns the DNS request carried by the HTTP request, or the status of the error response
+///
+/// [RFC 8484](https://tools.ietf.org/html/rfc8484#section-4.1), DNS Queries over HTTPS (DoH), October 2018
+///
+/// ```text
+/// When the HTTP method is GET, the single variable "dns" is defined as
+/// the content of the DNS request (as described in Section 6), encoded
+/// with base64url [RFC4648].
+///
+/// When using the POST method, the DNS query is included as the message
+/// body of the HTTP request, and the Content-Type request header field
+/// indicates the media type of the message.
+/// ```
+///
+/// # Arguments
+///
+/// * `method` - GET or POST, other methods are not allowed
+/// * `uri` - the path must be `DNS_QUERY_PATH`, with the `dns` variable for GET
+/// * `content_type` - the Content-Type header, which must be `MIME_APPLICATION_DNS` for POST
+/// * `body` - the body of the request, the DNS request for POST
+pub fn dns_request(
+    method: &Method,
+    uri: &Uri,
+    content_type: Option<&HeaderValue>,
+    body: &[u8],
+) -> Result<Vec<u8>, StatusCode> {
+    if uri.path() != DNS_QUERY_PATH {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let request = if *method == Method::GET {
+        let dns = uri.query()
+            .and_then(|query| {
+                query
+                    .split('&')
+                    .filter_map(|pair| {
+                        let mut pair = pair.splitn(2, '=');
+                        match (pair.next(), pair.next()) {
+                            (Some("dns"), Some(value)) => Some(value),
+                            _ => None,
+                        }
+                    })
+                    .next()
+            })
+            .ok_or(StatusCode::BAD_REQUEST)?;
+
+        // the padding is left out of base64url, which the decoder accepts
+        dns.from_base64().map_err(|_| StatusCode::BAD_REQUEST)?
+    } else if *method == Method::POST {
+        if content_type.map(HeaderValue::as_bytes) != Some(MIME_APPLICATION_DNS.as_bytes()) {
+            return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+        body.to_vec()
+    } else {
+        return Err(StatusCode::METHOD_NOT_ALLOWED);
+    };
+
+    if request.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if request.len() > MAX_MESSAGE_LEN {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    Ok(request)
+}
+
+/// The freshness lifetime of the response, the smallest TTL of its records
+///
+/// [RFC 8484](https://tools.ietf.org/html/rfc8484#section-5.1), DNS Queries over HTTPS (DoH), October 2018
+///
+/// ```text
+/// The assigned freshness lifetime of a DoH HTTP response MUST be less
+/// than or equal to the smallest TTL in the Answer section of the DNS
+/// response.
+/// ```
+pub fn max_age(response: &[u8]) -> u32 {
+    Message::from_bytes(response)
+        .ok()
+        .and_then(|message| {
+            message
+                .answers()
+                .iter()
+                .chain(message.name_servers())
+                .chain(message.additionals())
+                .map(|record| record.ttl())
+                .min()
+        })
+        .unwrap_or(0)
+}
+
+/// Sends the HTTP response on the stream of the request
+///
+/// # Arguments
+///
+/// * `respond` - the stream of the request
+/// * `status` - the status of the response
+/// * `dns_response` - the DNS response, the body of the response, none for errors
+pub fn send_response(
+    mut respond: SendResponse<Bytes>,
+    status: StatusCode,
+    dns_response: Option<Vec<u8>>,
+) -> io::Result<()> {
+    let mut builder = Response::builder();
+    builder.status(status);
+    if let Some(ref dns_response) = dns_response {
+        builder
+            .header(CONTENT_TYPE, MIME_APPLICATION_DNS)
+            .header(
+                CACHE_CONTROL,
+                &*format!("max-age={}", max_age(dns_response)),
+            );
+    }
+    let response = builder
+        .body(())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("http error: {}", e)))?;
+
+    match dns_response {
+        Some(dns_response) => {
+            let mut stream = respond.send_response(response, false).map_err(http2_error)?;
+            stream
+                .send_data(Bytes::from(dns_response), true)
+                .map_err(http2_error)
+        }
+        None => respond
+            .send_response(response, true)
+            .map(|_| ())
+            .map_err(http2_error),
+    }
+}
+
+/// Converts an error of the HTTP/2 connection or stream
+pub fn http2_error(error: h2::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("http/2 error: {}", error))
+}
+
+/// Passes the encoded response to the HTTP/2 stream of the request
+pub struct HttpsResponseHandle {
+    sender: oneshot::Sender<Vec<u8>>,
+}
+
+impl HttpsResponseHandle {
+    /// Returns a new `HttpsResponseHandle`, the response is received on the receiver
+    pub fn new() -> (Self, oneshot::Receiver<Vec<u8>>) {
+        let (sender, receiver) = oneshot::channel();
+        (HttpsResponseHandle { sender }, receiver)
+    }
+}
+
+impl ResponseHandler for HttpsResponseHandle {
+    fn send<M: EncodableMessage>(self, response: M) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(512);
+        {
+            let mut encoder: BinEncoder = BinEncoder::new(&mut buffer);
+            response.emit(&mut encoder).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("error encoding message: {}", e),
+                )
+            })?;
+        }
+
+        self.sender
+            .send(buffer)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "https stream closed"))
+    }
+}
@@ -16,12 +16,22 @@
 
 //! `Server` component for hosting a domain name servers operations.
 
+#[cfg(feature = "dns-over-https")]
+pub mod https;
+mod cookies;
 mod server_future;
 mod timeout_stream;
+mod rate_limiter;
 mod request_handler;
 mod response_handler;
+mod truncation;
 
+pub use self::cookies::{CheckedCookie, ServerCookies, SECRET_LEN};
+pub use self::rate_limiter::DEFAULT_MAX_NETWORKS;
+pub use self::rate_limiter::{Action, RateLimitedResponseHandle, RateLimiter, ResponseClass};
 pub use self::response_handler::{ResponseHandle, ResponseHandler};
 pub use self::server_future::ServerFuture;
 pub use self::timeout_stream::TimeoutStream;
-pub use self::request_handler::{Request, RequestHandler};
+pub use self::truncation::{TruncatingResponseHandle, DEFAULT_MAX_UDP_PAYLOAD, MIN_PAYLOAD_LEN};
+pub(crate) use self::truncation::send_fitted;
+pub use self::request_handler::{ClientIdentity, Request, RequestContext, RequestHandler};
@@ -0,0 +1,342 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Response Rate Limiting, RRL, of UDP responses
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use trust_dns::op::ResponseCode;
+use trust_dns::rr::rdata::opt::truncate_address;
+use trust_dns_proto::op::EncodableMessage;
+
+use server::truncation::PartialResponse;
+use server::ResponseHandler;
+
+/// The class of a response, the responses of each class to a client are limited separately
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ResponseClass {
+    /// NoError with records in the answer section, or with no records at all, i.e. NoData
+    Answer,
+    /// NXDomain, the name does not exist
+    NxDomain,
+    /// NoError without answers, delegating to the name servers of a child zone
+    Referral,
+    /// any other response code
+    Error,
+}
+
+impl ResponseClass {
+    /// Classifies the response
+    pub fn of<M: EncodableMessage>(response: &M) -> Self {
+        let header = response.header();
+        match ResponseCode::from(0, header.response_code()) {
+            ResponseCode::NoError
+                if response.answers_len() == 0
+                    && response.name_servers_len() > 0
+                    && !header.authoritative() =>
+            {
+                ResponseClass::Referral
+            }
+            ResponseCode::NoError => ResponseClass::Answer,
+            ResponseCode::NXDomain => ResponseClass::NxDomain,
+            _ => ResponseClass::Error,
+        }
+    }
+}
+
+/// What is to be done with a response
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// the response is sent
+    Send,
+    /// a truncated response is sent in its place, a real client will retry over TCP
+    Slip,
+    /// nothing is sent
+    Drop,
+}
+
+/// The default maximum number of client networks whose responses are limited separately
+pub const DEFAULT_MAX_NETWORKS: usize = 65_536;
+
+// the number of stripes of the buckets, each with a lock of its own, so that the workers rarely
+//  wait on one another
+const STRIPES: usize = 64;
+
+struct Bucket {
+    balance: f64,
+    last: Instant,
+    limited: u32,
+}
+
+impl Bucket {
+    // the credit accrues at the rate, up to one second of responses
+    fn credit(&self, rate: f64, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.last);
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+        (self.balance + elapsed * rate).min(rate)
+    }
+}
+
+type Buckets = HashMap<(IpAddr, ResponseClass), Bucket>;
+
+/// Limits the rate of the responses sent to each client network, per class of response
+///
+/// [RRL](https://kb.isc.org/docs/aa-00994) keeps the server from being used to amplify
+///  reflection attacks with spoofed source addresses. Clients are grouped by the prefix of their
+///  address, and each group has a credit of `responses_per_second` for each class of response.
+///  Credit accrues every second, up to one second of responses, and may be overdrawn by up to
+///  `window` seconds of responses, so that a client which is limited stays limited until it has
+///  slowed down for a while.
+///
+/// Clients which send a valid server cookie are not limited, their address is not spoofed, see
+///  `RateLimitedResponseHandle`.
+///
+/// At most `max_networks` networks are limited separately, see `set_max_networks`. Once there are
+///  as many, networks which have regained all their credit are forgotten, and if none has, the
+///  other networks share one limit until some have.
+///
+/// The limits are shared by the UDP sockets of all the workers, the buckets of the networks are
+///  in stripes, by the hash of the network, each locked separately.
+pub struct RateLimiter {
+    responses_per_second: u32,
+    window: Duration,
+    slip: u32,
+    ipv4_prefix_len: u8,
+    ipv6_prefix_len: u8,
+    max_networks: usize,
+    stripes: Vec<Mutex<Buckets>>,
+    // the networks which share one limit, once there are max_networks
+    shared: Mutex<Buckets>,
+    // the number of networks in all the stripes
+    networks: AtomicUsize,
+    last_cleaned: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Constructs a new RateLimiter
+    ///
+    /// # Arguments
+    ///
+    /// * `responses_per_second` - the responses of each class sent to a client network per second
+    /// * `window` - the period over which the rate is averaged
+    /// * `slip` - every `slip`th limited response is sent truncated, rather than dropped, 0 drops
+    ///            all limited responses
+    /// * `ipv4_prefix_len` - IPv4 clients within this prefix length share their limits
+    /// * `ipv6_prefix_len` - IPv6 clients within this prefix length share their limits
+    pub fn new(
+        responses_per_second: u32,
+        window: Duration,
+        slip: u32,
+        ipv4_prefix_len: u8,
+        ipv6_prefix_len: u8,
+    ) -> Self {
+        RateLimiter {
+            responses_per_second,
+            window,
+            slip,
+            ipv4_prefix_len: ipv4_prefix_len.min(32),
+            ipv6_prefix_len: ipv6_prefix_len.min(128),
+            max_networks: DEFAULT_MAX_NETWORKS,
+            stripes: (0..STRIPES).map(|_| Mutex::new(HashMap::new())).collect(),
+            shared: Mutex::new(HashMap::new()),
+            networks: AtomicUsize::new(0),
+            last_cleaned: Mutex::new(None),
+        }
+    }
+
+    /// Sets the maximum number of client networks whose responses are limited separately, for each
+    ///  class of response, by default `DEFAULT_MAX_NETWORKS`
+    pub fn set_max_networks(&mut self, max_networks: usize) {
+        self.max_networks = max_networks;
+    }
+
+    /// Decides what is done with a response to the client, the response is counted
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the address to which the response is sent
+    /// * `class` - the class of the response
+    /// * `now` - the current time
+    pub fn check(&self, client: IpAddr, class: ResponseClass, now: Instant) -> Action {
+        self.clean(now);
+
+        let rate = self.responses_per_second as f64;
+        let network = self.network(client);
+        let stripe = &self.stripes[stripe_of(network)];
+
+        let is_new = !lock(stripe).contains_key(&(network, class));
+        if is_new && self.networks.load(Ordering::Relaxed) >= self.max_networks {
+            // buckets at full credit are the same as new ones
+            self.retain(|bucket| bucket.credit(rate, now) < rate);
+            if self.networks.load(Ordering::Relaxed) >= self.max_networks {
+                debug!("too many limited networks, sharing the limit: {}", network);
+                let shared = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+                return self.check_bucket(&mut lock(&self.shared), shared, class, now, false);
+            }
+        }
+
+        self.check_bucket(&mut lock(stripe), network, class, now, true)
+    }
+
+    fn check_bucket(
+        &self,
+        buckets: &mut Buckets,
+        network: IpAddr,
+        class: ResponseClass,
+        now: Instant,
+        is_counted: bool,
+    ) -> Action {
+        let rate = self.responses_per_second as f64;
+        let window = self.window.as_secs() as f64;
+        let networks = &self.networks;
+        let bucket = buckets.entry((network, class)).or_insert_with(|| {
+            if is_counted {
+                networks.fetch_add(1, Ordering::Relaxed);
+            }
+            Bucket {
+                balance: rate,
+                last: now,
+                limited: 0,
+            }
+        });
+
+        bucket.balance = bucket.credit(rate, now);
+        bucket.last = now;
+
+        // and is overdrawn by up to the window of responses
+        let balance = bucket.balance - 1.0;
+        bucket.balance = balance.max(-window * rate);
+        if balance >= 0.0 {
+            bucket.limited = 0;
+            return Action::Send;
+        }
+
+        bucket.limited = bucket.limited.wrapping_add(1);
+        if self.slip > 0 && bucket.limited % self.slip == 0 {
+            debug!("rate limited, truncating: {} {:?}", network, class);
+            Action::Slip
+        } else {
+            debug!("rate limited, dropping: {} {:?}", network, class);
+            Action::Drop
+        }
+    }
+
+    // the address of the network of the client, with the bits past the prefix cleared
+    fn network(&self, client: IpAddr) -> IpAddr {
+        match client {
+            IpAddr::V4(..) => truncate_address(client, self.ipv4_prefix_len),
+            IpAddr::V6(..) => truncate_address(client, self.ipv6_prefix_len),
+        }
+    }
+
+    // buckets idle for longer than the window have regained all their credit, they are dropped,
+    //  by one of the workers, the others do not wait for it
+    fn clean(&self, now: Instant) {
+        let idle = self.window + Duration::from_secs(1);
+        {
+            let mut last_cleaned = match self.last_cleaned.try_lock() {
+                Ok(last_cleaned) => last_cleaned,
+                Err(_) => return,
+            };
+            match *last_cleaned {
+                Some(last_cleaned) if now.duration_since(last_cleaned) < idle => return,
+                _ => *last_cleaned = Some(now),
+            }
+        }
+
+        self.retain(|bucket| now.duration_since(bucket.last) < idle);
+        lock(&self.shared).retain(|_, bucket| now.duration_since(bucket.last) < idle);
+    }
+
+    // keeps the buckets of the stripes for which `f` is true, locking one stripe at a time
+    fn retain<F: Fn(&Bucket) -> bool>(&self, f: F) {
+        for stripe in &self.stripes {
+            let mut buckets = lock(stripe);
+            let len = buckets.len();
+            buckets.retain(|_, bucket| f(bucket));
+            self.networks
+                .fetch_sub(len - buckets.len(), Ordering::Relaxed);
+        }
+    }
+}
+
+fn stripe_of(network: IpAddr) -> usize {
+    let mut hasher = DefaultHasher::new();
+    network.hash(&mut hasher);
+    hasher.finish() as usize % STRIPES
+}
+
+fn lock(buckets: &Mutex<Buckets>) -> MutexGuard<Buckets> {
+    buckets.lock().expect("rate limiter lock poisoned")
+}
+
+/// Sends UDP responses subject to the `RateLimiter`
+///
+/// The responses to requests with a valid server cookie are not limited.
+///
+/// [RFC 7873](https://tools.ietf.org/html/rfc7873#section-5.2.3), Domain Name System (DNS) Cookies, May 2016
+///
+/// ```text
+/// If the server cookie is valid, the server responds to the request
+/// normally.
+/// ```
+pub struct RateLimitedResponseHandle<R: ResponseHandler> {
+    response_handle: R,
+    client: IpAddr,
+    is_exempt: bool,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl<R: ResponseHandler> RateLimitedResponseHandle<R> {
+    /// Returns a new `RateLimitedResponseHandle` wrapping the `ResponseHandler`
+    ///
+    /// # Arguments
+    ///
+    /// * `response_handle` - sends the response
+    /// * `client` - the address to which the response is sent
+    /// * `is_exempt` - true if the server cookie of the request is valid, see
+    ///                 `RequestContext::cookie`
+    /// * `rate_limiter` - the limits, shared by all the UDP sockets
+    pub fn new(
+        response_handle: R,
+        client: IpAddr,
+        is_exempt: bool,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        RateLimitedResponseHandle {
+            response_handle,
+            client,
+            is_exempt,
+            rate_limiter,
+        }
+    }
+}
+
+impl<R: ResponseHandler> ResponseHandler for RateLimitedResponseHandle<R> {
+    /// Sends the response, a truncated response, or nothing at all, see `RateLimiter::check`
+    fn send<M: EncodableMessage>(self, response: M) -> io::Result<()> {
+        let action = if self.is_exempt {
+            Action::Send
+        } else {
+            self.rate_limiter
+                .check(self.client, ResponseClass::of(&response), Instant::now())
+        };
+
+        match action {
+            Action::Send => self.response_handle.send(response),
+            Action::Slip => self.response_handle.send(PartialResponse::truncated(&response)),
+            Action::Drop => Ok(()),
+        }
+    }
+}
@@ -7,11 +7,18 @@
 
 //! Request Handler for incoming requests
 
-use std::net::SocketAddr;
+use std::fmt;
 use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_core::reactor::Handle;
+
+use trust_dns::rr::Name;
 
 use authority::MessageRequest;
-use server::ResponseHandler;
+use server::{CheckedCookie, ResponseHandler, ServerCookies, MIN_PAYLOAD_LEN};
 
 /// An incoming request to the DNS catalog
 pub struct Request<'r> {
@@ -21,6 +28,224 @@ pub struct Request<'r> {
     pub src: SocketAddr,
 }
 
+/// The identity of a client, from its certificate, which verified against the client CA of the
+///  TLS listener, see `ServerFuture::register_tls_listener_with_client_ca` and
+///  `RequestContext::client_identity`
+#[derive(Clone)]
+pub struct ClientIdentity {
+    names: Vec<Name>,
+    // matches the names against the certificate itself, see `with_verifier`
+    verifier: Option<Arc<Fn(&Name) -> bool + Send + Sync>>,
+}
+
+impl ClientIdentity {
+    /// Constructs a new ClientIdentity
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - the DNS names of the subject alternative names of the certificate
+    pub fn new(names: Vec<Name>) -> Self {
+        ClientIdentity {
+            names,
+            verifier: None,
+        }
+    }
+
+    /// Constructs a new ClientIdentity, whose certificate is checked for each name, e.g. by
+    ///  webpki, which also matches wildcards, see `trust_dns_rustls::tls_server::is_valid_for_name`
+    ///
+    /// # Arguments
+    ///
+    /// * `verifier` - returns true if the certificate of the client is for the name
+    pub fn with_verifier<F>(verifier: F) -> Self
+    where
+        F: Fn(&Name) -> bool + Send + Sync + 'static,
+    {
+        ClientIdentity {
+            names: vec![],
+            verifier: Some(Arc::new(verifier)),
+        }
+    }
+
+    /// the names of the certificate of the client, empty if it is checked by a verifier
+    pub fn names(&self) -> &[Name] {
+        &self.names
+    }
+
+    /// Returns true if the certificate of the client is for the name
+    pub fn is(&self, name: &Name) -> bool {
+        match self.verifier {
+            Some(ref verifier) => verifier(name),
+            None => self.names.iter().any(|n| n == name),
+        }
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("names", &self.names)
+            .field("verifier", &self.verifier.is_some())
+            .finish()
+    }
+}
+
+impl PartialEq for ClientIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        let is_same_verifier = match (&self.verifier, &other.verifier) {
+            (&Some(ref verifier), &Some(ref other)) => Arc::ptr_eq(verifier, other),
+            (&None, &None) => true,
+            _ => false,
+        };
+        self.names == other.names && is_same_verifier
+    }
+}
+
+/// The context in which a request was received, from the listener of the server, the responses
+///  to the request are shaped by it, see `RequestHandler::handle_request_with_context`
+#[derive(Clone, Debug, Default)]
+pub struct RequestContext {
+    dst: Option<SocketAddr>,
+    max_udp_payload: Option<u16>,
+    client_identity: Option<ClientIdentity>,
+    handle: Option<Handle>,
+    server_cookies: Option<Arc<ServerCookies>>,
+    cookie: Option<CheckedCookie>,
+    idle_timeout: Option<Duration>,
+    encrypted: bool,
+}
+
+impl RequestContext {
+    /// Returns the context of a request which was not received over UDP, e.g. over TCP
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the address of the listener which received the request
+    pub fn set_dst(&mut self, dst: SocketAddr) {
+        self.dst = Some(dst);
+    }
+
+    /// The address of the listener which received the request, as it is bound, not the
+    ///  destination address of the request: it is the unspecified address, `0.0.0.0` or `::`, for
+    ///  listeners which are bound to it. None if the request was not received by a listener.
+    pub fn dst(&self) -> Option<SocketAddr> {
+        self.dst
+    }
+
+    /// Sets the largest UDP payload which the server sends, for a request received over UDP
+    pub fn set_max_udp_payload(&mut self, max_udp_payload: u16) {
+        self.max_udp_payload = Some(max_udp_payload.max(MIN_PAYLOAD_LEN));
+    }
+
+    /// The largest UDP payload which the server sends, if the request was received over UDP
+    pub fn max_udp_payload(&self) -> Option<u16> {
+        self.max_udp_payload
+    }
+
+    /// Sets the identity of the client, from the certificate it presented over mutual TLS
+    pub fn set_client_identity(&mut self, client_identity: Option<ClientIdentity>) {
+        self.client_identity = client_identity;
+    }
+
+    /// The identity of the client, if it presented a certificate over mutual TLS
+    pub fn client_identity(&self) -> Option<&ClientIdentity> {
+        self.client_identity.as_ref()
+    }
+
+    /// Sets the reactor which received the request
+    pub fn set_handle(&mut self, handle: &Handle) {
+        self.handle = Some(handle.clone());
+    }
+
+    /// The reactor which received the request, on which recursive and forwarded queries are
+    ///  resolved, if the request was received by a listener of the server
+    pub fn handle(&self) -> Option<&Handle> {
+        self.handle.as_ref()
+    }
+
+    /// Sets the DNS Cookies of the server, with which the cookie of the request is checked
+    pub fn set_server_cookies(&mut self, server_cookies: Arc<ServerCookies>) {
+        self.server_cookies = Some(server_cookies);
+    }
+
+    /// The DNS Cookies of the server, None if they are disabled
+    pub fn server_cookies(&self) -> Option<&ServerCookies> {
+        self.server_cookies
+            .as_ref()
+            .map(|server_cookies| &**server_cookies)
+    }
+
+    /// Checks the cookie of the request with the DNS Cookies of the server, once, by the listener
+    ///  which received it, for the rate limits and for the response, see `cookie`
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the request, with its cookie if it has a well formed one
+    /// * `client` - the address of the client
+    pub fn check_cookie(&mut self, request: &MessageRequest, client: IpAddr) {
+        self.cookie = match (self.server_cookies.as_ref(), request.cookie()) {
+            (Some(server_cookies), Some(cookie)) => Some(server_cookies.check(&cookie, client)),
+            _ => None,
+        };
+    }
+
+    /// The checked cookie of the request, None if cookies are disabled, or if the request had no
+    ///  well formed cookie, or it was not checked, see `check_cookie`
+    pub fn cookie(&self) -> Option<&CheckedCookie> {
+        self.cookie.as_ref()
+    }
+
+    /// The largest response which is sent to the client over UDP, the payload size from the EDNS
+    ///  of the request, at least 512 bytes, up to the `max_udp_payload` of the server. None if
+    ///  the request was not received over UDP.
+    pub fn max_payload(&self, request: &MessageRequest) -> Option<u16> {
+        self.max_udp_payload
+            .map(|max_udp_payload| request.max_payload().min(max_udp_payload))
+    }
+
+    /// Sets the timeout after which the server closes idle connections, for a request received
+    ///  over TCP or TLS, zero for none
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = Some(idle_timeout);
+    }
+
+    /// The idle timeout advertised in the edns-tcp-keepalive option, in units of 100
+    ///  milliseconds, the longest timeout if the connection has none. None if the request was
+    ///  not received over TCP or TLS.
+    ///
+    /// [RFC 7828](https://tools.ietf.org/html/rfc7828#section-3.3.2), The edns-tcp-keepalive EDNS0 Option, April 2016
+    ///
+    /// ```text
+    /// A DNS server that receives a query sent using TCP transport that
+    /// includes an OPT RR (with or without the edns-tcp-keepalive option)
+    /// MAY include the edns-tcp-keepalive option in the response to signal
+    /// the expected idle timeout on a connection.
+    /// ```
+    pub fn keepalive_timeout(&self) -> Option<u16> {
+        self.idle_timeout.map(|idle_timeout| {
+            if idle_timeout == Duration::from_secs(0) {
+                return u16::max_value();
+            }
+
+            let timeout =
+                idle_timeout.as_secs() * 10 + u64::from(idle_timeout.subsec_nanos() / 100_000_000);
+            timeout.min(u64::from(u16::max_value())) as u16
+        })
+    }
+
+    /// Sets whether the request was received over an encrypted connection, TLS or HTTPS
+    pub fn set_encrypted(&mut self, encrypted: bool) {
+        self.encrypted = encrypted;
+    }
+
+    /// Returns true if the request was received over TLS or HTTPS, only then are the responses
+    ///  to requests with the Padding option padded, see RFC 7830 and RFC 8467
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+}
+
 /// Trait for handling incoming requests, and providing a message response.
 pub trait RequestHandler {
     // TODO: allow associated error type
@@ -37,4 +262,21 @@ pub trait RequestHandler {
         request: &'q Request,
         response_handle: R,
     ) -> io::Result<()>;
+
+    /// Determine's what needs to happen given the type of request, in the context in which it
+    ///  was received. By default the context is ignored, see `handle_request`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - the requested action to perform.
+    /// * `context` - how the request was received, e.g. the payload size of UDP responses
+    /// * `response_handle` - handle to which a return message should be sent
+    fn handle_request_with_context<'q, 'a, R: ResponseHandler + 'static>(
+        &'a self,
+        request: &'q Request,
+        _context: &RequestContext,
+        response_handle: R,
+    ) -> io::Result<()> {
+        self.handle_request(request, response_handle)
+    }
 }
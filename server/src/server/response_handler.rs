@@ -33,6 +33,11 @@ impl ResponseHandle {
     pub fn new(dst: SocketAddr, stream_handle: BufStreamHandle<ClientError>) -> Self {
         ResponseHandle { dst, stream_handle }
     }
+
+    /// The address to which the response is sent
+    pub fn dst(&self) -> SocketAddr {
+        self.dst
+    }
 }
 
 impl ResponseHandler for ResponseHandle {
@@ -8,27 +8,52 @@ use std;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "dns-over-https")]
+use bytes::Bytes;
+#[cfg(feature = "dns-over-https")]
+use futures::future::{self, Either};
 use futures::{Async, Future, Poll, Stream};
+#[cfg(feature = "dns-over-https")]
+use h2;
+#[cfg(feature = "dns-over-https")]
+use http::{self, StatusCode};
+#[cfg(feature = "dns-over-https")]
+use http::header::CONTENT_TYPE;
 
 use tokio_core;
 use tokio_core::reactor::Core;
+#[cfg(feature = "dns-over-https")]
+use tokio_core::reactor::Timeout;
 
-use trust_dns::error::*;
-use trust_dns::BufStreamHandle;
 use trust_dns::udp::UdpStream;
 use trust_dns::tcp::TcpStream;
 use trust_dns::serialize::binary::{BinDecodable, BinDecoder};
+#[cfg(any(feature = "tls-openssl", feature = "tls-ring"))]
+use trust_dns::rr::Name;
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 use trust_dns_openssl::{tls_server, TlsStream};
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 use trust_dns_openssl::tls_server::*;
 
+#[cfg(feature = "tls-ring")]
+use trust_dns::tcp::TcpStream as DnsTcpStream;
+#[cfg(feature = "tls-ring")]
+use trust_dns_rustls::tls_server::{self as rustls_server, Certificate, PrivateKey,
+                                   ServerConfigExt};
+
 use authority::MessageRequest;
-use server::{Request, RequestHandler, ResponseHandle, TimeoutStream};
+use metrics::{self, MeteredResponseHandle};
+#[cfg(feature = "dns-over-https")]
+use server::https::{self, HttpsResponseHandle};
+#[cfg(any(feature = "tls-openssl", feature = "tls-ring"))]
+use server::ClientIdentity;
+use server::{RateLimitedResponseHandle, RateLimiter, Request, RequestContext, RequestHandler,
+             ResponseHandle, ResponseHandler, ServerCookies, TimeoutStream, TruncatingResponseHandle,
+             DEFAULT_MAX_UDP_PAYLOAD, MIN_PAYLOAD_LEN};
 
 // TODO, would be nice to have a Slab for buffers here...
 
@@ -36,31 +61,106 @@ use server::{Request, RequestHandler, ResponseHandle, TimeoutStream};
 pub struct ServerFuture<T: RequestHandler + 'static> {
     io_loop: Core,
     handler: Arc<T>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    max_udp_payload: u16,
+    server_cookies: Option<Arc<ServerCookies>>,
 }
 
 impl<T: RequestHandler> ServerFuture<T> {
     /// Creates a new ServerFuture with the specified Handler.
     pub fn new(handler: T) -> io::Result<ServerFuture<T>> {
-        Ok(ServerFuture {
-            io_loop: Core::new()?,
+        Ok(Self::with_core(Core::new()?, handler))
+    }
+
+    /// Creates a new ServerFuture with the specified Handler, running on an existing Core.
+    ///
+    /// This allows the handler to be constructed with a handle to the Core, e.g. to send queries
+    ///  to other servers.
+    pub fn with_core(io_loop: Core, handler: T) -> ServerFuture<T> {
+        ServerFuture {
+            io_loop,
             handler: Arc::new(handler),
-        })
+            rate_limiter: None,
+            max_udp_payload: DEFAULT_MAX_UDP_PAYLOAD,
+            server_cookies: None,
+        }
+    }
+
+    /// Limits the rate of responses to UDP requests, for sockets registered after this call
+    ///
+    /// All the UDP sockets share the limits, as do other `ServerFuture`s given the same
+    ///  `rate_limiter`, e.g. those of the threads serving the same SO_REUSEPORT addresses. TCP is
+    ///  not limited, as the source address of a TCP connection can not be spoofed.
+    pub fn set_rate_limiter(&mut self, rate_limiter: Arc<RateLimiter>) {
+        self.rate_limiter = Some(rate_limiter);
+    }
+
+    /// Limits the size of UDP responses, for sockets registered after this call
+    ///
+    /// Responses are fitted into the payload size advertised in the EDNS of the request, or 512
+    ///  bytes without EDNS, up to this limit, see `TruncatingResponseHandle`. The default is
+    ///  `DEFAULT_MAX_UDP_PAYLOAD`, values below 512 are raised to 512.
+    pub fn set_max_udp_payload(&mut self, max_udp_payload: u16) {
+        self.max_udp_payload = max_udp_payload.max(MIN_PAYLOAD_LEN);
+    }
+
+    /// Answers the DNS Cookies of clients, for listeners registered after this call
+    ///
+    /// [RFC 7873](https://tools.ietf.org/html/rfc7873#section-5.2), Domain Name System (DNS) Cookies, May 2016
+    ///
+    /// ```text
+    /// If the server responds to the request, it SHOULD
+    /// generate its own COOKIE option containing both the Client Cookie
+    /// copied from the request and a Server Cookie it has generated, and it
+    /// will add this COOKIE option to the response's OPT record.
+    /// ```
+    ///
+    /// The cookie of each request is checked once, by the listener which received it, see
+    ///  `RequestContext::check_cookie`. Clients with a valid server cookie are exempt from the
+    ///  rate limits, the handler answers the cookies, and requests with a server cookie which is
+    ///  not valid, e.g. which has expired, with BADCOOKIE and a new server cookie, with which the
+    ///  client retries.
+    pub fn set_server_cookies(&mut self, server_cookies: Arc<ServerCookies>) {
+        self.server_cookies = Some(server_cookies);
+    }
+
+    // the context of the requests received by the listener, on this reactor
+    fn context(&self, dst: SocketAddr) -> RequestContext {
+        let mut context = RequestContext::new();
+        context.set_dst(dst);
+        context.set_handle(&self.io_loop.handle());
+        if let Some(ref server_cookies) = self.server_cookies {
+            context.set_server_cookies(server_cookies.clone());
+        }
+        context
     }
 
     /// Register a UDP socket. Should be bound before calling this function.
     pub fn register_socket(&self, socket: std::net::UdpSocket) {
         debug!("registered udp: {:?}", socket);
+        let addr = socket.local_addr().expect("socket is not bound?");
 
         // create the new UdpStream
         let (buf_stream, stream_handle) = UdpStream::with_bound(socket, &self.io_loop.handle());
         //let request_stream = RequestStream::new(buf_stream, stream_handle);
         let handler = self.handler.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let mut context = self.context(addr);
+        context.set_max_udp_payload(self.max_udp_payload);
 
         // this spawns a ForEach future which handles all the requests into a Handler.
         self.io_loop.handle().spawn(
             buf_stream
                 .for_each(move |(buffer, src_addr)| {
-                    Self::handle_request(buffer, src_addr, stream_handle.clone(), handler.clone())
+                    Self::handle_request(
+                        buffer,
+                        src_addr,
+                        "udp",
+                        context.clone(),
+                        rate_limiter.clone(),
+                        ResponseHandle::new(src_addr, stream_handle.clone()),
+                        handler.clone(),
+                    )
                 })
                 .map_err(|e| debug!("error in UDP request_stream handler: {}", e)),
         );
@@ -90,6 +190,8 @@ impl<T: RequestHandler> ServerFuture<T> {
         let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, &handle)
             .expect("could not register listener");
         debug!("registered tcp: {:?}", listener);
+        let mut context = self.context(addr);
+        context.set_idle_timeout(timeout);
 
         // for each incoming request...
         self.io_loop.handle().spawn(
@@ -97,11 +199,13 @@ impl<T: RequestHandler> ServerFuture<T> {
                 .incoming()
                 .for_each(move |(tcp_stream, src_addr)| {
                     debug!("accepted request from: {}", src_addr);
+                    metrics::count_connection("tcp");
                     // take the created stream...
                     let (buf_stream, stream_handle) = TcpStream::from_stream(tcp_stream, src_addr);
                     let timeout_stream = TimeoutStream::new(buf_stream, timeout, &handle)?;
                     //let request_stream = RequestStream::new(timeout_stream, stream_handle);
                     let handler = handler.clone();
+                    let context = context.clone();
 
                     // and spawn to the io_loop
                     handle.spawn(
@@ -110,7 +214,10 @@ impl<T: RequestHandler> ServerFuture<T> {
                                 Self::handle_request(
                                     buffer,
                                     src_addr,
-                                    stream_handle.clone(),
+                                    "tcp",
+                                    context.clone(),
+                                    None,
+                                    ResponseHandle::new(src_addr, stream_handle.clone()),
                                     handler.clone(),
                                 )
                             })
@@ -144,12 +251,36 @@ impl<T: RequestHandler> ServerFuture<T> {
     ///               possible to create long-lived queries, but these should be from trusted sources
     ///               only, this would require some type of whitelisting.
     /// * `pkcs12` - certificate used to announce to clients
-    #[cfg(feature = "tls")]
+    #[cfg(feature = "tls-openssl")]
     pub fn register_tls_listener(
         &self,
         listener: std::net::TcpListener,
         timeout: Duration,
         pkcs12: ParsedPkcs12,
+    ) -> io::Result<()> {
+        self.register_tls_listener_with_client_ca(listener, timeout, pkcs12, Vec::new())
+    }
+
+    /// Register a TlsListener to the Server, for mutual TLS. See `register_tls_listener`.
+    ///
+    /// Clients are asked for a certificate, signed by one of the CAs, the names of the certificate
+    ///  are passed as the `ClientIdentity` of the requests on the connection, see
+    ///  `trust_dns_openssl::tls_server::peer_names`. Clients without a certificate are accepted,
+    ///  without an identity.
+    ///
+    /// # Arguments
+    /// * `listener` - a bound TCP (needs to be on a different port from standard TCP connections) socket
+    /// * `timeout` - timeout duration of incoming requests, any connection that does not send
+    ///               requests within this time period will be closed.
+    /// * `pkcs12` - certificate used to announce to clients
+    /// * `client_ca` - certificates of the CAs of the clients, none disables client certificates
+    #[cfg(feature = "tls-openssl")]
+    pub fn register_tls_listener_with_client_ca(
+        &self,
+        listener: std::net::TcpListener,
+        timeout: Duration,
+        pkcs12: ParsedPkcs12,
+        client_ca: Vec<X509>,
     ) -> io::Result<()> {
         let handle = self.io_loop.handle();
         let handler = self.handler.clone();
@@ -158,8 +289,11 @@ impl<T: RequestHandler> ServerFuture<T> {
         let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, &handle)
             .expect("could not register listener");
         debug!("registered tcp: {:?}", listener);
+        let mut context = self.context(addr);
+        context.set_idle_timeout(timeout);
+        context.set_encrypted(true);
 
-        let tls_acceptor = tls_server::new_acceptor(&pkcs12)?;
+        let tls_acceptor = tls_server::new_acceptor_with_client_ca(&pkcs12, &client_ca)?;
 
         // for each incoming request...
         self.io_loop.handle().spawn(
@@ -167,8 +301,10 @@ impl<T: RequestHandler> ServerFuture<T> {
                 .incoming()
                 .for_each(move |(tcp_stream, src_addr)| {
                     debug!("accepted request from: {}", src_addr);
+                    metrics::count_connection("tls");
                     let handle = handle.clone();
                     let handler = handler.clone();
+                    let mut context = context.clone();
 
                     // take the created stream...
                     tls_acceptor
@@ -180,6 +316,9 @@ impl<T: RequestHandler> ServerFuture<T> {
                             )
                         })
                         .and_then(move |tls_stream| {
+                            context.set_client_identity(Self::client_identity(
+                                tls_server::peer_names(tls_stream.get_ref().ssl()),
+                            ));
                             let (buf_stream, stream_handle) =
                                 TlsStream::from_stream(tls_stream, src_addr);
                             let timeout_stream = TimeoutStream::new(buf_stream, timeout, &handle)?;
@@ -189,11 +328,14 @@ impl<T: RequestHandler> ServerFuture<T> {
                             // and spawn to the io_loop
                             handle.spawn(
                                 timeout_stream
-                                    .for_each(move |(buffer, addr)| {
+                                    .for_each(move |(buffer, src_addr)| {
                                         Self::handle_request(
                                             buffer,
-                                            addr,
-                                            stream_handle.clone(),
+                                            src_addr,
+                                            "tls",
+                                            context.clone(),
+                                            None,
+                                            ResponseHandle::new(src_addr, stream_handle.clone()),
                                             handler.clone(),
                                         )
                                     })
@@ -216,9 +358,242 @@ impl<T: RequestHandler> ServerFuture<T> {
         Ok(())
     }
 
-    /// TODO: how to do threads? should we do a bunch of listener threads and then query threads?
-    /// Ideally the processing would be n-threads for recieving, which hand off to m-threads for
-    ///  request handling. It would generally be the case that n <= m.
+    /// Register a TlsListener to the Server, with TLS from rustls rather than OpenSSL. The
+    /// TlsListener should already be bound to either an IPv6 or an IPv4 address.
+    ///
+    /// See `register_tls_listener`, this accepts the PEM certificate chain and private key,
+    ///  see `trust_dns_rustls::tls_server::{read_cert, read_key}`.
+    ///
+    /// # Arguments
+    /// * `listener` - a bound TCP (needs to be on a different port from standard TCP connections) socket
+    /// * `timeout` - timeout duration of incoming requests, any connection that does not send
+    ///               requests within this time period will be closed.
+    /// * `cert_chain` - certificate used to announce to clients, followed by its intermediates
+    /// * `key` - the private key of the certificate
+    #[cfg(feature = "tls-ring")]
+    pub fn register_rustls_listener(
+        &self,
+        listener: std::net::TcpListener,
+        timeout: Duration,
+        cert_chain: Vec<Certificate>,
+        key: PrivateKey,
+    ) -> io::Result<()> {
+        self.register_rustls_listener_with_client_ca(listener, timeout, cert_chain, key, Vec::new())
+    }
+
+    /// Register a TlsListener to the Server, with TLS from rustls, for mutual TLS. See
+    ///  `register_rustls_listener` and `register_tls_listener_with_client_ca`.
+    ///
+    /// Clients are asked for a certificate, signed by one of the CAs, the certificate is passed as
+    ///  the `ClientIdentity` of the requests on the connection, which matches names with
+    ///  `trust_dns_rustls::tls_server::is_valid_for_name`. Clients without a certificate are
+    ///  accepted, without an identity.
+    ///
+    /// # Arguments
+    /// * `listener` - a bound TCP (needs to be on a different port from standard TCP connections) socket
+    /// * `timeout` - timeout duration of incoming requests, any connection that does not send
+    ///               requests within this time period will be closed.
+    /// * `cert_chain` - certificate used to announce to clients, followed by its intermediates
+    /// * `key` - the private key of the certificate
+    /// * `client_ca` - certificates of the CAs of the clients, none disables client certificates
+    #[cfg(feature = "tls-ring")]
+    pub fn register_rustls_listener_with_client_ca(
+        &self,
+        listener: std::net::TcpListener,
+        timeout: Duration,
+        cert_chain: Vec<Certificate>,
+        key: PrivateKey,
+        client_ca: Vec<Certificate>,
+    ) -> io::Result<()> {
+        let handle = self.io_loop.handle();
+        let handler = self.handler.clone();
+        let addr = listener.local_addr()?;
+        let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, &handle)?;
+        debug!("registered tls: {:?}", listener);
+        let mut context = self.context(addr);
+        context.set_idle_timeout(timeout);
+        context.set_encrypted(true);
+
+        let tls_acceptor = Arc::new(rustls_server::new_acceptor_with_client_ca(
+            cert_chain, key, client_ca,
+        )?);
+
+        // for each incoming request...
+        self.io_loop.handle().spawn(
+            listener
+                .incoming()
+                .for_each(move |(tcp_stream, src_addr)| {
+                    debug!("accepted request from: {}", src_addr);
+                    metrics::count_connection("tls");
+                    let handle = handle.clone();
+                    let handler = handler.clone();
+                    let mut context = context.clone();
+
+                    // take the created stream...
+                    tls_acceptor
+                        .accept_async(tcp_stream)
+                        .map_err(|e| {
+                            io::Error::new(
+                                io::ErrorKind::ConnectionRefused,
+                                format!("tls error: {}", e),
+                            )
+                        })
+                        .and_then(move |tls_stream| {
+                            // the names of the ACLs are matched against the certificate by webpki
+                            context.set_client_identity(
+                                rustls_server::peer_certificate(tls_stream.get_ref().1).map(
+                                    |cert| {
+                                        ClientIdentity::with_verifier(move |name: &Name| {
+                                            rustls_server::is_valid_for_name(
+                                                &cert,
+                                                &name.to_ascii(),
+                                            )
+                                        })
+                                    },
+                                ),
+                            );
+                            let (buf_stream, stream_handle) =
+                                DnsTcpStream::from_stream(tls_stream, src_addr);
+                            let timeout_stream = TimeoutStream::new(buf_stream, timeout, &handle)?;
+                            let handler = handler.clone();
+
+                            // and spawn to the io_loop
+                            handle.spawn(
+                                timeout_stream
+                                    .for_each(move |(buffer, src_addr)| {
+                                        Self::handle_request(
+                                            buffer,
+                                            src_addr,
+                                            "tls",
+                                            context.clone(),
+                                            None,
+                                            ResponseHandle::new(src_addr, stream_handle.clone()),
+                                            handler.clone(),
+                                        )
+                                    })
+                                    .map_err(move |e| {
+                                        debug!(
+                                            "error in TLS request_stream src: {:?} error: {}",
+                                            src_addr, e
+                                        )
+                                    }),
+                            );
+
+                            Ok(())
+                        })
+                })
+                .map_err(|e| debug!("error in inbound tls_stream: {}", e)),
+        );
+
+        Ok(())
+    }
+
+    /// Register a listener for DNS over HTTPS, DoH, to the Server. The listener should already be
+    ///  bound to either an IPv6 or an IPv4 address.
+    ///
+    /// Requests are received over HTTP/2, negotiated with ALPN, with GET and the `dns` variable
+    ///  in base64url, or with POST and the DNS request in the body, at `https::DNS_QUERY_PATH`.
+    ///
+    /// # Arguments
+    /// * `listener` - a bound TCP socket, on a different port from TCP and TLS connections
+    /// * `timeout` - timeout duration of the TLS and HTTP/2 handshakes, and of each request
+    /// * `pkcs12` - certificate used to announce to clients
+    #[cfg(feature = "dns-over-https")]
+    pub fn register_https_listener(
+        &self,
+        listener: std::net::TcpListener,
+        timeout: Duration,
+        pkcs12: ParsedPkcs12,
+    ) -> io::Result<()> {
+        let handle = self.io_loop.handle();
+        let handler = self.handler.clone();
+        let addr = listener.local_addr()?;
+        let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, &handle)?;
+        debug!("registered https: {:?}", listener);
+        let mut context = self.context(addr);
+        context.set_encrypted(true);
+
+        let tls_acceptor = tls_server::new_acceptor_with_alpn(&pkcs12, &[b"h2"])?;
+
+        // for each incoming request...
+        self.io_loop.handle().spawn(
+            listener
+                .incoming()
+                .for_each(move |(tcp_stream, src_addr)| {
+                    debug!("accepted https request from: {}", src_addr);
+                    metrics::count_connection("https");
+                    let inner_handle = handle.clone();
+                    let handler = handler.clone();
+                    let context = context.clone();
+
+                    let handshake = tls_acceptor
+                        .accept_async(tcp_stream)
+                        .map_err(|e| {
+                            io::Error::new(
+                                io::ErrorKind::ConnectionRefused,
+                                format!("tls error: {}", e),
+                            )
+                        })
+                        .and_then(|tls_stream| {
+                            h2::server::handshake(tls_stream).map_err(https::http2_error)
+                        })
+                        .select2(Timeout::new(timeout, &handle)?)
+                        .then(|result| match result {
+                            Ok(Either::A((connection, _))) => Ok(connection),
+                            Ok(Either::B(_)) => Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "https handshake timed out",
+                            )),
+                            Err(Either::A((e, _))) => Err(e),
+                            Err(Either::B((e, _))) => Err(e),
+                        });
+
+                    // and spawn each stream of the connection to the io_loop
+                    handle.spawn(
+                        handshake
+                            .and_then(move |connection| {
+                                connection
+                                    .map_err(https::http2_error)
+                                    .for_each(move |(request, respond)| {
+                                        let request = Self::handle_https_request(
+                                            request,
+                                            respond,
+                                            src_addr,
+                                            context.clone(),
+                                            handler.clone(),
+                                        ).select2(Timeout::new(timeout, &inner_handle)?)
+                                            .map(|_| ())
+                                            .map_err(move |e| match e {
+                                                Either::A((e, _)) | Either::B((e, _)) => debug!(
+                                                    "error in https request src: {:?} error: {}",
+                                                    src_addr, e
+                                                ),
+                                            });
+                                        inner_handle.spawn(request);
+                                        Ok(())
+                                    })
+                            })
+                            .map_err(move |e| {
+                                debug!(
+                                    "error in https connection src: {:?} error: {}",
+                                    src_addr, e
+                                )
+                            }),
+                    );
+
+                    Ok(())
+                })
+                .map_err(|e| debug!("error in inbound https_stream: {}", e)),
+        );
+
+        Ok(())
+    }
+
+    /// Runs the reactor, handling requests until it fails
+    ///
+    /// To handle requests on several threads, each thread runs its own ServerFuture, with clones
+    ///  of a handler which is shared between them, e.g. a `ReloadableCatalog`, and sockets bound
+    ///  to the same addresses with SO_REUSEPORT, across which the kernel balances the clients.
     pub fn listen(&mut self) -> io::Result<()> {
         info!("Server starting up");
         self.io_loop.run(Forever)?;
@@ -234,13 +609,110 @@ impl<T: RequestHandler> ServerFuture<T> {
         &mut self.io_loop
     }
 
-    fn handle_request(
+    // receives the body of the request, and responds with the response of the handler
+    #[cfg(feature = "dns-over-https")]
+    fn handle_https_request(
+        request: http::Request<h2::RecvStream>,
+        respond: h2::server::SendResponse<Bytes>,
+        src_addr: SocketAddr,
+        context: RequestContext,
+        handler: Arc<T>,
+    ) -> Box<Future<Item = (), Error = io::Error>> {
+        let (parts, mut body) = request.into_parts();
+        // the received data is handed back to the flow control of the connection, or the window of
+        //  the connection is exhausted after some requests
+        let mut release_capacity = body.release_capacity().clone();
+
+        Box::new(
+            body.map_err(https::http2_error)
+                .fold(Vec::new(), move |mut buffer, chunk| {
+                    release_capacity
+                        .release_capacity(chunk.len())
+                        .map_err(https::http2_error)?;
+
+                    if buffer.len() + chunk.len() > https::MAX_MESSAGE_LEN {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "https request too large",
+                        ));
+                    }
+                    buffer.extend_from_slice(&chunk);
+                    Ok(buffer)
+                })
+                .and_then(move |body| {
+                    let dns_request = https::dns_request(
+                        &parts.method,
+                        &parts.uri,
+                        parts.headers.get(CONTENT_TYPE),
+                        &body,
+                    );
+                    let buffer = match dns_request {
+                        Ok(buffer) => buffer,
+                        Err(status) => {
+                            debug!("bad https request src: {:?} status: {}", src_addr, status);
+                            return Box::new(future::result(https::send_response(
+                                respond, status, None,
+                            )))
+                                as Box<Future<Item = (), Error = io::Error>>;
+                        }
+                    };
+
+                    let (response_handle, response) = HttpsResponseHandle::new();
+                    if let Err(e) = Self::handle_request(
+                        buffer,
+                        src_addr,
+                        "https",
+                        context,
+                        None,
+                        response_handle,
+                        handler,
+                    ) {
+                        debug!("bad https request src: {:?} error: {}", src_addr, e);
+                        return Box::new(future::result(https::send_response(
+                            respond,
+                            StatusCode::BAD_REQUEST,
+                            None,
+                        )));
+                    }
+
+                    Box::new(response.then(move |response| match response {
+                        Ok(response) => https::send_response(respond, StatusCode::OK, Some(response)),
+                        // the handler did not respond
+                        Err(_) => https::send_response(
+                            respond,
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            None,
+                        ),
+                    }))
+                }),
+        )
+    }
+
+    // the identity of the client from the names of its certificate, over mutual TLS
+    #[cfg(feature = "tls-openssl")]
+    fn client_identity(names: Vec<String>) -> Option<ClientIdentity> {
+        let names: Vec<Name> = names
+            .iter()
+            .filter_map(|name| Name::parse(name, Some(&Name::root())).ok())
+            .collect();
+
+        if names.is_empty() {
+            return None;
+        }
+        debug!("client certificate for: {:?}", names);
+        Some(ClientIdentity::new(names))
+    }
+
+    fn handle_request<R: ResponseHandler + 'static>(
         buffer: Vec<u8>,
         src_addr: SocketAddr,
-        stream_handle: BufStreamHandle<ClientError>,
+        protocol: &'static str,
+        mut context: RequestContext,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        response_handle: R,
         handler: Arc<T>,
     ) -> io::Result<()> {
-        let response_handle = ResponseHandle::new(src_addr, stream_handle);
+        let received = Instant::now();
 
         // TODO: rather than decoding the message here, this RequestStream should instead
         //       forward the request to another sender such that we could pull serialization off
@@ -271,7 +743,53 @@ impl<T: RequestHandler> ServerFuture<T> {
                 .unwrap_or_else(|| "empty_queries".to_string()),
         );
 
-        handler.handle_request(&request, response_handle)
+        metrics::count_request(
+            protocol,
+            request.message.op_code(),
+            request.message.queries().first().map(|q| q.query_type()),
+        );
+
+        // the cookie of the request is checked once, clients with a valid one are not limited
+        context.check_cookie(&request.message, src_addr.ip());
+        match rate_limiter {
+            Some(rate_limiter) => {
+                let response_handle = RateLimitedResponseHandle::new(
+                    response_handle,
+                    src_addr.ip(),
+                    context.cookie().map_or(false, |cookie| cookie.is_valid()),
+                    rate_limiter,
+                );
+                let response_handle =
+                    MeteredResponseHandle::new(response_handle, protocol, received);
+                Self::dispatch_request(&request, &context, response_handle, &handler)
+            }
+            None => {
+                let response_handle =
+                    MeteredResponseHandle::new(response_handle, protocol, received);
+                Self::dispatch_request(&request, &context, response_handle, &handler)
+            }
+        }
+    }
+
+    fn dispatch_request<R: ResponseHandler + 'static>(
+        request: &Request,
+        context: &RequestContext,
+        response_handle: R,
+        handler: &Arc<T>,
+    ) -> io::Result<()> {
+        // responses over UDP must fit in the payload size of the client, the handler fits them
+        //  before they are signed, the others are fit here
+        match context.max_udp_payload() {
+            Some(max_udp_payload) => {
+                let response_handle = TruncatingResponseHandle::new(
+                    response_handle,
+                    request.message.max_payload(),
+                    max_udp_payload,
+                );
+                handler.handle_request_with_context(request, context, response_handle)
+            }
+            None => handler.handle_request_with_context(request, context, response_handle),
+        }
     }
 }
 
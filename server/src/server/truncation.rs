@@ -0,0 +1,403 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Truncation of UDP responses to the payload size of the client
+
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use trust_dns::op::{Edns, Header, ResponseCode};
+use trust_dns::rr::dnssec::TSigner;
+use trust_dns::rr::rdata::opt::{padding_len, EdnsOption, RESPONSE_PADDING_BLOCK_LEN};
+use trust_dns::rr::Record;
+use trust_dns::serialize::binary::{BinEncodable, BinEncoder};
+use trust_dns_proto::error::ProtoResult;
+use trust_dns_proto::op::EncodableMessage;
+
+use server::ResponseHandler;
+
+/// The largest UDP payload of a client without EDNS
+///
+/// [RFC 1035](https://tools.ietf.org/html/rfc1035#section-4.2.1), DOMAIN NAMES - IMPLEMENTATION AND SPECIFICATION, November 1987
+///
+/// ```text
+/// Messages carried by UDP are restricted to 512 bytes (not counting the IP
+/// or UDP headers).  Longer messages are truncated and the TC bit is set in
+/// the header.
+/// ```
+pub const MIN_PAYLOAD_LEN: u16 = 512;
+
+/// The default for the largest UDP payload sent by the server, small enough to avoid IP
+///  fragmentation on common links
+pub const DEFAULT_MAX_UDP_PAYLOAD: u16 = 1232;
+
+/// The sections of a response which are sent, see `TruncatingResponseHandle`
+#[derive(Clone, Copy, PartialEq)]
+enum Sections {
+    All,
+    WithoutAdditionals,
+    QueriesOnly,
+}
+
+/// A response with some of its sections left out, and the EDNS of the server
+pub(crate) struct PartialResponse<'m, M: EncodableMessage + 'm> {
+    header: Header,
+    response: &'m M,
+    edns: Option<Edns>,
+    sections: Sections,
+    // the TSIG of the partial response, in place of the signatures of the response
+    tsig: Option<Vec<Record>>,
+}
+
+impl<'m, M: EncodableMessage> PartialResponse<'m, M> {
+    fn new(response: &'m M, edns: Option<Edns>, sections: Sections) -> Self {
+        let mut header: Header = Clone::clone(response.header());
+        let truncated = match sections {
+            Sections::All => false,
+            Sections::WithoutAdditionals => response.has_in_domain_glue(),
+            Sections::QueriesOnly => true,
+        };
+        if truncated {
+            header.set_truncated(true);
+        }
+
+        PartialResponse {
+            header,
+            response,
+            edns,
+            sections,
+            tsig: None,
+        }
+    }
+
+    /// Signs the partial response with the key of the request, the MAC covers only the sections
+    ///  which are sent
+    fn sign_tsig(&mut self, tsigner: &TSigner, request_mac: &[u8]) -> io::Result<()> {
+        // RFC 8945, a truncated signed response has no other records, and NOERROR
+        if self.sections == Sections::QueriesOnly {
+            self.header.set_response_code(ResponseCode::NoError);
+        }
+
+        self.tsig = Some(vec![]);
+        let tsig = tsigner
+            .sign_message(self, Some(request_mac), now())
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("error signing message: {}", e),
+                )
+            })?;
+        self.tsig = Some(vec![tsig]);
+        Ok(())
+    }
+
+    /// Pads the response to a multiple of `RESPONSE_PADDING_BLOCK_LEN`, the length of the TSIG
+    ///  with which it is signed is part of the padded length
+    fn pad(&mut self, tsig: Option<(&TSigner, &[u8])>) -> io::Result<()> {
+        // the length of the signed response with an empty padding option
+        match self.edns {
+            Some(ref mut edns) => edns.set_option(EdnsOption::Padding(0)),
+            None => return Ok(()),
+        }
+        if let Some((tsigner, request_mac)) = tsig {
+            self.sign_tsig(tsigner, request_mac)?;
+        }
+
+        let len = encoded_len(self)?;
+        if let Some(ref mut edns) = self.edns {
+            edns.set_option(EdnsOption::Padding(padding_len(
+                len,
+                RESPONSE_PADDING_BLOCK_LEN,
+            )));
+        }
+        Ok(())
+    }
+
+    /// The header and queries of the response, with TC set, so the client retries over TCP
+    pub(crate) fn truncated(response: &'m M) -> Self {
+        Self::new(response, response.edns().cloned(), Sections::QueriesOnly)
+    }
+}
+
+impl<'m, M: EncodableMessage> EncodableMessage for PartialResponse<'m, M> {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn queries_len(&self) -> usize {
+        self.response.queries_len()
+    }
+
+    fn emit_queries(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        self.response.emit_queries(encoder)
+    }
+
+    fn answers_len(&self) -> usize {
+        match self.sections {
+            Sections::QueriesOnly => 0,
+            _ => self.response.answers_len(),
+        }
+    }
+
+    fn emit_answers(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        match self.sections {
+            Sections::QueriesOnly => Ok(()),
+            _ => self.response.emit_answers(encoder),
+        }
+    }
+
+    fn name_servers_len(&self) -> usize {
+        match self.sections {
+            Sections::QueriesOnly => 0,
+            _ => self.response.name_servers_len(),
+        }
+    }
+
+    fn emit_name_servers(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        match self.sections {
+            Sections::QueriesOnly => Ok(()),
+            _ => self.response.emit_name_servers(encoder),
+        }
+    }
+
+    fn additionals_len(&self) -> usize {
+        match self.sections {
+            Sections::All => self.response.additionals_len(),
+            _ => 0,
+        }
+    }
+
+    fn emit_additionals(&self, encoder: &mut BinEncoder) -> ProtoResult<()> {
+        match self.sections {
+            Sections::All => self.response.emit_additionals(encoder),
+            _ => Ok(()),
+        }
+    }
+
+    fn edns(&self) -> Option<&Edns> {
+        self.edns.as_ref()
+    }
+
+    fn has_in_domain_glue(&self) -> bool {
+        self.sections == Sections::All && self.response.has_in_domain_glue()
+    }
+
+    fn sig0(&self) -> &[Record] {
+        match (&self.tsig, self.sections) {
+            (&Some(ref tsig), _) => tsig,
+            (&None, Sections::QueriesOnly) => &[],
+            (&None, _) => self.response.sig0(),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn encoded_len<M: EncodableMessage>(response: &M) -> io::Result<usize> {
+    let mut buffer = Vec::with_capacity(512);
+    {
+        let mut encoder: BinEncoder = BinEncoder::new(&mut buffer);
+        response.emit(&mut encoder).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("error encoding message: {}", e),
+            )
+        })?;
+    }
+
+    Ok(buffer.len())
+}
+
+/// Sends the response, fit into the payload size of the client, and then signed with the TSIG key
+///  of the request, if there is one
+///
+/// [RFC 8945](https://tools.ietf.org/html/rfc8945#section-5.3), Secret Key Transaction Authentication for DNS (TSIG), November 2020
+///
+/// ```text
+/// If addition of the TSIG record will cause the message to be
+/// truncated, the server MUST alter the response so that a TSIG can be
+/// included.  This response contains only the question and a TSIG
+/// record, has the TC bit set, and has an RCODE of 0 (NOERROR).  At
+/// this point, the client SHOULD retry the request using TCP (as per
+/// Section 4.2.2 of [RFC1035]).
+/// ```
+///
+/// The sections are left out as by `TruncatingResponseHandle`, the length of the TSIG is part of
+///  the payload, and the TSIG covers the sections which are sent. Padded responses are padded
+///  before they are signed, to a multiple of `RESPONSE_PADDING_BLOCK_LEN` with the TSIG, see
+///  RFC 8467.
+///
+/// # Arguments
+///
+/// * `response_handle` - sends the response
+/// * `response` - the response, with the EDNS of the server
+/// * `max_payload` - the largest response which is sent to the client, none over TCP
+/// * `tsig` - the key which signed the request, and the MAC of the request
+/// * `padding` - true if the response is padded, it is sent over an encrypted connection to a
+///               client which asked for padding
+pub(crate) fn send_fitted<R: ResponseHandler, M: EncodableMessage>(
+    response_handle: R,
+    response: &M,
+    max_payload: Option<u16>,
+    tsig: Option<(&TSigner, &[u8])>,
+    padding: bool,
+) -> io::Result<()> {
+    let edns = response.edns().cloned();
+    let candidates = match max_payload {
+        Some(_) => vec![
+            Sections::All,
+            Sections::WithoutAdditionals,
+            Sections::QueriesOnly,
+        ],
+        None => vec![Sections::All],
+    };
+
+    let last = candidates.len() - 1;
+    for (i, sections) in candidates.into_iter().enumerate() {
+        let mut partial = PartialResponse::new(response, edns.clone(), sections);
+        if padding {
+            partial.pad(tsig)?;
+        }
+        if let Some((tsigner, request_mac)) = tsig {
+            partial.sign_tsig(tsigner, request_mac)?;
+        }
+
+        let fits = match max_payload {
+            Some(max_payload) => encoded_len(&partial)? <= max_payload as usize,
+            None => true,
+        };
+        if fits || i == last {
+            if sections != Sections::All {
+                debug!(
+                    "response: {} sent without {}, max payload: {:?}",
+                    response.header().id(),
+                    if sections == Sections::QueriesOnly {
+                        "records"
+                    } else {
+                        "additionals"
+                    },
+                    max_payload
+                );
+            }
+            return response_handle.send(partial);
+        }
+    }
+
+    unreachable!("the last candidate is always sent")
+}
+
+/// Fits UDP responses into the payload size of the client
+///
+/// [RFC 2181](https://tools.ietf.org/html/rfc2181#section-9), Clarifications to the DNS Specification, July 1997
+///
+/// ```text
+/// Where TC is set, the partial RRSet that would not completely fit may be
+/// left in the response.  When a DNS client receives a reply with TC set,
+/// it should ignore that response, and query again, using a mechanism, such
+/// as a TCP connection, that will permit larger replies.
+///
+/// ...
+///
+/// The TC bit should not be set merely because some extra information
+/// could have been included, but there was insufficient room.  This
+/// includes the results of additional section processing.
+/// ```
+///
+/// [RFC 9471](https://tools.ietf.org/html/rfc9471#section-3.1), DNS Glue Requirements in Referral Responses, September 2023
+///
+/// ```text
+/// If message size constraints prevent the inclusion of all glue records
+/// for in-domain name servers, the server MUST set the TC (Truncated) flag
+/// to inform the client that the response is incomplete and that the
+/// client SHOULD use another transport to retrieve the full response.
+/// ```
+///
+/// Responses which are too large are sent without their additional section, if the rest fits,
+///  with TC set if it held the in-domain glue of a referral, see
+///  `EncodableMessage::has_in_domain_glue`, otherwise only the header and the queries are sent,
+///  with TC set. The EDNS of the response advertises the largest payload of the server.
+///
+/// Signed responses which fit are sent as they are, as their signature covers all the sections
+///  and the EDNS, the `Catalog` fits them before they are signed, see `RequestContext`.
+pub struct TruncatingResponseHandle<R: ResponseHandler> {
+    response_handle: R,
+    max_payload: u16,
+    max_udp_payload: u16,
+}
+
+impl<R: ResponseHandler> TruncatingResponseHandle<R> {
+    /// Returns a new `TruncatingResponseHandle` wrapping the `ResponseHandler`
+    ///
+    /// # Arguments
+    ///
+    /// * `response_handle` - sends the response
+    /// * `request_max_payload` - the payload size of the client, from the EDNS of the request,
+    ///                           see `MessageRequest::max_payload`
+    /// * `max_udp_payload` - the largest payload the server sends, at least 512 bytes
+    pub fn new(response_handle: R, request_max_payload: u16, max_udp_payload: u16) -> Self {
+        let max_udp_payload = max_udp_payload.max(MIN_PAYLOAD_LEN);
+        TruncatingResponseHandle {
+            response_handle,
+            max_payload: request_max_payload
+                .max(MIN_PAYLOAD_LEN)
+                .min(max_udp_payload),
+            max_udp_payload,
+        }
+    }
+
+    /// The largest payload which is sent to the client
+    pub fn max_payload(&self) -> u16 {
+        self.max_payload
+    }
+}
+
+impl<R: ResponseHandler> ResponseHandler for TruncatingResponseHandle<R> {
+    /// Sends the response, without the sections which do not fit in the payload size
+    fn send<M: EncodableMessage>(self, response: M) -> io::Result<()> {
+        let max_payload = self.max_payload as usize;
+        if !response.sig0().is_empty() && encoded_len(&response)? <= max_payload {
+            return self.response_handle.send(response);
+        }
+
+        let edns = response.edns().map(|edns| {
+            let mut edns = edns.clone();
+            edns.set_max_payload(self.max_udp_payload);
+            edns
+        });
+
+        let full = PartialResponse::new(&response, edns.clone(), Sections::All);
+        if encoded_len(&full)? <= max_payload {
+            return self.response_handle.send(full);
+        }
+
+        if response.sig0().is_empty() {
+            let without_additionals =
+                PartialResponse::new(&response, edns.clone(), Sections::WithoutAdditionals);
+            if encoded_len(&without_additionals)? <= max_payload {
+                debug!(
+                    "response: {} sent without additionals, max payload: {}",
+                    response.header().id(),
+                    max_payload
+                );
+                return self.response_handle.send(without_additionals);
+            }
+        }
+
+        debug!(
+            "response: {} truncated, max payload: {}",
+            response.header().id(),
+            max_payload
+        );
+        self.response_handle
+            .send(PartialResponse::new(&response, edns, Sections::QueriesOnly))
+    }
+}
@@ -16,6 +16,7 @@
 extern crate log;
 extern crate trust_dns;
 extern crate trust_dns_proto;
+extern crate trust_dns_resolver;
 extern crate trust_dns_server;
 
 use std::env;
@@ -23,8 +24,11 @@ use std::path::{Path, PathBuf};
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
-use trust_dns_server::authority::ZoneType;
+use trust_dns_resolver::config::Protocol;
+use trust_dns::rr::Name;
+use trust_dns_server::authority::{Acl, AclElement, ZoneType, DEFAULT_IXFR_DEPTH};
 use trust_dns_server::config::*;
+use trust_dns_server::server::ClientIdentity;
 
 #[test]
 fn test_read_config() {
@@ -248,4 +252,593 @@ tls_listen_port = 8853
         config.get_tls_cert().unwrap().get_path(),
         Path::new("path/to/some.pkcs12")
     );
+    assert_eq!(config.get_tls_cert().unwrap().get_private_key(), None);
+    assert_eq!(config.get_tls_cert().unwrap().get_client_ca(), None);
+
+    let config: Config = "
+tls_cert = { path = \"path/to/chain.pem\", private_key = \"path/to/key.pem\" }
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        config.get_tls_cert().unwrap().get_private_key(),
+        Some(Path::new("path/to/key.pem"))
+    );
+
+    let config: Config = "
+tls_cert = { path = \"path/to/some.pkcs12\", client_ca = \"path/to/clients.pem\" }
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        config.get_tls_cert().unwrap().get_client_ca(),
+        Some(Path::new("path/to/clients.pem"))
+    );
+}
+
+#[test]
+fn test_parse_masters() {
+    use std::net::{IpAddr, SocketAddr};
+
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Slave\"
+file = \"example.com.zone\"
+masters = [\"10.0.0.1\", \"[::1]:5353\"]
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(config.get_zones()[0].get_zone_type(), ZoneType::Slave);
+    assert_eq!(
+        config.get_zones()[0].get_masters().unwrap(),
+        vec![
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 53),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)), 5353),
+        ]
+    );
+
+    // bad addresses are reported
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Slave\"
+file = \"example.com.zone\"
+masters = [\"not.an.address\"]
+  "
+        .parse()
+        .unwrap();
+
+    assert!(config.get_zones()[0].get_masters().is_err());
+}
+
+#[test]
+fn test_parse_also_notify() {
+    use std::net::{IpAddr, SocketAddr};
+
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+also_notify = [\"10.0.0.2\", \"10.0.0.3:5353\"]
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        config.get_zones()[0].get_also_notify().unwrap(),
+        vec![
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 53),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), 5353),
+        ]
+    );
+    assert!(config.get_zones()[0].get_masters().unwrap().is_empty());
+}
+
+#[test]
+fn test_parse_tsig_keys() {
+    use trust_dns::rr::Name;
+    use trust_dns::rr::dnssec::TsigAlgorithm;
+
+    let config: Config = "
+[[tsig_keys]]
+name = \"transfer.example.com\"
+algorithm = \"hmac-sha256\"
+secret = \"c2hhcmVkIHNlY3JldCBmb3Igem9uZSB0cmFuc2ZlcnM=\"
+
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+tsig_keys = [\"transfer.example.com\"]
+  "
+        .parse()
+        .unwrap();
+
+    let tsig_key = &config.get_tsig_keys()[0];
+    assert_eq!(
+        tsig_key.name().unwrap(),
+        Name::from_ascii("transfer.example.com.").unwrap()
+    );
+    assert_eq!(tsig_key.algorithm().unwrap(), TsigAlgorithm::HmacSha256);
+    assert_eq!(
+        tsig_key.secret().unwrap(),
+        b"shared secret for zone transfers".to_vec()
+    );
+    assert_eq!(
+        config.get_zones()[0].get_tsig_keys(),
+        &["transfer.example.com".to_string()]
+    );
+}
+
+#[test]
+fn test_parse_nsec3() {
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+enable_dnssec = true
+
+[zones.nsec3]
+salt = \"aabbccdd\"
+iterations = 12
+opt_out = true
+  "
+        .parse()
+        .unwrap();
+
+    let nsec3 = config.get_zones()[0].get_nsec3().unwrap();
+    assert_eq!(nsec3.salt().unwrap(), vec![0xAA, 0xBB, 0xCC, 0xDD]);
+    assert_eq!(nsec3.iterations(), 12);
+    assert!(nsec3.opt_out());
+
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+enable_dnssec = true
+
+[zones.nsec3]
+  "
+        .parse()
+        .unwrap();
+
+    let nsec3 = config.get_zones()[0].get_nsec3().unwrap();
+    assert!(nsec3.salt().unwrap().is_empty());
+    assert_eq!(nsec3.iterations(), 0);
+    assert!(!nsec3.opt_out());
+}
+
+#[test]
+fn test_parse_ixfr_depth() {
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+ixfr_depth = 10
+
+[[zones]]
+zone = \"example.net\"
+zone_type = \"Master\"
+file = \"example.net.zone\"
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(config.get_zones()[0].get_ixfr_depth(), 10);
+    assert_eq!(config.get_zones()[1].get_ixfr_depth(), DEFAULT_IXFR_DEPTH);
+}
+
+#[test]
+fn test_parse_forwarders() {
+    let config: Config = "
+[[zones]]
+zone = \".\"
+zone_type = \"Forward\"
+trust_authentic_data = true
+
+[[zones.forwarders]]
+addr = \"8.8.8.8\"
+
+[[zones.forwarders]]
+addr = \"[2001:4860:4860::8888]:5353\"
+protocol = \"tcp\"
+  "
+        .parse()
+        .unwrap();
+
+    let zone = &config.get_zones()[0];
+    assert_eq!(zone.get_zone_type(), ZoneType::Forward);
+    assert!(zone.get_file().is_none());
+    assert!(zone.is_authentic_data_trusted());
+
+    let forwarders = zone.get_forwarders();
+    assert_eq!(forwarders.len(), 2);
+
+    let udp = forwarders[0].get_name_servers().unwrap();
+    assert_eq!(udp.len(), 2);
+    assert_eq!(udp[0].socket_addr, "8.8.8.8:53".parse().unwrap());
+    assert_eq!(udp[0].protocol, Protocol::Udp);
+    assert_eq!(udp[1].protocol, Protocol::Tcp);
+
+    let tcp = forwarders[1].get_name_servers().unwrap();
+    assert_eq!(tcp.len(), 1);
+    assert_eq!(
+        tcp[0].socket_addr,
+        "[2001:4860:4860::8888]:5353".parse().unwrap()
+    );
+    assert_eq!(tcp[0].protocol, Protocol::Tcp);
+
+    assert!(ForwarderConfig::new("8.8.8.8".to_string(), Some("quic".to_string()), None)
+        .get_name_servers()
+        .is_err());
+}
+
+#[test]
+fn test_parse_rate_limit() {
+    let config: Config = "
+[rate_limit]
+responses_per_second = 10
+slip = 0
+ipv4_prefix_len = 32
+max_networks = 1024
+  "
+        .parse()
+        .unwrap();
+
+    let rate_limit = config.get_rate_limit().unwrap();
+    assert_eq!(rate_limit.get_responses_per_second(), 10);
+    assert_eq!(rate_limit.get_window(), Duration::from_secs(15));
+    assert_eq!(rate_limit.get_slip(), 0);
+    assert_eq!(rate_limit.get_ipv4_prefix_len(), 32);
+    assert_eq!(rate_limit.get_ipv6_prefix_len(), 56);
+    assert_eq!(rate_limit.get_max_networks(), 1024);
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert!(config.get_rate_limit().is_none());
+}
+
+#[test]
+fn test_parse_cookies() {
+    let config: Config = "
+[cookies]
+secret = \"000102030405060708090a0b0c0d0e0f\"
+  "
+        .parse()
+        .unwrap();
+
+    let cookies = config.get_cookies().unwrap();
+    assert!(cookies.is_enabled());
+    assert_eq!(
+        cookies.get_secret().unwrap(),
+        Some([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15])
+    );
+
+    let config: Config = "
+[cookies]
+enabled = false
+secret = \"0001\"
+  "
+        .parse()
+        .unwrap();
+
+    let cookies = config.get_cookies().unwrap();
+    assert!(!cookies.is_enabled());
+    assert!(cookies.get_secret().is_err());
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert!(config.get_cookies().is_none());
+}
+
+#[test]
+fn test_parse_identity() {
+    let config: Config = "
+[identity]
+nsid = \"ns1.example.com\"
+hostname = \"ns1\"
+  "
+        .parse()
+        .unwrap();
+
+    let identity = config.get_identity().unwrap();
+    assert_eq!(identity.get_nsid(), Some("ns1.example.com"));
+    assert_eq!(identity.get_hostname(), Some("ns1"));
+    assert_eq!(identity.get_version(), None);
+    assert_eq!(identity.get_id(), None);
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert!(config.get_identity().is_none());
+}
+
+#[test]
+fn test_parse_acls() {
+    let config: Config = "
+transfer_acl = [\"none\"]
+update_acl = [\"key update.example.com\"]
+
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+transfer_acl = [\"!10.0.0.5\", \"10.0.0.0/8\", \"2001:db8::/32\", \"key transfer.example.com\"]
+update_acl = [\"cert updater.example.com\"]
+update_certificates = [\"updater.example.com\"]
+  "
+        .parse()
+        .unwrap();
+
+    let defaults = config.get_default_acls().unwrap();
+    let acls = config.get_zones()[0].get_acls(&defaults).unwrap();
+
+    // queries are allowed by default
+    assert_eq!(acls.query(), &Acl::any());
+    assert!(!defaults.transfer().allows("10.1.2.3".parse().unwrap(), None, None));
+
+    let transfer = acls.transfer();
+    assert!(transfer.allows("10.1.2.3".parse().unwrap(), None, None));
+    assert!(!transfer.allows("10.0.0.5".parse().unwrap(), None, None));
+    assert!(transfer.allows("2001:db8::53".parse().unwrap(), None, None));
+    assert!(!transfer.allows("192.0.2.1".parse().unwrap(), None, None));
+
+    let key = Name::parse("Transfer.Example.Com.", None).unwrap();
+    assert!(transfer.allows("192.0.2.1".parse().unwrap(), Some(&key), None));
+
+    // clients are identified by the names of their TLS certificates
+    let updater = Name::parse("updater.example.com.", None).unwrap();
+    let identity = ClientIdentity::new(vec![updater.clone()]);
+    let update = acls.update();
+    assert!(update.allows("192.0.2.1".parse().unwrap(), None, Some(&identity)));
+    assert!(!update.allows("192.0.2.1".parse().unwrap(), Some(&updater), None));
+
+    // or their certificate is checked for the names of the ACLs, e.g. for wildcards
+    let wildcard = ClientIdentity::with_verifier(|name: &Name| name.num_labels() == 3);
+    assert!(update.allows("192.0.2.1".parse().unwrap(), None, Some(&wildcard)));
+    let other = ClientIdentity::with_verifier(|_: &Name| false);
+    assert!(!update.allows("192.0.2.1".parse().unwrap(), None, Some(&other)));
+    assert_eq!(
+        config.get_zones()[0].get_update_certificates().unwrap(),
+        vec![updater]
+    );
+
+    let config: Config = "query_acl = [\"10.0.0.0/33\"]".parse().unwrap();
+    assert!(config.get_default_acls().is_err());
+
+    // transfers are refused unless they are configured
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    let defaults = config.get_default_acls().unwrap();
+    assert_eq!(defaults.query(), &Acl::any());
+    assert_eq!(defaults.transfer(), &Acl::none());
+}
+
+#[test]
+fn test_parse_recursion_acl() {
+    let config: Config = "recursion_acl = [\"10.0.0.0/8\"]".parse().unwrap();
+    let recursion = config.get_recursion_acl().unwrap();
+    assert!(recursion.allows("10.1.2.3".parse().unwrap(), None, None));
+    assert!(!recursion.allows("127.0.0.1".parse().unwrap(), None, None));
+
+    // recursion is only allowed from localhost by default
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert_eq!(config.get_recursion_acl().unwrap(), Acl::localhost());
+    let recursion = config.get_recursion_acl().unwrap();
+    assert!(recursion.allows("127.0.0.1".parse().unwrap(), None, None));
+    assert!(recursion.allows("::1".parse().unwrap(), None, None));
+    assert!(!recursion.allows("192.0.2.1".parse().unwrap(), None, None));
+}
+
+#[test]
+fn test_parse_subnets() {
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+
+[[zones.subnets]]
+networks = [\"10.0.0.0/8\", \"2001:db8::/32\", \"192.0.2.1\"]
+file = \"example.com.internal.zone\"
+  "
+        .parse()
+        .unwrap();
+
+    let subnets = config.get_zones()[0].get_subnets();
+    assert_eq!(subnets.len(), 1);
+    assert_eq!(
+        subnets[0].get_file(),
+        PathBuf::from("example.com.internal.zone")
+    );
+    assert_eq!(
+        subnets[0].get_networks().unwrap(),
+        vec![
+            ("10.0.0.0".parse().unwrap(), 8),
+            ("2001:db8::".parse().unwrap(), 32),
+            ("192.0.2.1".parse().unwrap(), 32),
+        ]
+    );
+
+    assert!(
+        SubnetConfig::new(vec!["10.0.0.0/33".to_string()], "internal.zone".to_string())
+            .get_networks()
+            .is_err()
+    );
+    assert!(
+        SubnetConfig::new(vec!["any".to_string()], "internal.zone".to_string())
+            .get_networks()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_views() {
+    let config: Config = "
+[[zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"example.com.zone\"
+
+[[views]]
+name = \"internal\"
+match_clients = [\"10.0.0.0/8\", \"key internal.example.com\"]
+match_destinations = [\"192.0.2.53\"]
+
+[[views.zones]]
+zone = \"example.com\"
+zone_type = \"Master\"
+file = \"internal/example.com.zone\"
+
+[[views]]
+name = \"everyone\"
+zones = []
+  "
+        .parse()
+        .unwrap();
+
+    assert_eq!(config.get_zones().len(), 1);
+
+    let views = config.get_views();
+    assert_eq!(views.len(), 2);
+    assert_eq!(views[0].get_name(), "internal");
+    assert_eq!(
+        views[0].get_match_clients().unwrap(),
+        Acl::new(vec![
+            (
+                false,
+                AclElement::Network {
+                    addr: "10.0.0.0".parse().unwrap(),
+                    prefix_len: 8,
+                },
+            ),
+            (
+                false,
+                AclElement::Key(Name::parse("internal.example.com.", None).unwrap()),
+            ),
+        ])
+    );
+    assert_eq!(
+        views[0].get_match_destinations().unwrap(),
+        Acl::new(vec![
+            (
+                false,
+                AclElement::Network {
+                    addr: "192.0.2.53".parse().unwrap(),
+                    prefix_len: 32,
+                },
+            ),
+        ])
+    );
+    assert_eq!(views[0].get_zones().len(), 1);
+    assert_eq!(
+        views[0].get_zones()[0].get_file(),
+        Some(PathBuf::from("internal/example.com.zone"))
+    );
+
+    // all clients and listeners match by default
+    assert_eq!(views[1].get_match_clients().unwrap(), Acl::any());
+    assert_eq!(views[1].get_match_destinations().unwrap(), Acl::any());
+    assert!(views[1].get_zones().is_empty());
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert!(config.get_views().is_empty());
+}
+
+#[test]
+fn test_parse_workers() {
+    let config: Config = "workers = 8".parse().unwrap();
+    assert_eq!(config.get_workers(), 8);
+
+    // there is always at least the main thread
+    let config: Config = "workers = 0".parse().unwrap();
+    assert_eq!(config.get_workers(), 1);
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert_eq!(config.get_workers(), 1);
+}
+
+#[test]
+fn test_parse_max_udp_payload() {
+    let config: Config = "max_udp_payload = 4096".parse().unwrap();
+    assert_eq!(config.get_max_udp_payload(), 4096);
+
+    // smaller than any client may advertise
+    let config: Config = "max_udp_payload = 100".parse().unwrap();
+    assert_eq!(config.get_max_udp_payload(), 512);
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert_eq!(config.get_max_udp_payload(), 1232);
+}
+
+#[test]
+fn test_parse_https_listen_port() {
+    let config: Config = "https_listen_port = 443".parse().unwrap();
+    assert_eq!(config.get_https_listen_port(), Some(443));
+
+    // DNS over HTTPS is disabled by default
+    let config: Config = "tls_listen_port = 853".parse().unwrap();
+    assert_eq!(config.get_https_listen_port(), None);
+}
+
+#[test]
+fn test_parse_metrics_listen_addr() {
+    let config: Config = "metrics_listen_addr = \"127.0.0.1\"".parse().unwrap();
+    assert_eq!(
+        config.get_metrics_listen_addr().unwrap(),
+        Some("127.0.0.1:9153".parse().unwrap())
+    );
+
+    let config: Config = "metrics_listen_addr = \"[::1]:8053\"".parse().unwrap();
+    assert_eq!(
+        config.get_metrics_listen_addr().unwrap(),
+        Some("[::1]:8053".parse().unwrap())
+    );
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert_eq!(config.get_metrics_listen_addr().unwrap(), None);
+}
+
+#[test]
+fn test_parse_control() {
+    let config: Config = "
+[[tsig_keys]]
+name = \"control.example.com\"
+algorithm = \"hmac-sha256\"
+secret = \"c2hhcmVkIHNlY3JldCBmb3IgdGhlIGNvbnRyb2wgY2hhbm5lbA==\"
+
+[control]
+unix_socket = \"/var/run/named/control.sock\"
+listen_addr = \"127.0.0.1\"
+tsig_key = \"control.example.com\"
+"
+        .parse()
+        .unwrap();
+
+    let control = config.get_control().unwrap();
+    assert_eq!(
+        control.get_unix_socket(),
+        Some(Path::new("/var/run/named/control.sock"))
+    );
+    assert_eq!(
+        control.get_listen_addr().unwrap(),
+        Some("127.0.0.1:953".parse().unwrap())
+    );
+
+    let key = control.get_tsig_key(config.get_tsig_keys()).unwrap().unwrap();
+    assert_eq!(key.signer_name(), &Name::parse("control.example.com.", None).unwrap());
+
+    // the key must be one of the tsig_keys
+    assert!(control.get_tsig_key(&[]).is_err());
+
+    let config: Config = "[control]\nunix_socket = \"control.sock\"".parse().unwrap();
+    let control = config.get_control().unwrap();
+    assert_eq!(control.get_listen_addr().unwrap(), None);
+    assert!(control.get_tsig_key(&[]).unwrap().is_none());
+
+    let config: Config = "listen_port = 2053".parse().unwrap();
+    assert!(config.get_control().is_none());
 }
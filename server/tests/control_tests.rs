@@ -0,0 +1,94 @@
+extern crate trust_dns;
+extern crate trust_dns_server;
+
+use std::str::FromStr;
+
+use trust_dns::rr::Name;
+use trust_dns::rr::dnssec::{TSigner, TsigAlgorithm, DEFAULT_FUDGE};
+use trust_dns_server::control::{self, sign_command, sign_response, verify_command,
+                                verify_response, Command};
+
+fn control_key(secret: &[u8]) -> TSigner {
+    TSigner::new(
+        secret.to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::parse("control.example.com.", None).unwrap(),
+        DEFAULT_FUDGE,
+    )
+}
+
+#[test]
+fn test_parse_command() {
+    let zone = Name::parse("example.com.", None).unwrap();
+
+    assert_eq!(Command::from_str("reload").unwrap(), Command::Reload(None));
+    assert_eq!(
+        Command::from_str("reload example.com").unwrap(),
+        Command::Reload(Some(zone.clone()))
+    );
+    assert_eq!(
+        Command::from_str(" freeze  example.com.\n").unwrap(),
+        Command::Freeze(zone.clone())
+    );
+    assert_eq!(
+        Command::from_str("retransfer example.com").unwrap(),
+        Command::Retransfer(zone.clone())
+    );
+    assert_eq!(Command::from_str("status").unwrap(), Command::Status);
+
+    assert!(Command::from_str("").is_err());
+    assert!(Command::from_str("thaw").is_err());
+    assert!(Command::from_str("flush example.com").is_err());
+    assert!(Command::from_str("sync example.com extra").is_err());
+    assert!(Command::from_str("halt").is_err());
+
+    // commands are sent as their text
+    let command = Command::Sync(zone);
+    assert_eq!(Command::from_str(&command.to_string()).unwrap(), command);
+}
+
+// the MAC requires one of the crypto libraries
+#[cfg(feature = "dnssec")]
+#[test]
+fn test_verify_command() {
+    let key = control_key(b"shared secret for the control channel");
+    let command = Command::Thaw(Name::parse("example.com.", None).unwrap());
+
+    let challenge = control::challenge();
+    let line = sign_command(&command, &key, &challenge).unwrap();
+    assert_eq!(verify_command(&line, &key, &challenge).unwrap(), command);
+
+    // the command can not be replayed on another connection, with another challenge
+    assert_ne!(challenge, control::challenge());
+    assert!(verify_command(&line, &key, &control::challenge()).is_err());
+
+    // a different key, or a changed command, is not authorized
+    assert!(verify_command(&line, &control_key(b"another secret"), &challenge).is_err());
+    let tampered = line.replace("thaw", "freeze");
+    assert!(verify_command(&tampered, &key, &challenge).is_err());
+
+    // unsigned commands are not authorized over TCP
+    assert!(verify_command("thaw example.com.", &key, &challenge).is_err());
+}
+
+#[cfg(feature = "dnssec")]
+#[test]
+fn test_verify_response() {
+    let key = control_key(b"shared secret for the control channel");
+    let command = Command::Status;
+    let line = sign_command(&command, &key, &control::challenge()).unwrap();
+
+    let response = "ok\nzones: 1\n";
+    let signed = sign_response(response, &key, &line).unwrap();
+    assert_eq!(verify_response(&signed, &key, &line).unwrap(), response);
+
+    // the response to another request, or from another key, is not accepted
+    let other_line = sign_command(&command, &key, &control::challenge()).unwrap();
+    assert!(verify_response(&signed, &key, &other_line).is_err());
+    assert!(verify_response(&signed, &control_key(b"another secret"), &line).is_err());
+
+    // nor is a changed or an unsigned response
+    let tampered = signed.replace("zones: 1", "zones: 2");
+    assert!(verify_response(&tampered, &key, &line).is_err());
+    assert!(verify_response(response, &key, &line).is_err());
+}
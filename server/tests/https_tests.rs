@@ -0,0 +1,262 @@
+#![cfg(feature = "dns-over-https")]
+
+extern crate bytes;
+#[macro_use]
+extern crate futures;
+extern crate h2;
+extern crate http;
+extern crate openssl;
+extern crate rustc_serialize;
+extern crate tokio_core;
+extern crate tokio_openssl;
+extern crate trust_dns;
+extern crate trust_dns_server;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::future::{self, Either};
+use futures::{Async, Future, Stream};
+use http::header::CONTENT_TYPE;
+use http::{HeaderValue, Method, Request, StatusCode, Uri};
+use openssl::pkcs12::Pkcs12;
+use openssl::ssl::{SslConnectorBuilder, SslMethod, SSL_VERIFY_NONE};
+use rustc_serialize::base64::{ToBase64, URL_SAFE};
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::{Core, Timeout};
+use tokio_openssl::SslConnectorExt;
+
+use trust_dns::op::{Edns, Message, MessageType, Query};
+use trust_dns::rr::rdata::opt::EdnsOption;
+use trust_dns::rr::{Name, RData, Record, RecordType};
+use trust_dns::serialize::binary::{BinDecodable, BinEncodable};
+use trust_dns_server::ServerFuture;
+use trust_dns_server::authority::Catalog;
+use trust_dns_server::server::https::{dns_request, http2_error, max_age, DNS_QUERY_PATH,
+                                      MIME_APPLICATION_DNS};
+
+fn query_bytes() -> Vec<u8> {
+    let mut message = Message::new();
+    message.add_query(Query::query(
+        Name::parse("www.example.com.", None).unwrap(),
+        RecordType::A,
+    ));
+    message.to_bytes().unwrap()
+}
+
+#[test]
+fn test_get_request() {
+    let query = query_bytes();
+    let uri: Uri = format!("/dns-query?dns={}", query.to_base64(URL_SAFE))
+        .parse()
+        .unwrap();
+
+    assert_eq!(dns_request(&Method::GET, &uri, None, &[]), Ok(query));
+
+    let uri: Uri = "/dns-query?dns=not+base64!".parse().unwrap();
+    assert_eq!(
+        dns_request(&Method::GET, &uri, None, &[]),
+        Err(StatusCode::BAD_REQUEST)
+    );
+
+    let uri: Uri = "/dns-query".parse().unwrap();
+    assert_eq!(
+        dns_request(&Method::GET, &uri, None, &[]),
+        Err(StatusCode::BAD_REQUEST)
+    );
+
+    let uri: Uri = "/resolve?dns=AAABAAABAAAAAAAA".parse().unwrap();
+    assert_eq!(
+        dns_request(&Method::GET, &uri, None, &[]),
+        Err(StatusCode::NOT_FOUND)
+    );
+}
+
+#[test]
+fn test_post_request() {
+    let query = query_bytes();
+    let uri: Uri = "/dns-query".parse().unwrap();
+    let content_type = HeaderValue::from_static(MIME_APPLICATION_DNS);
+
+    assert_eq!(
+        dns_request(&Method::POST, &uri, Some(&content_type), &query),
+        Ok(query.clone())
+    );
+
+    let json = HeaderValue::from_static("application/dns-json");
+    assert_eq!(
+        dns_request(&Method::POST, &uri, Some(&json), &query),
+        Err(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    );
+    assert_eq!(
+        dns_request(&Method::POST, &uri, None, &query),
+        Err(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    );
+    assert_eq!(
+        dns_request(&Method::POST, &uri, Some(&content_type), &[]),
+        Err(StatusCode::BAD_REQUEST)
+    );
+    assert_eq!(
+        dns_request(&Method::PUT, &uri, Some(&content_type), &query),
+        Err(StatusCode::METHOD_NOT_ALLOWED)
+    );
+}
+
+#[test]
+fn test_max_age() {
+    let name = Name::parse("www.example.com.", None).unwrap();
+    let mut message = Message::new();
+    message.add_answer(Record::from_rdata(
+        name.clone(),
+        300,
+        RecordType::A,
+        RData::A("93.184.216.34".parse().unwrap()),
+    ));
+    message.add_answer(Record::from_rdata(
+        name,
+        60,
+        RecordType::A,
+        RData::A("93.184.216.35".parse().unwrap()),
+    ));
+
+    assert_eq!(max_age(&message.to_bytes().unwrap()), 60);
+    assert_eq!(max_age(&Message::new().to_bytes().unwrap()), 0);
+}
+
+// a query of some KiB, the window of a connection holds a few of them
+fn padded_query(id: u16) -> Vec<u8> {
+    let mut edns = Edns::new();
+    edns.set_option(EdnsOption::Padding(4096));
+
+    let mut message = Message::new();
+    message.set_id(id);
+    message.add_query(Query::query(
+        Name::parse("www.example.com.", None).unwrap(),
+        RecordType::A,
+    ));
+    message.set_edns(edns);
+    message.to_bytes().unwrap()
+}
+
+#[test]
+fn test_many_posts_on_connection() {
+    let (addr_sender, addr_receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("test_many_posts_on_connection:server".to_string())
+        .spawn(move || {
+            let server_path = env::var("TDNS_SERVER_SRC_ROOT").unwrap_or_else(|_| ".".to_owned());
+            let mut pkcs12_der = Vec::new();
+            File::open(format!(
+                "{}/tests/named_test_configs/sec/example.p12",
+                server_path
+            )).and_then(|mut file| file.read_to_end(&mut pkcs12_der))
+                .expect("failed to read pkcs12");
+            let pkcs12 = Pkcs12::from_der(&pkcs12_der)
+                .and_then(|pkcs12| pkcs12.parse("mypass"))
+                .unwrap();
+
+            let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            addr_sender.send(listener.local_addr().unwrap()).unwrap();
+
+            let mut server = ServerFuture::new(Catalog::new()).unwrap();
+            server
+                .register_https_listener(listener, Duration::from_secs(30), pkcs12)
+                .unwrap();
+            server.listen().unwrap();
+        })
+        .unwrap();
+    let addr = addr_receiver.recv().unwrap();
+
+    let mut io_loop = Core::new().unwrap();
+    let handle = io_loop.handle();
+
+    // the certificate of the server is not what is tested here
+    let mut connector = SslConnectorBuilder::new(SslMethod::tls()).unwrap();
+    connector.builder_mut().set_verify(SSL_VERIFY_NONE);
+    connector
+        .builder_mut()
+        .set_alpn_protocols(&[b"h2"])
+        .unwrap();
+    let connector = connector.build();
+
+    let connect = TcpStream::connect(&addr, &handle)
+        .and_then(move |tcp_stream| {
+            connector
+                .danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication_async(tcp_stream)
+                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))
+        })
+        .and_then(|tls_stream| h2::client::handshake(tls_stream).map_err(http2_error));
+    let (mut send_request, connection) = io_loop.run(connect).unwrap();
+    handle.spawn(connection.map_err(|e| panic!("connection failed: {}", e)));
+
+    // the bodies of the requests together are many times the window of the connection, all of
+    //  them are received before any request is finished, i.e. before their streams are closed.
+    //  The server must hand the data back to the flow control of the connection as it reads it.
+    let mut requests = Vec::new();
+    for id in 0..64 {
+        send_request = io_loop.run(send_request.ready()).unwrap();
+
+        let request = Request::post(DNS_QUERY_PATH)
+            .header(CONTENT_TYPE, MIME_APPLICATION_DNS)
+            .body(())
+            .unwrap();
+        let (response, mut body) = send_request.send_request(request, false).unwrap();
+
+        let query = padded_query(id);
+        body.reserve_capacity(query.len());
+        let capacity = future::poll_fn(|| loop {
+            match try_ready!(body.poll_capacity().map_err(http2_error)) {
+                Some(capacity) if capacity >= query.len() => return Ok(Async::Ready(())),
+                Some(_) => continue,
+                None => return Err(io::Error::new(io::ErrorKind::Other, "stream closed")),
+            }
+        });
+        run_with_timeout(&mut io_loop, capacity, &format!("capacity of request {}", id));
+        body.send_data(Bytes::from(query), false).unwrap();
+
+        requests.push((id, response, body));
+    }
+
+    for (id, response, mut body) in requests {
+        body.send_data(Bytes::new(), true).unwrap();
+
+        let response = response.map_err(http2_error).and_then(|response| {
+            assert_eq!(response.status(), StatusCode::OK);
+            let mut body = response.into_body();
+            let mut release_capacity = body.release_capacity().clone();
+            body.map_err(http2_error)
+                .fold(Vec::new(), move |mut buffer, chunk| {
+                    release_capacity
+                        .release_capacity(chunk.len())
+                        .map_err(http2_error)?;
+                    buffer.extend_from_slice(&chunk);
+                    Ok(buffer) as io::Result<Vec<u8>>
+                })
+        });
+        let response = run_with_timeout(&mut io_loop, response, &format!("request {}", id));
+
+        let response = Message::from_bytes(&response).unwrap();
+        assert_eq!(response.id(), id);
+        assert_eq!(response.message_type(), MessageType::Response);
+    }
+}
+
+fn run_with_timeout<F: Future<Error = io::Error>>(
+    io_loop: &mut Core,
+    future: F,
+    what: &str,
+) -> F::Item {
+    let timeout = Timeout::new(Duration::from_secs(5), &io_loop.handle()).unwrap();
+    match io_loop.run(future.select2(timeout)) {
+        Ok(Either::A((item, _))) => item,
+        Ok(Either::B(_)) => panic!("{} timed out", what),
+        Err(Either::A((e, _))) => panic!("{} failed: {}", what, e),
+        Err(Either::B((e, _))) => panic!("timer failed: {}", e),
+    }
+}
@@ -0,0 +1,78 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate trust_dns;
+extern crate trust_dns_server;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use futures::sync::oneshot;
+use tokio_core::reactor::Core;
+
+use trust_dns::op::{OpCode, ResponseCode};
+use trust_dns::rr::{Name, RecordType};
+use trust_dns_server::metrics;
+
+#[test]
+fn test_render() {
+    metrics::enable();
+
+    metrics::count_request("udp", OpCode::Query, Some(RecordType::AAAA));
+    metrics::count_request("tcp", OpCode::Update, Some(RecordType::SOA));
+    metrics::count_response("udp", ResponseCode::NXDomain, Duration::from_millis(2));
+    metrics::count_zone_query(&Name::parse("example.com.", None).unwrap().into());
+    metrics::count_connection("tcp");
+    metrics::count_update(false);
+    metrics::count_journal_write();
+
+    let text = metrics::render();
+    assert!(text.contains("trust_dns_requests_total{protocol=\"udp\",type=\"AAAA\"} 1\n"));
+    assert!(text.contains("trust_dns_requests_total{protocol=\"tcp\",type=\"UPDATE\"} 1\n"));
+    assert!(text.contains("trust_dns_responses_total{protocol=\"udp\",rcode=\"NXDOMAIN\"} 1\n"));
+    assert!(text.contains("trust_dns_zone_queries_total{zone=\"example.com.\"} 1\n"));
+    assert!(text.contains("trust_dns_connections_total{protocol=\"tcp\"} 1\n"));
+    assert!(text.contains("trust_dns_updates_total{result=\"failure\"} 1\n"));
+    assert!(text.contains("trust_dns_journal_writes_total 1\n"));
+
+    // the latency is in the buckets from 2.5ms up
+    assert!(text.contains("trust_dns_response_seconds_bucket{protocol=\"udp\",le=\"0.001\"} 0\n"));
+    assert!(text.contains("trust_dns_response_seconds_bucket{protocol=\"udp\",le=\"0.0025\"} 1\n"));
+    assert!(text.contains("trust_dns_response_seconds_bucket{protocol=\"udp\",le=\"+Inf\"} 1\n"));
+    assert!(text.contains("trust_dns_response_seconds_count{protocol=\"udp\"} 1\n"));
+}
+
+#[test]
+fn test_serve() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut io_loop = Core::new().unwrap();
+    metrics::serve(listener, &io_loop.handle()).unwrap();
+
+    let (done, responses) = oneshot::channel();
+    thread::spawn(move || {
+        let get = |request: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let responses = vec![
+            get("GET /metrics?name=trust_dns HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            get("POST /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            get("GET /other HTTP/1.1\r\n\r\n"),
+            get("GET /metrics\r\n\r\n"),
+        ];
+        done.send(responses).unwrap();
+    });
+
+    let responses = io_loop.run(responses).unwrap();
+    assert!(responses[0].starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(responses[0].contains("# TYPE trust_dns_requests_total counter\n"));
+    assert!(responses[1].starts_with("HTTP/1.0 405 Method Not Allowed\r\n"));
+    assert!(responses[2].starts_with("HTTP/1.0 404 Not Found\r\n"));
+    assert!(responses[3].starts_with("HTTP/1.0 400 Bad Request\r\n"));
+}
@@ -99,3 +99,20 @@ fn test_iterator() {
     );
     assert_eq!(None, iter.next());
 }
+
+#[test]
+fn test_refresh() {
+    let conn = Connection::open_in_memory().expect("could not create in memory DB");
+    let mut journal = Journal::new(conn).unwrap();
+    journal.schema_up().unwrap();
+    assert_eq!(journal.select_refresh().unwrap(), None);
+
+    // only the last refresh is kept
+    journal.insert_refresh(1_500_000_000).unwrap();
+    journal.insert_refresh(1_500_003_600).unwrap();
+    assert_eq!(journal.select_refresh().unwrap(), Some(1_500_003_600));
+
+    // a dump of the zone, e.g. after a transfer, keeps it
+    journal.insert_zone(1, &[]).unwrap();
+    assert_eq!(journal.select_refresh().unwrap(), Some(1_500_003_600));
+}
@@ -0,0 +1,199 @@
+extern crate trust_dns;
+extern crate trust_dns_server;
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use trust_dns::op::{Message, MessageType, ResponseCode};
+use trust_dns::rr::{Name, RData, Record, RecordType};
+use trust_dns::rr::rdata::opt::Cookie;
+use trust_dns_server::server::{Action, RateLimiter, ResponseClass, ServerCookies};
+
+#[test]
+fn test_limit_and_slip() {
+    let rate_limiter = RateLimiter::new(2, Duration::from_secs(5), 2, 24, 56);
+    let client: IpAddr = "192.0.2.1".parse().unwrap();
+    let now = Instant::now();
+
+    let actions: Vec<Action> = (0..6)
+        .map(|_| rate_limiter.check(client, ResponseClass::Answer, now))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            Action::Send,
+            Action::Send,
+            Action::Drop,
+            Action::Slip,
+            Action::Drop,
+            Action::Slip,
+        ]
+    );
+
+    // the network of the client shares the limit, other classes do not
+    let neighbour: IpAddr = "192.0.2.200".parse().unwrap();
+    let other: IpAddr = "198.51.100.1".parse().unwrap();
+    assert_eq!(
+        rate_limiter.check(neighbour, ResponseClass::Answer, now),
+        Action::Drop
+    );
+    assert_eq!(
+        rate_limiter.check(neighbour, ResponseClass::NxDomain, now),
+        Action::Send
+    );
+    assert_eq!(
+        rate_limiter.check(other, ResponseClass::Answer, now),
+        Action::Send
+    );
+}
+
+#[test]
+fn test_window() {
+    let rate_limiter = RateLimiter::new(1, Duration::from_secs(3), 0, 24, 56);
+    let client: IpAddr = "2001:db8::1".parse().unwrap();
+    let now = Instant::now();
+
+    // overdrawn by the window of responses
+    assert_eq!(
+        rate_limiter.check(client, ResponseClass::Error, now),
+        Action::Send
+    );
+    for _ in 0..10 {
+        assert_eq!(
+            rate_limiter.check(client, ResponseClass::Error, now),
+            Action::Drop
+        );
+    }
+
+    // the credit is regained at the rate
+    let later = now + Duration::from_secs(3);
+    assert_eq!(
+        rate_limiter.check(client, ResponseClass::Error, later),
+        Action::Drop
+    );
+    let later = now + Duration::from_secs(5);
+    assert_eq!(
+        rate_limiter.check(client, ResponseClass::Error, later),
+        Action::Send
+    );
+}
+
+#[test]
+fn test_response_class() {
+    let name = Name::parse("www.example.com.", None).unwrap();
+    let mut response = Message::new();
+    response
+        .set_message_type(MessageType::Response)
+        .set_response_code(ResponseCode::NoError);
+    assert_eq!(ResponseClass::of(&response), ResponseClass::Answer);
+
+    let mut ns = Record::with(name.clone(), RecordType::NS, 86400);
+    ns.set_rdata(RData::NS(Name::parse("ns.example.com.", None).unwrap()));
+    response.add_name_server(ns);
+    assert_eq!(ResponseClass::of(&response), ResponseClass::Referral);
+
+    response.set_authoritative(true);
+    assert_eq!(ResponseClass::of(&response), ResponseClass::Answer);
+
+    response.set_response_code(ResponseCode::NXDomain);
+    assert_eq!(ResponseClass::of(&response), ResponseClass::NxDomain);
+
+    response.set_response_code(ResponseCode::Refused);
+    assert_eq!(ResponseClass::of(&response), ResponseClass::Error);
+}
+
+#[test]
+fn test_cookie_exempt() {
+    let server_cookies = Arc::new(ServerCookies::new());
+    let client: IpAddr = "192.0.2.1".parse().unwrap();
+    let is_exempt =
+        |client: IpAddr, cookie: &Cookie| server_cookies.check(cookie, client).is_valid();
+
+    // the server cookie is only valid for the client to which it was sent
+    let cookie = Cookie::new([1; 8], None);
+    let checked = server_cookies.check(&cookie, client);
+    assert!(!checked.is_valid());
+    let cookie = checked.response();
+    assert!(is_exempt(client, &cookie));
+    assert!(!is_exempt("192.0.2.2".parse().unwrap(), &cookie));
+    let server = cookie.server().map(|server| server.to_vec());
+    assert!(!is_exempt(client, &Cookie::new([2; 8], server)));
+
+    // it is echoed while it is recent
+    assert_eq!(server_cookies.check(&cookie, client).response(), cookie);
+
+    // and is not valid for other servers
+    let other = ServerCookies::new().check(&cookie, client).response();
+    assert!(other != cookie);
+    assert!(!is_exempt(client, &other));
+}
+
+#[test]
+fn test_max_networks() {
+    let mut rate_limiter = RateLimiter::new(1, Duration::from_secs(3), 0, 24, 56);
+    rate_limiter.set_max_networks(2);
+    let first: IpAddr = "192.0.2.1".parse().unwrap();
+    let second: IpAddr = "198.51.100.1".parse().unwrap();
+    let third: IpAddr = "203.0.113.1".parse().unwrap();
+    let fourth: IpAddr = "2001:db8::1".parse().unwrap();
+    let now = Instant::now();
+
+    for client in &[first, second] {
+        assert_eq!(
+            rate_limiter.check(*client, ResponseClass::Answer, now),
+            Action::Send
+        );
+    }
+
+    // no network has regained its credit, the others share one limit
+    assert_eq!(
+        rate_limiter.check(third, ResponseClass::Answer, now),
+        Action::Send
+    );
+    assert_eq!(
+        rate_limiter.check(fourth, ResponseClass::Answer, now),
+        Action::Drop
+    );
+
+    // once the credit is regained the networks are forgotten, and limited separately again
+    let later = now + Duration::from_secs(10);
+    assert_eq!(
+        rate_limiter.check(third, ResponseClass::Answer, later),
+        Action::Send
+    );
+    assert_eq!(
+        rate_limiter.check(fourth, ResponseClass::Answer, later),
+        Action::Send
+    );
+    assert_eq!(
+        rate_limiter.check(fourth, ResponseClass::Answer, later),
+        Action::Drop
+    );
+}
+
+#[test]
+fn test_shared_by_threads() {
+    let rate_limiter = Arc::new(RateLimiter::new(10, Duration::from_secs(5), 0, 24, 56));
+    let now = Instant::now();
+
+    // the limits hold in aggregate, whichever thread sends the responses
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let rate_limiter = rate_limiter.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|i| {
+                        let client = IpAddr::from([192, 0, 2, i as u8]);
+                        rate_limiter.check(client, ResponseClass::Answer, now)
+                    })
+                    .filter(|action| *action == Action::Send)
+                    .count()
+            })
+        })
+        .collect();
+
+    let sent: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(sent, 10);
+}
@@ -418,3 +418,38 @@ _443._tcp.www.example.com. IN TLSA (
         assert!(false);
     }
 }
+
+#[test]
+fn test_emit_zone_file() {
+    let lexer = Lexer::new(
+        "@   IN  SOA     ns.example.com. admin.example.com. 2018030301 7200 600 3600000 60
+      NS      ns
+      MX      10      mail
+ns      A       192.0.2.1
+mail    AAAA    2001:db8::1
+www     CNAME   ns
+txt     TXT     \"quoted \\\"text\\\"\" \"line\\010break\"
+_sip._udp SRV 1 2 5060 ns
+",
+    );
+    let (origin, records) = Parser::new()
+        .parse(lexer, Some(Name::from_str("example.com.").unwrap()))
+        .expect("failed to parse zone");
+    let authority = Authority::new(origin, records, ZoneType::Master, true, false);
+
+    let mut emitted = Vec::new();
+    authority.emit_zone_file(&mut emitted).unwrap();
+    let emitted = String::from_utf8(emitted).unwrap();
+    assert!(emitted.lines().next().unwrap().contains(" IN SOA "));
+
+    // the emitted zone file is read back as the same zone
+    let (origin, records) = Parser::new()
+        .parse(Lexer::new(&emitted), Some(authority.origin().clone().into()))
+        .expect("failed to parse emitted zone");
+    let reloaded = Authority::new(origin, records, ZoneType::Master, true, false);
+
+    let records: Vec<&Record> = authority.records().values().flat_map(|r| r.iter()).collect();
+    let reloaded_records: Vec<&Record> =
+        reloaded.records().values().flat_map(|r| r.iter()).collect();
+    assert_eq!(records, reloaded_records);
+}
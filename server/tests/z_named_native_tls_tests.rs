@@ -33,6 +33,7 @@ use trust_dns_native_tls::TlsClientStreamBuilder;
 
 use server_harness::{named_test_harness, query_a};
 
+#[cfg(feature = "tls-openssl")]
 #[test]
 fn test_example_tls_toml_startup() {
     named_test_harness("dns_over_tls.toml", move |_, tls_port| {
@@ -82,6 +83,39 @@ fn test_example_tls_toml_startup() {
     })
 }
 
+#[cfg(feature = "tls-ring")]
+#[test]
+fn test_example_rustls_toml_startup() {
+    named_test_harness("dns_over_tls_rustls.toml", move |_, tls_port| {
+        let mut cert_der = vec![];
+        let server_path = env::var("TDNS_SERVER_SRC_ROOT").unwrap_or_else(|_| ".".to_owned());
+        println!("using server src path: {}", server_path);
+
+        File::open(&format!(
+            "{}/tests/named_test_configs/sec/example.cert",
+            server_path
+        )).expect("failed to open cert")
+            .read_to_end(&mut cert_der)
+            .expect("failed to read cert");
+
+        let mut io_loop = Core::new().unwrap();
+        let addr: SocketAddr = ("127.0.0.1", tls_port)
+            .to_socket_addrs()
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut tls_conn_builder = TlsClientStreamBuilder::new();
+        let cert = to_trust_anchor(&cert_der);
+        tls_conn_builder.add_ca(cert);
+        let (stream, sender) =
+            tls_conn_builder.build(addr, "ns.example.com".to_string(), &io_loop.handle());
+        let mut client = ClientFuture::new(stream, sender, &io_loop.handle(), None);
+
+        // served with the PEM certificate and key
+        query_a(&mut io_loop, &mut client);
+    })
+}
+
 fn to_trust_anchor(cert_der: &[u8]) -> Certificate {
     Certificate::from_der(cert_der).unwrap()
 }
@@ -13,7 +13,7 @@ extern crate trust_dns;
 extern crate trust_dns_proto;
 extern crate trust_dns_server;
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 extern crate trust_dns_openssl;
 
 mod server_harness;
@@ -193,7 +193,7 @@ fn test_nxdomain_where_no_name_exists() {
     })
 }
 
-#[cfg(feature = "tls")]
+#[cfg(feature = "tls-openssl")]
 #[test]
 fn test_example_tls_toml_startup() {
     use std::env;
@@ -0,0 +1,142 @@
+// Copyright 2015-2018 Benjamin Fry <benjaminfry@me.com>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+extern crate clap;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate trust_dns_server;
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use clap::{App, Arg, ArgMatches};
+
+use trust_dns_server::config::Config;
+use trust_dns_server::control::{self, Command};
+use trust_dns_server::error::ControlResult;
+
+fn args<'a>() -> ArgMatches<'a> {
+    App::new("TRust-DNS named-control")
+        .version(trust_dns_server::version())
+        .author("Benjamin Fry <benjaminfry@me.com>")
+        .about("Sends a command to a running named, on its control channel")
+        .arg(
+            Arg::with_name("command")
+                .value_name("COMMAND")
+                .help("One of: reload, freeze, thaw, sync, retransfer, flush, status")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("zone")
+                .value_name("ZONE")
+                .help("The zone of the command, reload without a zone reloads the configuration")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("config")
+                .value_name("FILE")
+                .long("config")
+                .short("c")
+                .takes_value(true)
+                .help("Configuration FILE of named, with the control channel and its key")
+                .default_value("/etc/named.toml"),
+        )
+        .arg(
+            Arg::with_name("socket")
+                .value_name("PATH")
+                .long("socket")
+                .short("s")
+                .takes_value(true)
+                .help("Unix domain socket to connect to, overrides the configuration"),
+        )
+        .arg(
+            Arg::with_name("addr")
+                .value_name("ADDR")
+                .long("addr")
+                .short("a")
+                .takes_value(true)
+                .help("TCP address to connect to, e.g. 127.0.0.1:953, overrides the configuration"),
+        )
+        .get_matches()
+}
+
+pub fn main() {
+    env_logger::init();
+    let matches = args();
+
+    let mut line = matches.value_of("command").unwrap().to_string();
+    if let Some(zone) = matches.value_of("zone") {
+        line.push(' ');
+        line.push_str(zone);
+    }
+
+    match send(&matches, &line) {
+        Ok(message) => println!("{}", message),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn send(matches: &ArgMatches, line: &str) -> ControlResult<String> {
+    let command = Command::from_str(line)?;
+
+    let config_path = Path::new(matches.value_of("config").unwrap());
+    info!("reading configuration from: {:?}", config_path);
+    let config = Config::read_config(config_path)
+        .map_err(|e| format!("could not read config: {:?}: {}", config_path, e))?;
+    let control_config = config
+        .get_control()
+        .ok_or_else(|| format!("no control channel configured in: {:?}", config_path))?;
+
+    let addr: Option<SocketAddr> = match matches.value_of("addr") {
+        Some(addr) => Some(
+            addr.parse::<SocketAddr>()
+                .map_err(|e| format!("bad address: {}: {}", addr, e))?,
+        ),
+        None => control_config
+            .get_listen_addr()
+            .map_err(|e| format!("bad control listen_addr: {}", e))?,
+    };
+    let socket = matches
+        .value_of("socket")
+        .map(Path::new)
+        .or_else(|| control_config.get_unix_socket());
+
+    // the Unix domain socket is preferred, unless an address is given
+    match socket {
+        Some(socket) if !matches.is_present("addr") => send_unix(socket, &command),
+        _ => {
+            let addr = addr.ok_or_else(|| "no control socket or listen_addr configured")?;
+            let key = control_config
+                .get_tsig_key(config.get_tsig_keys())
+                .map_err(|e| format!("bad control tsig_key: {}", e))?
+                .ok_or_else(|| "a tsig_key is required for control over TCP")?;
+            info!("sending command to: {}", addr);
+            control::send_tcp(addr, &key, &command)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send_unix(socket: &Path, command: &Command) -> ControlResult<String> {
+    info!("sending command to: {:?}", socket);
+    control::send_unix(socket, command)
+}
+
+#[cfg(not(unix))]
+fn send_unix(socket: &Path, _command: &Command) -> ControlResult<String> {
+    Err(format!(
+        "Unix domain sockets are only supported on unix: {:?}",
+        socket
+    )
+    .into())
+}